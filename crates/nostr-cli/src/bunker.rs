@@ -0,0 +1,117 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Bunker approval policy
+
+use std::collections::HashSet;
+
+use nostr_connect::prelude::*;
+
+use crate::cli::io;
+
+/// Decision for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Approve,
+    Reject,
+    Ask,
+}
+
+/// Approval policy of the bunker requests
+#[derive(Debug, Clone, Default)]
+pub struct BunkerPolicy {
+    /// Kinds signed without asking
+    pub auto_approve_kinds: HashSet<Kind>,
+    /// Allowed client public keys (all allowed if empty)
+    pub allowed_clients: HashSet<PublicKey>,
+    /// Reject the requests that need approval, instead of asking (for headless use)
+    pub no_prompt: bool,
+}
+
+impl BunkerPolicy {
+    /// Decide what to do with a request from a client
+    pub fn decide(&self, client: &PublicKey, req: &NostrConnectRequest) -> Decision {
+        // Not allowed client
+        if !self.allowed_clients.is_empty() && !self.allowed_clients.contains(client) {
+            return Decision::Reject;
+        }
+
+        let approve: bool = match req {
+            // Reveal nothing secret
+            NostrConnectRequest::GetPublicKey | NostrConnectRequest::Ping => true,
+            // An allowed client is explicitly trusted
+            NostrConnectRequest::Connect { .. } => !self.allowed_clients.is_empty(),
+            NostrConnectRequest::SignEvent(unsigned) => {
+                self.auto_approve_kinds.contains(&unsigned.kind)
+            }
+            _ => false,
+        };
+
+        if approve {
+            Decision::Approve
+        } else if self.no_prompt {
+            Decision::Reject
+        } else {
+            Decision::Ask
+        }
+    }
+}
+
+impl NostrConnectSignerActions for BunkerPolicy {
+    fn approve(&self, public_key: &PublicKey, req: &NostrConnectRequest) -> bool {
+        match self.decide(public_key, req) {
+            Decision::Approve => {
+                println!("Auto-approved {} request from {public_key}", req.method());
+                true
+            }
+            Decision::Reject => {
+                println!("Rejected {} request from {public_key}", req.method());
+                false
+            }
+            Decision::Ask => {
+                println!("Public key: {public_key}");
+                println!("{req:#?}\n");
+                io::ask("Approve request?").unwrap_or_default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bunker_policy() {
+        let client = Keys::generate().public_key();
+        let other = Keys::generate().public_key();
+        let note = NostrConnectRequest::SignEvent(EventBuilder::text_note("Hello").build(client));
+        let metadata =
+            NostrConnectRequest::SignEvent(EventBuilder::metadata(&Metadata::new()).build(client));
+
+        let policy = BunkerPolicy {
+            auto_approve_kinds: HashSet::from([Kind::TextNote]),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.decide(&client, &NostrConnectRequest::GetPublicKey),
+            Decision::Approve
+        );
+        assert_eq!(policy.decide(&client, &note), Decision::Approve);
+        assert_eq!(policy.decide(&client, &metadata), Decision::Ask);
+
+        // Headless, with allowlist
+        let policy = BunkerPolicy {
+            allowed_clients: HashSet::from([client]),
+            no_prompt: true,
+            ..policy
+        };
+        assert_eq!(policy.decide(&client, &note), Decision::Approve);
+        assert_eq!(policy.decide(&client, &metadata), Decision::Reject);
+        assert_eq!(
+            policy.decide(&other, &NostrConnectRequest::GetPublicKey),
+            Decision::Reject
+        );
+    }
+}
@@ -5,6 +5,15 @@
 use dialoguer::{Confirm, Input, Password};
 use nostr_sdk::{Keys, Result};
 
+/// Prefixes of the bech32 secrets that must never be stored in the shell history
+const SECRET_PREFIXES: [&str; 2] = ["nsec1", "ncryptsec1"];
+
+/// Check if a shell line contains a secret key, so it must not be stored in the history.
+pub fn is_sensitive(line: &str) -> bool {
+    let line: String = line.to_lowercase();
+    SECRET_PREFIXES.iter().any(|prefix| line.contains(prefix))
+}
+
 pub fn get_optional_input<S>(prompt: S) -> Result<Option<String>>
 where
     S: Into<String>,
@@ -51,3 +60,23 @@ where
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_sensitive() {
+        assert!(is_sensitive(
+            "publish --key nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99 hello"
+        ));
+        assert!(is_sensitive("import NSEC1ABC"));
+        assert!(is_sensitive(
+            "keys ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjv"
+        ));
+        assert!(!is_sensitive("query --kind 1 --limit 10"));
+        assert!(!is_sensitive(
+            "info npub1drvpzev3syqt0kjrls50050uzf25gehpz9vgdw08hvex7e0vgfeq0eseet"
+        ));
+    }
+}
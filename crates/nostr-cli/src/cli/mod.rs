@@ -4,6 +4,8 @@
 
 #![allow(clippy::large_enum_variant)]
 
+use std::fmt;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand, ValueEnum};
@@ -12,6 +14,8 @@ use nostr_sdk::prelude::*;
 pub mod io;
 pub mod parser;
 
+use self::parser::{IdentityClaim, TagFilter};
+
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about)]
 pub struct Cli {
@@ -25,26 +29,117 @@ pub enum Command {
     Shell {
         #[clap(long)]
         relays: Vec<RelayUrl>,
-        // tor: bool,
-        // proxy: Option<SocketAddr>,
+        /// Use the embedded tor client for `.onion` relays
+        #[clap(long, conflicts_with = "proxy")]
+        tor: bool,
+        /// Route also clearnet relays through tor (require `tor` flag!)
+        #[clap(long, requires = "tor")]
+        force_tor: bool,
+        /// Route all relays through a SOCKS5 proxy
+        #[clap(long)]
+        proxy: Option<SocketAddr>,
+        /// Sign with a remote signer (NIP-46 `bunker://` URI), instead of a local secret key
+        #[clap(long)]
+        bunker: Option<String>,
+        /// Use an in-memory database, instead of the persistent one
+        #[clap(long)]
+        memory: bool,
+        /// Max number of events kept in memory (require `memory` flag!)
+        #[clap(long, requires = "memory")]
+        max_events: Option<usize>,
+        /// Max size of the events kept in memory, in bytes (require `memory` flag!)
+        #[clap(long, requires = "memory")]
+        max_bytes: Option<usize>,
     },
     /// Serve a local relay for test purpose
     Serve {
         /// Port
         #[clap(long)]
         port: Option<u16>,
+        /// Persist events to a LMDB database at this path (default: in-memory)
+        #[clap(long)]
+        db: Option<PathBuf>,
+        /// Max number of events kept in memory
+        #[clap(long, conflicts_with = "db")]
+        max_events: Option<usize>,
+        /// Max size of the events kept in memory, in bytes
+        #[clap(long, conflicts_with = "db")]
+        max_bytes: Option<usize>,
     },
     /// Serve Nostr Connect signer
     ///
+    /// The `get_public_key` and `ping` requests are always approved, the others are asked,
+    /// unless auto-approved by the options below.
+    ///
     /// <https://github.com/nostr-protocol/nips/blob/master/46.md>
-    Bunker,
+    Bunker {
+        /// Kinds to sign without asking (i.e., `1,7`)
+        #[clap(long, value_delimiter = ',')]
+        auto_approve_kinds: Vec<Kind>,
+        /// Allowed client public key (can be repeated). The requests of the other clients are rejected.
+        ///
+        /// The `connect` requests of the allowed clients are approved without asking.
+        #[clap(long = "allow-client")]
+        allowed_clients: Vec<PublicKey>,
+        /// Reject the requests that aren't auto-approved, instead of asking (for headless use)
+        #[clap(long)]
+        no_prompt: bool,
+    },
+    /// Convert between hex, bech32 (NIP-19) and `nostr:` URI (NIP-21) representations
+    ///
+    /// A hex input is interpreted as a public key or an event ID, according to the target.
+    /// Coordinates are accepted in the `<kind>:<pubkey>:<d-tag>` format.
+    #[command(arg_required_else_help = true)]
+    Convert {
+        /// Public key, event ID or coordinate
+        input: String,
+        /// Target encoding
+        #[clap(long)]
+        to: EncodingTarget,
+        /// Relay hint (can be repeated). If not set, the ones of the input are kept.
+        #[clap(long = "relay")]
+        relays: Vec<RelayUrl>,
+        /// Event author (only for `nevent`)
+        #[clap(long)]
+        author: Option<PublicKey>,
+        /// Event kind (only for `nevent`)
+        #[clap(long)]
+        kind: Option<Kind>,
+    },
+    /// Developer tools
+    #[command(arg_required_else_help = true)]
+    Dev {
+        #[command(subcommand)]
+        command: DevCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DevCommand {
+    /// Generate reproducible signed events and print them as JSON (one per line)
+    ///
+    /// The same seed always gives the same output. The keys are predictable: use them only for testing!
+    TestVectors {
+        /// Number of events
+        count: usize,
+        /// RNG seed
+        #[clap(long)]
+        seed: u64,
+    },
 }
 
 #[derive(Debug, Parser)]
 #[command(name = "")]
 pub enum ShellCommand {
     /// Generate random keys
-    Generate,
+    Generate {
+        /// Mine keys until the bech32 public key starts with this prefix (after `npub1`)
+        #[clap(long, conflicts_with = "vanity_hex")]
+        vanity: Option<String>,
+        /// Mine keys until the hex public key starts with this prefix
+        #[clap(long)]
+        vanity_hex: Option<String>,
+    },
     /// Sync public key's event with specified relays (negentropy)
     #[command(arg_required_else_help = true)]
     Sync {
@@ -56,6 +151,71 @@ pub enum ShellCommand {
         /// Direction
         #[clap(short, long, value_enum, default_value_t = ShellSyncDirection::Down)]
         direction: ShellSyncDirection,
+        /// Only compute and print the events that would be transferred, without sending or storing anything
+        #[clap(long)]
+        dry_run: bool,
+        /// Seconds to wait for the first reconciliation message of every relay (default: 10)
+        #[clap(long, value_name = "SECS")]
+        timeout: Option<u64>,
+    },
+    /// Sign and publish an event
+    #[command(arg_required_else_help = true)]
+    Publish {
+        /// Content
+        #[clap(short, long)]
+        content: String,
+        /// Kind
+        #[clap(short, long, default_value_t = Kind::TextNote)]
+        kind: Kind,
+        /// Tag (i.e. `--tag "p <public-key>"`)
+        #[clap(short, long)]
+        tag: Vec<String>,
+        /// POW difficulty
+        #[clap(long)]
+        pow: Option<u8>,
+    },
+    /// Request the deletion of events (NIP-09)
+    #[command(arg_required_else_help = true)]
+    Delete {
+        /// Event IDs
+        #[clap(required = true)]
+        ids: Vec<EventId>,
+        /// Reason
+        #[clap(long)]
+        reason: Option<String>,
+    },
+    /// React to an event (NIP-25)
+    #[command(arg_required_else_help = true)]
+    React {
+        /// Event ID
+        id: EventId,
+        /// Reaction (i.e. `+`, `-` or an emoji)
+        #[clap(long, default_value = "+")]
+        content: String,
+        /// Image URL of a custom emoji (NIP-30): the content is used as shortcode (i.e. `:soapbox:`)
+        #[clap(long)]
+        emoji_url: Option<Url>,
+    },
+    /// Fetch an event and republish it, unchanged, to other relays
+    #[command(arg_required_else_help = true)]
+    Broadcast {
+        /// Event ID
+        id: EventId,
+        /// Relays to fetch the event from
+        #[clap(long, required = true)]
+        from: Vec<RelayUrl>,
+        /// Relays to publish the event to
+        #[clap(long, required = true)]
+        to: Vec<RelayUrl>,
+    },
+    /// Recompute the IDs and verify the signatures of events
+    #[command(arg_required_else_help = true)]
+    Verify {
+        /// Path of a file with a JSON array of events or an event per line
+        #[clap(long, conflicts_with = "json")]
+        file: Option<PathBuf>,
+        /// Event JSON (or JSON array of events)
+        json: Option<String>,
     },
     /// Query
     Query {
@@ -74,6 +234,35 @@ pub enum ShellCommand {
         /// Full-text search
         #[clap(long)]
         search: Option<String>,
+        /// Referenced event (`e` tag), i.e. to find the replies to a note
+        ///
+        /// Can be repeated: the events referencing any of them match.
+        #[clap(long = "referenced-event")]
+        referenced_events: Vec<EventId>,
+        /// Mentioned public key (`p` tag)
+        ///
+        /// Can be repeated: the events mentioning any of them match.
+        #[clap(long = "mentions")]
+        mentions: Vec<PublicKey>,
+        /// Tag filter, as `<name>=<value>` (i.e., `t=bitcoin`)
+        ///
+        /// Can be repeated: values of the same tag match any of them, different tags must all match.
+        #[clap(long = "tag", value_name = "NAME=VALUE")]
+        tags: Vec<TagFilter>,
+        /// Exclude the events with a tag, as `<name>=<value>` (i.e., `t=nsfw`)
+        ///
+        /// Can be repeated: the events matching any of them are excluded.
+        /// Applied to the returned events, so the results may be less than the limit.
+        #[clap(long = "exclude-tag", value_name = "NAME=VALUE")]
+        exclude_tags: Vec<TagFilter>,
+        /// Exclude the replies (NIP-10)
+        ///
+        /// Applied to the returned events, so the results may be less than the limit.
+        #[clap(long)]
+        exclude_replies: bool,
+        /// Sort results
+        #[clap(long, value_enum, default_value_t = ShellQuerySort::CreatedAtDesc)]
+        sort: ShellQuerySort,
         /// Since
         #[clap(short, long)]
         since: Option<Timestamp>,
@@ -83,16 +272,138 @@ pub enum ShellCommand {
         /// Limit
         #[clap(short, long)]
         limit: Option<usize>,
+        /// Page token: return the events that come after this event (newest first)
+        #[clap(long)]
+        after: Option<EventId>,
         /// Query only database
         #[clap(long)]
         database: bool,
+        /// Seconds to wait for the relays (default: 10)
+        #[clap(long, value_name = "SECS", conflicts_with = "database")]
+        timeout: Option<u64>,
         /// Print result
         #[clap(long)]
         print: bool,
-        /// Print result as JSON (require `print` flag!)
+        /// Print result as JSON (require `print` or `output` flag!)
         #[clap(long)]
         json: bool,
+        /// Don't colorize the printed result (colors are disabled also if `NO_COLOR` is set or if the output isn't a terminal)
+        #[clap(long)]
+        no_color: bool,
+        /// Write result to file (as JSONL if `json` flag is set)
+        ///
+        /// The JSONL results are streamed to the file, without buffering them,
+        /// unless `print`, `limit`, `after` or a sort other than the default are set.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+        /// Overwrite the output file if already exists
+        #[clap(long, requires = "output")]
+        force: bool,
+    },
+    /// Encrypt a message for a receiver
+    Encrypt {
+        /// Receiver public key
+        receiver: PublicKey,
+        /// Content to encrypt
+        content: String,
+        /// Encryption scheme
+        #[clap(long, value_enum, default_value_t = EncryptionVersion::Nip44)]
+        nip: EncryptionVersion,
+    },
+    /// Decrypt a message from a sender
+    Decrypt {
+        /// Sender public key
+        sender: PublicKey,
+        /// Ciphertext
+        ciphertext: String,
+        /// Encryption scheme (if not set, all the schemes are tried)
+        #[clap(long, value_enum)]
+        nip: Option<EncryptionVersion>,
+    },
+    /// Query the events of a public key from its NIP-65 write relays (outbox model)
+    ///
+    /// Fall back to the session relays if the relay list isn't found.
+    Gossip {
+        /// Public key
+        public_key: PublicKey,
+        /// Kind (default: text note)
+        #[clap(short, long)]
+        kind: Option<Kind>,
+        /// Limit
+        #[clap(short, long, default_value_t = 20)]
+        limit: usize,
+        /// Print result as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Follow public keys, merging them into the current contact list
+    #[command(arg_required_else_help = true)]
+    Follow {
+        /// Public keys (hex or bech32)
+        public_keys: Vec<PublicKey>,
+        /// Read public keys from file (one per line)
+        #[clap(long)]
+        from_file: Option<PathBuf>,
     },
+    /// View and edit profile metadata
+    #[command(arg_required_else_help = true)]
+    Profile {
+        #[command(subcommand)]
+        command: ShellCommandProfile,
+    },
+    /// Stream the messages of a relay-based group, until Ctrl-C (NIP-29)
+    ///
+    /// The group metadata, admins, members and roles are printed distinctly.
+    #[command(arg_required_else_help = true)]
+    Group {
+        /// Relay hosting the group
+        relay: RelayUrl,
+        /// Group ID
+        id: String,
+        /// Max number of past messages to print
+        #[clap(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Fetch and print the reply tree of a thread (NIP-10)
+    Thread {
+        /// Root event ID
+        id: EventId,
+        /// Max depth of replies
+        #[clap(long)]
+        depth: Option<usize>,
+    },
+    /// Request a zap invoice (NIP-57)
+    ///
+    /// The zap request is signed and sent to the recipient's LNURL-pay endpoint.
+    /// The receipt will be published to the session relays.
+    #[command(arg_required_else_help = true)]
+    Zap {
+        /// Recipient public key
+        public_key: PublicKey,
+        /// Amount in millisats
+        amount_msat: u64,
+        /// Event to zap
+        #[clap(long)]
+        event: Option<EventId>,
+        /// Comment
+        #[clap(long)]
+        comment: Option<String>,
+    },
+    /// Verify NIP-05 identifier
+    Nip05 {
+        /// Public key
+        public_key: PublicKey,
+        /// Identifier (i.e. `name@example.com`)
+        identifier: String,
+    },
+    /// Manage the relays of the session
+    #[command(arg_required_else_help = true)]
+    Relay {
+        #[command(subcommand)]
+        command: ShellCommandRelay,
+    },
+    /// Show session info: public key, relays and subscriptions
+    Info,
     /// Database
     #[command(arg_required_else_help = true)]
     Database {
@@ -103,6 +414,70 @@ pub enum ShellCommand {
     Exit,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum ShellCommandProfile {
+    /// Show profile metadata
+    Show {
+        /// Public key (default: session public key)
+        public_key: Option<PublicKey>,
+    },
+    /// Update the profile metadata of the session public key
+    ///
+    /// The latest metadata is fetched first, so the fields not specified are kept.
+    Set {
+        /// Name
+        #[clap(long)]
+        name: Option<String>,
+        /// Description
+        #[clap(long)]
+        about: Option<String>,
+        /// Picture URL
+        #[clap(long)]
+        picture: Option<Url>,
+        /// NIP-05 identifier (i.e. `name@example.com`)
+        #[clap(long)]
+        nip05: Option<String>,
+        /// Lightning address
+        #[clap(long)]
+        lud16: Option<String>,
+        /// External identity (NIP-39), in the `<platform>:<identity>:<proof>` format (i.e. `github:alice:<gist-id>`)
+        ///
+        /// Can be repeated. Supported platforms: github, twitter, mastodon, telegram.
+        #[clap(long = "identity")]
+        identities: Vec<IdentityClaim>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ShellCommandRelay {
+    /// Add and connect a relay
+    ///
+    /// If neither `--read` nor `--write` is specified, the relay is added for both.
+    /// If the relay already exists, the flags are added to it.
+    #[command(arg_required_else_help = true)]
+    Add {
+        /// Relay URL
+        url: RelayUrl,
+        /// Read from the relay
+        #[clap(long)]
+        read: bool,
+        /// Write to the relay
+        #[clap(long)]
+        write: bool,
+    },
+    /// Disconnect and remove a relay
+    #[command(arg_required_else_help = true)]
+    Remove {
+        /// Relay URL
+        url: RelayUrl,
+        /// Remove the relay also if it's used for the gossip model
+        #[clap(long)]
+        force: bool,
+    },
+    /// List the relays, with their status and flags
+    List,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum ShellCommandDatabase {
     /// Populate database
@@ -110,11 +485,81 @@ pub enum ShellCommandDatabase {
     Populate {
         /// Path of JSON file
         path: PathBuf,
+        /// Skip the events already in the database (i.e., to resume an interrupted import)
+        #[clap(long)]
+        resume: bool,
+        /// Recompute the ID and check the signature of the events, skipping the invalid ones
+        #[clap(long)]
+        verify: bool,
+        /// Don't fail if some events are invalid (require `verify` flag!)
+        #[clap(long, requires = "verify")]
+        lenient: bool,
+    },
+    /// Export events to a JSONL file
+    #[command(arg_required_else_help = true)]
+    Export {
+        /// Path of JSONL file
+        path: PathBuf,
+        /// Kind
+        #[clap(long)]
+        kind: Option<Kind>,
+        /// Author
+        #[clap(long)]
+        author: Option<PublicKey>,
+    },
+    /// Verify IDs and signatures of the stored events
+    Check {
+        /// Remove the invalid events
+        #[clap(long)]
+        repair: bool,
     },
     /// Database stats
     Stats,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ShellQuerySort {
+    /// Sort by search terms frequency in content and tags (newest first on ties)
+    Relevance,
+    /// Oldest first
+    CreatedAtAsc,
+    /// Newest first
+    CreatedAtDesc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EncryptionVersion {
+    /// NIP-04 (deprecated)
+    Nip04,
+    /// NIP-44
+    Nip44,
+}
+
+impl fmt::Display for EncryptionVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nip04 => write!(f, "NIP-04"),
+            Self::Nip44 => write!(f, "NIP-44"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EncodingTarget {
+    /// Hex public key or event ID, or `<kind>:<pubkey>:<d-tag>` coordinate
+    Hex,
+    /// Bech32 public key
+    Npub,
+    /// Bech32 public key with relay hints
+    Nprofile,
+    /// Bech32 event ID
+    Note,
+    /// Bech32 event ID with relay hints, author and kind
+    Nevent,
+    /// Bech32 coordinate with relay hints
+    Naddr,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum ShellSyncDirection {
     /// Send events to relay
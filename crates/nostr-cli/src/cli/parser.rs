@@ -2,6 +2,10 @@
 // Copyright (c) 2023-2025 Rust Nostr Developers
 // Distributed under the MIT software license
 
+use std::fmt;
+use std::str::FromStr;
+
+use nostr_sdk::prelude::{EventId, ExternalIdentity, PublicKey, SingleLetterTag};
 use once_cell::sync::Lazy; // TODO: use `std::sync::LazyLock` when MSRV >= 1.80.0
 use regex::Regex;
 
@@ -67,9 +71,138 @@ impl std::fmt::Display for MismatchedQuotes {
 
 impl std::error::Error for MismatchedQuotes {}
 
+/// Tag constraint of a filter, in the `<name>=<value>` format (i.e., `t=bitcoin`)
+///
+/// The values of the `p` and `e` tags can also be bech32 encoded (i.e., `npub`, `note`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    /// Tag name
+    pub tag: SingleLetterTag,
+    /// Tag value
+    pub value: String,
+}
+
+impl FromStr for TagFilter {
+    type Err = InvalidTagFilter;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s.split_once('=').ok_or(InvalidTagFilter::MissingValue)?;
+
+        let mut chars = name.chars();
+        let tag: SingleLetterTag = match (chars.next(), chars.next()) {
+            (Some(c), None) => {
+                SingleLetterTag::from_char(c).map_err(|_| InvalidTagFilter::InvalidName)?
+            }
+            _ => return Err(InvalidTagFilter::InvalidName),
+        };
+
+        if value.is_empty() {
+            return Err(InvalidTagFilter::MissingValue);
+        }
+
+        // Normalize the public keys and event IDs to hex
+        let value: String = match name {
+            "p" => PublicKey::parse(value)
+                .map_err(|_| InvalidTagFilter::InvalidValue)?
+                .to_hex(),
+            "e" => EventId::parse(value)
+                .map_err(|_| InvalidTagFilter::InvalidValue)?
+                .to_hex(),
+            _ => value.to_string(),
+        };
+
+        Ok(Self { tag, value })
+    }
+}
+
+/// An error when parsing a [`TagFilter`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InvalidTagFilter {
+    /// Not a single letter tag name
+    InvalidName,
+    /// Missing `=<value>`
+    MissingValue,
+    /// Invalid public key or event ID
+    InvalidValue,
+}
+
+impl fmt::Display for InvalidTagFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName => write!(f, "Tag name must be a single letter"),
+            Self::MissingValue => write!(f, "Expected <name>=<value>"),
+            Self::InvalidValue => write!(f, "Invalid public key or event ID"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidTagFilter {}
+
+/// External identity claim (NIP39), in the `<platform>:<identity>:<proof>` format (i.e., `github:alice:<gist-id>`)
+///
+/// The proof can contain `:` (i.e., a URL).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityClaim {
+    /// Platform
+    pub platform: ExternalIdentity,
+    /// Identity (username) on the platform
+    pub identity: String,
+    /// Proof
+    pub proof: String,
+}
+
+impl FromStr for IdentityClaim {
+    type Err = InvalidIdentityClaim;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (platform, rest) = s
+            .split_once(':')
+            .ok_or(InvalidIdentityClaim::InvalidFormat)?;
+        let (identity, proof) = rest
+            .split_once(':')
+            .ok_or(InvalidIdentityClaim::InvalidFormat)?;
+
+        if identity.is_empty() || proof.is_empty() {
+            return Err(InvalidIdentityClaim::InvalidFormat);
+        }
+
+        Ok(Self {
+            platform: ExternalIdentity::from_str(platform)
+                .map_err(|_| InvalidIdentityClaim::UnknownPlatform)?,
+            identity: identity.to_string(),
+            proof: proof.to_string(),
+        })
+    }
+}
+
+/// An error when parsing an [`IdentityClaim`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InvalidIdentityClaim {
+    /// Not in the `<platform>:<identity>:<proof>` format
+    InvalidFormat,
+    /// Unsupported platform
+    UnknownPlatform,
+}
+
+impl fmt::Display for InvalidIdentityClaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "Expected <platform>:<identity>:<proof>"),
+            Self::UnknownPlatform => write!(
+                f,
+                "Unknown platform (supported: github, twitter, mastodon, telegram)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidIdentityClaim {}
+
 #[cfg(test)]
 mod tests {
-    use super::{split, MismatchedQuotes};
+    use nostr_sdk::prelude::{Alphabet, Keys, ToBech32};
+
+    use super::*;
 
     #[test]
     fn nothing_special() {
@@ -120,4 +253,57 @@ mod tests {
     fn percent_signs() {
         assert_eq!(split("abc '%foo bar%'").unwrap(), ["abc", "%foo bar%"]);
     }
+
+    #[test]
+    fn tag_filter() {
+        assert_eq!(
+            TagFilter::from_str("t=bitcoin").unwrap(),
+            TagFilter {
+                tag: SingleLetterTag::lowercase(Alphabet::T),
+                value: String::from("bitcoin"),
+            }
+        );
+
+        // Only the first `=` is the separator
+        assert_eq!(TagFilter::from_str("r=a=b").unwrap().value, "a=b");
+
+        let public_key = Keys::generate().public_key();
+        let filter =
+            TagFilter::from_str(&format!("p={}", public_key.to_bech32().unwrap())).unwrap();
+        assert_eq!(filter.value, public_key.to_hex());
+
+        assert_eq!(
+            TagFilter::from_str("tt=bitcoin").unwrap_err(),
+            InvalidTagFilter::InvalidName
+        );
+        assert_eq!(
+            TagFilter::from_str("t").unwrap_err(),
+            InvalidTagFilter::MissingValue
+        );
+        assert_eq!(
+            TagFilter::from_str("p=abc").unwrap_err(),
+            InvalidTagFilter::InvalidValue
+        );
+    }
+
+    #[test]
+    fn identity_claim() {
+        assert_eq!(
+            IdentityClaim::from_str("github:alice:https://gist.github.com/alice/abc").unwrap(),
+            IdentityClaim {
+                platform: ExternalIdentity::GitHub,
+                identity: String::from("alice"),
+                proof: String::from("https://gist.github.com/alice/abc"),
+            }
+        );
+
+        assert_eq!(
+            IdentityClaim::from_str("github:alice").unwrap_err(),
+            InvalidIdentityClaim::InvalidFormat
+        );
+        assert_eq!(
+            IdentityClaim::from_str("myspace:alice:proof").unwrap_err(),
+            InvalidIdentityClaim::UnknownPlatform
+        );
+    }
 }
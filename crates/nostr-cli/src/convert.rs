@@ -0,0 +1,219 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Encoding conversions
+
+use nostr_sdk::prelude::*;
+
+use crate::cli::EncodingTarget;
+
+/// Decoded input
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entity {
+    /// Hex: may be a public key or an event ID, according to the target
+    Hex(String),
+    Bech32(Nip19),
+    /// `<kind>:<pubkey>:<d-tag>` coordinate
+    Coordinate(Coordinate),
+}
+
+impl Entity {
+    fn parse(input: &str) -> Result<Self> {
+        let input: &str = input.trim();
+        let input: &str = input.strip_prefix("nostr:").unwrap_or(input);
+
+        if let Ok(nip19) = Nip19::from_bech32(input) {
+            return Ok(Self::Bech32(nip19));
+        }
+
+        if let Ok(coordinate) = Coordinate::from_kpi_format(input) {
+            return Ok(Self::Coordinate(coordinate));
+        }
+
+        if input.len() == 64 && input.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(Self::Hex(input.to_lowercase()));
+        }
+
+        Err(format!("Unknown encoding: {input}").into())
+    }
+
+    fn public_key(&self) -> Result<PublicKey> {
+        match self {
+            Self::Hex(hex) => Ok(PublicKey::from_hex(hex)?),
+            Self::Bech32(Nip19::Pubkey(public_key)) => Ok(*public_key),
+            Self::Bech32(Nip19::Profile(profile)) => Ok(profile.public_key),
+            _ => Err("Not a public key".into()),
+        }
+    }
+
+    fn event_id(&self) -> Result<EventId> {
+        match self {
+            Self::Hex(hex) => Ok(EventId::from_hex(hex)?),
+            Self::Bech32(Nip19::EventId(id)) => Ok(*id),
+            Self::Bech32(Nip19::Event(event)) => Ok(event.event_id),
+            _ => Err("Not an event ID".into()),
+        }
+    }
+
+    fn coordinate(&self) -> Result<Coordinate> {
+        match self {
+            Self::Coordinate(coordinate) => Ok(coordinate.clone()),
+            Self::Bech32(Nip19::Coordinate(coordinate)) => Ok(coordinate.coordinate.clone()),
+            _ => Err("Not a coordinate".into()),
+        }
+    }
+
+    /// Relay hints of the input
+    fn relays(&self) -> Vec<RelayUrl> {
+        match self {
+            Self::Bech32(Nip19::Profile(profile)) => profile.relays.clone(),
+            Self::Bech32(Nip19::Event(event)) => event.relays.clone(),
+            Self::Bech32(Nip19::Coordinate(coordinate)) => coordinate.relays.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Conversion options
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// Relay hints (if empty, the ones of the input are kept)
+    pub relays: Vec<RelayUrl>,
+    /// Event author (only for `nevent`)
+    pub author: Option<PublicKey>,
+    /// Event kind (only for `nevent`)
+    pub kind: Option<Kind>,
+}
+
+/// Convert between hex, bech32 and NIP21 URI representations
+///
+/// A hex input is interpreted as a public key or an event ID, according to the target.
+pub fn convert(input: &str, to: EncodingTarget, opts: ConvertOptions) -> Result<String> {
+    let entity: Entity = Entity::parse(input)?;
+
+    let relays: Vec<RelayUrl> = if opts.relays.is_empty() {
+        entity.relays()
+    } else {
+        opts.relays
+    };
+
+    match to {
+        EncodingTarget::Hex => match &entity {
+            Entity::Hex(hex) => Ok(hex.clone()),
+            Entity::Coordinate(..) | Entity::Bech32(Nip19::Coordinate(..)) => {
+                Ok(entity.coordinate()?.to_string())
+            }
+            Entity::Bech32(Nip19::EventId(..) | Nip19::Event(..)) => {
+                Ok(entity.event_id()?.to_hex())
+            }
+            Entity::Bech32(Nip19::Pubkey(..) | Nip19::Profile(..)) => {
+                Ok(entity.public_key()?.to_hex())
+            }
+            Entity::Bech32(..) => Err("Secret keys aren't supported".into()),
+        },
+        EncodingTarget::Npub => Ok(entity.public_key()?.to_bech32()?),
+        EncodingTarget::Nprofile => {
+            let profile = Nip19Profile::new(entity.public_key()?, relays);
+            Ok(profile.to_bech32()?)
+        }
+        EncodingTarget::Note => Ok(entity.event_id()?.to_bech32()?),
+        EncodingTarget::Nevent => {
+            let mut event = Nip19Event::new(entity.event_id()?).relays(relays);
+
+            // Keep the author and the kind of the input, if not overwritten
+            if let Entity::Bech32(Nip19::Event(input)) = &entity {
+                event.author = input.author;
+                event.kind = input.kind;
+            }
+
+            if let Some(author) = opts.author {
+                event = event.author(author);
+            }
+
+            if let Some(kind) = opts.kind {
+                event = event.kind(kind);
+            }
+
+            Ok(event.to_bech32()?)
+        }
+        EncodingTarget::Naddr => {
+            let coordinate = Nip19Coordinate::new(entity.coordinate()?, relays)?;
+            Ok(coordinate.to_bech32()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4";
+    const RELAY: &str = "wss://relay.damus.io";
+
+    #[test]
+    fn test_convert_public_key() {
+        let npub: String =
+            convert(PUBLIC_KEY, EncodingTarget::Npub, ConvertOptions::default()).unwrap();
+        assert!(npub.starts_with("npub1"));
+        assert_eq!(
+            convert(&npub, EncodingTarget::Hex, ConvertOptions::default()).unwrap(),
+            PUBLIC_KEY
+        );
+
+        let opts = ConvertOptions {
+            relays: vec![RelayUrl::parse(RELAY).unwrap()],
+            ..Default::default()
+        };
+        let nprofile: String = convert(&npub, EncodingTarget::Nprofile, opts).unwrap();
+        let profile = Nip19Profile::from_bech32(&nprofile).unwrap();
+        assert_eq!(profile.public_key.to_hex(), PUBLIC_KEY);
+        assert_eq!(profile.relays, vec![RelayUrl::parse(RELAY).unwrap()]);
+
+        // Relay hints are kept, also from a NIP21 URI
+        let uri: String = format!("nostr:{nprofile}");
+        assert_eq!(
+            convert(&uri, EncodingTarget::Nprofile, ConvertOptions::default()).unwrap(),
+            nprofile
+        );
+    }
+
+    #[test]
+    fn test_convert_event_id() {
+        let id = EventId::from_hex(PUBLIC_KEY).unwrap();
+        let author = PublicKey::from_hex(PUBLIC_KEY).unwrap();
+
+        let opts = ConvertOptions {
+            relays: vec![RelayUrl::parse(RELAY).unwrap()],
+            author: Some(author),
+            kind: Some(Kind::TextNote),
+        };
+        let nevent: String = convert(PUBLIC_KEY, EncodingTarget::Nevent, opts).unwrap();
+        let event = Nip19Event::from_bech32(&nevent).unwrap();
+        assert_eq!(event.event_id, id);
+        assert_eq!(event.author, Some(author));
+        assert_eq!(event.kind, Some(Kind::TextNote));
+        assert_eq!(event.relays, vec![RelayUrl::parse(RELAY).unwrap()]);
+
+        let note: String =
+            convert(&nevent, EncodingTarget::Note, ConvertOptions::default()).unwrap();
+        assert_eq!(note, id.to_bech32().unwrap());
+
+        // An event isn't a public key
+        assert!(convert(&note, EncodingTarget::Npub, ConvertOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_convert_coordinate() {
+        let kpi: String = format!("30023:{PUBLIC_KEY}:article");
+        let naddr: String =
+            convert(&kpi, EncodingTarget::Naddr, ConvertOptions::default()).unwrap();
+        assert!(naddr.starts_with("naddr1"));
+        assert_eq!(
+            convert(&naddr, EncodingTarget::Hex, ConvertOptions::default()).unwrap(),
+            kpi
+        );
+
+        assert!(convert("invalid", EncodingTarget::Hex, ConvertOptions::default()).is_err());
+    }
+}
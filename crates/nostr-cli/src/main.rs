@@ -2,26 +2,58 @@
 // Copyright (c) 2023-2025 Rust Nostr Developers
 // Distributed under the MIT software license
 
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, BufWriter, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use clap::Parser;
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use indicatif::{HumanBytes, ProgressBar, ProgressState, ProgressStyle};
 use nostr_connect::prelude::*;
 use nostr_relay_builder::prelude::*;
+use nostr_sdk::nostr::key::vanity::VanityFormat;
 use nostr_sdk::prelude::*;
 use rustyline::error::ReadlineError;
 use rustyline::history::FileHistory;
 use rustyline::{Config, Editor};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time::Instant;
 
+mod bunker;
 mod cli;
+mod convert;
+mod render;
 mod util;
-
-use self::cli::{io, parser, Cli, Command, ShellCommand, ShellCommandDatabase};
+mod vanity;
+mod vectors;
+mod zap;
+
+use self::bunker::BunkerPolicy;
+use self::cli::parser::TagFilter;
+use self::cli::{
+    io, parser, Cli, Command, DevCommand, EncryptionVersion, ShellCommand, ShellCommandDatabase,
+    ShellCommandProfile, ShellCommandRelay, ShellQuerySort,
+};
+use self::convert::ConvertOptions;
+use self::util::{EventVerification, ImportReport, MetadataChanges, SyncReport, Thread};
+
+const SCAN_BATCH_SIZE: usize = 10_000;
+const IMPORT_PROGRESS_INTERVAL: usize = 10_000;
+const MAX_HISTORY_SIZE: usize = 2000;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+const GOSSIP_TIMEOUT: Duration = Duration::from_secs(10);
+const THREAD_TIMEOUT: Duration = Duration::from_secs(10);
+const CONTACT_LIST_TIMEOUT: Duration = Duration::from_secs(10);
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+const DELETE_TIMEOUT: Duration = Duration::from_secs(10);
+const BROADCAST_TIMEOUT: Duration = Duration::from_secs(10);
+const REACT_TIMEOUT: Duration = Duration::from_secs(10);
+const GROUP_TIMEOUT: Duration = Duration::from_secs(10);
+const NIP46_TIMEOUT: Duration = Duration::from_secs(120);
 
 #[tokio::main]
 async fn main() {
@@ -34,7 +66,16 @@ async fn run() -> Result<()> {
     let args = Cli::parse();
 
     match args.command {
-        Command::Shell { relays } => {
+        Command::Shell {
+            relays,
+            tor,
+            force_tor,
+            proxy,
+            bunker,
+            memory,
+            max_events,
+            max_bytes,
+        } => {
             // Get data dir
             let data_dir: PathBuf = dirs::data_dir().expect("Can't find data directory");
 
@@ -42,17 +83,33 @@ async fn run() -> Result<()> {
             let nostr_cli_dir: PathBuf = data_dir.join("rust-nostr/cli");
             let db_path = nostr_cli_dir.join("data/lmdb");
             let history_path = nostr_cli_dir.join(".shell_history");
+            let tor_path = nostr_cli_dir.join("tor");
 
             // Create main dir if not exists
             fs::create_dir_all(nostr_cli_dir)?;
 
             // Open database
-            let db: NostrLMDB = NostrLMDB::open(db_path)?;
+            let db: Arc<dyn NostrDatabase> = if memory {
+                Arc::new(memory_database(max_events, max_bytes))
+            } else {
+                Arc::new(NostrLMDB::open(db_path)?)
+            };
 
             // Configure connection
-            let connection: Connection = Connection::new()
-                .target(ConnectionTarget::Onion)
-                .embedded_tor();
+            let connection: Connection = if let Some(addr) = proxy {
+                Connection::new().proxy(addr)
+            } else if tor {
+                let target: ConnectionTarget = if force_tor {
+                    ConnectionTarget::All
+                } else {
+                    ConnectionTarget::Onion
+                };
+                Connection::new()
+                    .target(target)
+                    .embedded_tor_with_path(tor_path)
+            } else {
+                Connection::new()
+            };
 
             // Build client
             let opts: Options = Options::new().connection(connection);
@@ -65,7 +122,20 @@ async fn run() -> Result<()> {
 
             client.connect().await;
 
-            let config = Config::builder().max_history_size(2000)?.build();
+            // Connect to the remote signer
+            if let Some(uri) = bunker {
+                let signer = NostrConnect::from_uri(uri, Keys::generate(), NIP46_TIMEOUT, None)?;
+                let public_key: PublicKey = signer.get_public_key().await?;
+                client.set_signer(signer).await;
+
+                println!("Signing with remote signer: {}", public_key.to_bech32()?);
+            }
+
+            let config = Config::builder()
+                .max_history_size(MAX_HISTORY_SIZE)?
+                .history_ignore_dups(true)?
+                .history_ignore_space(true)
+                .build();
             let history = FileHistory::with_config(config);
             let rl: &mut Editor<(), FileHistory> = &mut Editor::with_history(config, history)?;
 
@@ -76,8 +146,10 @@ async fn run() -> Result<()> {
                 let readline = rl.readline("nostr> ");
                 match readline {
                     Ok(line) => {
-                        // Add to history
-                        rl.add_history_entry(line.as_str())?;
+                        // Add to history, skipping the lines with secret keys
+                        if !io::is_sensitive(&line) {
+                            rl.add_history_entry(line.as_str())?;
+                        }
 
                         // Split command line
                         let mut vec: Vec<String> = parser::split(&line)?;
@@ -113,13 +185,26 @@ async fn run() -> Result<()> {
 
             Ok(())
         }
-        Command::Serve { port } => {
+        Command::Serve {
+            port,
+            db,
+            max_events,
+            max_bytes,
+        } => {
             let mut builder = RelayBuilder::default();
 
             if let Some(port) = port {
                 builder = builder.port(port);
             }
 
+            if let Some(path) = db {
+                let db: NostrLMDB = NostrLMDB::open(&path)?;
+                builder = builder.database(db);
+                println!("Events persisted at {}", path.display());
+            } else if max_events.is_some() || max_bytes.is_some() {
+                builder = builder.database(memory_database(max_events, max_bytes));
+            }
+
             let relay = LocalRelay::run(builder).await?;
 
             println!("Relay running at {}", relay.url());
@@ -128,7 +213,11 @@ async fn run() -> Result<()> {
                 tokio::time::sleep(Duration::from_secs(60)).await
             }
         }
-        Command::Bunker => {
+        Command::Bunker {
+            auto_approve_kinds,
+            allowed_clients,
+            no_prompt,
+        } => {
             // Ask keys
             let keys = NostrConnectKeys {
                 signer: io::get_keys("Signer Keys")?,
@@ -152,17 +241,62 @@ async fn run() -> Result<()> {
             println!("\nBunker URI: {uri}\n");
 
             // Serve signer
-            signer.serve(CustomActions).await?;
+            let policy = BunkerPolicy {
+                auto_approve_kinds: auto_approve_kinds.into_iter().collect(),
+                allowed_clients: allowed_clients.into_iter().collect(),
+                no_prompt,
+            };
+            signer.serve(policy).await?;
 
             Ok(())
         }
+        Command::Convert {
+            input,
+            to,
+            relays,
+            author,
+            kind,
+        } => {
+            let opts = ConvertOptions {
+                relays,
+                author,
+                kind,
+            };
+            println!("{}", convert::convert(&input, to, opts)?);
+            Ok(())
+        }
+        Command::Dev { command } => match command {
+            DevCommand::TestVectors { count, seed } => {
+                for event in vectors::generate(count, seed)?.into_iter() {
+                    println!("{}", event.as_json());
+                }
+
+                Ok(())
+            }
+        },
     }
 }
 
 async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
     match command {
-        ShellCommand::Generate => {
-            let keys: Keys = Keys::generate();
+        ShellCommand::Generate { vanity, vanity_hex } => {
+            let prefix: Option<(String, VanityFormat)> = match (vanity, vanity_hex) {
+                (Some(prefix), ..) => Some((prefix, VanityFormat::Bech32)),
+                (None, Some(prefix)) => Some((prefix, VanityFormat::Hex)),
+                (None, None) => None,
+            };
+
+            let keys: Keys = match prefix {
+                Some((prefix, format)) => match vanity::mine(prefix, format).await? {
+                    Some(keys) => keys,
+                    None => {
+                        println!("Aborted.");
+                        return Ok(());
+                    }
+                },
+                None => Keys::generate(),
+            };
+
             println!("Secret key: {}", keys.secret_key().to_bech32()?);
             println!("Public key: {}", keys.public_key().to_bech32()?);
             Ok(())
@@ -171,6 +305,8 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
             public_key,
             relays,
             direction,
+            dry_run,
+            timeout,
         } => {
             let current_relays = client.relays().await;
 
@@ -196,29 +332,72 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
             let filter: Filter = Filter::default().author(public_key);
             let direction: SyncDirection = direction.into();
             let (tx, mut rx) = SyncProgress::channel();
-            let opts: SyncOptions = SyncOptions::default().direction(direction).progress(tx);
+            let mut opts: SyncOptions = SyncOptions::default().direction(direction).progress(tx);
+
+            if dry_run {
+                opts = opts.dry_run();
+            }
+
+            if let Some(timeout) = timeout {
+                opts = opts.initial_timeout(Duration::from_secs(timeout));
+            }
 
             tokio::spawn(async move {
                 let pb = ProgressBar::new(0);
-                let style = ProgressStyle::with_template("[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({percent_precise}%) - ETA: {eta}")
+                let style = ProgressStyle::with_template("[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({percent_precise}%) - ETA: {eta} - {msg}")
                     .unwrap()
                     .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
                     .progress_chars("#>-");
                 pb.set_style(style);
 
                 while rx.changed().await.is_ok() {
-                    let SyncProgress { total, current } = *rx.borrow_and_update();
-                    pb.set_length(total);
-                    pb.set_position(current);
+                    let progress: SyncProgress = *rx.borrow_and_update();
+                    pb.set_length(progress.total);
+                    pb.set_position(progress.current);
+                    pb.set_message(format!(
+                        "{} rounds, {} differences, {} sent, {} received",
+                        progress.rounds,
+                        progress.differences,
+                        HumanBytes(progress.bytes_sent),
+                        HumanBytes(progress.bytes_received)
+                    ));
                 }
             });
 
             // Reconcile
             let output: Output<Reconciliation> = client.sync_with(list, filter, &opts).await?;
 
-            println!("Sync terminated:");
-            println!("- Sent {} events", output.sent.len());
-            println!("- Received {} events", output.received.len());
+            if dry_run {
+                println!("Dry run:");
+
+                if matches!(direction, SyncDirection::Up | SyncDirection::Both) {
+                    println!("- {} events would be sent", output.local.len());
+                    for id in output.local.iter() {
+                        println!("  {id}");
+                    }
+                }
+
+                if matches!(direction, SyncDirection::Down | SyncDirection::Both) {
+                    println!("- {} events would be received", output.remote.len());
+                    for id in output.remote.iter() {
+                        println!("  {id}");
+                    }
+                }
+
+                println!("- {} events exist on both sides", output.common.len());
+            } else {
+                let report: SyncReport = SyncReport::new(&output, direction);
+
+                println!("Sync terminated:");
+                println!("- Received {} events", report.received);
+                println!("- Sent {} events", report.sent);
+                println!("- {} events existed on both sides", report.common);
+                println!("- {} events failed to reconcile", report.failed);
+            }
+
+            for (url, e) in output.failed.iter() {
+                println!("- {url}: {e}");
+            }
 
             // Remove relays
             for url in relays.into_iter() {
@@ -229,21 +408,243 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
 
             Ok(())
         }
+        ShellCommand::Publish {
+            content,
+            kind,
+            tag,
+            pow,
+        } => {
+            // Check if there is at least a connected relay
+            let relays = client.relays().await;
+            if !relays.values().any(|relay| relay.is_connected()) {
+                return Err("No connected relays".into());
+            }
+
+            // Load keys, if not already loaded in this session
+            load_keys(client).await?;
+
+            // Parse tags
+            let mut tags: Vec<Tag> = Vec::with_capacity(tag.len());
+            for tag in tag.iter() {
+                let tag: Vec<String> = parser::split(tag)?;
+                tags.push(Tag::parse(tag)?);
+            }
+
+            // Compose builder
+            let mut builder: EventBuilder = EventBuilder::new(kind, content).tags(tags);
+
+            if let Some(difficulty) = pow {
+                builder = builder.pow(difficulty);
+            }
+
+            // Sign and send
+            let output: Output<EventId> = client.send_event_builder(builder).await?;
+
+            println!("Event ID: {}", output.id().to_bech32()?);
+
+            for url in output.success.iter() {
+                println!("- {url}: OK");
+            }
+
+            for (url, e) in output.failed.iter() {
+                println!("- {url}: {e}");
+            }
+
+            Ok(())
+        }
+        ShellCommand::Delete { ids, reason } => {
+            // Load keys, if not already loaded in this session
+            load_keys(client).await?;
+
+            let signer: Arc<dyn NostrSigner> = client.signer().await?;
+            let public_key: PublicKey = signer.get_public_key().await?;
+
+            // Fetch the targets, to check their author
+            let filter: Filter = Filter::new().ids(ids.iter().copied());
+            let mut events: Events = client.database().query(filter.clone()).await?;
+
+            if events.len() < ids.len() {
+                let remote: Events = client.fetch_events(filter, DELETE_TIMEOUT).await?;
+                events = events.merge(remote);
+            }
+
+            let check: util::DeletionCheck =
+                util::check_deletion_targets(&ids, events.iter(), &public_key);
+
+            for id in check.foreign.iter() {
+                println!("Warning: {id} isn't authored by the session key, relays will reject its deletion");
+            }
+
+            for id in check.not_found.iter() {
+                println!("Warning: {id} not found, impossible to check its author");
+            }
+
+            // Compose deletion request
+            let mut request: EventDeletionRequest = EventDeletionRequest::new().ids(ids);
+
+            if let Some(reason) = reason {
+                request = request.reason(reason);
+            }
+
+            // Sign and send
+            let output: Output<EventId> = client
+                .send_event_builder(EventBuilder::delete(request))
+                .await?;
+
+            println!("Event ID: {}", output.id().to_bech32()?);
+
+            for url in output.success.iter() {
+                println!("- {url}: OK");
+            }
+
+            for (url, e) in output.failed.iter() {
+                println!("- {url}: {e}");
+            }
+
+            Ok(())
+        }
+        ShellCommand::React {
+            id,
+            content,
+            emoji_url,
+        } => {
+            // Load keys, if not already loaded in this session
+            load_keys(client).await?;
+
+            // Get the target, to tag its author
+            let event: Event = match client.database().event_by_id(&id).await? {
+                Some(event) => event,
+                None => client
+                    .fetch_events(Filter::new().id(id), REACT_TIMEOUT)
+                    .await?
+                    .into_iter()
+                    .find(|event| event.id == id)
+                    .ok_or(format!("Event {id} not found"))?,
+            };
+
+            let builder: EventBuilder = match emoji_url {
+                Some(url) => {
+                    let shortcode: &str = util::parse_emoji_shortcode(&content)
+                        .ok_or(format!("Invalid emoji shortcode: {content}"))?;
+                    EventBuilder::emoji_reaction(&event, shortcode, url)
+                }
+                None => EventBuilder::reaction(&event, content),
+            };
+
+            let output: Output<EventId> = client.send_event_builder(builder).await?;
+
+            println!("Event ID: {}", output.id().to_bech32()?);
+
+            for url in output.success.iter() {
+                println!("- {url}: OK");
+            }
+
+            for (url, e) in output.failed.iter() {
+                println!("- {url}: {e}");
+            }
+
+            Ok(())
+        }
+        ShellCommand::Broadcast { id, from, to } => {
+            // Transiently add the relays not already in the session
+            let mut added: Vec<RelayUrl> = Vec::new();
+            for url in from.iter().chain(to.iter()) {
+                if client.add_relay(url).await? {
+                    added.push(url.clone());
+                }
+            }
+
+            let res = broadcast(client, id, &from, &to).await;
+
+            // Disconnect and remove the transient relays
+            for url in added.into_iter() {
+                client.force_remove_relay(url).await?;
+            }
+
+            let output: Output<EventId> = res?;
+
+            println!("Event ID: {}", output.id().to_bech32()?);
+
+            for url in output.success.iter() {
+                println!("- {url}: OK");
+            }
+
+            for (url, e) in output.failed.iter() {
+                println!("- {url}: {e}");
+            }
+
+            Ok(())
+        }
+        ShellCommand::Verify { file, json } => {
+            let input: String = match (file, json) {
+                (Some(path), ..) => fs::read_to_string(path)?,
+                (None, Some(json)) => json,
+                (None, None) => return Err("Specify the event JSON or a file".into()),
+            };
+
+            let mut invalid: usize = 0;
+            let events: Vec<String> = util::split_events_json(&input)?;
+
+            for (i, json) in events.iter().enumerate() {
+                let res: EventVerification = util::verify_event_json(json);
+
+                if !res.is_valid() {
+                    invalid += 1;
+                }
+
+                println!("#{i} {res}");
+            }
+
+            println!("\n{} valid, {invalid} invalid", events.len() - invalid);
+
+            Ok(())
+        }
         ShellCommand::Query {
             id,
             author,
             kind,
             identifier,
             search,
+            referenced_events,
+            mentions,
+            tags,
+            exclude_tags,
+            exclude_replies,
+            sort,
             since,
             until,
             limit,
+            after,
             database,
+            timeout,
             print,
             json,
+            no_color,
+            output,
+            force,
         } => {
             let db = client.database();
 
+            // Check output file before querying
+            if let Some(path) = &output {
+                if path.exists() && !force {
+                    return Err(format!(
+                        "{} already exists, use --force to overwrite it",
+                        path.display()
+                    )
+                    .into());
+                }
+            }
+
+            // Get the cursor event
+            let cursor: Option<Event> = match after {
+                Some(id) => match db.event_by_id(&id).await? {
+                    Some(event) => Some(event),
+                    None => return Err(format!("Page token {id} not found in database").into()),
+                },
+                None => None,
+            };
+
             let mut filter = Filter::new();
 
             if let Some(id) = id {
@@ -262,10 +663,22 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
                 filter = filter.identifier(identifier);
             }
 
-            if let Some(search) = search {
+            if let Some(search) = &search {
                 filter = filter.search(search);
             }
 
+            if !referenced_events.is_empty() {
+                filter = filter.events(referenced_events);
+            }
+
+            if !mentions.is_empty() {
+                filter = filter.pubkeys(mentions);
+            }
+
+            for TagFilter { tag, value } in tags.into_iter() {
+                filter = filter.custom_tag(tag, value);
+            }
+
             if let Some(since) = since {
                 filter = filter.since(since);
             }
@@ -274,39 +687,630 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
                 filter = filter.until(until);
             }
 
+            if let Some(cursor) = &cursor {
+                // Walk backward from the cursor
+                let until: Timestamp = match filter.until {
+                    Some(until) => until.min(cursor.created_at),
+                    None => cursor.created_at,
+                };
+                filter = filter.until(until);
+            }
+
             if let Some(limit) = limit {
-                filter = filter.limit(limit);
+                // Events sharing the cursor timestamp may come before it,
+                // so must be fetched in addition to the limit and then skipped.
+                let extra: usize = match &cursor {
+                    Some(cursor) => {
+                        let mut same_ts: Filter = filter
+                            .clone()
+                            .since(cursor.created_at)
+                            .until(cursor.created_at);
+                        same_ts.limit = None;
+                        db.count(same_ts).await?
+                    }
+                    None => 0,
+                };
+                filter = filter.limit(limit + extra);
             }
 
+            // Without a limit, a pagination or a sort, stream the JSONL results to the file,
+            // without buffering them: newest first from the database, as they arrive from relays.
+            let streaming: bool = json
+                && !print
+                && limit.is_none()
+                && cursor.is_none()
+                && matches!(sort, ShellQuerySort::CreatedAtDesc);
+
             if filter.is_empty() {
                 eprintln!("Filters empty!");
-            } else if database {
-                // Query database
+            } else if let Some(path) = output.as_ref().filter(|_| streaming) {
+                let now = Instant::now();
+                let mut writer: BufWriter<File> = create_output_file(path)?;
+                let mut written: usize = 0;
+                let mut excluded: usize = 0;
+
+                let mut write = |events: Vec<Event>| -> Result<()> {
+                    let total: usize = events.len();
+                    let events: Vec<Event> =
+                        util::exclude_events(events, &exclude_tags, exclude_replies);
+                    excluded += total - events.len();
+                    written += events.len();
+                    util::write_events(&events, true, &mut writer)?;
+                    Ok(())
+                };
+
+                if database {
+                    // Query database, in batches
+                    util::scan_events(db, filter, SCAN_BATCH_SIZE, &mut write).await?;
+                } else {
+                    // Query relays
+                    let timeout: Duration = timeout.map_or(QUERY_TIMEOUT, Duration::from_secs);
+                    let mut stream = client.stream_events(filter, timeout).await?;
+                    while let Some(event) = stream.next().await {
+                        write(vec![event])?;
+                    }
+                }
+
+                writer.flush()?;
+
+                println!(
+                    "{written} results{} written to {} in {:.2} sec",
+                    if excluded > 0 {
+                        format!(" ({excluded} excluded)")
+                    } else {
+                        String::new()
+                    },
+                    path.display(),
+                    now.elapsed().as_secs_f64()
+                );
+            } else {
                 let now = Instant::now();
-                let events = db.query(filter).await?;
+                let events: Events = if database {
+                    // Query database
+                    db.query(filter).await?
+                } else {
+                    // Query relays
+                    let timeout: Duration = timeout.map_or(QUERY_TIMEOUT, Duration::from_secs);
+                    client.fetch_events(filter, timeout).await?
+                };
+                let events: Vec<Event> = util::paginate(events, cursor.as_ref(), limit);
+
+                // Token for the next page, before the exclusions to not skip events
+                let next: Option<EventId> = match (limit, events.last()) {
+                    (Some(limit), Some(last)) if events.len() >= limit => Some(last.id),
+                    _ => None,
+                };
+
+                // Exclude events
+                let total: usize = events.len();
+                let events: Vec<Event> =
+                    util::exclude_events(events, &exclude_tags, exclude_replies);
+                let excluded: usize = total - events.len();
 
                 let duration = now.elapsed();
                 println!(
-                    "{} results in {}",
+                    "{} results{} in {}",
                     events.len(),
+                    if excluded > 0 {
+                        format!(" ({excluded} excluded)")
+                    } else {
+                        String::new()
+                    },
                     if duration.as_secs() == 0 {
                         format!("{:.6} ms", duration.as_secs_f64() * 1000.0)
                     } else {
                         format!("{:.2} sec", duration.as_secs_f64())
                     }
                 );
+
+                // Sort events
+                let events: Vec<Event> = util::sort_events(events, sort, search.as_deref());
+
+                if let Some(path) = output {
+                    // Write events
+                    let mut writer: BufWriter<File> = create_output_file(&path)?;
+                    util::write_events(&events, json, &mut writer)?;
+                    writer.flush()?;
+
+                    println!("Results written to {}", path.display());
+                }
+
                 if print {
-                    // Print events
-                    util::print_events(events, json);
+                    if json {
+                        util::print_events(events, json);
+                    } else {
+                        // Resolve the author names from the cached metadata
+                        let authors: HashSet<PublicKey> = events.iter().map(|e| e.pubkey).collect();
+                        let mut metadata: Vec<(PublicKey, Metadata)> =
+                            Vec::with_capacity(authors.len());
+                        for author in authors.into_iter() {
+                            if let Some(m) = db.metadata(author).await? {
+                                metadata.push((author, m));
+                            }
+                        }
+                        let names = render::author_names(metadata.iter().map(|(p, m)| (*p, m)));
+
+                        render::print_events(&events, &names, render::use_color(no_color));
+                    }
+                }
+
+                if let Some(next) = next {
+                    println!("Next page: --after {}", next.to_bech32()?);
+                }
+            }
+
+            Ok(())
+        }
+        ShellCommand::Encrypt {
+            receiver,
+            content,
+            nip,
+        } => {
+            load_keys(client).await?;
+
+            let signer: Arc<dyn NostrSigner> = client.signer().await?;
+            let ciphertext: String = match nip {
+                EncryptionVersion::Nip04 => signer.nip04_encrypt(&receiver, &content).await?,
+                EncryptionVersion::Nip44 => signer.nip44_encrypt(&receiver, &content).await?,
+            };
+
+            println!("{ciphertext}");
+
+            Ok(())
+        }
+        ShellCommand::Decrypt {
+            sender,
+            ciphertext,
+            nip,
+        } => {
+            load_keys(client).await?;
+
+            // Try only the requested scheme or all of them
+            let versions: Vec<EncryptionVersion> = match nip {
+                Some(nip) => vec![nip],
+                None => vec![EncryptionVersion::Nip44, EncryptionVersion::Nip04],
+            };
+
+            let signer: Arc<dyn NostrSigner> = client.signer().await?;
+
+            for version in versions.into_iter() {
+                let res = match version {
+                    EncryptionVersion::Nip04 => signer.nip04_decrypt(&sender, &ciphertext).await,
+                    EncryptionVersion::Nip44 => signer.nip44_decrypt(&sender, &ciphertext).await,
+                };
+
+                match res {
+                    Ok(plaintext) => {
+                        println!("Decrypted with {version}:\n{plaintext}");
+                        return Ok(());
+                    }
+                    Err(e) => eprintln!("{version} decryption failed: {e}"),
                 }
+            }
+
+            Err("Impossible to decrypt the message".into())
+        }
+        ShellCommand::Gossip {
+            public_key,
+            kind,
+            limit,
+            json,
+        } => {
+            // Fetch NIP-65 relay list from the session relays
+            let filter: Filter = Filter::new()
+                .author(public_key)
+                .kind(Kind::RelayList)
+                .limit(1);
+            let relay_list: Option<Event> = client
+                .fetch_events(filter, GOSSIP_TIMEOUT)
+                .await?
+                .first_owned();
+
+            let write_relays: Vec<RelayUrl> = match &relay_list {
+                Some(event) => util::write_relays(event),
+                None => Vec::new(),
+            };
+
+            let filter: Filter = Filter::new()
+                .author(public_key)
+                .kind(kind.unwrap_or(Kind::TextNote))
+                .limit(limit);
+
+            let events: Events = if write_relays.is_empty() {
+                println!("Relay list not found, falling back to session relays");
+                client.fetch_events(filter, GOSSIP_TIMEOUT).await?
             } else {
-                // Query relays
+                println!("Write relays:");
+
+                // Transiently add the relays not already in the session
+                let mut added: Vec<RelayUrl> = Vec::new();
+                for url in write_relays.iter() {
+                    if client.add_relay(url).await? {
+                        added.push(url.clone());
+                    }
+
+                    match client.try_connect_relay(url, GOSSIP_TIMEOUT).await {
+                        Ok(()) => println!("- {url}: connected"),
+                        Err(e) => println!("- {url}: {e}"),
+                    }
+                }
+
+                let res = client
+                    .fetch_events_from(&write_relays, filter, GOSSIP_TIMEOUT)
+                    .await;
+
+                // Disconnect and remove the transient relays
+                for url in added.into_iter() {
+                    client.force_remove_relay(url).await?;
+                }
+
+                res?
+            };
+
+            util::print_events(events, json);
+
+            Ok(())
+        }
+        ShellCommand::Follow {
+            mut public_keys,
+            from_file,
+        } => {
+            if let Some(path) = from_file {
+                let file: File = File::open(path)?;
+                public_keys.extend(util::read_public_keys(BufReader::new(file))?);
+            }
+
+            if public_keys.is_empty() {
+                return Err("No public keys to follow".into());
+            }
+
+            // Load keys, if not already loaded in this session
+            load_keys(client).await?;
+
+            let signer: Arc<dyn NostrSigner> = client.signer().await?;
+            let public_key: PublicKey = signer.get_public_key().await?;
+
+            // Fetch the current contact list
+            let filter: Filter = Filter::new()
+                .author(public_key)
+                .kind(Kind::ContactList)
+                .limit(1);
+            let contact_list: Option<Event> = client
+                .fetch_events(filter, CONTACT_LIST_TIMEOUT)
+                .await?
+                .first_owned();
+
+            if contact_list.is_none() {
+                println!("Contact list not found, creating a new one");
+            }
+
+            let (builder, added) = util::merge_contact_list(contact_list.as_ref(), public_keys);
+
+            if added == 0 {
+                println!("Already following all the public keys");
+                return Ok(());
+            }
+
+            let output: Output<EventId> = client.send_event_builder(builder).await?;
+
+            println!("Followed {added} public keys");
+            println!("Event ID: {}", output.id().to_bech32()?);
+
+            for url in output.success.iter() {
+                println!("- {url}: OK");
+            }
+
+            for (url, e) in output.failed.iter() {
+                println!("- {url}: {e}");
+            }
+
+            Ok(())
+        }
+        ShellCommand::Group { relay, id, limit } => {
+            // Transiently add the group relay, if not already in the session
+            let added: bool = client.add_relay(&relay).await?;
+
+            let res = stream_group(client, &relay, &id, limit).await;
+
+            if added {
+                client.force_remove_relay(&relay).await?;
+            }
+
+            res
+        }
+        ShellCommand::Thread { id, depth } => {
+            // Fetch root event
+            let root: Event = match client.database().event_by_id(&id).await? {
+                Some(event) => event,
+                None => client
+                    .fetch_events(Filter::new().id(id), THREAD_TIMEOUT)
+                    .await?
+                    .first_owned()
+                    .ok_or(format!("Event {id} not found"))?,
+            };
+
+            let mut thread: Thread = Thread::new(root);
+
+            // Fetch replies, level by level
+            let mut level: Vec<EventId> = vec![id];
+            let mut current_depth: usize = 0;
+
+            while !level.is_empty() && depth.is_none_or(|max| current_depth < max) {
+                let filter: Filter = Filter::new()
+                    .kind(Kind::TextNote)
+                    .events(level.iter().copied());
+                let events: Events = client.fetch_events(filter, THREAD_TIMEOUT).await?;
+
+                let mut next: Vec<EventId> = Vec::new();
+
+                for event in events.into_iter() {
+                    // Attach to the first e-tagged event of the current level, if the NIP-10 parent is unknown
+                    let fallback: Option<EventId> = event
+                        .tags
+                        .event_ids()
+                        .copied()
+                        .find(|id| level.contains(id));
+
+                    let event_id: EventId = event.id;
+                    if let Some(fallback) = fallback {
+                        if thread.add_reply(event, fallback) {
+                            next.push(event_id);
+                        }
+                    }
+                }
+
+                level = next;
+                current_depth += 1;
+            }
+
+            thread.print(&id);
+
+            Ok(())
+        }
+        ShellCommand::Zap {
+            public_key,
+            amount_msat,
+            event,
+            comment,
+        } => {
+            // Load keys, if not already loaded in this session
+            load_keys(client).await?;
+
+            // Fetch recipient metadata
+            let metadata: Metadata = client
+                .fetch_metadata(public_key, METADATA_TIMEOUT)
+                .await?
+                .ok_or("Metadata not found")?;
+
+            // Fetch the LNURL-pay endpoint
+            let url: Url = zap::pay_url(&metadata)?;
+            let lnurl: String = zap::encode_lnurl(&url)?;
+            let http = reqwest::Client::new();
+            let endpoint: zap::PayEndpoint = zap::fetch_pay_endpoint(&http, url).await?;
+
+            if !endpoint.allows_nostr {
+                return Err("The recipient doesn't support zaps".into());
+            }
+
+            if amount_msat < endpoint.min_sendable || amount_msat > endpoint.max_sendable {
+                return Err(format!(
+                    "Amount out of range: min={} msat, max={} msat",
+                    endpoint.min_sendable, endpoint.max_sendable
+                )
+                .into());
+            }
+
+            // Compose and sign the zap request
+            let relays: Vec<RelayUrl> = client.relays().await.into_keys().collect();
+
+            if relays.is_empty() {
+                return Err("No relays to publish the zap receipt to".into());
+            }
+
+            let mut data: ZapRequestData = ZapRequestData::new(public_key, relays)
+                .amount(amount_msat)
+                .lnurl(lnurl.clone());
+
+            if let Some(event_id) = event {
+                data = data.event_id(event_id);
+            }
+
+            if let Some(comment) = comment {
+                data = data.message(comment);
+            }
+
+            let zap_request: Event = client
+                .sign_event_builder(EventBuilder::public_zap_request(data))
+                .await?;
+
+            // Request the invoice
+            let invoice: String =
+                zap::fetch_invoice(&http, &endpoint, amount_msat, &zap_request, &lnurl).await?;
+
+            println!("Zap request: {}", zap_request.id.to_bech32()?);
+            println!("Invoice: {invoice}");
+
+            Ok(())
+        }
+        ShellCommand::Profile { command } => match command {
+            ShellCommandProfile::Show { public_key } => {
+                let public_key: PublicKey = match public_key {
+                    Some(public_key) => public_key,
+                    None => {
+                        load_keys(client).await?;
+                        let signer: Arc<dyn NostrSigner> = client.signer().await?;
+                        signer.get_public_key().await?
+                    }
+                };
+
+                match client.fetch_metadata(public_key, METADATA_TIMEOUT).await? {
+                    Some(metadata) => util::print_metadata(&metadata),
+                    None => println!("Metadata not found"),
+                }
+
+                Ok(())
+            }
+            ShellCommandProfile::Set {
+                name,
+                about,
+                picture,
+                nip05,
+                lud16,
+                identities,
+            } => {
+                let changes = MetadataChanges {
+                    name,
+                    about,
+                    picture,
+                    nip05,
+                    lud16,
+                    identities,
+                };
+
+                if changes.is_empty() {
+                    return Err("No fields to update".into());
+                }
+
+                // Load keys, if not already loaded in this session
+                load_keys(client).await?;
+
+                let signer: Arc<dyn NostrSigner> = client.signer().await?;
+                let public_key: PublicKey = signer.get_public_key().await?;
+
+                // Fetch the latest metadata, to not wipe the other fields
+                let current: Metadata =
+                    match client.fetch_metadata(public_key, METADATA_TIMEOUT).await? {
+                        Some(metadata) => metadata,
+                        None => {
+                            println!("Metadata not found, creating a new one");
+                            Metadata::new()
+                        }
+                    };
+
+                let metadata: Metadata = changes.apply(current);
+                let output: Output<EventId> = client.set_metadata(&metadata).await?;
+
+                util::print_metadata(&metadata);
+                println!("\nEvent ID: {}", output.id().to_bech32()?);
+
+                for url in output.success.iter() {
+                    println!("- {url}: OK");
+                }
+
+                for (url, e) in output.failed.iter() {
+                    println!("- {url}: {e}");
+                }
+
+                Ok(())
+            }
+        },
+        ShellCommand::Nip05 {
+            public_key,
+            identifier,
+        } => {
+            let profile: Nip05Profile = client.fetch_nip05_profile(&identifier).await?;
+
+            if profile.public_key == public_key {
+                println!("{identifier} verified");
+            } else {
+                println!(
+                    "{identifier} NOT verified: maps to {}",
+                    profile.public_key.to_bech32()?
+                );
+                return Ok(());
+            }
+
+            if !profile.relays.is_empty() {
+                println!("\nRelays:");
+                for url in profile.relays.iter() {
+                    println!("- {url}");
+                }
+            }
+
+            if !profile.nip46.is_empty() {
+                println!("\nNIP-46 relays:");
+                for url in profile.nip46.iter() {
+                    println!("- {url}");
+                }
+            }
+
+            Ok(())
+        }
+        ShellCommand::Relay { command } => match command {
+            ShellCommandRelay::Add { url, read, write } => {
+                let added: bool = match (read, write) {
+                    (true, false) => client.add_read_relay(&url).await?,
+                    (false, true) => client.add_write_relay(&url).await?,
+                    _ => client.add_relay(&url).await?,
+                };
+
+                client.connect_relay(&url).await?;
+
+                if added {
+                    println!("Relay {url} added");
+                } else {
+                    println!("Relay {url} already added");
+                }
+
+                Ok(())
+            }
+            ShellCommandRelay::Remove { url, force } => {
+                if force {
+                    client.force_remove_relay(&url).await?;
+                } else {
+                    client.remove_relay(&url).await?;
+                }
+
+                println!("Relay {url} removed");
+
+                Ok(())
+            }
+            ShellCommandRelay::List => {
+                let relays: HashMap<RelayUrl, Relay> = client.relays().await;
+
+                if relays.is_empty() {
+                    println!("No relays");
+                } else {
+                    util::relays_table(&relays).printstd();
+                }
+
+                Ok(())
+            }
+        },
+        ShellCommand::Info => {
+            // Public key
+            if client.has_signer().await {
+                let signer: Arc<dyn NostrSigner> = client.signer().await?;
+                let public_key: PublicKey = signer.get_public_key().await?;
+                println!("Public key: {}", public_key.to_hex());
+                println!("            {}", public_key.to_bech32()?);
+            } else {
+                println!("Public key: not loaded");
+            }
+
+            // Relays
+            let relays: HashMap<RelayUrl, Relay> = client.relays().await;
+            println!("\nRelays ({}):", relays.len());
+            if !relays.is_empty() {
+                util::relays_table(&relays).printstd();
+            }
+
+            // Subscriptions
+            let subscriptions: HashMap<SubscriptionId, Filter> = client.subscriptions().await;
+            println!("\nSubscriptions ({}):", subscriptions.len());
+            for (id, filter) in subscriptions.into_iter() {
+                println!("- {id}: {}", filter.as_json());
             }
 
             Ok(())
         }
         ShellCommand::Database { command } => match command {
-            ShellCommandDatabase::Populate { path } => {
+            ShellCommandDatabase::Populate {
+                path,
+                resume,
+                verify,
+                lenient,
+            } => {
                 if path.exists() && path.is_file() {
                     // Open JSON file
                     let file = File::open(path)?;
@@ -316,41 +1320,128 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
 
                     println!("File size: {} bytes", metadata.len());
 
-                    // Deserialize events
-                    let iter = reader.lines().map_while(Result::ok).filter_map(|msg| {
-                        if let Ok(RelayMessage::Event { event, .. }) = RelayMessage::from_json(msg)
-                        {
-                            Some(event)
-                        } else {
-                            None
-                        }
-                    });
-
                     // Bulk load
-                    let mut counter: u32 = 0;
-                    let db = client.database();
                     let now = Instant::now();
+                    let report: ImportReport = util::import_events(
+                        client.database(),
+                        reader.lines().map_while(Result::ok),
+                        resume,
+                        verify,
+                        IMPORT_PROGRESS_INTERVAL,
+                        |report| {
+                            print!("\r{report}");
+                            let _ = std::io::stdout().flush();
+                        },
+                    )
+                    .await;
+
+                    println!("\r{report} in {:.6} secs", now.elapsed().as_secs_f64());
+
+                    if report.invalid > 0 && !lenient {
+                        return Err(
+                            format!("{} events failed the verification", report.invalid).into()
+                        );
+                    }
+                } else {
+                    println!("File not found")
+                }
+
+                Ok(())
+            }
+            ShellCommandDatabase::Export { path, kind, author } => {
+                // Create JSONL file
+                let file = File::create(path)?;
+                let mut writer = BufWriter::new(file);
 
-                    for event in iter {
-                        if let Ok(status) = db.save_event(&event).await {
-                            if status.is_success() {
-                                counter += 1;
+                let mut filter: Filter = Filter::new();
+
+                if let Some(kind) = kind {
+                    filter = filter.kind(kind);
+                }
+
+                if let Some(author) = author {
+                    filter = filter.author(author);
+                }
+
+                let mut counter: usize = 0;
+                let now = Instant::now();
+
+                // Write events in batches, to avoid loading the whole database in memory
+                util::scan_events(client.database(), filter, SCAN_BATCH_SIZE, |events| {
+                    for event in events.into_iter() {
+                        writeln!(writer, "{}", event.as_json())?;
+                        counter += 1;
+                    }
+
+                    writer.flush()?;
+
+                    print!("\rExported {counter} events");
+                    std::io::stdout().flush()?;
+
+                    Ok(())
+                })
+                .await?;
+
+                println!(
+                    "\rExported {counter} events in {:.6} secs",
+                    now.elapsed().as_secs_f64()
+                );
+
+                Ok(())
+            }
+            ShellCommandDatabase::Check { repair } => {
+                let mut scanned: usize = 0;
+                let mut invalid: Vec<EventId> = Vec::new();
+                let now = Instant::now();
+
+                // Re-verify IDs and signatures, in batches
+                util::scan_events(
+                    client.database(),
+                    Filter::new(),
+                    SCAN_BATCH_SIZE,
+                    |events| {
+                        for event in events.into_iter() {
+                            scanned += 1;
+
+                            if !event.verify_id() {
+                                println!("\rInvalid ID: {}", event.id);
+                                invalid.push(event.id);
+                            } else if !event.verify_signature() {
+                                println!("\rInvalid signature: {}", event.id);
+                                invalid.push(event.id);
                             }
                         }
-                    }
 
-                    println!(
-                        "Imported {counter} events in {:.6} secs",
-                        now.elapsed().as_secs_f64()
-                    );
-                } else {
-                    println!("File not found")
+                        print!("\rScanned {scanned} events");
+                        std::io::stdout().flush()?;
+
+                        Ok(())
+                    },
+                )
+                .await?;
+
+                println!(
+                    "\rScanned {scanned} events in {:.6} secs",
+                    now.elapsed().as_secs_f64()
+                );
+                println!("Valid: {}", scanned - invalid.len());
+                println!("Invalid: {}", invalid.len());
+
+                if repair && !invalid.is_empty() {
+                    let removed: usize = invalid.len();
+                    client.database().delete(Filter::new().ids(invalid)).await?;
+                    println!("Removed {removed} invalid events");
                 }
 
                 Ok(())
             }
             ShellCommandDatabase::Stats => {
-                println!("TODO");
+                let db = client.database();
+                let count: usize = db.count(Filter::new()).await?;
+
+                println!("Backend: {:?}", db.backend());
+                println!("Events: {count}");
+
                 Ok(())
             }
         },
@@ -358,12 +1449,126 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
     }
 }
 
-struct CustomActions;
+/// In-memory database, storing the events
+///
+/// The oldest events are evicted when a limit is reached (no limits if both are `None`).
+fn memory_database(max_events: Option<usize>, max_bytes: Option<usize>) -> MemoryDatabase {
+    MemoryDatabase::with_opts(MemoryDatabaseOptions {
+        events: true,
+        max_events,
+        max_bytes,
+    })
+}
 
-impl NostrConnectSignerActions for CustomActions {
-    fn approve(&self, public_key: &PublicKey, req: &NostrConnectRequest) -> bool {
-        println!("Public key: {public_key}");
-        println!("{req:#?}\n");
-        io::ask("Approve request?").unwrap_or_default()
+/// Create the output file, and its parent dirs
+fn create_output_file(path: &Path) -> Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
     }
+
+    let file: File = File::create(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// Ask and load the keys, if not already loaded in this session
+async fn load_keys(client: &Client) -> Result<()> {
+    if !client.has_signer().await {
+        let keys: Keys = io::get_keys("Secret key")?;
+        client.set_signer(keys).await;
+    }
+
+    Ok(())
+}
+
+/// Print the events of a NIP-29 group, until Ctrl-C
+async fn stream_group(
+    client: &Client,
+    relay: &RelayUrl,
+    group_id: &str,
+    limit: usize,
+) -> Result<()> {
+    client.try_connect_relay(relay, GROUP_TIMEOUT).await?;
+
+    let mut notifications = client.notifications();
+
+    // Subscribe only to the relay hosting the group
+    let messages: SubscriptionId = client
+        .subscribe_to(
+            [relay],
+            nip29::group_messages_filter(group_id).limit(limit),
+            None,
+        )
+        .await?
+        .val;
+    let metadata: SubscriptionId = client
+        .subscribe_to([relay], nip29::group_metadata_filter(group_id), None)
+        .await?
+        .val;
+
+    println!("Streaming group '{group_id}' from {relay} (press Ctrl-C to stop)\n");
+
+    let mut names: HashMap<PublicKey, String> = HashMap::new();
+    let mut seen: HashSet<EventId> = HashSet::new();
+
+    loop {
+        tokio::select! {
+            res = notifications.recv() => match res {
+                Ok(RelayPoolNotification::Message {
+                    message: RelayMessage::Event { subscription_id, event },
+                    ..
+                }) => {
+                    if (*subscription_id != messages && *subscription_id != metadata)
+                        || !seen.insert(event.id)
+                    {
+                        continue;
+                    }
+
+                    // Resolve the author name, from the local database
+                    if let Entry::Vacant(entry) = names.entry(event.pubkey) {
+                        if let Some(m) = client.database().metadata(event.pubkey).await? {
+                            let mut resolved = render::author_names([(event.pubkey, &m)]);
+                            if let Some(name) = resolved.remove(&event.pubkey) {
+                                entry.insert(name);
+                            }
+                        }
+                    }
+
+                    println!("{}", render::render_group_event(&event, &names));
+                }
+                Ok(..) | Err(RecvError::Lagged(..)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    client.unsubscribe(&messages).await;
+    client.unsubscribe(&metadata).await;
+
+    Ok(())
+}
+
+/// Fetch the event from the `from` relays and send it, as is, to the `to` relays
+async fn broadcast(
+    client: &Client,
+    id: EventId,
+    from: &[RelayUrl],
+    to: &[RelayUrl],
+) -> Result<Output<EventId>> {
+    for url in from.iter().chain(to.iter()) {
+        if let Err(e) = client.try_connect_relay(url, BROADCAST_TIMEOUT).await {
+            println!("- {url}: {e}");
+        }
+    }
+
+    let filter: Filter = Filter::new().id(id);
+    let event: Event = client
+        .fetch_events_from(from, filter, BROADCAST_TIMEOUT)
+        .await?
+        .into_iter()
+        .find(|event| event.id == id)
+        .ok_or(format!("Event {id} not found"))?;
+
+    // The event is sent verbatim: never re-signed
+    Ok(client.send_event_to(to, &event).await?)
 }
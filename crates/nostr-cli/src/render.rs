@@ -0,0 +1,342 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Human-friendly events rendering
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::io::{self, IsTerminal};
+
+use nostr_sdk::prelude::*;
+
+/// Max number of chars of the content preview
+const CONTENT_PREVIEW_LEN: usize = 120;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+
+/// Check if the output can be colorized
+///
+/// Colors are disabled by the `--no-color` flag, the `NO_COLOR` env var or if the stdout isn't a terminal.
+pub fn use_color(no_color: bool) -> bool {
+    !no_color && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal()
+}
+
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn kind_style(kind: &Kind) -> &'static str {
+    match kind {
+        Kind::TextNote | Kind::LongFormTextNote | Kind::Comment => GREEN,
+        Kind::Reaction | Kind::Repost | Kind::GenericRepost | Kind::ZapReceipt => YELLOW,
+        Kind::EventDeletion | Kind::Reporting => RED,
+        Kind::EncryptedDirectMessage | Kind::PrivateDirectMessage | Kind::GiftWrap | Kind::Seal => {
+            MAGENTA
+        }
+        Kind::Metadata | Kind::ContactList | Kind::RelayList => CYAN,
+        _ => BLUE,
+    }
+}
+
+/// Get the kind label (i.e., `TextNote (1)`)
+pub fn kind_label(kind: &Kind) -> String {
+    match kind {
+        Kind::Custom(n) => n.to_string(),
+        kind => format!("{kind:?} ({})", kind.as_u16()),
+    }
+}
+
+/// Format the elapsed time from `created_at` to `now` (i.e., `5 minutes ago`)
+pub fn relative_time(created_at: Timestamp, now: Timestamp) -> String {
+    if created_at > now {
+        return String::from("in the future");
+    }
+
+    let secs: u64 = now.as_u64() - created_at.as_u64();
+
+    let (value, unit) = match secs {
+        0..=59 => return String::from("just now"),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        86_400..=2_591_999 => (secs / 86_400, "day"),
+        2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+
+    if value == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{value} {unit}s ago")
+    }
+}
+
+/// Summarize the tags, counting them by kind (i.e., `e×2, p×1, t×3`)
+pub fn tags_summary(tags: &Tags) -> String {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+
+    for tag in tags.iter() {
+        *counts.entry(tag.kind().to_string()).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(kind, count)| format!("{kind}×{count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Single line preview of the content, truncated to `max` chars
+pub fn content_preview(content: &str, max: usize) -> String {
+    let line: String = content.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if line.chars().count() > max {
+        let truncated: String = line.chars().take(max).collect();
+        format!("{truncated}…")
+    } else {
+        line
+    }
+}
+
+/// Get the display name of the authors, from their metadata
+pub fn author_names<'a, I>(metadata: I) -> HashMap<PublicKey, String>
+where
+    I: IntoIterator<Item = (PublicKey, &'a Metadata)>,
+{
+    metadata
+        .into_iter()
+        .filter_map(|(public_key, metadata)| {
+            let name: &String = metadata
+                .display_name
+                .as_ref()
+                .filter(|n| !n.is_empty())
+                .or(metadata.name.as_ref())
+                .filter(|n| !n.is_empty())?;
+            Some((public_key, name.clone()))
+        })
+        .collect()
+}
+
+fn render_event(
+    painter: &Painter,
+    index: usize,
+    event: &Event,
+    names: &HashMap<PublicKey, String>,
+    now: Timestamp,
+) -> String {
+    let id: String = event.id.to_bech32().unwrap_or_else(|_| event.id.to_hex());
+    let npub: String = event
+        .pubkey
+        .to_bech32()
+        .unwrap_or_else(|_| event.pubkey.to_hex());
+    let author: String = match names.get(&event.pubkey) {
+        Some(name) => format!(
+            "{} {}",
+            painter.paint(BOLD, name),
+            painter.paint(DIM, &npub)
+        ),
+        None => npub,
+    };
+
+    let mut lines: Vec<String> = vec![
+        format!("{}. {}", index + 1, painter.paint(DIM, &id)),
+        format!(
+            "   {} · {} · {} {}",
+            painter.paint(kind_style(&event.kind), &kind_label(&event.kind)),
+            author,
+            relative_time(event.created_at, now),
+            painter.paint(DIM, &format!("({})", event.created_at.to_human_datetime()))
+        ),
+    ];
+
+    if !event.tags.is_empty() {
+        lines.push(format!(
+            "   {} {}",
+            painter.paint(DIM, "tags:"),
+            tags_summary(&event.tags)
+        ));
+    }
+
+    if !event.content.is_empty() {
+        lines.push(format!(
+            "   {}",
+            content_preview(&event.content, CONTENT_PREVIEW_LEN)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Print the events in a human-friendly format
+pub fn print_events(events: &[Event], names: &HashMap<PublicKey, String>, color: bool) {
+    let painter: Painter = Painter { color };
+    let now: Timestamp = Timestamp::now();
+
+    for (index, event) in events.iter().enumerate() {
+        println!("{}\n", render_event(&painter, index, event, names, now));
+    }
+}
+
+/// Get the values of the tags of a kind
+fn tag_values<'a>(event: &'a Event, kind: &'a str) -> Vec<&'a str> {
+    event
+        .tags
+        .filter(TagKind::custom(kind))
+        .filter_map(|tag| tag.content())
+        .collect()
+}
+
+/// Render an event of a NIP-29 group, as a single line
+///
+/// The group metadata, admins, members and roles are rendered distinctly from the messages.
+pub fn render_group_event(event: &Event, names: &HashMap<PublicKey, String>) -> String {
+    let name = |public_key: &PublicKey| -> String {
+        match names.get(public_key) {
+            Some(name) => name.clone(),
+            None => public_key
+                .to_bech32()
+                .unwrap_or_else(|_| public_key.to_hex()),
+        }
+    };
+    let members = || -> String {
+        event
+            .tags
+            .public_keys()
+            .map(name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match event.kind {
+        Kind::GroupMetadata => {
+            let mut fields: Vec<String> = Vec::new();
+
+            if let Some(name) = tag_values(event, "name").first() {
+                fields.push(format!("name: {name}"));
+            }
+
+            if let Some(about) = tag_values(event, "about").first() {
+                fields.push(format!(
+                    "about: {}",
+                    content_preview(about, CONTENT_PREVIEW_LEN)
+                ));
+            }
+
+            for flag in ["private", "closed"] {
+                if event.tags.find(TagKind::custom(flag)).is_some() {
+                    fields.push(flag.to_string());
+                }
+            }
+
+            format!("[metadata] {}", fields.join(" · "))
+        }
+        Kind::GroupAdmins => format!("[admins] {}", members()),
+        Kind::GroupMembers => format!("[members] {}", members()),
+        Kind::GroupRoles => format!("[roles] {}", tag_values(event, "role").join(", ")),
+        _ => format!(
+            "[{}] {}: {}",
+            event.created_at.to_human_datetime(),
+            name(&event.pubkey),
+            content_preview(&event.content, CONTENT_PREVIEW_LEN)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_time() {
+        let now = Timestamp::from(1_700_000_000);
+        assert_eq!(relative_time(now, now), "just now");
+        assert_eq!(relative_time(now - 60, now), "1 minute ago");
+        assert_eq!(relative_time(now - 7_200, now), "2 hours ago");
+        assert_eq!(relative_time(now - 86_400 * 3, now), "3 days ago");
+        assert_eq!(relative_time(now + 10, now), "in the future");
+    }
+
+    #[test]
+    fn test_render_group_event() {
+        let keys = Keys::generate();
+        let names = author_names([(keys.public_key(), &Metadata::new().name("alice"))]);
+
+        let message = EventBuilder::group_message("pizza-lovers", "Hello\nworld")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let rendered = render_group_event(&message, &names);
+        assert!(rendered.ends_with("] alice: Hello world"));
+
+        let metadata = EventBuilder::new(Kind::GroupMetadata, "")
+            .tags([
+                Tag::identifier("pizza-lovers"),
+                Tag::custom(TagKind::Name, ["Pizza Lovers"]),
+                Tag::custom(TagKind::custom("closed"), Vec::<String>::new()),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            render_group_event(&metadata, &names),
+            "[metadata] name: Pizza Lovers · closed"
+        );
+
+        let admins = EventBuilder::new(Kind::GroupAdmins, "")
+            .tags([
+                Tag::identifier("pizza-lovers"),
+                Tag::public_key(keys.public_key()),
+            ])
+            .allow_self_tagging()
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(render_group_event(&admins, &names), "[admins] alice");
+    }
+
+    #[test]
+    fn test_render_event() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello\nworld")
+            .tag(Tag::hashtag("nostr"))
+            .tag(Tag::hashtag("rust"))
+            .tag(Tag::public_key(keys.public_key()))
+            .allow_self_tagging()
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        assert_eq!(kind_label(&event.kind), "TextNote (1)");
+        assert_eq!(tags_summary(&event.tags), "p×1, t×2");
+        assert_eq!(content_preview(&event.content, 120), "Hello world");
+        assert_eq!(content_preview("abcdef", 3), "abc…");
+
+        let metadata = Metadata::new().name("alice");
+        let names = author_names([(keys.public_key(), &metadata)]);
+
+        // No ANSI codes without colors
+        let painter = Painter { color: false };
+        let rendered = render_event(&painter, 0, &event, &names, event.created_at);
+        assert!(rendered.contains("TextNote (1) · alice npub1"));
+        assert!(rendered.contains("just now"));
+        assert!(!rendered.contains('\x1b'));
+
+        let painter = Painter { color: true };
+        let rendered = render_event(&painter, 0, &event, &names, event.created_at);
+        assert!(rendered.contains(GREEN));
+    }
+}
@@ -2,9 +2,812 @@
 // Copyright (c) 2023-2025 Rust Nostr Developers
 // Distributed under the MIT software license
 
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
 use nostr_sdk::prelude::*;
 use prettytable::{row, Table};
 
+use crate::cli::parser::{IdentityClaim, TagFilter};
+use crate::cli::ShellQuerySort;
+
+/// Sync summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Events received from relays
+    pub received: usize,
+    /// Events sent to relays
+    pub sent: usize,
+    /// Events that existed on both sides
+    pub common: usize,
+    /// Events that failed to reconcile
+    pub failed: usize,
+}
+
+impl SyncReport {
+    pub fn new(output: &Output<Reconciliation>, direction: SyncDirection) -> Self {
+        let up: bool = matches!(direction, SyncDirection::Up | SyncDirection::Both);
+        let down: bool = matches!(direction, SyncDirection::Down | SyncDirection::Both);
+
+        // Events missing on relays and not sent
+        let failed_up: usize = if up {
+            output.local.difference(&output.sent).count()
+        } else {
+            0
+        };
+
+        // Events missing locally and not received
+        let failed_down: usize = if down {
+            output.remote.difference(&output.received).count()
+        } else {
+            0
+        };
+
+        Self {
+            received: output.received.len(),
+            sent: output.sent.len(),
+            common: output.common.len(),
+            failed: failed_up + failed_down,
+        }
+    }
+}
+
+pub fn sort_events<I>(events: I, sort: ShellQuerySort, search: Option<&str>) -> Vec<Event>
+where
+    I: IntoIterator<Item = Event>,
+{
+    let mut events: Vec<Event> = events.into_iter().collect();
+
+    match sort {
+        ShellQuerySort::Relevance => {
+            let terms: Vec<String> = search
+                .map(|s| s.split_whitespace().map(|t| t.to_lowercase()).collect())
+                .unwrap_or_default();
+            events.sort_by_cached_key(|e| {
+                (Reverse(relevance_score(e, &terms)), Reverse(e.created_at))
+            });
+        }
+        ShellQuerySort::CreatedAtAsc => events.sort_by_key(|e| e.created_at),
+        ShellQuerySort::CreatedAtDesc => events.sort_by_key(|e| Reverse(e.created_at)),
+    }
+
+    events
+}
+
+/// Get the page of events that come after the `cursor`
+///
+/// The events are ordered by `created_at` (newest first) and then by `id`,
+/// so that events sharing the same timestamp are never skipped or duplicated.
+pub fn paginate<I>(events: I, cursor: Option<&Event>, limit: Option<usize>) -> Vec<Event>
+where
+    I: IntoIterator<Item = Event>,
+{
+    let mut events: Vec<Event> = events
+        .into_iter()
+        .filter(|event| match cursor {
+            Some(cursor) => event > cursor,
+            None => true,
+        })
+        .collect();
+
+    // Lookup ID: EVENT_ORD_IMPL
+    events.sort();
+
+    if let Some(limit) = limit {
+        events.truncate(limit);
+    }
+
+    events
+}
+
+/// Remove the events matching any of the excluded tags and, if `exclude_replies` is set, the replies (NIP-10)
+///
+/// Relays can't express negations, so the exclusions are applied to the returned events.
+pub fn exclude_events(
+    events: Vec<Event>,
+    exclude_tags: &[TagFilter],
+    exclude_replies: bool,
+) -> Vec<Event> {
+    events
+        .into_iter()
+        .filter(|event| {
+            let excluded_tag: bool = event.tags.iter().any(|tag| {
+                exclude_tags.iter().any(|TagFilter { tag: name, value }| {
+                    tag.single_letter_tag() == Some(*name) && tag.content() == Some(value.as_str())
+                })
+            });
+            let excluded_reply: bool = exclude_replies && reply_parent(event).is_some();
+            !excluded_tag && !excluded_reply
+        })
+        .collect()
+}
+
+/// Count the occurrences of the search terms in the event content and tags
+fn relevance_score(event: &Event, terms: &[String]) -> usize {
+    let content: String = event.content.to_lowercase();
+    let tags: Vec<String> = event
+        .tags
+        .iter()
+        .flat_map(|tag| tag.as_slice().iter().skip(1))
+        .map(|value| value.to_lowercase())
+        .collect();
+
+    terms
+        .iter()
+        .map(|term| {
+            content.matches(term.as_str()).count()
+                + tags
+                    .iter()
+                    .map(|value| value.matches(term.as_str()).count())
+                    .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Write events to `writer`, one JSON per line if `json` is set or as table otherwise
+pub fn write_events<'a, I, W>(events: I, json: bool, mut writer: W) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a Event>,
+    W: Write,
+{
+    if json {
+        for event in events.into_iter() {
+            writeln!(writer, "{}", event.as_json())?;
+        }
+    } else {
+        let table: Table = events_table(events);
+        table.print(&mut writer)?;
+    }
+
+    Ok(())
+}
+
+fn events_table<'a, I>(events: I) -> Table
+where
+    I: IntoIterator<Item = &'a Event>,
+{
+    let mut table: Table = Table::new();
+
+    table.set_titles(row!["#", "ID", "Author", "Kind", "Created At"]);
+
+    for (index, event) in events.into_iter().enumerate() {
+        table.add_row(row![
+            index + 1,
+            event.id,
+            event.pubkey,
+            event.kind,
+            event.created_at.to_human_datetime()
+        ]);
+    }
+
+    table
+}
+
+/// Get the ID of the event to which the event replies (NIP-10)
+///
+/// Use the `reply` marker, if any, otherwise the `root` one.
+/// Fall back to the deprecated positional scheme (last `e` tag) for unmarked tags.
+pub fn reply_parent(event: &Event) -> Option<EventId> {
+    let mut root: Option<EventId> = None;
+    let mut last: Option<EventId> = None;
+
+    for tag in event.tags.iter() {
+        if let Some(TagStandard::Event {
+            event_id,
+            marker,
+            uppercase: false,
+            ..
+        }) = tag.as_standardized()
+        {
+            match marker {
+                Some(Marker::Reply) => return Some(*event_id),
+                Some(Marker::Root) => root = Some(*event_id),
+                None => last = Some(*event_id),
+            }
+        }
+    }
+
+    root.or(last)
+}
+
+/// Reply tree of a thread
+#[derive(Debug, Default)]
+pub struct Thread {
+    events: HashMap<EventId, Event>,
+    children: HashMap<EventId, Vec<EventId>>,
+}
+
+impl Thread {
+    pub fn new(root: Event) -> Self {
+        let mut thread = Self::default();
+        thread.events.insert(root.id, root);
+        thread
+    }
+
+    #[inline]
+    pub fn contains(&self, id: &EventId) -> bool {
+        self.events.contains_key(id)
+    }
+
+    /// Add a reply to the thread, returning `false` if already added or if the parent is unknown
+    ///
+    /// If the NIP-10 parent isn't in the thread, the reply is attached to `fallback`.
+    pub fn add_reply(&mut self, event: Event, fallback: EventId) -> bool {
+        if self.contains(&event.id) {
+            return false;
+        }
+
+        let parent: EventId = match reply_parent(&event) {
+            Some(parent) if self.contains(&parent) => parent,
+            _ if self.contains(&fallback) => fallback,
+            _ => return false,
+        };
+
+        self.children.entry(parent).or_default().push(event.id);
+        self.events.insert(event.id, event);
+        true
+    }
+
+    /// Print the tree, indenting the replies
+    pub fn print(&self, root: &EventId) {
+        let mut lines: Vec<String> = Vec::new();
+        self.compose_lines(root, 0, &mut lines);
+        for line in lines.into_iter() {
+            println!("{line}");
+        }
+    }
+
+    fn compose_lines(&self, id: &EventId, depth: usize, lines: &mut Vec<String>) {
+        let Some(event) = self.events.get(id) else {
+            return;
+        };
+
+        let content: &str = event.content.lines().next().unwrap_or_default();
+        lines.push(format!(
+            "{}- [{}] {}: {content}",
+            "  ".repeat(depth),
+            event.created_at.to_human_datetime(),
+            event
+                .pubkey
+                .to_bech32()
+                .unwrap_or_else(|_| event.pubkey.to_hex())
+        ));
+
+        if let Some(children) = self.children.get(id) {
+            // Oldest replies first
+            let mut children: Vec<&Event> = children
+                .iter()
+                .filter_map(|id| self.events.get(id))
+                .collect();
+            children.sort_by_key(|e| (e.created_at, e.id));
+
+            for child in children.into_iter() {
+                self.compose_lines(&child.id, depth + 1, lines);
+            }
+        }
+    }
+}
+
+/// Event verification result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventVerification {
+    /// Valid ID and signature
+    Valid(EventId),
+    /// The ID doesn't match the hash of the serialized fields
+    InvalidId {
+        /// Event ID
+        id: EventId,
+        /// Recomputed ID
+        expected: EventId,
+        /// Canonical serialization used to compute the ID
+        serialized: String,
+    },
+    /// The signature doesn't match the ID and public key
+    InvalidSignature(EventId),
+    /// Impossible to deserialize the event
+    Malformed(String),
+}
+
+impl EventVerification {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid(..))
+    }
+}
+
+impl fmt::Display for EventVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Valid(id) => write!(f, "{id}: valid"),
+            Self::InvalidId {
+                id,
+                expected,
+                serialized,
+            } => write!(
+                f,
+                "{id}: invalid ID, expected {expected} (serialized as {serialized})"
+            ),
+            Self::InvalidSignature(id) => write!(f, "{id}: invalid signature"),
+            Self::Malformed(e) => write!(f, "malformed event: {e}"),
+        }
+    }
+}
+
+/// Recompute the ID and check the signature of an event JSON
+pub fn verify_event_json(json: &str) -> EventVerification {
+    match Event::from_json(json) {
+        Ok(event) => verify_event(&event),
+        Err(e) => EventVerification::Malformed(e.to_string()),
+    }
+}
+
+/// Recompute the ID and check the signature of an event
+pub fn verify_event(event: &Event) -> EventVerification {
+    if !event.verify_id() {
+        // Same serialization used for the ID: [0, <pubkey>, <created_at>, <kind>, <tags>, <content>]
+        let serialized: String = serde_json::json!([
+            0,
+            event.pubkey,
+            event.created_at,
+            event.kind,
+            event.tags,
+            event.content
+        ])
+        .to_string();
+
+        return EventVerification::InvalidId {
+            id: event.id,
+            expected: EventId::new(
+                &event.pubkey,
+                &event.created_at,
+                &event.kind,
+                &event.tags,
+                &event.content,
+            ),
+            serialized,
+        };
+    }
+
+    if !event.verify_signature() {
+        return EventVerification::InvalidSignature(event.id);
+    }
+
+    EventVerification::Valid(event.id)
+}
+
+/// Split the input into event JSONs
+///
+/// The input can be a JSON array of events or an event per line (JSONL).
+pub fn split_events_json(input: &str) -> Result<Vec<String>> {
+    let input: &str = input.trim();
+
+    if input.starts_with('[') {
+        let events: Vec<Value> = serde_json::from_str(input)?;
+        return Ok(events.into_iter().map(|e| e.to_string()).collect());
+    }
+
+    Ok(input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Import summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Events saved into the database
+    pub imported: usize,
+    /// Events already in the database
+    pub duplicates: usize,
+    /// Events rejected by the database (i.e., ephemeral, expired or replaced)
+    pub rejected: usize,
+    /// Events with invalid ID or signature (only if the verification is enabled)
+    pub invalid: usize,
+    /// Invalid lines or database errors
+    pub errors: usize,
+}
+
+impl ImportReport {
+    /// Number of processed lines
+    pub fn processed(&self) -> usize {
+        self.imported + self.duplicates + self.rejected + self.invalid + self.errors
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} events, skipped {} duplicates, {} rejected, {} invalid, {} errors",
+            self.imported, self.duplicates, self.rejected, self.invalid, self.errors
+        )
+    }
+}
+
+/// Parse an import line
+///
+/// The line can be a relay `EVENT` message or a raw event (i.e., `database export` output).
+pub fn parse_import_line(line: &str) -> Option<Event> {
+    match RelayMessage::from_json(line) {
+        Ok(RelayMessage::Event { event, .. }) => Some(event.into_owned()),
+        _ => Event::from_json(line).ok(),
+    }
+}
+
+/// Import the events into the database, calling `progress` every `progress_interval` processed lines
+///
+/// If `resume` is enabled, the events already in the database are skipped without saving them again.
+/// If `verify` is enabled, the ID and the signature of the events are checked before saving them:
+/// the invalid events are skipped and logged.
+/// Empty lines are ignored.
+pub async fn import_events<I, F>(
+    db: &Arc<dyn NostrDatabase>,
+    lines: I,
+    resume: bool,
+    verify: bool,
+    progress_interval: usize,
+    mut progress: F,
+) -> ImportReport
+where
+    I: IntoIterator<Item = String>,
+    F: FnMut(&ImportReport),
+{
+    let mut report: ImportReport = ImportReport::default();
+
+    for line in lines.into_iter() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_import_line(&line) {
+            Some(event) => {
+                // Never trust the source: recompute ID and signature
+                let invalid: Option<EventVerification> = if verify {
+                    Some(verify_event(&event)).filter(|v| !v.is_valid())
+                } else {
+                    None
+                };
+
+                // Skip the events imported before the interruption
+                let exists: bool = invalid.is_none()
+                    && resume
+                    && matches!(
+                        db.check_id(&event.id).await,
+                        Ok(DatabaseEventStatus::Saved | DatabaseEventStatus::Deleted)
+                    );
+
+                if let Some(invalid) = invalid {
+                    eprintln!("\rInvalid event {invalid}");
+                    report.invalid += 1;
+                } else if exists {
+                    report.duplicates += 1;
+                } else {
+                    match db.save_event(&event).await {
+                        Ok(SaveEventStatus::Success) => report.imported += 1,
+                        Ok(SaveEventStatus::Rejected(RejectedReason::Duplicate)) => {
+                            report.duplicates += 1
+                        }
+                        Ok(SaveEventStatus::Rejected(..)) => report.rejected += 1,
+                        Err(..) => report.errors += 1,
+                    }
+                }
+            }
+            None => report.errors += 1,
+        }
+
+        if report.processed() % progress_interval.max(1) == 0 {
+            progress(&report);
+        }
+    }
+
+    report
+}
+
+/// Scan the database events matching the filter, in batches of `batch_size` events (newest first)
+///
+/// Avoids loading the whole database in memory.
+/// The `limit` of the filter is ignored.
+pub async fn scan_events<F>(
+    db: &Arc<dyn NostrDatabase>,
+    filter: Filter,
+    batch_size: usize,
+    mut f: F,
+) -> Result<()>
+where
+    F: FnMut(Vec<Event>) -> Result<()>,
+{
+    let mut until: Option<Timestamp> = None;
+    let mut oldest: Option<Timestamp> = None;
+
+    // IDs of the events with the `oldest` timestamp, to avoid duplicates between batches
+    let mut seen: HashSet<EventId> = HashSet::new();
+
+    loop {
+        // The events with the `until` timestamp already seen are returned again, so fetch them in addition to the batch
+        let limit: usize = batch_size + seen.len();
+        let mut filter: Filter = filter.clone().limit(limit);
+
+        if let Some(until) = until {
+            filter = filter.until(until);
+        }
+
+        let events: Events = db.query(filter).await?;
+        let len: usize = events.len();
+        let mut batch: Vec<Event> = Vec::with_capacity(len);
+
+        // Events are sorted by timestamp (DESC)
+        for event in events.into_iter() {
+            if oldest != Some(event.created_at) {
+                oldest = Some(event.created_at);
+                seen.clear();
+            }
+
+            if seen.insert(event.id) {
+                batch.push(event);
+            }
+        }
+
+        f(batch)?;
+
+        // No more events
+        if len < limit {
+            break;
+        }
+
+        // Page from the oldest timestamp, skipping the events already seen:
+        // the events sharing it may not all fit in the batch.
+        until = match oldest {
+            Some(oldest) => Some(oldest),
+            None => break,
+        };
+    }
+
+    Ok(())
+}
+
+/// Get the write relays from a NIP-65 relay list
+pub fn write_relays(event: &Event) -> Vec<RelayUrl> {
+    nip65::extract_relay_list(event)
+        .filter(|(.., metadata)| !matches!(metadata, Some(RelayMetadata::Read)))
+        .map(|(url, ..)| url.clone())
+        .collect()
+}
+
+/// Parse public keys (hex or bech32), one per line
+///
+/// Empty lines and lines starting with `#` are skipped.
+pub fn read_public_keys<R>(reader: R) -> Result<Vec<PublicKey>>
+where
+    R: BufRead,
+{
+    let mut public_keys: Vec<PublicKey> = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let line: &str = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let public_key: PublicKey = PublicKey::parse(line)
+            .map_err(|e| format!("Invalid public key at line {}: {e}", index + 1))?;
+        public_keys.push(public_key);
+    }
+
+    Ok(public_keys)
+}
+
+/// Merge public keys into a contact list
+///
+/// Existing tags (with their relay hints and petnames) and content are preserved.
+/// Return the builder and the number of added public keys.
+pub fn merge_contact_list<I>(existing: Option<&Event>, public_keys: I) -> (EventBuilder, usize)
+where
+    I: IntoIterator<Item = PublicKey>,
+{
+    let mut tags: Vec<Tag> = Vec::new();
+    let mut content: String = String::new();
+    let mut followed: HashSet<PublicKey> = HashSet::new();
+
+    if let Some(event) = existing {
+        tags.extend(event.tags.iter().cloned());
+        content = event.content.clone();
+        followed.extend(event.tags.public_keys().copied());
+    }
+
+    let mut added: usize = 0;
+
+    for public_key in public_keys.into_iter() {
+        if followed.insert(public_key) {
+            tags.push(Tag::public_key(public_key));
+            added += 1;
+        }
+    }
+
+    (
+        EventBuilder::new(Kind::ContactList, content).tags(tags),
+        added,
+    )
+}
+
+/// Changes to the profile metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataChanges {
+    /// Name
+    pub name: Option<String>,
+    /// Description
+    pub about: Option<String>,
+    /// Picture URL
+    pub picture: Option<Url>,
+    /// NIP-05 identifier
+    pub nip05: Option<String>,
+    /// Lightning address
+    pub lud16: Option<String>,
+    /// External identities (NIP-39)
+    pub identities: Vec<IdentityClaim>,
+}
+
+impl MetadataChanges {
+    /// Check if there are no changes
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Apply the changes, keeping the other fields (custom ones included)
+    pub fn apply(self, mut metadata: Metadata) -> Metadata {
+        if let Some(name) = self.name {
+            metadata = metadata.name(name);
+        }
+
+        if let Some(about) = self.about {
+            metadata = metadata.about(about);
+        }
+
+        if let Some(picture) = self.picture {
+            metadata = metadata.picture(picture);
+        }
+
+        if let Some(nip05) = self.nip05 {
+            metadata = metadata.nip05(nip05);
+        }
+
+        if let Some(lud16) = self.lud16 {
+            metadata = metadata.lud16(lud16);
+        }
+
+        for claim in self.identities.into_iter() {
+            metadata = metadata.add_identity(claim.platform, claim.identity, claim.proof);
+        }
+
+        metadata
+    }
+}
+
+/// Print the metadata fields, skipping the ones not set
+pub fn print_metadata(metadata: &Metadata) {
+    let fields = [
+        ("Name", &metadata.name),
+        ("Display name", &metadata.display_name),
+        ("About", &metadata.about),
+        ("Website", &metadata.website),
+        ("Picture", &metadata.picture),
+        ("Banner", &metadata.banner),
+        ("NIP-05", &metadata.nip05),
+        ("LUD-06", &metadata.lud06),
+        ("LUD-16", &metadata.lud16),
+    ];
+
+    for (label, value) in fields.into_iter() {
+        if let Some(value) = value {
+            println!("{label}: {value}");
+        }
+    }
+
+    for (key, value) in metadata.custom.iter() {
+        println!("{key}: {value}");
+    }
+
+    for identity in metadata.identities.iter() {
+        println!(
+            "Identity: {}:{} (proof: {})",
+            identity.platform, identity.ident, identity.proof
+        );
+    }
+}
+
+/// Deletion targets that can't be verified as authored by the public key
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DeletionCheck {
+    /// Targets authored by another public key
+    pub foreign: Vec<EventId>,
+    /// Targets not found
+    pub not_found: Vec<EventId>,
+}
+
+/// Check the authors of the deletion targets
+pub fn check_deletion_targets<'a, I>(
+    ids: &[EventId],
+    events: I,
+    public_key: &PublicKey,
+) -> DeletionCheck
+where
+    I: IntoIterator<Item = &'a Event>,
+{
+    let authors: HashMap<EventId, PublicKey> = events
+        .into_iter()
+        .map(|event| (event.id, event.pubkey))
+        .collect();
+
+    let mut check: DeletionCheck = DeletionCheck::default();
+
+    for id in ids.iter() {
+        match authors.get(id) {
+            Some(author) if author == public_key => {}
+            Some(..) => check.foreign.push(*id),
+            None => check.not_found.push(*id),
+        }
+    }
+
+    check
+}
+
+/// Parse a custom emoji shortcode (i.e., `soapbox` or `:soapbox:`)
+///
+/// Returns `None` if it contains chars other than alphanumerics and underscores.
+pub fn parse_emoji_shortcode(content: &str) -> Option<&str> {
+    let shortcode: &str = content.trim_matches(':');
+
+    if !shortcode.is_empty()
+        && shortcode
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Some(shortcode)
+    } else {
+        None
+    }
+}
+
+/// Compose a table with the relays, sorted by URL, and their status and flags
+pub fn relays_table(relays: &HashMap<RelayUrl, Relay>) -> Table {
+    let mut relays: Vec<(&RelayUrl, &Relay)> = relays.iter().collect();
+    relays.sort_by_key(|(url, ..)| *url);
+
+    let mut table: Table = Table::new();
+
+    table.set_titles(row!["#", "URL", "Status", "Flags"]);
+
+    for (index, (url, relay)) in relays.into_iter().enumerate() {
+        table.add_row(row![
+            index + 1,
+            url,
+            relay.status(),
+            flags_names(relay.flags()).join(", ")
+        ]);
+    }
+
+    table
+}
+
+fn flags_names(flags: &AtomicRelayServiceFlags) -> Vec<&'static str> {
+    [
+        (RelayServiceFlags::READ, "read"),
+        (RelayServiceFlags::WRITE, "write"),
+        (RelayServiceFlags::PING, "ping"),
+        (RelayServiceFlags::GOSSIP, "gossip"),
+        (RelayServiceFlags::DISCOVERY, "discovery"),
+    ]
+    .into_iter()
+    .filter(|(flag, ..)| flags.has_all(*flag))
+    .map(|(.., name)| name)
+    .collect()
+}
+
 pub fn print_events<I>(events: I, json: bool)
 where
     I: IntoIterator<Item = Event>,
@@ -14,20 +817,520 @@ where
             println!("{}. {}", index + 1, event.as_pretty_json());
         }
     } else {
-        let mut table: Table = Table::new();
+        let events: Vec<Event> = events.into_iter().collect();
+        let table: Table = events_table(&events);
+        table.printstd();
+    }
+}
 
-        table.set_titles(row!["#", "ID", "Author", "Kind", "Created At"]);
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::str::FromStr;
 
-        for (index, event) in events.into_iter().enumerate() {
-            table.add_row(row![
-                index + 1,
-                event.id,
-                event.pubkey,
-                event.kind,
-                event.created_at.to_human_datetime()
-            ]);
+    use super::*;
+
+    #[test]
+    fn test_parse_emoji_shortcode() {
+        assert_eq!(parse_emoji_shortcode("soapbox"), Some("soapbox"));
+        assert_eq!(parse_emoji_shortcode(":soap_box2:"), Some("soap_box2"));
+        assert_eq!(parse_emoji_shortcode("+"), None);
+        assert_eq!(parse_emoji_shortcode("::"), None);
+        assert_eq!(parse_emoji_shortcode(":soap box:"), None);
+    }
+
+    #[test]
+    fn test_write_events_json() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..3)
+            .map(|i| {
+                EventBuilder::text_note(format!("Note {i}"))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        let mut buf: Vec<u8> = Vec::new();
+        write_events(&events, true, &mut buf).unwrap();
+
+        let parsed: Vec<Event> = String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(|line| Event::from_json(line).unwrap())
+            .collect();
+        assert_eq!(parsed, events);
+    }
+
+    #[test]
+    fn test_thread() {
+        let keys = Keys::generate();
+        let root = EventBuilder::text_note("Root")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reply = EventBuilder::text_note("Reply")
+            .tag(Tag::from_standardized(TagStandard::Event {
+                event_id: root.id,
+                relay_url: None,
+                marker: Some(Marker::Root),
+                public_key: None,
+                uppercase: false,
+            }))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let nested = EventBuilder::text_note("Nested")
+            .tags([
+                Tag::from_standardized(TagStandard::Event {
+                    event_id: root.id,
+                    relay_url: None,
+                    marker: Some(Marker::Root),
+                    public_key: None,
+                    uppercase: false,
+                }),
+                Tag::from_standardized(TagStandard::Event {
+                    event_id: reply.id,
+                    relay_url: None,
+                    marker: Some(Marker::Reply),
+                    public_key: None,
+                    uppercase: false,
+                }),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        assert_eq!(reply_parent(&root), None);
+        assert_eq!(reply_parent(&reply), Some(root.id));
+        assert_eq!(reply_parent(&nested), Some(reply.id));
+
+        let mut thread = Thread::new(root.clone());
+        assert!(thread.add_reply(reply.clone(), root.id));
+        assert!(!thread.add_reply(reply.clone(), root.id));
+        assert!(thread.add_reply(nested.clone(), root.id));
+
+        let mut lines: Vec<String> = Vec::new();
+        thread.compose_lines(&root.id, 0, &mut lines);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("- ") && lines[0].ends_with("Root"));
+        assert!(lines[1].starts_with("  - ") && lines[1].ends_with("Reply"));
+        assert!(lines[2].starts_with("    - ") && lines[2].ends_with("Nested"));
+    }
+
+    #[test]
+    fn test_verify_event_json() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .tag(Tag::hashtag("nostr"))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        assert_eq!(
+            verify_event_json(&event.as_json()),
+            EventVerification::Valid(event.id)
+        );
+
+        // Tampered content
+        let mut tampered = event.clone();
+        tampered.content = String::from("Other");
+        match verify_event_json(&tampered.as_json()) {
+            EventVerification::InvalidId {
+                id,
+                expected,
+                serialized,
+            } => {
+                assert_eq!(id, event.id);
+                assert_ne!(expected, event.id);
+                assert!(serialized.contains("\"Other\""));
+            }
+            v => panic!("Unexpected result: {v:?}"),
         }
 
-        table.printstd();
+        // Signature of another event
+        let other = EventBuilder::text_note("Other")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let mut invalid_sig = event.clone();
+        invalid_sig.sig = other.sig;
+        assert_eq!(
+            verify_event_json(&invalid_sig.as_json()),
+            EventVerification::InvalidSignature(event.id)
+        );
+
+        assert!(matches!(
+            verify_event_json("{}"),
+            EventVerification::Malformed(..)
+        ));
+    }
+
+    #[test]
+    fn test_split_events_json() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..2)
+            .map(|i| {
+                EventBuilder::text_note(format!("Note {i}"))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        let array = format!("[{},{}]", events[0].as_json(), events[1].as_json());
+        let lines = format!("{}\n\n{}\n", events[0].as_json(), events[1].as_json());
+
+        for input in [array, lines] {
+            let split = split_events_json(&input).unwrap();
+            assert_eq!(split.len(), 2);
+            assert_eq!(Event::from_json(&split[1]).unwrap(), events[1]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_events() {
+        let db: Arc<dyn NostrDatabase> =
+            Arc::new(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                ..Default::default()
+            }));
+        let keys = Keys::generate();
+
+        let events: Vec<Event> = (0..4)
+            .map(|i| {
+                EventBuilder::text_note(format!("Note {i}"))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        // Raw events, relay messages, empty and invalid lines
+        let mut lines: Vec<String> = vec![
+            events[0].as_json(),
+            RelayMessage::event(SubscriptionId::new("test"), events[1].clone()).as_json(),
+            String::new(),
+            String::from("invalid"),
+        ];
+
+        let mut calls: usize = 0;
+        let report = import_events(&db, lines.clone(), false, false, 1, |_| calls += 1).await;
+        assert_eq!(
+            report,
+            ImportReport {
+                imported: 2,
+                duplicates: 0,
+                rejected: 0,
+                invalid: 0,
+                errors: 1,
+            }
+        );
+        assert_eq!(calls, 3);
+
+        // Resume after the interruption
+        lines.push(events[2].as_json());
+        lines.push(events[3].as_json());
+        let report = import_events(&db, lines, true, false, 100, |_| {}).await;
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(report.errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_events_verify() {
+        let db: Arc<dyn NostrDatabase> =
+            Arc::new(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                ..Default::default()
+            }));
+        let keys = Keys::generate();
+
+        let valid = EventBuilder::text_note("Valid")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let mut tampered = EventBuilder::text_note("Original")
+            .sign_with_keys(&keys)
+            .unwrap();
+        tampered.content = String::from("Tampered");
+
+        let lines: Vec<String> = vec![valid.as_json(), tampered.as_json()];
+
+        let report = import_events(&db, lines, false, true, 100, |_| {}).await;
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.invalid, 1);
+        assert!(db.event_by_id(&tampered.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scan_events() {
+        let db: Arc<dyn NostrDatabase> =
+            Arc::new(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                ..Default::default()
+            }));
+        let keys = Keys::generate();
+
+        // Many events with the same timestamp, to test the batches boundaries.
+        // The last 30 share the same timestamp, more than two batches.
+        let mut expected: HashSet<EventId> = HashSet::new();
+        for i in 0..50 {
+            let event = EventBuilder::text_note(format!("Event {i}"))
+                .custom_created_at(Timestamp::from((i / 10).min(2)))
+                .sign_with_keys(&keys)
+                .unwrap();
+            db.save_event(&event).await.unwrap();
+            expected.insert(event.id);
+        }
+
+        let mut scanned: Vec<EventId> = Vec::new();
+        scan_events(&db, Filter::new(), 10, |events| {
+            scanned.extend(events.into_iter().map(|e| e.id));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(scanned.len(), expected.len());
+        assert_eq!(scanned.into_iter().collect::<HashSet<_>>(), expected);
+    }
+
+    #[test]
+    fn test_write_relays() {
+        let keys = Keys::generate();
+        let read = RelayUrl::parse("wss://read.example.com").unwrap();
+        let write = RelayUrl::parse("wss://write.example.com").unwrap();
+        let both = RelayUrl::parse("wss://both.example.com").unwrap();
+
+        let event = EventBuilder::relay_list([
+            (read, Some(RelayMetadata::Read)),
+            (write.clone(), Some(RelayMetadata::Write)),
+            (both.clone(), None),
+        ])
+        .sign_with_keys(&keys)
+        .unwrap();
+
+        assert_eq!(write_relays(&event), vec![write, both]);
+    }
+
+    #[test]
+    fn test_read_public_keys() {
+        let keys = Keys::generate();
+        let other = Keys::generate();
+        let input = format!(
+            "# follows\n{}\n\n  {}  \n",
+            keys.public_key().to_bech32().unwrap(),
+            other.public_key().to_hex()
+        );
+
+        let public_keys = read_public_keys(input.as_bytes()).unwrap();
+        assert_eq!(public_keys, vec![keys.public_key(), other.public_key()]);
+
+        assert!(read_public_keys("npub1invalid".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_merge_contact_list() {
+        let keys = Keys::generate();
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+
+        let existing = EventBuilder::contact_list([Contact {
+            public_key: alice,
+            relay_url: Some(relay_url.clone()),
+            alias: Some(String::from("alice")),
+        }])
+        .sign_with_keys(&keys)
+        .unwrap();
+
+        let (builder, added) = merge_contact_list(Some(&existing), [alice, bob, bob]);
+        assert_eq!(added, 1);
+
+        let event = builder.sign_with_keys(&keys).unwrap();
+        assert_eq!(event.kind, Kind::ContactList);
+        assert_eq!(
+            event.tags.public_keys().copied().collect::<Vec<_>>(),
+            vec![alice, bob]
+        );
+
+        // Relay hint and petname are preserved
+        assert_eq!(event.tags.first(), existing.tags.first());
+    }
+
+    #[test]
+    fn test_metadata_changes() {
+        let current = Metadata::new()
+            .name("alice")
+            .about("Old about")
+            .lud16("alice@example.com")
+            .custom_field("bot", false);
+
+        let changes = MetadataChanges {
+            about: Some(String::from("New about")),
+            nip05: Some(String::from("alice@example.com")),
+            identities: vec![IdentityClaim {
+                platform: ExternalIdentity::GitHub,
+                identity: String::from("alice"),
+                proof: String::from("gist-id"),
+            }],
+            ..Default::default()
+        };
+        assert!(!changes.is_empty());
+        assert!(MetadataChanges::default().is_empty());
+
+        let updated = changes.apply(current.clone());
+        assert_eq!(updated.name, current.name);
+        assert_eq!(updated.lud16, current.lud16);
+        assert_eq!(updated.custom, current.custom);
+        assert_eq!(updated.about.as_deref(), Some("New about"));
+        assert_eq!(updated.nip05.as_deref(), Some("alice@example.com"));
+        assert_eq!(updated.identities.len(), 1);
+        assert_eq!(updated.identities[0].ident, "alice");
+    }
+
+    #[test]
+    fn test_check_deletion_targets() {
+        let keys = Keys::generate();
+        let other = Keys::generate();
+
+        let own = EventBuilder::text_note("Own")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let foreign = EventBuilder::text_note("Foreign")
+            .sign_with_keys(&other)
+            .unwrap();
+        let missing = EventId::all_zeros();
+
+        let check = check_deletion_targets(
+            &[own.id, foreign.id, missing],
+            [&own, &foreign],
+            &keys.public_key(),
+        );
+        assert_eq!(
+            check,
+            DeletionCheck {
+                foreign: vec![foreign.id],
+                not_found: vec![missing],
+            }
+        );
+    }
+
+    #[test]
+    fn test_flags_names() {
+        let flags = AtomicRelayServiceFlags::new(RelayServiceFlags::READ | RelayServiceFlags::PING);
+        assert_eq!(flags_names(&flags), vec!["read", "ping"]);
+
+        let flags = AtomicRelayServiceFlags::new(RelayServiceFlags::NONE);
+        assert!(flags_names(&flags).is_empty());
+    }
+
+    #[test]
+    fn test_sync_report() {
+        let ids: Vec<EventId> = (0..6u8)
+            .map(|i| EventId::from_byte_array([i; 32]))
+            .collect();
+
+        let mut output: Output<Reconciliation> = Output::default();
+        output.val.local = HashSet::from([ids[0], ids[1]]);
+        output.val.remote = HashSet::from([ids[2], ids[3]]);
+        output.val.common = HashSet::from([ids[4], ids[5]]);
+        output.val.sent = HashSet::from([ids[0]]);
+        output.val.received = HashSet::from([ids[2], ids[3]]);
+
+        let report = SyncReport::new(&output, SyncDirection::Both);
+        assert_eq!(
+            report,
+            SyncReport {
+                received: 2,
+                sent: 1,
+                common: 2,
+                failed: 1,
+            }
+        );
+
+        // Not sent events aren't failures when syncing only down
+        let report = SyncReport::new(&output, SyncDirection::Down);
+        assert_eq!(report.failed, 0);
+    }
+
+    #[test]
+    fn test_exclude_events() {
+        let keys = Keys::generate();
+        let root = EventBuilder::text_note("Root")
+            .tag(Tag::hashtag("bitcoin"))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reply = EventBuilder::text_note("Reply")
+            .tag(Tag::event(root.id))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let other = EventBuilder::text_note("Other")
+            .tag(Tag::hashtag("nostr"))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let events = vec![root.clone(), reply.clone(), other.clone()];
+
+        let exclude_tags = vec![TagFilter::from_str("t=bitcoin").unwrap()];
+        assert_eq!(
+            exclude_events(events.clone(), &exclude_tags, false),
+            vec![reply, other.clone()]
+        );
+        assert_eq!(
+            exclude_events(events.clone(), &exclude_tags, true),
+            vec![other]
+        );
+        assert_eq!(exclude_events(events.clone(), &[], false), events);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let keys = Keys::generate();
+
+        let events: Vec<Event> = (0..5)
+            .map(|i| {
+                EventBuilder::text_note(format!("Note {i}"))
+                    .custom_created_at(Timestamp::from(i / 2))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        let mut expected: Vec<Event> = events.clone();
+        expected.sort();
+
+        // Walk all the pages
+        let mut pages: Vec<Event> = Vec::new();
+        let mut cursor: Option<Event> = None;
+        loop {
+            let page: Vec<Event> = paginate(events.clone(), cursor.as_ref(), Some(2));
+            if page.is_empty() {
+                break;
+            }
+            cursor = page.last().cloned();
+            pages.extend(page);
+        }
+
+        assert_eq!(pages, expected);
+    }
+
+    #[test]
+    fn test_sort_by_relevance() {
+        let keys = Keys::generate();
+
+        let low = EventBuilder::text_note("rust")
+            .custom_created_at(Timestamp::from(3))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let high = EventBuilder::text_note("rust nostr, nostr")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let tagged = EventBuilder::text_note("rust")
+            .tag(Tag::hashtag("nostr"))
+            .custom_created_at(Timestamp::from(2))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let tie = EventBuilder::text_note("Rust")
+            .custom_created_at(Timestamp::from(4))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let events = vec![low.clone(), high.clone(), tagged.clone(), tie.clone()];
+        let sorted = sort_events(events, ShellQuerySort::Relevance, Some("Rust nostr"));
+
+        assert_eq!(sorted, vec![high, tagged, tie, low]);
     }
 }
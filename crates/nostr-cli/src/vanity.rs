@@ -0,0 +1,66 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use nostr_sdk::nostr::key::vanity::{Error as VanityError, VanityFormat, VanityProgress};
+use nostr_sdk::prelude::*;
+use tokio::task;
+use tokio::time::{self, Instant};
+
+/// Mine keys, using all the cores, until the public key matches the prefix.
+///
+/// Print the progress every second.
+/// Returns `None` if aborted with `Ctrl-C`.
+pub async fn mine(prefix: String, format: VanityFormat) -> Result<Option<Keys>> {
+    let num_threads: usize = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1);
+
+    let progress: Arc<VanityProgress> = Arc::new(VanityProgress::new());
+
+    let handle = {
+        let progress = progress.clone();
+        task::spawn_blocking(move || {
+            Keys::vanity_parallel_with_progress(&[prefix.as_str()], format, num_threads, progress)
+        })
+    };
+    tokio::pin!(handle);
+
+    println!("Mining with {num_threads} threads (press Ctrl-C to abort)...");
+
+    let now = Instant::now();
+    let mut interval = time::interval(Duration::from_secs(1));
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut aborted: bool = false;
+
+    let res: Result<Keys, VanityError> = loop {
+        tokio::select! {
+            res = &mut handle => break res?,
+            // The threads are stopped by the abort: wait for them to exit
+            _ = &mut ctrl_c, if !aborted => {
+                progress.abort();
+                aborted = true;
+            }
+            _ = interval.tick() => {
+                let attempts: u64 = progress.attempts();
+                let rate: f64 = attempts as f64 / now.elapsed().as_secs_f64().max(f64::EPSILON);
+                print!("\rAttempts: {attempts} ({rate:.0}/sec)");
+                std::io::Write::flush(&mut std::io::stdout())?;
+            }
+        }
+    };
+
+    println!();
+
+    match res {
+        Ok(keys) => Ok(Some(keys)),
+        Err(VanityError::Aborted) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Deterministic test vectors
+
+use nostr_sdk::hashes::sha256::Hash as Sha256Hash;
+use nostr_sdk::hashes::Hash;
+use nostr_sdk::prelude::*;
+use nostr_sdk::secp256k1::rand::{CryptoRng, Error as RandError, RngCore};
+
+/// Base timestamp of the test vectors (2023-11-14T22:13:20Z)
+const BASE_TIMESTAMP: u64 = 1_700_000_000;
+/// Number of authors of the test vectors
+const NUM_AUTHORS: usize = 4;
+const WORDS: [&str; 16] = [
+    "nostr", "relay", "event", "note", "key", "zap", "tag", "filter", "sign", "hello", "world",
+    "pubkey", "bech32", "gossip", "sync", "vector",
+];
+
+/// Deterministic RNG: SHA256 of the seed and a counter
+///
+/// It doesn't depend on the `rand` implementations, so the output is stable across versions and platforms.
+struct SeededRng {
+    seed: u64,
+    counter: u64,
+    block: [u8; 32],
+    pos: usize,
+}
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            counter: 0,
+            block: [0; 32],
+            pos: 32,
+        }
+    }
+
+    fn next_block(&mut self) {
+        let mut data: [u8; 16] = [0; 16];
+        data[..8].copy_from_slice(&self.seed.to_le_bytes());
+        data[8..].copy_from_slice(&self.counter.to_le_bytes());
+
+        self.block = Sha256Hash::hash(&data).to_byte_array();
+        self.counter += 1;
+        self.pos = 0;
+    }
+
+    /// Get a number in `0..max`
+    fn below(&mut self, max: u64) -> u64 {
+        self.next_u64() % max
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf: [u8; 4] = [0; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf: [u8; 8] = [0; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            if self.pos == self.block.len() {
+                self.next_block();
+            }
+
+            *byte = self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Only for test purpose: the keys and the signatures are predictable
+impl CryptoRng for SeededRng {}
+
+fn generate_keys(rng: &mut SeededRng) -> Keys {
+    loop {
+        let mut bytes: [u8; 32] = [0; 32];
+        rng.fill_bytes(&mut bytes);
+
+        // Retry in the (very unlikely) case of invalid secret key
+        if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+            return Keys::new(secret_key);
+        }
+    }
+}
+
+fn generate_content(rng: &mut SeededRng) -> String {
+    let len: u64 = 1 + rng.below(12);
+    (0..len)
+        .map(|_| WORDS[rng.below(WORDS.len() as u64) as usize])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Generate signed events from a seeded RNG
+///
+/// The same seed always gives the same events.
+pub fn generate(count: usize, seed: u64) -> Result<Vec<Event>> {
+    let mut rng: SeededRng = SeededRng::new(seed);
+
+    let authors: Vec<Keys> = (0..NUM_AUTHORS).map(|_| generate_keys(&mut rng)).collect();
+
+    let mut events: Vec<Event> = Vec::with_capacity(count);
+
+    for index in 0..count {
+        let keys: &Keys = &authors[rng.below(NUM_AUTHORS as u64) as usize];
+        let other: PublicKey = authors[rng.below(NUM_AUTHORS as u64) as usize].public_key;
+        let created_at = Timestamp::from(BASE_TIMESTAMP + index as u64 * 60 + rng.below(60));
+
+        // Reply or react only to the previous events
+        let previous: Option<&Event> = if events.is_empty() {
+            None
+        } else {
+            events.get(rng.below(events.len() as u64) as usize)
+        };
+
+        let builder: EventBuilder = match (rng.below(5), previous) {
+            (0, ..) => EventBuilder::metadata(
+                &Metadata::new()
+                    .name(format!("author-{}", &keys.public_key.to_hex()[..8]))
+                    .about(generate_content(&mut rng)),
+            ),
+            (1, ..) => EventBuilder::contact_list([Contact::new(other)]),
+            (2, Some(target)) => EventBuilder::reaction(target, "+"),
+            (3, Some(target)) => EventBuilder::text_note(generate_content(&mut rng))
+                .tag(Tag::event(target.id))
+                .tag(Tag::public_key(target.pubkey)),
+            _ => EventBuilder::text_note(generate_content(&mut rng)),
+        };
+
+        let event: Event = builder
+            .custom_created_at(created_at)
+            .build(keys.public_key)
+            .sign_with_ctx(SECP256K1, &mut rng, keys)?;
+
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_deterministic() {
+        let events = generate(50, 42).unwrap();
+        assert_eq!(events.len(), 50);
+        assert!(events.iter().all(|e| e.verify().is_ok()));
+
+        // Same seed, same output
+        let json = |events: &[Event]| events.iter().map(|e| e.as_json()).collect::<Vec<_>>();
+        assert_eq!(json(&events), json(&generate(50, 42).unwrap()));
+
+        // Different seed, different output
+        assert_ne!(json(&events), json(&generate(50, 43).unwrap()));
+
+        // Prefix of a longer run
+        assert_eq!(json(&events[..10]), json(&generate(10, 42).unwrap()));
+    }
+}
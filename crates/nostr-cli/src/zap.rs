@@ -0,0 +1,148 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP-57 zap helpers (LNURL-pay)
+
+use bech32::{Bech32, Hrp};
+use nostr_sdk::prelude::*;
+use reqwest::Client as HttpClient;
+
+const LNURL_HRP: Hrp = Hrp::parse_unchecked("lnurl");
+
+/// LNURL-pay endpoint of the recipient
+#[derive(Debug, Clone)]
+pub struct PayEndpoint {
+    /// Callback URL to request the invoice
+    pub callback: Url,
+    /// Min amount in millisats
+    pub min_sendable: u64,
+    /// Max amount in millisats
+    pub max_sendable: u64,
+    /// Whether the endpoint supports zaps
+    pub allows_nostr: bool,
+}
+
+/// Get the LNURL-pay URL from the `lud16` (preferred) or `lud06` field of the metadata
+pub fn pay_url(metadata: &Metadata) -> Result<Url> {
+    if let Some(lud16) = &metadata.lud16 {
+        let (name, domain) = lud16
+            .split_once('@')
+            .ok_or(format!("Invalid lightning address: {lud16}"))?;
+        return Ok(Url::parse(&format!(
+            "https://{domain}/.well-known/lnurlp/{name}"
+        ))?);
+    }
+
+    if let Some(lud06) = &metadata.lud06 {
+        let (hrp, data) = bech32::decode(lud06)?;
+
+        if hrp != LNURL_HRP {
+            return Err(format!("Invalid LNURL: {lud06}").into());
+        }
+
+        return Ok(Url::parse(&String::from_utf8(data)?)?);
+    }
+
+    Err("Lightning address not found in metadata".into())
+}
+
+/// Encode the LNURL-pay URL as bech32 `lnurl`
+pub fn encode_lnurl(url: &Url) -> Result<String> {
+    Ok(bech32::encode::<Bech32>(
+        LNURL_HRP,
+        url.as_str().as_bytes(),
+    )?)
+}
+
+/// Fetch the LNURL-pay endpoint
+pub async fn fetch_pay_endpoint(http: &HttpClient, url: Url) -> Result<PayEndpoint> {
+    let res: Value = http
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let callback: &str = res
+        .get("callback")
+        .and_then(Value::as_str)
+        .ok_or("Callback not found in LNURL-pay response")?;
+
+    Ok(PayEndpoint {
+        callback: Url::parse(callback)?,
+        min_sendable: res.get("minSendable").and_then(Value::as_u64).unwrap_or(0),
+        max_sendable: res
+            .get("maxSendable")
+            .and_then(Value::as_u64)
+            .unwrap_or(u64::MAX),
+        allows_nostr: res
+            .get("allowsNostr")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+    })
+}
+
+/// Request a BOLT11 invoice for the zap request
+pub async fn fetch_invoice(
+    http: &HttpClient,
+    endpoint: &PayEndpoint,
+    amount_msat: u64,
+    zap_request: &Event,
+    lnurl: &str,
+) -> Result<String> {
+    let mut url: Url = endpoint.callback.clone();
+    url.query_pairs_mut()
+        .append_pair("amount", &amount_msat.to_string())
+        .append_pair("nostr", &zap_request.as_json())
+        .append_pair("lnurl", lnurl);
+
+    let res: Value = http
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    if let Some(reason) = res.get("reason").and_then(Value::as_str) {
+        return Err(format!("Invoice request failed: {reason}").into());
+    }
+
+    let invoice: &str = res
+        .get("pr")
+        .and_then(Value::as_str)
+        .ok_or("Invoice not found in LNURL-pay response")?;
+
+    Ok(invoice.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pay_url_lud16() {
+        let metadata = Metadata::new().lud16("alice@example.com");
+        assert_eq!(
+            pay_url(&metadata).unwrap().as_str(),
+            "https://example.com/.well-known/lnurlp/alice"
+        );
+
+        let metadata = Metadata::new().lud16("alice");
+        assert!(pay_url(&metadata).is_err());
+
+        assert!(pay_url(&Metadata::new()).is_err());
+    }
+
+    #[test]
+    fn test_pay_url_lud06() {
+        let url = Url::parse("https://example.com/.well-known/lnurlp/alice").unwrap();
+        let lnurl = encode_lnurl(&url).unwrap();
+        assert!(lnurl.starts_with("lnurl1"));
+
+        let metadata = Metadata::new().lud06(lnurl.to_uppercase());
+        assert_eq!(pay_url(&metadata).unwrap(), url);
+    }
+}
@@ -12,7 +12,6 @@ use std::time::Duration;
 use async_utility::time;
 use nostr::nips::nip46::ResponseResult;
 use nostr_relay_pool::prelude::*;
-use tokio::sync::broadcast::Receiver;
 use tokio::sync::OnceCell;
 
 use crate::error::Error;
@@ -63,6 +62,24 @@ impl NostrConnect {
         })
     }
 
+    /// Construct Nostr Connect client from a `bunker://` or `nostrconnect://` URI
+    ///
+    /// The session with the remote signer is established on the first request (i.e., [`NostrSigner::get_public_key`]).
+    /// For `nostrconnect://` URIs, the `app_keys` must match the public key of the URI.
+    #[inline]
+    pub fn from_uri<S>(
+        uri: S,
+        app_keys: Keys,
+        timeout: Duration,
+        opts: Option<RelayOptions>,
+    ) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let uri: NostrConnectURI = NostrConnectURI::parse(uri)?;
+        Self::new(uri, app_keys, timeout, opts)
+    }
+
     /// Set an `auth_url` handler
     ///
     /// ```rust
@@ -165,7 +182,7 @@ impl NostrConnect {
         Ok(remote_signer_public_key)
     }
 
-    async fn subscribe(&self) -> Result<Receiver<RelayPoolNotification>, Error> {
+    async fn subscribe(&self) -> Result<NotificationReceiver, Error> {
         let public_key: PublicKey = self.app_keys.public_key();
 
         let filter = Filter::new()
@@ -399,7 +416,7 @@ enum GetRemoteSignerPublicKey {
 
 async fn get_remote_signer_public_key(
     app_keys: &Keys,
-    mut notifications: Receiver<RelayPoolNotification>,
+    mut notifications: NotificationReceiver,
     timeout: Duration,
 ) -> Result<GetRemoteSignerPublicKey, Error> {
     time::timeout(Some(timeout), async {
@@ -0,0 +1,352 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Bloom filter database wrapper, for fast event existence checks
+
+use std::f64::consts::LN_2;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use nostr::prelude::*;
+
+use crate::{
+    Backend, DatabaseError, DatabaseEventStatus, Events, IntoNostrDatabase, NostrDatabase,
+    NostrDatabaseWipe, NostrEventsDatabase, SaveEventStatus,
+};
+
+/// Default false positive rate
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Bloom filter of event IDs
+///
+/// The event IDs are already uniformly distributed hashes,
+/// so the bit positions are derived directly from their bytes (double hashing).
+#[derive(Debug)]
+struct BloomFilter {
+    bits: Vec<AtomicU64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n: f64 = expected_items.max(1) as f64;
+        let p: f64 = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+
+        // Optimal number of bits and hash functions
+        let num_bits: u64 = (-(n * p.ln()) / (LN_2 * LN_2)).ceil().max(64.0) as u64;
+        let num_hashes: u32 = ((num_bits as f64 / n) * LN_2).round().max(1.0) as u32;
+
+        let words: usize = ((num_bits + 63) / 64) as usize;
+
+        Self {
+            bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            num_bits: words as u64 * 64,
+            num_hashes,
+        }
+    }
+
+    fn positions<'a>(&'a self, id: &EventId) -> impl Iterator<Item = u64> + 'a {
+        let bytes: &[u8; 32] = id.as_bytes();
+        let h1: u64 = u64::from_le_bytes(bytes[0..8].try_into().expect("8 bytes"));
+        let h2: u64 = u64::from_le_bytes(bytes[8..16].try_into().expect("8 bytes")) | 1;
+
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    fn insert(&self, id: &EventId) {
+        for pos in self.positions(id) {
+            self.bits[(pos / 64) as usize].fetch_or(1 << (pos % 64), Ordering::Relaxed);
+        }
+    }
+
+    /// Insert the IDs deleted by a deletion event
+    fn insert_deleted(&self, event: &Event) {
+        if event.kind == Kind::EventDeletion {
+            for id in event.tags.event_ids() {
+                self.insert(id);
+            }
+        }
+    }
+
+    /// Check if the ID may be in the set (false positives are possible, false negatives aren't)
+    fn contains(&self, id: &EventId) -> bool {
+        self.positions(id).all(|pos| {
+            self.bits[(pos / 64) as usize].load(Ordering::Relaxed) & (1 << (pos % 64)) != 0
+        })
+    }
+
+    fn clear(&self) {
+        for word in self.bits.iter() {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Database wrapper with an in-memory bloom filter of the stored event IDs
+///
+/// The bloom filter is populated on creation and updated on every saved event,
+/// so [`BloomDatabase::has_event`] and [`NostrEventsDatabase::check_id`] can skip the database lookup for the events never seen.
+/// The possible false positives fall through to the wrapped database.
+///
+/// The IDs referenced by the deletion events are added too, to not miss the [`DatabaseEventStatus::Deleted`] status.
+///
+/// All the writes must go through the wrapper, to keep the bloom filter in sync.
+#[derive(Debug)]
+pub struct BloomDatabase {
+    db: Arc<dyn NostrDatabase>,
+    bloom: BloomFilter,
+}
+
+impl BloomDatabase {
+    /// Wrap the database and populate the bloom filter with the stored event IDs
+    ///
+    /// The bloom filter is sized for `expected_events` with a 1% false positive rate.
+    /// If more events are stored, the false positive rate increases.
+    pub async fn new<T>(db: T, expected_events: usize) -> Result<Self, DatabaseError>
+    where
+        T: IntoNostrDatabase,
+    {
+        Self::with_false_positive_rate(db, expected_events, DEFAULT_FALSE_POSITIVE_RATE).await
+    }
+
+    /// Wrap the database, using a custom false positive rate for the bloom filter
+    pub async fn with_false_positive_rate<T>(
+        db: T,
+        expected_events: usize,
+        false_positive_rate: f64,
+    ) -> Result<Self, DatabaseError>
+    where
+        T: IntoNostrDatabase,
+    {
+        let db: Arc<dyn NostrDatabase> = db.into_nostr_database();
+        let bloom: BloomFilter = BloomFilter::new(expected_events, false_positive_rate);
+
+        // Populate with the stored IDs
+        let items: Vec<(EventId, Timestamp)> = db.negentropy_items(Filter::new()).await?;
+        for (id, ..) in items.iter() {
+            bloom.insert(id);
+        }
+
+        // Populate with the deleted IDs
+        let deletions: Events = db.query(Filter::new().kind(Kind::EventDeletion)).await?;
+        for deletion in deletions.iter() {
+            bloom.insert_deleted(deletion);
+        }
+
+        Ok(Self { db, bloom })
+    }
+
+    /// Get the wrapped database
+    #[inline]
+    pub fn inner(&self) -> &Arc<dyn NostrDatabase> {
+        &self.db
+    }
+
+    /// Check if the event is saved
+    ///
+    /// The database is queried only if the event ID is in the bloom filter.
+    pub async fn has_event(&self, id: &EventId) -> Result<bool, DatabaseError> {
+        if !self.bloom.contains(id) {
+            return Ok(false);
+        }
+
+        // Possible false positive: check the database
+        Ok(matches!(
+            self.db.check_id(id).await?,
+            DatabaseEventStatus::Saved
+        ))
+    }
+}
+
+impl NostrDatabase for BloomDatabase {
+    #[inline]
+    fn backend(&self) -> Backend {
+        self.db.backend()
+    }
+}
+
+impl NostrEventsDatabase for BloomDatabase {
+    fn save_event<'a>(
+        &'a self,
+        event: &'a Event,
+    ) -> BoxedFuture<'a, Result<SaveEventStatus, DatabaseError>> {
+        Box::pin(async move {
+            let status: SaveEventStatus = self.db.save_event(event).await?;
+
+            if status.is_success() {
+                self.bloom.insert(&event.id);
+                self.bloom.insert_deleted(event);
+            }
+
+            Ok(status)
+        })
+    }
+
+    fn check_id<'a>(
+        &'a self,
+        event_id: &'a EventId,
+    ) -> BoxedFuture<'a, Result<DatabaseEventStatus, DatabaseError>> {
+        Box::pin(async move {
+            if !self.bloom.contains(event_id) {
+                return Ok(DatabaseEventStatus::NotExistent);
+            }
+
+            // Possible false positive: check the database
+            self.db.check_id(event_id).await
+        })
+    }
+
+    #[inline]
+    fn has_coordinate_been_deleted<'a>(
+        &'a self,
+        coordinate: &'a CoordinateBorrow<'a>,
+        timestamp: &'a Timestamp,
+    ) -> BoxedFuture<'a, Result<bool, DatabaseError>> {
+        self.db.has_coordinate_been_deleted(coordinate, timestamp)
+    }
+
+    fn event_by_id<'a>(
+        &'a self,
+        event_id: &'a EventId,
+    ) -> BoxedFuture<'a, Result<Option<Event>, DatabaseError>> {
+        Box::pin(async move {
+            if !self.bloom.contains(event_id) {
+                return Ok(None);
+            }
+
+            self.db.event_by_id(event_id).await
+        })
+    }
+
+    #[inline]
+    fn count(&self, filter: Filter) -> BoxedFuture<Result<usize, DatabaseError>> {
+        self.db.count(filter)
+    }
+
+    #[inline]
+    fn query(&self, filter: Filter) -> BoxedFuture<Result<Events, DatabaseError>> {
+        self.db.query(filter)
+    }
+
+    #[inline]
+    fn negentropy_items(
+        &self,
+        filter: Filter,
+    ) -> BoxedFuture<Result<Vec<(EventId, Timestamp)>, DatabaseError>> {
+        self.db.negentropy_items(filter)
+    }
+
+    #[inline]
+    fn delete(&self, filter: Filter) -> BoxedFuture<Result<(), DatabaseError>> {
+        // The bloom filter doesn't support removals: the deleted IDs become false positives
+        self.db.delete(filter)
+    }
+}
+
+impl NostrDatabaseWipe for BloomDatabase {
+    fn wipe(&self) -> BoxedFuture<Result<(), DatabaseError>> {
+        Box::pin(async move {
+            self.db.wipe().await?;
+            self.bloom.clear();
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryDatabase, MemoryDatabaseOptions};
+
+    fn memory_db() -> MemoryDatabase {
+        MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        })
+    }
+
+    fn text_note(keys: &Keys, content: &str) -> Event {
+        EventBuilder::text_note(content)
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let keys = Keys::generate();
+        let bloom = BloomFilter::new(1000, 0.01);
+
+        let events: Vec<Event> = (0..100)
+            .map(|i| text_note(&keys, &format!("Note {i}")))
+            .collect();
+
+        for event in events.iter() {
+            bloom.insert(&event.id);
+        }
+
+        // No false negatives
+        assert!(events.iter().all(|e| bloom.contains(&e.id)));
+
+        bloom.clear();
+        assert!(!bloom.contains(&events[0].id));
+    }
+
+    #[tokio::test]
+    async fn test_bloom_database() {
+        let keys = Keys::generate();
+        let db = memory_db();
+
+        // Stored before wrapping
+        let stored = text_note(&keys, "Stored");
+        db.save_event(&stored).await.unwrap();
+
+        let db = BloomDatabase::new(db, 1000).await.unwrap();
+        assert!(db.has_event(&stored.id).await.unwrap());
+
+        let new = text_note(&keys, "New");
+        assert!(!db.has_event(&new.id).await.unwrap());
+        assert!(db.event_by_id(&new.id).await.unwrap().is_none());
+
+        db.save_event(&new).await.unwrap();
+        assert!(db.has_event(&new.id).await.unwrap());
+        assert_eq!(db.event_by_id(&new.id).await.unwrap(), Some(new.clone()));
+
+        // Deleted: false positive checked against the database
+        db.delete(Filter::new().id(new.id)).await.unwrap();
+        assert!(!db.has_event(&new.id).await.unwrap());
+
+        db.wipe().await.unwrap();
+        assert!(!db.has_event(&stored.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_bloom_database_check_id() {
+        let keys = Keys::generate();
+        let db = BloomDatabase::new(memory_db(), 1000).await.unwrap();
+
+        let note = text_note(&keys, "Note");
+        assert_eq!(
+            db.check_id(&note.id).await.unwrap(),
+            DatabaseEventStatus::NotExistent
+        );
+
+        db.save_event(&note).await.unwrap();
+        assert_eq!(
+            db.check_id(&note.id).await.unwrap(),
+            DatabaseEventStatus::Saved
+        );
+
+        // The deleted IDs are still reported as deleted
+        let deletion = EventBuilder::delete(EventDeletionRequest::new().id(note.id))
+            .sign_with_keys(&keys)
+            .unwrap();
+        db.save_event(&deletion).await.unwrap();
+        assert_eq!(
+            db.check_id(&note.id).await.unwrap(),
+            DatabaseEventStatus::Deleted
+        );
+    }
+}
@@ -4,9 +4,10 @@
 
 use std::collections::btree_set::IntoIter;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 
-use nostr::{Event, Filter};
+use nostr::{Event, Filter, Kind, PublicKey};
 
 use super::tree::{BTreeCappedSet, Capacity, OverCapacityPolicy};
 
@@ -114,6 +115,31 @@ impl Events {
         self
     }
 
+    /// Keep only the newest version of replaceable and addressable events
+    ///
+    /// Events are collapsed by `(kind, pubkey)` for replaceable events and by `(kind, pubkey, d-tag)`
+    /// for addressable ones. If two versions have the same `created_at`, the one with the lowest ID is kept (NIP-01).
+    /// Other events are left untouched.
+    pub fn dedup_replaceable(mut self) -> Self {
+        let mut seen: HashSet<(Kind, PublicKey, Option<String>)> = HashSet::new();
+
+        // Lookup ID: EVENT_ORD_IMPL
+        // Events are sorted by `created_at` (descending) and then by ID (ascending),
+        // so the first event of every group is the one to keep.
+        self.set.retain(|event| {
+            if event.kind.is_replaceable() {
+                seen.insert((event.kind, event.pubkey, None))
+            } else if event.kind.is_addressable() {
+                let identifier: String = event.tags.identifier().unwrap_or_default().to_string();
+                seen.insert((event.kind, event.pubkey, Some(identifier)))
+            } else {
+                true
+            }
+        });
+
+        self
+    }
+
     /// Get first [`Event`] (descending order)
     #[inline]
     pub fn first(&self) -> Option<&Event> {
@@ -168,7 +194,9 @@ impl IntoIterator for Events {
 
 #[cfg(test)]
 mod tests {
-    use nostr::{JsonUtil, Kind};
+    use std::cmp;
+
+    use nostr::{EventBuilder, JsonUtil, Keys, Tag, Timestamp};
 
     use super::*;
 
@@ -283,4 +311,81 @@ mod tests {
         assert!(events.prev_not_match);
         assert_eq!(events.set.capacity(), Capacity::Unbounded);
     }
+
+    #[test]
+    fn test_dedup_replaceable() {
+        let keys = Keys::generate();
+
+        let metadata_old = EventBuilder::new(Kind::Metadata, "old")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let metadata_new = EventBuilder::new(Kind::Metadata, "new")
+            .custom_created_at(Timestamp::from(2))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let article_a_old = EventBuilder::new(Kind::LongFormTextNote, "a old")
+            .tag(Tag::identifier("a"))
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let article_a_new = EventBuilder::new(Kind::LongFormTextNote, "a new")
+            .tag(Tag::identifier("a"))
+            .custom_created_at(Timestamp::from(2))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let article_b = EventBuilder::new(Kind::LongFormTextNote, "b")
+            .tag(Tag::identifier("b"))
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let note1 = EventBuilder::text_note("1")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let note2 = EventBuilder::text_note("2")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let mut events = Events::new(&Filter::new());
+        events.extend([
+            metadata_old,
+            metadata_new.clone(),
+            article_a_old,
+            article_a_new.clone(),
+            article_b.clone(),
+            note1.clone(),
+            note2.clone(),
+        ]);
+
+        let events = events.dedup_replaceable();
+        assert_eq!(events.len(), 5);
+        assert!(events.contains(&metadata_new));
+        assert!(events.contains(&article_a_new));
+        assert!(events.contains(&article_b));
+        assert!(events.contains(&note1));
+        assert!(events.contains(&note2));
+    }
+
+    #[test]
+    fn test_dedup_replaceable_same_timestamp() {
+        let keys = Keys::generate();
+        let first = EventBuilder::new(Kind::Metadata, "first")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let second = EventBuilder::new(Kind::Metadata, "second")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let lowest = cmp::min(first.id, second.id);
+
+        let mut events = Events::new(&Filter::new());
+        events.extend([first, second]);
+
+        let events = events.dedup_replaceable();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.first().unwrap().id, lowest);
+    }
 }
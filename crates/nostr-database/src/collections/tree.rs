@@ -286,6 +286,15 @@ where
         self.set.remove(value)
     }
 
+    /// Retain only the values specified by the predicate
+    #[inline]
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.set.retain(f)
+    }
+
     /// Get first value
     #[inline]
     pub fn first(&self) -> Option<&T>
@@ -304,6 +313,15 @@ where
         self.set.last()
     }
 
+    /// Remove and return the last value
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<T>
+    where
+        T: Ord,
+    {
+        self.set.pop_last()
+    }
+
     #[inline]
     pub fn iter(&self) -> Iter<'_, T> {
         self.set.iter()
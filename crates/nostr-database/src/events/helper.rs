@@ -12,7 +12,9 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use nostr::nips::nip01::{Coordinate, CoordinateBorrow};
-use nostr::{Alphabet, Event, EventId, Filter, Kind, PublicKey, SingleLetterTag, Timestamp};
+use nostr::{
+    Alphabet, Event, EventId, Filter, JsonUtil, Kind, PublicKey, SingleLetterTag, Timestamp,
+};
 use tokio::sync::{OwnedRwLockReadGuard, RwLock};
 
 use crate::collections::tree::{BTreeCappedSet, Capacity, InsertResult, OverCapacityPolicy};
@@ -157,6 +159,10 @@ struct InternalDatabaseHelper {
     param_replaceable_index: HashMap<(Kind, PublicKey, String), DatabaseEvent>,
     deleted_ids: HashSet<EventId>,
     deleted_coordinates: HashMap<Coordinate, Timestamp>,
+    /// Max size of the stored events, in bytes
+    max_bytes: Option<usize>,
+    /// Size of the stored events, in bytes
+    bytes: usize,
 }
 
 impl InternalDatabaseHelper {
@@ -169,6 +175,15 @@ impl InternalDatabaseHelper {
         helper
     }
 
+    pub fn with_limits(max_events: Option<usize>, max_bytes: Option<usize>) -> Self {
+        let mut helper: InternalDatabaseHelper = match max_events {
+            Some(max) => Self::bounded(max),
+            None => Self::default(),
+        };
+        helper.max_bytes = max_bytes;
+        helper
+    }
+
     // Bulk load
     //
     // NOT CHANGE `events` ARG! Processing events in ASC it's much more performant
@@ -221,6 +236,16 @@ impl InternalDatabaseHelper {
             };
         }
 
+        // Check if it can fit
+        if let Some(max_bytes) = self.max_bytes {
+            if event_size(event) > max_bytes {
+                return DatabaseEventResult {
+                    status: SaveEventStatus::Rejected(RejectedReason::Other),
+                    to_discard: HashSet::new(),
+                };
+            }
+        }
+
         let mut to_discard: HashSet<EventId> = HashSet::new();
 
         // Compose others fields
@@ -328,6 +353,7 @@ impl InternalDatabaseHelper {
             let InsertResult { inserted, pop } = self.events.insert(e.clone());
 
             if inserted {
+                self.bytes += event_size(&e);
                 self.ids.insert(e.id, e.clone());
                 self.author_index
                     .entry(author)
@@ -359,6 +385,24 @@ impl InternalDatabaseHelper {
                 to_discard.insert(event.id);
                 self.discard_event(event);
             }
+
+            // Evict the oldest events, until the size limit is respected
+            if let Some(max_bytes) = self.max_bytes {
+                while self.bytes > max_bytes {
+                    match self.events.pop_last() {
+                        Some(event) => {
+                            to_discard.insert(event.id);
+                            self.discard_event(event);
+                        }
+                        None => break,
+                    }
+                }
+
+                // Too old to fit
+                if to_discard.contains(&event.id) {
+                    status = SaveEventStatus::Rejected(RejectedReason::Other);
+                }
+            }
         }
 
         DatabaseEventResult { status, to_discard }
@@ -367,6 +411,7 @@ impl InternalDatabaseHelper {
     fn discard_events(&mut self, ids: &HashSet<EventId>) {
         for id in ids.iter() {
             if let Some(ev) = self.ids.remove(id) {
+                self.bytes = self.bytes.saturating_sub(event_size(&ev));
                 self.events.remove(&ev);
 
                 if let Some(set) = self.author_index.get_mut(&ev.pubkey) {
@@ -392,7 +437,9 @@ impl InternalDatabaseHelper {
     }
 
     fn discard_event(&mut self, ev: DatabaseEvent) {
-        self.ids.remove(&ev.id);
+        if self.ids.remove(&ev.id).is_some() {
+            self.bytes = self.bytes.saturating_sub(event_size(&ev));
+        }
 
         if let Some(set) = self.author_index.get_mut(&ev.pubkey) {
             set.remove(&ev);
@@ -649,17 +696,25 @@ impl InternalDatabaseHelper {
     }
 
     pub fn clear(&mut self) {
-        // Get current capacity
+        // Get current limits
         let capacity: Capacity = self.events.capacity();
+        let max_bytes: Option<usize> = self.max_bytes;
 
         // Reset helper to default
         *self = Self::default();
 
-        // Change capacity
+        // Restore limits
         self.events.change_capacity(capacity);
+        self.max_bytes = max_bytes;
     }
 }
 
+/// Size of the event, in bytes (JSON serialized)
+#[inline]
+fn event_size(event: &Event) -> usize {
+    event.as_json().len()
+}
+
 /// Database helper transaction
 pub struct QueryTransaction {
     guard: OwnedRwLockReadGuard<InternalDatabaseHelper>,
@@ -686,6 +741,18 @@ impl DatabaseHelper {
         }
     }
 
+    /// Database helper bounded by number of events and/or size (in bytes)
+    ///
+    /// When a limit is exceeded, the oldest events are evicted.
+    #[inline]
+    pub fn with_limits(max_events: Option<usize>, max_bytes: Option<usize>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(InternalDatabaseHelper::with_limits(
+                max_events, max_bytes,
+            ))),
+        }
+    }
+
     /// Query transaction
     #[inline]
     pub async fn qtxn(&self) -> QueryTransaction {
@@ -790,7 +857,7 @@ impl DatabaseHelper {
 
 #[cfg(test)]
 mod tests {
-    use nostr::{FromBech32, JsonUtil, Keys, SecretKey};
+    use nostr::{EventBuilder, FromBech32, Keys, SecretKey};
 
     use super::*;
 
@@ -1017,4 +1084,43 @@ mod tests {
             vec![ev]
         );
     }
+
+    #[tokio::test]
+    async fn test_bounded_by_bytes() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (1..=3)
+            .map(|i| {
+                EventBuilder::text_note("Text note")
+                    .custom_created_at(Timestamp::from(1_700_000_000 + i))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+        let size: usize = event_size(&events[0]);
+
+        let helper = DatabaseHelper::with_limits(None, Some(size * 2));
+
+        assert!(helper.index_event(&events[0]).await.status.is_success());
+        assert!(helper.index_event(&events[2]).await.status.is_success());
+
+        // Full: the oldest event is evicted
+        let res = helper.index_event(&events[1]).await;
+        assert!(res.status.is_success());
+        assert!(res.to_discard.contains(&events[0].id));
+        assert!(!helper.has_event(&events[0].id).await);
+        assert_eq!(helper.count(Filter::new()).await, 2);
+
+        // Older than all the stored events: not stored
+        let res = helper.index_event(&events[0]).await;
+        assert_eq!(res.status, SaveEventStatus::Rejected(RejectedReason::Other));
+        assert!(!helper.has_event(&events[0].id).await);
+
+        // Bigger than the limit
+        let big = EventBuilder::text_note("a".repeat(size * 2))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let res = helper.index_event(&big).await;
+        assert_eq!(res.status, SaveEventStatus::Rejected(RejectedReason::Other));
+        assert_eq!(helper.count(Filter::new()).await, 2);
+    }
 }
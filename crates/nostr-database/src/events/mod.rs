@@ -163,7 +163,7 @@ pub trait NostrEventsDatabaseExt: NostrEventsDatabase {
             let events: Events = self.query(filter).await?;
             match events.first_owned() {
                 Some(event) => Ok(Some(
-                    Metadata::from_json(event.content).map_err(DatabaseError::backend)?,
+                    Metadata::try_from(&event).map_err(DatabaseError::backend)?,
                 )),
                 None => Ok(None),
             }
@@ -210,8 +210,7 @@ pub trait NostrEventsDatabaseExt: NostrEventsDatabase {
                         .await?
                         .into_iter()
                         .map(|e| {
-                            let metadata: Metadata =
-                                Metadata::from_json(&e.content).unwrap_or_default();
+                            let metadata: Metadata = Metadata::try_from(&e).unwrap_or_default();
                             Profile::new(e.pubkey, metadata)
                         })
                         .collect();
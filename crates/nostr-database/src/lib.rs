@@ -13,16 +13,19 @@ use std::sync::Arc;
 
 pub use nostr;
 
+pub mod bloom;
 mod collections;
 mod error;
 mod events;
 #[cfg(feature = "flatbuf")]
 pub mod flatbuffers;
 pub mod memory;
+pub mod observable;
 pub mod prelude;
 pub mod profile;
 mod wipe;
 
+pub use self::bloom::BloomDatabase;
 pub use self::collections::events::Events;
 pub use self::error::DatabaseError;
 pub use self::events::helper::{DatabaseEventResult, DatabaseHelper};
@@ -33,6 +36,7 @@ pub use self::events::{
 #[cfg(feature = "flatbuf")]
 pub use self::flatbuffers::{FlatBufferBuilder, FlatBufferDecode, FlatBufferEncode};
 pub use self::memory::{MemoryDatabase, MemoryDatabaseOptions};
+pub use self::observable::ObservableDatabase;
 pub use self::profile::Profile;
 pub use self::wipe::NostrDatabaseWipe;
 
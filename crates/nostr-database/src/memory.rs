@@ -29,6 +29,12 @@ pub struct MemoryDatabaseOptions {
     ///
     /// If `Some(0)` is passed, the default value will be used.
     pub max_events: Option<usize>,
+    /// Max size of the stored events, in bytes (default: None)
+    ///
+    /// When exceeded, the oldest events are evicted. Used only if `events` is `true`.
+    ///
+    /// `None` means no limits.
+    pub max_bytes: Option<usize>,
 }
 
 impl Default for MemoryDatabaseOptions {
@@ -36,6 +42,7 @@ impl Default for MemoryDatabaseOptions {
         Self {
             events: false,
             max_events: Some(MAX_EVENTS),
+            max_bytes: None,
         }
     }
 }
@@ -82,9 +89,10 @@ impl MemoryDatabase {
 
         // Check if event storing is allowed
         let inner: InnerMemoryDatabase = if opts.events {
-            let helper: DatabaseHelper = match opts.max_events {
-                Some(max) => DatabaseHelper::bounded(max),
-                None => DatabaseHelper::unbounded(),
+            let helper: DatabaseHelper = match (opts.max_events, opts.max_bytes) {
+                (Some(max), None) => DatabaseHelper::bounded(max),
+                (None, None) => DatabaseHelper::unbounded(),
+                (max_events, max_bytes) => DatabaseHelper::with_limits(max_events, max_bytes),
             };
             InnerMemoryDatabase::Full(helper)
         } else {
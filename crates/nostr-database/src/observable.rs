@@ -0,0 +1,216 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Observable database wrapper, for local subscriptions
+
+use std::sync::Arc;
+
+use async_utility::futures_util::stream::{self, Stream};
+use nostr::prelude::*;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::{
+    Backend, DatabaseError, DatabaseEventStatus, Events, IntoNostrDatabase, NostrDatabase,
+    NostrDatabaseWipe, NostrEventsDatabase, SaveEventStatus,
+};
+
+/// Default channel size of the saved events
+const DEFAULT_CHANNEL_SIZE: usize = 1024;
+
+/// Database wrapper notifying the saved events to the local subscriptions
+///
+/// Useful to react to the events saved in background (i.e., by a sync with the relays),
+/// without subscribing to the relays.
+///
+/// All the writes must go through the wrapper, to be notified:
+/// to use it with a client, wrap it in an [`Arc`] and pass a clone to the client.
+#[derive(Debug)]
+pub struct ObservableDatabase {
+    db: Arc<dyn NostrDatabase>,
+    sender: Sender<Event>,
+}
+
+impl ObservableDatabase {
+    /// Wrap the database
+    #[inline]
+    pub fn new<T>(db: T) -> Self
+    where
+        T: IntoNostrDatabase,
+    {
+        Self::with_channel_size(db, DEFAULT_CHANNEL_SIZE)
+    }
+
+    /// Wrap the database, using a custom channel size for the saved events
+    ///
+    /// If a subscription falls behind by more than `size` events, the oldest are skipped.
+    pub fn with_channel_size<T>(db: T, size: usize) -> Self
+    where
+        T: IntoNostrDatabase,
+    {
+        let (sender, ..) = broadcast::channel(size.max(1));
+        Self {
+            db: db.into_nostr_database(),
+            sender,
+        }
+    }
+
+    /// Get the wrapped database
+    #[inline]
+    pub fn inner(&self) -> &Arc<dyn NostrDatabase> {
+        &self.db
+    }
+
+    /// Subscribe to the events matching the filter, saved from now on
+    ///
+    /// The already stored events aren't emitted: use [`NostrEventsDatabase::query`] to get them.
+    /// The stream ends when the database is dropped.
+    pub fn subscribe_local(&self, filter: Filter) -> impl Stream<Item = Event> + Send + 'static {
+        let receiver: Receiver<Event> = self.sender.subscribe();
+
+        stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if filter.match_event(&event) {
+                            return Some((event, (receiver, filter)));
+                        }
+                    }
+                    // Fell behind: skip the missed events
+                    Err(broadcast::error::RecvError::Lagged(..)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl NostrDatabase for ObservableDatabase {
+    #[inline]
+    fn backend(&self) -> Backend {
+        self.db.backend()
+    }
+}
+
+impl NostrEventsDatabase for ObservableDatabase {
+    fn save_event<'a>(
+        &'a self,
+        event: &'a Event,
+    ) -> BoxedFuture<'a, Result<SaveEventStatus, DatabaseError>> {
+        Box::pin(async move {
+            let status: SaveEventStatus = self.db.save_event(event).await?;
+
+            // Notify only the new events. The send fails only if there are no subscriptions.
+            if status.is_success() {
+                let _ = self.sender.send(event.clone());
+            }
+
+            Ok(status)
+        })
+    }
+
+    #[inline]
+    fn check_id<'a>(
+        &'a self,
+        event_id: &'a EventId,
+    ) -> BoxedFuture<'a, Result<DatabaseEventStatus, DatabaseError>> {
+        self.db.check_id(event_id)
+    }
+
+    #[inline]
+    fn has_coordinate_been_deleted<'a>(
+        &'a self,
+        coordinate: &'a CoordinateBorrow<'a>,
+        timestamp: &'a Timestamp,
+    ) -> BoxedFuture<'a, Result<bool, DatabaseError>> {
+        self.db.has_coordinate_been_deleted(coordinate, timestamp)
+    }
+
+    #[inline]
+    fn event_by_id<'a>(
+        &'a self,
+        event_id: &'a EventId,
+    ) -> BoxedFuture<'a, Result<Option<Event>, DatabaseError>> {
+        self.db.event_by_id(event_id)
+    }
+
+    #[inline]
+    fn count(&self, filter: Filter) -> BoxedFuture<Result<usize, DatabaseError>> {
+        self.db.count(filter)
+    }
+
+    #[inline]
+    fn query(&self, filter: Filter) -> BoxedFuture<Result<Events, DatabaseError>> {
+        self.db.query(filter)
+    }
+
+    #[inline]
+    fn negentropy_items(
+        &self,
+        filter: Filter,
+    ) -> BoxedFuture<Result<Vec<(EventId, Timestamp)>, DatabaseError>> {
+        self.db.negentropy_items(filter)
+    }
+
+    #[inline]
+    fn delete(&self, filter: Filter) -> BoxedFuture<Result<(), DatabaseError>> {
+        self.db.delete(filter)
+    }
+}
+
+impl NostrDatabaseWipe for ObservableDatabase {
+    #[inline]
+    fn wipe(&self) -> BoxedFuture<Result<(), DatabaseError>> {
+        self.db.wipe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_utility::futures_util::StreamExt;
+
+    use super::*;
+    use crate::{MemoryDatabase, MemoryDatabaseOptions};
+
+    #[tokio::test]
+    async fn test_subscribe_local() {
+        let keys = Keys::generate();
+        let db = ObservableDatabase::new(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        }));
+
+        let stream = db.subscribe_local(Filter::new().kind(Kind::TextNote));
+        let mut stream = Box::pin(stream);
+
+        let metadata = EventBuilder::metadata(&Metadata::new().name("Alice"))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        db.save_event(&metadata).await.unwrap();
+        db.save_event(&note).await.unwrap();
+
+        // Duplicated: not notified
+        db.save_event(&note).await.unwrap();
+
+        let received = tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert_eq!(received, Some(note));
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), stream.next())
+                .await
+                .is_err()
+        );
+
+        // Stream ends when the database is dropped
+        drop(db);
+        assert_eq!(stream.next().await, None);
+    }
+}
@@ -202,6 +202,7 @@ impl Default for RelayBuilder {
             database: Arc::new(MemoryDatabase::with_opts(MemoryDatabaseOptions {
                 events: true,
                 max_events: Some(75_000),
+                max_bytes: None,
             })),
             mode: RelayBuilderMode::default(),
             rate_limit: RateLimit::default(),
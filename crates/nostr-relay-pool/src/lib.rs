@@ -27,15 +27,23 @@ pub mod relay;
 mod shared;
 pub mod stream;
 pub mod transport;
+mod ttl;
+mod verifier;
 
-pub use self::pool::options::RelayPoolOptions;
-pub use self::pool::{Output, RelayPool, RelayPoolNotification};
+pub use self::pool::options::{
+    BatchEventOptions, EventTtl, EventTtlBasis, EventVerificationMode, NotificationOverflowPolicy,
+    RelayFilter, RelayPoolOptions,
+};
+pub use self::pool::{
+    BatchOutput, NotificationReceiver, Output, RejectReason, RelayPool, RelayPoolNotification,
+};
 pub use self::relay::flags::{AtomicRelayServiceFlags, RelayServiceFlags};
 pub use self::relay::limits::RelayLimits;
 pub use self::relay::options::{
-    RelayOptions, SubscribeAutoCloseOptions, SubscribeOptions, SyncDirection, SyncOptions,
+    BackoffPolicy, ConnectPolicy, EoseQuorum, RateLimitAction, RelayOptions,
+    SubscribeAutoCloseOptions, SubscribeOptions, SyncDirection, SyncOptions, WriteConcern,
 };
-pub use self::relay::stats::RelayConnectionStats;
+pub use self::relay::stats::{RelayConnectionStats, RelayStats};
 pub use self::relay::{Reconciliation, Relay, RelayNotification, RelayStatus};
 
 // Not public API.
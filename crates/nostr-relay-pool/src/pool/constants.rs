@@ -4,5 +4,13 @@
 
 //! Constants
 
+use std::time::Duration;
+
 /// Relay Pool default notification channel size
 pub const DEFAULT_NOTIFICATION_CHANNEL_SIZE: usize = 4096;
+
+/// Default interval between the expired events sweeps
+pub(crate) const DEFAULT_TTL_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Subscription stream channel size
+pub(crate) const SUBSCRIPTION_STREAM_CHANNEL_SIZE: usize = 1024;
@@ -6,6 +6,7 @@ use std::convert::Infallible;
 use std::fmt;
 
 use nostr::types::url;
+use nostr::{RelayUrl, SubscriptionId};
 use nostr_database::DatabaseError;
 
 use crate::__private::SharedStateError;
@@ -29,6 +30,11 @@ pub enum Error {
         /// Max numer allowed
         limit: usize,
     },
+    /// Relay not allowed by the [`RelayFilter`](crate::RelayFilter)
+    RelayNotAllowed {
+        /// Relay URL
+        url: RelayUrl,
+    },
     /// No relays
     NoRelays,
     /// No relays specified
@@ -39,6 +45,15 @@ pub enum Error {
     RelayNotFound,
     /// Relay Pool is shutdown
     Shutdown,
+    /// Subscription ID already used
+    SubscriptionExists(SubscriptionId),
+    /// The write concern isn't satisfied
+    WriteConcernNotSatisfied {
+        /// Required number of relays
+        required: usize,
+        /// Number of relays that accepted the event
+        accepted: usize,
+    },
 }
 
 impl std::error::Error for Error {}
@@ -52,11 +67,17 @@ impl fmt::Display for Error {
             Self::Database(e) => write!(f, "{e}"),
             Self::Handler(e) => write!(f, "{e}"),
             Self::TooManyRelays { limit } => write!(f, "too many relays (limit: {limit})"),
+            Self::RelayNotAllowed { url } => write!(f, "relay '{url}' not allowed"),
             Self::NoRelays => write!(f, "no relays"),
             Self::NoRelaysSpecified => write!(f, "no relays specified"),
             Self::NegentropyReconciliationFailed => write!(f, "negentropy reconciliation failed"),
             Self::RelayNotFound => write!(f, "relay not found"),
             Self::Shutdown => write!(f, "relay pool is shutdown"),
+            Self::SubscriptionExists(id) => write!(f, "subscription '{id}' already exists"),
+            Self::WriteConcernNotSatisfied { required, accepted } => write!(
+                f,
+                "write concern not satisfied (required: {required}, accepted: {accepted})"
+            ),
         }
     }
 }
@@ -5,13 +5,14 @@
 //! Relay Pool
 
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use async_utility::task;
+use async_utility::{task, time};
 use atomic_destructor::AtomicDestroyer;
 use nostr_database::prelude::*;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, Notify, RwLock};
 
 use super::options::RelayPoolOptions;
 use super::{RelayPoolBuilder, RelayPoolNotification};
@@ -26,7 +27,40 @@ pub(super) type Relays = HashMap<RelayUrl, Relay>;
 pub(super) struct AtomicPrivateData {
     pub(super) relays: RwLock<Relays>,
     pub(super) subscriptions: RwLock<HashMap<SubscriptionId, Filter>>,
+    /// Termination signals of the subscription handlers
+    pub(super) handlers: RwLock<HashMap<SubscriptionId, Arc<Notify>>>,
+    /// Termination signals of the sliding window tasks
+    pub(super) windows: RwLock<HashMap<SubscriptionId, Arc<Notify>>>,
     pub(super) shutdown: AtomicBool,
+    /// Graceful shutdown in progress: new sends are rejected
+    pub(super) draining: AtomicBool,
+    pub(super) in_flight_sends: AtomicUsize,
+    pub(super) sends_done: Notify,
+    /// Whether the expired events sweeper has been spawned
+    pub(super) ttl_sweeper: AtomicBool,
+}
+
+/// Track an in-flight send, until dropped
+pub(super) struct InFlightSend {
+    atomic: Arc<AtomicPrivateData>,
+}
+
+impl InFlightSend {
+    pub(super) fn new(atomic: &Arc<AtomicPrivateData>) -> Self {
+        atomic.in_flight_sends.fetch_add(1, Ordering::SeqCst);
+        Self {
+            atomic: atomic.clone(),
+        }
+    }
+}
+
+impl Drop for InFlightSend {
+    fn drop(&mut self) {
+        // Notify only when the last send completes
+        if self.atomic.in_flight_sends.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.atomic.sends_done.notify_waiters();
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -34,6 +68,8 @@ pub struct InnerRelayPool {
     pub(super) state: SharedState,
     pub(super) atomic: Arc<AtomicPrivateData>,
     pub(super) notification_sender: broadcast::Sender<RelayPoolNotification>, // TODO: move to shared state?
+    /// Notified when the notifications are consumed
+    pub(super) notification_drained: Arc<Notify>,
     pub(super) opts: RelayPoolOptions,
 }
 
@@ -55,14 +91,27 @@ impl InnerRelayPool {
                 builder.__signer,
                 builder.admit_policy,
                 builder.opts.nip42_auto_authentication,
+                builder.opts.dedup_cache_size,
+                builder.opts.min_pow_difficulty,
+                builder.opts.verification_mode,
                 builder.monitor,
+                builder.opts.max_concurrent_connections,
+                builder.opts.event_ttl,
+                builder.opts.relay_filter.clone(),
             ),
             atomic: Arc::new(AtomicPrivateData {
                 relays: RwLock::new(HashMap::new()),
                 subscriptions: RwLock::new(HashMap::new()),
+                handlers: RwLock::new(HashMap::new()),
+                windows: RwLock::new(HashMap::new()),
                 shutdown: AtomicBool::new(false),
+                draining: AtomicBool::new(false),
+                in_flight_sends: AtomicUsize::new(0),
+                sends_done: Notify::new(),
+                ttl_sweeper: AtomicBool::new(false),
             }),
             notification_sender,
+            notification_drained: Arc::new(Notify::new()),
             opts: builder.opts,
         }
     }
@@ -84,6 +133,40 @@ impl InnerRelayPool {
             .send(RelayPoolNotification::Shutdown);
     }
 
+    pub async fn shutdown_graceful(&self, timeout: Duration) {
+        // Already shutdown
+        if self.atomic.shutdown.load(Ordering::SeqCst) {
+            return;
+        }
+
+        // Stop accepting new sends
+        self.atomic.draining.store(true, Ordering::SeqCst);
+
+        // Wait for the in-flight sends
+        let drained = time::timeout(Some(timeout), async {
+            loop {
+                // Register the waiter before checking the counter, to not miss the notification
+                let notified = self.atomic.sends_done.notified();
+
+                if self.atomic.in_flight_sends.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+
+                notified.await;
+            }
+        })
+        .await;
+
+        if drained.is_none() {
+            tracing::warn!(
+                pending = self.atomic.in_flight_sends.load(Ordering::SeqCst),
+                "Timeout while waiting for in-flight sends, forcing shutdown."
+            );
+        }
+
+        self.shutdown().await;
+    }
+
     pub async fn force_remove_all_relays(&self) {
         // Acquire write lock
         let mut relays = self.atomic.relays.write().await;
@@ -0,0 +1,166 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Filters merging
+
+use std::collections::BTreeSet;
+
+use nostr::Filter;
+
+/// Merge compatible filters, to reduce the number of filters sent to relays
+///
+/// Two filters are compatible if they differ only by one of the `ids`, `authors` or `kinds` fields.
+/// Filters with a `limit` are never merged, since merging would change their meaning.
+///
+/// If `max_terms` is set, the merged field never exceeds the specified number of items,
+/// so the output respects the relay limits (i.e., [`RelayOptions::max_filter_terms`](crate::RelayOptions::max_filter_terms)).
+pub fn merge_filters(filters: Vec<Filter>, max_terms: Option<usize>) -> Vec<Filter> {
+    let mut merged: Vec<Filter> = Vec::with_capacity(filters.len());
+
+    'outer: for filter in filters.into_iter() {
+        for current in merged.iter_mut() {
+            if try_merge(current, &filter, max_terms) {
+                continue 'outer;
+            }
+        }
+
+        merged.push(filter);
+    }
+
+    merged
+}
+
+/// Try to merge `other` into `filter`
+///
+/// Returns `false` if the filters aren't compatible.
+fn try_merge(filter: &mut Filter, other: &Filter, max_terms: Option<usize>) -> bool {
+    if filter.limit.is_some() || other.limit.is_some() {
+        return false;
+    }
+
+    if filter.search != other.search
+        || filter.since != other.since
+        || filter.until != other.until
+        || filter.generic_tags != other.generic_tags
+    {
+        return false;
+    }
+
+    let ids: bool = filter.ids != other.ids;
+    let authors: bool = filter.authors != other.authors;
+    let kinds: bool = filter.kinds != other.kinds;
+
+    match (ids, authors, kinds) {
+        // Same filter
+        (false, false, false) => true,
+        (true, false, false) => union(&mut filter.ids, &other.ids, max_terms),
+        (false, true, false) => union(&mut filter.authors, &other.authors, max_terms),
+        (false, false, true) => union(&mut filter.kinds, &other.kinds, max_terms),
+        // Differ by more than one field
+        _ => false,
+    }
+}
+
+fn union<T>(set: &mut Option<BTreeSet<T>>, other: &Option<BTreeSet<T>>, max: Option<usize>) -> bool
+where
+    T: Ord + Clone,
+{
+    match (set.as_mut(), other) {
+        (Some(set), Some(other)) => {
+            if let Some(max) = max {
+                if set.union(other).count() > max {
+                    return false;
+                }
+            }
+
+            set.extend(other.iter().cloned());
+            true
+        }
+        // A missing field matches everything, so the union is the missing field
+        _ => {
+            *set = None;
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{Keys, Kind, PublicKey, Timestamp};
+
+    use super::*;
+
+    fn public_keys(n: usize) -> Vec<PublicKey> {
+        (0..n).map(|_| Keys::generate().public_key()).collect()
+    }
+
+    #[test]
+    fn test_merge_authors() {
+        let authors = public_keys(3);
+        let filters: Vec<Filter> = authors
+            .iter()
+            .map(|author| Filter::new().kind(Kind::TextNote).author(*author))
+            .collect();
+
+        let merged = merge_filters(filters, None);
+        assert_eq!(
+            merged,
+            vec![Filter::new().kind(Kind::TextNote).authors(authors)]
+        );
+    }
+
+    #[test]
+    fn test_merge_respects_max_terms() {
+        let authors = public_keys(5);
+        let filters: Vec<Filter> = authors
+            .iter()
+            .map(|author| Filter::new().author(*author))
+            .collect();
+
+        let merged = merge_filters(filters, Some(2));
+        assert_eq!(merged.len(), 3);
+        assert!(merged
+            .iter()
+            .all(|f| f.authors.as_ref().unwrap().len() <= 2));
+    }
+
+    #[test]
+    fn test_not_mergeable() {
+        let authors = public_keys(2);
+
+        // Different by more than one field
+        let filters = vec![
+            Filter::new().kind(Kind::TextNote).author(authors[0]),
+            Filter::new().kind(Kind::Metadata).author(authors[1]),
+        ];
+        assert_eq!(merge_filters(filters.clone(), None), filters);
+
+        // Limit
+        let filters = vec![
+            Filter::new().author(authors[0]).limit(10),
+            Filter::new().author(authors[1]).limit(10),
+        ];
+        assert_eq!(merge_filters(filters.clone(), None), filters);
+
+        // Different since
+        let filters = vec![
+            Filter::new().author(authors[0]).since(Timestamp::from(1)),
+            Filter::new().author(authors[1]).since(Timestamp::from(2)),
+        ];
+        assert_eq!(merge_filters(filters.clone(), None), filters);
+    }
+
+    #[test]
+    fn test_merge_with_missing_field() {
+        let authors = public_keys(1);
+        let filters = vec![
+            Filter::new().kind(Kind::TextNote).author(authors[0]),
+            Filter::new().kind(Kind::TextNote),
+        ];
+        assert_eq!(
+            merge_filters(filters, None),
+            vec![Filter::new().kind(Kind::TextNote)]
+        );
+    }
+}
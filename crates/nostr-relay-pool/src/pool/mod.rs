@@ -4,36 +4,47 @@
 
 //! Relay Pool
 
+use std::cmp;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use async_utility::futures_util::stream::FuturesUnordered;
 use async_utility::futures_util::{future, StreamExt};
-use async_utility::task;
+use async_utility::{task, time};
 use atomic_destructor::{AtomicDestructor, StealthClone};
 use nostr_database::prelude::*;
-use tokio::sync::{broadcast, mpsc, RwLockReadGuard};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, Notify, RwLockReadGuard};
 
 pub mod builder;
 pub mod constants;
 mod error;
 mod inner;
+mod merge;
 pub mod options;
 mod output;
+mod receiver;
 
 pub use self::builder::RelayPoolBuilder;
+use self::constants::SUBSCRIPTION_STREAM_CHANNEL_SIZE;
 pub use self::error::Error;
-use self::inner::{InnerRelayPool, Relays};
-pub use self::options::RelayPoolOptions;
-pub use self::output::Output;
+use self::inner::{InFlightSend, InnerRelayPool, Relays};
+pub use self::merge::merge_filters;
+pub use self::options::{BatchEventOptions, RelayPoolOptions};
+pub use self::output::{BatchOutput, Output, RejectReason};
+pub use self::receiver::NotificationReceiver;
 use crate::monitor::Monitor;
 use crate::relay::flags::FlagCheck;
-use crate::relay::options::{RelayOptions, ReqExitPolicy, SyncOptions};
-use crate::relay::Relay;
+use crate::relay::options::{
+    EoseAggregation, RelayOptions, ReqExitPolicy, SlidingWindow, SyncOptions, WriteConcern,
+};
+use crate::relay::{Relay, RelayStats, RelayStatus};
 use crate::shared::SharedState;
-use crate::stream::ReceiverStream;
+use crate::stream::{ReceiverStream, SubscriptionStream, SubscriptionStreamItem};
 use crate::{Reconciliation, RelayServiceFlags, SubscribeOptions};
 
 /// Relay Pool Notification
@@ -68,6 +79,68 @@ pub enum RelayPoolNotification {
         /// The received relay message.
         message: RelayMessage<'static>,
     },
+    /// Relay status changed
+    ///
+    /// This notification is sent on every status transition of a relay of the pool (i.e., connecting, connected, disconnected).
+    /// Useful to track the live status of relays, without polling [`RelayPool::relays`].
+    RelayStatus {
+        /// The URL of the relay.
+        relay_url: RelayUrl,
+        /// The new status.
+        status: RelayStatus,
+    },
+    /// Authentication to a relay failed
+    ///
+    /// This notification is sent when the automatic [NIP-42](https://github.com/nostr-protocol/nips/blob/master/42.md) authentication fails.
+    /// The subscriptions waiting for the authentication are closed.
+    AuthenticationFailed {
+        /// The URL of the relay that requested the authentication.
+        relay_url: RelayUrl,
+    },
+    /// Received a relay message that can't be parsed
+    ///
+    /// This notification is sent when a relay sends invalid JSON or a malformed message.
+    /// Valid messages of unknown type are ignored.
+    RelayMessageError {
+        /// The URL of the relay that sent the message.
+        relay_url: RelayUrl,
+        /// The raw message.
+        raw: String,
+        /// The parsing error.
+        error: String,
+    },
+    /// A relay exceeded the incoming rate limit and has been disconnected
+    ///
+    /// This notification is sent only if the [`RateLimitAction::Disconnect`](crate::relay::RateLimitAction::Disconnect)
+    /// is set (see [`RelayOptions::incoming_rate_limit`](crate::RelayOptions::incoming_rate_limit)).
+    RateLimitExceeded {
+        /// The URL of the relay.
+        relay_url: RelayUrl,
+        /// The max events per second.
+        max_per_second: u32,
+    },
+    /// A relay closed a subscription because authentication is required
+    ///
+    /// If the NIP-42 automatic authentication is enabled (see [`RelayPoolOptions::automatic_authentication`]),
+    /// the client authenticates and re-sends the subscription once.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    AuthRequired {
+        /// The URL of the relay.
+        relay_url: RelayUrl,
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+        /// The reason sent by the relay, without the `auth-required:` prefix.
+        message: String,
+    },
+    /// The stored events of a subscription have been loaded
+    ///
+    /// This notification is sent once, when the EOSE quorum of the subscription is reached
+    /// or its timeout expires (see [`SubscribeOptions::eose_quorum`]).
+    Eose {
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+    },
     /// Shutdown
     ///
     /// This notification variant is sent after [`RelayPool::shutdown`] method is called and all connections have been closed.
@@ -133,11 +206,126 @@ impl RelayPool {
         self.inner.shutdown().await
     }
 
+    /// Gracefully shutdown pool
+    ///
+    /// Stop accepting new events to send and wait up to `timeout` for the in-flight
+    /// [`RelayPool::send_event`] operations to receive the `OK` from relays,
+    /// then shutdown the pool as [`RelayPool::shutdown`] does.
+    ///
+    /// While waiting, new sends are rejected with [`Error::Shutdown`].
+    #[inline]
+    pub async fn shutdown_graceful(&self, timeout: Duration) {
+        self.inner.shutdown_graceful(timeout).await
+    }
+
     /// Get new **pool** notification listener
     ///
     /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the channel!</div>
-    pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
-        self.inner.notification_sender.subscribe()
+    pub fn notifications(&self) -> NotificationReceiver {
+        NotificationReceiver::new(
+            self.inner.notification_sender.subscribe(),
+            self.inner.notification_drained.clone(),
+        )
+    }
+
+    /// Get new **pool** notifications stream
+    ///
+    /// Unlike [`RelayPool::notifications`], the stream doesn't terminate if the consumer is too slow:
+    /// the lagged notifications are skipped (and logged).
+    /// The stream ends after yielding the [`RelayPoolNotification::Shutdown`] notification.
+    ///
+    /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the stream!</div>
+    pub fn notifications_stream(&self) -> ReceiverStream<RelayPoolNotification> {
+        let mut notifications = self.notifications();
+        let (tx, rx) = mpsc::channel(self.inner.opts.notification_channel_size);
+
+        task::spawn(async move {
+            loop {
+                match notifications.recv().await {
+                    Ok(notification) => {
+                        let shutdown: bool = notification == RelayPoolNotification::Shutdown;
+
+                        // Stream dropped, exit.
+                        if tx.send(notification).await.is_err() {
+                            break;
+                        }
+
+                        if shutdown {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            skipped,
+                            "Notifications stream lagged, skipping notifications."
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+
+    /// Get new **pool** notifications stream, scoped to specific relays
+    ///
+    /// Only the notifications of the specified relays are received,
+    /// so the listener isn't woken up for the events received by the other relays.
+    /// Unlike [`RelayPool::notifications`], the events received by more relays are notified for each of them.
+    ///
+    /// The stream ends when all the relays are removed from the pool.
+    /// Returns [`Error::RelayNotFound`] if a relay isn't in the pool.
+    ///
+    /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the stream!</div>
+    pub async fn notifications_from<I, U>(
+        &self,
+        urls: I,
+    ) -> Result<ReceiverStream<RelayPoolNotification>, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let mut receivers: Vec<broadcast::Receiver<RelayPoolNotification>> = Vec::new();
+
+        {
+            let relays = self.inner.atomic.relays.read().await;
+
+            for url in urls.into_iter() {
+                let url: RelayUrl = url.try_into_url()?;
+                let relay: &Relay = self.internal_relay(&relays, &url)?;
+                receivers.push(relay.scoped_notifications());
+            }
+        }
+
+        let (tx, rx) = mpsc::channel(self.inner.opts.notification_channel_size);
+
+        for mut notifications in receivers.into_iter() {
+            let tx = tx.clone();
+            task::spawn(async move {
+                loop {
+                    match notifications.recv().await {
+                        Ok(notification) => {
+                            // Stream dropped, exit.
+                            if tx.send(notification).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(
+                                skipped,
+                                "Scoped notifications stream lagged, skipping notifications."
+                            );
+                        }
+                        // Relay removed
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        Ok(ReceiverStream::new(rx))
     }
 
     /// Returns the reference to the monitor, if any.
@@ -159,6 +347,44 @@ impl RelayPool {
         self.inner.state.database()
     }
 
+    /// Drop the expired events from the dedup cache and, if enabled, from the database
+    ///
+    /// This is periodically done in background, if the event TTL is set.
+    /// Check [`RelayPoolOptions::event_ttl`] to learn more.
+    #[inline]
+    pub async fn prune_expired_events(&self) -> Result<(), Error> {
+        Ok(self.inner.state.prune_expired_events().await?)
+    }
+
+    fn spawn_ttl_sweeper(&self) {
+        let interval: Duration = match self.inner.state.ttl_sweep_interval() {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        // Check if already spawned
+        if self.inner.atomic.ttl_sweeper.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // Not counted as a pool reference, so it doesn't prevent the pool to be dropped
+        let pool: Self = self.stealth_clone();
+
+        task::spawn(async move {
+            loop {
+                time::sleep(interval).await;
+
+                if pool.is_shutdown() {
+                    break;
+                }
+
+                if let Err(e) = pool.prune_expired_events().await {
+                    tracing::error!(error = %e, "Impossible to prune expired events.");
+                }
+            }
+        });
+    }
+
     fn internal_relays_with_flag<'a>(
         &self,
         txn: &'a RwLockReadGuard<'a, Relays>,
@@ -222,6 +448,44 @@ impl RelayPool {
         .await
     }
 
+    /// Get `READ` and `WRITE` relays sorted by average latency (fastest first)
+    ///
+    /// Relays without enough latency samples are put at the end.
+    ///
+    /// Check [`RelayConnectionStats::latency`](crate::relay::RelayConnectionStats::latency) to learn more.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn relays_sorted_by_latency(&self) -> Vec<(RelayUrl, Option<Duration>)> {
+        let relays = self.relays().await;
+
+        let mut list: Vec<(RelayUrl, Option<Duration>)> = relays
+            .into_iter()
+            .map(|(url, relay)| {
+                let latency: Option<Duration> = relay.stats().latency();
+                (url, latency)
+            })
+            .collect();
+
+        list.sort_by(|(a_url, a), (b_url, b)| {
+            a.is_none()
+                .cmp(&b.is_none())
+                .then_with(|| a.cmp(b))
+                .then_with(|| a_url.cmp(b_url))
+        });
+
+        list
+    }
+
+    /// Get a snapshot of the stats of all relays
+    ///
+    /// Useful to check which relays are actually receiving data and which are connected but idle.
+    pub async fn health(&self) -> HashMap<RelayUrl, RelayStats> {
+        let relays = self.inner.atomic.relays.read().await;
+        relays
+            .iter()
+            .map(|(url, relay)| (url.clone(), relay.health()))
+            .collect()
+    }
+
     /// Get relays that have a certain [`RelayServiceFlags`] enabled
     pub async fn relays_with_flag(
         &self,
@@ -234,6 +498,49 @@ impl RelayPool {
             .collect()
     }
 
+    /// Get relays tagged with `tag`
+    ///
+    /// Check [`RelayOptions::tag`] to learn more.
+    pub async fn relays_with_tag(&self, tag: &str) -> HashMap<RelayUrl, Relay> {
+        let relays = self.inner.atomic.relays.read().await;
+        relays
+            .iter()
+            .filter(|(_, r)| r.has_tag(tag))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Get relay URLs with `flag`, ranked for the top-N operations
+    ///
+    /// Connected relays come first, then relays are sorted by priority (highest first) and latency (lowest first).
+    async fn relay_urls_by_priority(&self, flag: RelayServiceFlags) -> Vec<RelayUrl> {
+        let relays = self.inner.atomic.relays.read().await;
+
+        let mut list: Vec<(&RelayUrl, &Relay)> = relays
+            .iter()
+            .filter(|(_, r)| r.flags().has_all(flag))
+            .collect();
+
+        list.sort_by(|(a_url, a), (b_url, b)| {
+            b.is_connected()
+                .cmp(&a.is_connected())
+                .then_with(|| b.priority().cmp(&a.priority()))
+                .then_with(|| cmp_latency(a, b))
+                .then_with(|| a_url.cmp(b_url))
+        });
+
+        list.into_iter().map(|(url, ..)| url.clone()).collect()
+    }
+
+    async fn relay_urls_with_tag(&self, tag: &str) -> Vec<RelayUrl> {
+        let relays = self.inner.atomic.relays.read().await;
+        relays
+            .iter()
+            .filter(|(_, r)| r.has_tag(tag))
+            .map(|(k, ..)| k.clone())
+            .collect()
+    }
+
     #[inline]
     fn internal_relay<'a>(
         &self,
@@ -254,6 +561,16 @@ impl RelayPool {
         self.internal_relay(&relays, &url).cloned()
     }
 
+    /// Check if the relay is allowed by the [`RelayFilter`](crate::RelayFilter)
+    ///
+    /// Always returns `true` if the [`RelayPoolOptions::relay_filter`] is not set.
+    pub fn is_relay_allowed(&self, url: &RelayUrl) -> bool {
+        match &self.inner.opts.relay_filter {
+            Some(filter) => filter.is_allowed(url),
+            None => true,
+        }
+    }
+
     /// Add new relay
     ///
     /// If the [`RelayServiceFlags::READ`] flag is set in [`RelayOptions`]
@@ -261,7 +578,14 @@ impl RelayPool {
     /// Use [`RelayPool::subscribe_to`] method instead of [`RelayPool::subscribe`],
     /// to avoid setting pool subscriptions.
     ///
+    /// Use [`RelayOptions::read`] and [`RelayOptions::write`] to add a read-only or write-only relay.
+    /// A relay without the [`RelayServiceFlags::WRITE`] flag will never receive events sent with [`RelayPool::send_event`].
+    ///
     /// Connection is **NOT** automatically started, remember to call [`RelayPool::connect`] or [`RelayPool::connect_relay`]!
+    /// Relays with [`ConnectPolicy::Lazy`] are instead connected when first targeted by a subscription, a request or an event:
+    /// the inherited pool subscriptions are sent once connected.
+    ///
+    /// [`ConnectPolicy::Lazy`]: crate::relay::ConnectPolicy::Lazy
     #[inline]
     pub async fn add_relay<U>(&self, url: U, opts: RelayOptions) -> Result<bool, Error>
     where
@@ -284,6 +608,11 @@ impl RelayPool {
             return Ok(false);
         }
 
+        // Check if the relay is allowed
+        if !self.is_relay_allowed(&url) {
+            return Err(Error::RelayNotAllowed { url });
+        }
+
         // Check number fo relays and limit
         if let Some(max) = self.inner.opts.max_relays {
             if relays.len() >= max {
@@ -295,9 +624,12 @@ impl RelayPool {
         let mut relay: Relay = Relay::new(url, self.inner.state.clone(), opts);
 
         // Set notification sender
-        relay
-            .inner
-            .set_notification_sender(self.inner.notification_sender.clone());
+        relay.inner.set_notification_sender(
+            self.inner.notification_sender.clone(),
+            self.inner.notification_drained.clone(),
+            self.inner.opts.notification_overflow,
+            self.inner.opts.notification_channel_size,
+        );
 
         // If relay has `READ` flag, inherit pool subscriptions
         if relay.flags().has_read() {
@@ -310,6 +642,9 @@ impl RelayPool {
         // Insert relay into map
         relays.insert(relay.url().clone(), relay);
 
+        // Start expiring the events, if the event TTL is set
+        self.spawn_ttl_sweeper();
+
         Ok(true)
     }
 
@@ -416,17 +751,19 @@ impl RelayPool {
     /// A background connection task is spawned for each such relay, which then tries
     /// to establish the connection.
     /// Any relay not in one of these two statuses is skipped.
+    /// The relays with [`ConnectPolicy::Lazy`] are skipped too, until first used.
     ///
     /// For further details, see the documentation of [`Relay::connect`].
     ///
     /// [`RelayStatus::Initialized`]: crate::relay::RelayStatus::Initialized
     /// [`RelayStatus::Terminated`]: crate::relay::RelayStatus::Terminated
+    /// [`ConnectPolicy::Lazy`]: crate::relay::ConnectPolicy::Lazy
     pub async fn connect(&self) {
         // Lock with read shared access
         let relays = self.inner.atomic.relays.read().await;
 
-        // Connect
-        for relay in relays.values() {
+        // Connect (skip the lazy relays)
+        for relay in relays.values().filter(|r| !r.inner.is_lazy_and_idle()) {
             relay.connect()
         }
     }
@@ -439,9 +776,9 @@ impl RelayPool {
         // Lock with read shared access
         let relays = self.inner.atomic.relays.read().await;
 
-        // Compose futures
+        // Compose futures (skip the lazy relays not yet started)
         let mut futures = Vec::with_capacity(relays.len());
-        for relay in relays.values() {
+        for relay in relays.values().filter(|r| !r.inner.is_lazy_and_idle()) {
             futures.push(relay.wait_for_connection(timeout));
         }
 
@@ -467,8 +804,11 @@ impl RelayPool {
         let mut futures = Vec::with_capacity(relays.len());
         let mut output: Output<()> = Output::default();
 
-        // Filter only relays that can connect and compose futures
-        for relay in relays.values().filter(|r| r.status().can_connect()) {
+        // Filter only relays that can connect (skip the lazy ones) and compose futures
+        for relay in relays
+            .values()
+            .filter(|r| r.status().can_connect() && !r.inner.is_lazy_and_idle())
+        {
             urls.push(relay.url().clone());
             futures.push(relay.try_connect(timeout));
         }
@@ -579,6 +919,12 @@ impl RelayPool {
         self.inner.atomic.subscriptions.read().await.clone()
     }
 
+    /// Get the number of subscriptions
+    #[inline]
+    pub async fn subscription_count(&self) -> usize {
+        self.inner.atomic.subscriptions.read().await.len()
+    }
+
     /// Get a subscription
     #[inline]
     pub async fn subscription(&self, id: &SubscriptionId) -> Option<Filter> {
@@ -596,6 +942,26 @@ impl RelayPool {
         *current = filter;
     }
 
+    /// Register subscription in the [RelayPool], only if the ID isn't already used
+    ///
+    /// Unlike [`RelayPool::save_subscription`], doesn't replace the filter of an existing subscription:
+    /// returns [`Error::SubscriptionExists`] instead.
+    /// Useful to detect the ID collisions between different components.
+    pub async fn try_save_subscription(
+        &self,
+        id: SubscriptionId,
+        filter: Filter,
+    ) -> Result<(), Error> {
+        let mut subscriptions = self.inner.atomic.subscriptions.write().await;
+        match subscriptions.entry(id) {
+            Entry::Occupied(entry) => Err(Error::SubscriptionExists(entry.key().clone())),
+            Entry::Vacant(entry) => {
+                entry.insert(filter);
+                Ok(())
+            }
+        }
+    }
+
     async fn remove_subscription(&self, id: &SubscriptionId) {
         let mut subscriptions = self.inner.atomic.subscriptions.write().await;
         subscriptions.remove(id);
@@ -670,7 +1036,7 @@ impl RelayPool {
         // Batch messages and construct outputs
         for url in set.into_iter() {
             let relay: &Relay = self.internal_relay(&relays, &url)?;
-            match relay.batch_msg(msgs.clone()) {
+            match relay.batch_msg(msgs.clone()).await {
                 Ok(..) => {
                     // Success, insert relay url in 'success' set result
                     output.success.insert(url);
@@ -690,12 +1056,71 @@ impl RelayPool {
         self.send_event_to(urls, event).await
     }
 
+    /// Send event to the best `n` relays with `WRITE` flag
+    ///
+    /// Connected relays are preferred, then relays are ranked by [`RelayOptions::priority`] (highest first)
+    /// and by average latency (lowest first).
+    /// Useful to avoid redundant writes to many relays.
+    pub async fn send_event_to_n(&self, event: &Event, n: usize) -> Result<Output<EventId>, Error> {
+        let urls: Vec<RelayUrl> = self.relay_urls_by_priority(RelayServiceFlags::WRITE).await;
+        self.send_event_to(urls.into_iter().take(n), event).await
+    }
+
+    /// Send event to all relays with `WRITE` flag, returning as soon as the [`WriteConcern`] is satisfied
+    ///
+    /// Check [`RelayPool::send_event_to_with_concern`] to learn more.
+    pub async fn send_event_with_concern(
+        &self,
+        event: &Event,
+        concern: WriteConcern,
+    ) -> Result<Output<EventId>, Error> {
+        let urls: Vec<RelayUrl> = self.__write_relay_urls().await;
+        self.send_event_to_with_concern(urls, event, concern).await
+    }
+
     /// Send event to specific relays
+    #[inline]
     pub async fn send_event_to<I, U>(
         &self,
         urls: I,
         event: &Event,
     ) -> Result<Output<EventId>, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self._send_event_to(urls, event, None).await
+    }
+
+    /// Send event to specific relays, returning as soon as the [`WriteConcern`] is satisfied
+    ///
+    /// The slower relays aren't waited for: their pending sends are cancelled
+    /// and they are included neither in the `success` nor in the `failed` output.
+    ///
+    /// Returns [`Error::WriteConcernNotSatisfied`] if not enough relays accepted the event,
+    /// after waiting for all of them.
+    #[inline]
+    pub async fn send_event_to_with_concern<I, U>(
+        &self,
+        urls: I,
+        event: &Event,
+        concern: WriteConcern,
+    ) -> Result<Output<EventId>, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self._send_event_to(urls, event, Some(concern)).await
+    }
+
+    async fn _send_event_to<I, U>(
+        &self,
+        urls: I,
+        event: &Event,
+        concern: Option<WriteConcern>,
+    ) -> Result<Output<EventId>, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
@@ -707,6 +1132,14 @@ impl RelayPool {
             .map(|u| u.try_into_url())
             .collect::<Result<_, _>>()?;
 
+        // Track the send, so a graceful shutdown can wait for it.
+        // The flags are checked after the registration, to not race with `shutdown_graceful`.
+        let _in_flight = InFlightSend::new(&self.inner.atomic);
+
+        if self.is_shutdown() || self.inner.atomic.draining.load(Ordering::SeqCst) {
+            return Err(Error::Shutdown);
+        }
+
         // Check if urls set is empty
         if set.is_empty() {
             return Err(Error::NoRelaysSpecified);
@@ -727,8 +1160,8 @@ impl RelayPool {
         // Save event into database
         self.inner.state.database().save_event(event).await?;
 
-        let mut urls: Vec<RelayUrl> = Vec::with_capacity(set.len());
-        let mut futures = Vec::with_capacity(set.len());
+        let required: Option<usize> = concern.map(|concern| concern.required(set.len()));
+        let mut futures = FuturesUnordered::new();
         let mut output: Output<EventId> = Output {
             val: event.id,
             success: HashSet::new(),
@@ -738,15 +1171,14 @@ impl RelayPool {
         // Compose futures
         for url in set.into_iter() {
             let relay: &Relay = self.internal_relay(&relays, &url)?;
-            urls.push(url);
-            futures.push(relay.send_event(event));
+            futures.push(async move {
+                let result = relay.send_event(event).await;
+                (url, result)
+            });
         }
 
-        // Join futures
-        let list = future::join_all(futures).await;
-
-        // Iter results and construct output
-        for (url, result) in urls.into_iter().zip(list.into_iter()) {
+        // Iter results, as soon as they are available, and construct output
+        while let Some((url, result)) = futures.next().await {
             match result {
                 Ok(..) => {
                     // Success, insert relay url in 'success' set result
@@ -756,164 +1188,154 @@ impl RelayPool {
                     output.failed.insert(url, e.to_string());
                 }
             }
+
+            if let Some(required) = required {
+                // Satisfied: stop waiting for the other relays
+                if output.success.len() >= required {
+                    break;
+                }
+            }
+        }
+
+        if let Some(required) = required {
+            if output.success.len() < required {
+                return Err(Error::WriteConcernNotSatisfied {
+                    required,
+                    accepted: output.success.len(),
+                });
+            }
         }
 
         Ok(output)
     }
 
-    /// Subscribe to filters to all relays with `READ` flag.
+    /// Send multiple events, as a unit, to all relays with `WRITE` flag
     ///
-    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
-    pub async fn subscribe(
+    /// Check [`RelayPool::batch_event_to`] to learn more.
+    pub async fn batch_event(
         &self,
-        filter: Filter,
-        opts: SubscribeOptions,
-    ) -> Result<Output<SubscriptionId>, Error> {
-        let id: SubscriptionId = SubscriptionId::generate();
-        let output: Output<()> = self.subscribe_with_id(id.clone(), filter, opts).await?;
-        Ok(Output {
-            val: id,
-            success: output.success,
-            failed: output.failed,
-        })
+        events: Vec<Event>,
+        opts: BatchEventOptions,
+    ) -> Result<BatchOutput, Error> {
+        let urls: Vec<RelayUrl> = self.__write_relay_urls().await;
+        self.batch_event_to(urls, events, opts).await
     }
 
-    /// Subscribe to filters with custom [SubscriptionId] to all relays with `READ` flag.
+    /// Send multiple events, as a unit, to specific relays
     ///
-    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
-    pub async fn subscribe_with_id(
-        &self,
-        id: SubscriptionId,
-        filter: Filter,
-        opts: SubscribeOptions,
-    ) -> Result<Output<()>, Error> {
-        // Check if isn't auto-closing subscription
-        if !opts.is_auto_closing() {
-            // Save subscription
-            self.save_subscription(id.clone(), filter.clone()).await;
-        }
-
-        // Get relay urls
-        let urls: Vec<RelayUrl> = self.__read_relay_urls().await;
-
-        // Subscribe
-        self.subscribe_with_id_to(urls, id, filter, opts).await
-    }
-
-    /// Subscribe to filters to specific relays
+    /// The events are sent to every relay in order, waiting for the `OK` of each one before sending the next,
+    /// while the relays are handled concurrently.
+    /// The output includes the result of every event and a combined result for every relay:
+    /// a relay succeeds only if it accepted all the events.
     ///
-    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
-    pub async fn subscribe_to<I, U>(
+    /// Use [`BatchEventOptions::stop_on_failure`] to stop sending to a relay as soon as it rejects an event.
+    pub async fn batch_event_to<I, U>(
         &self,
         urls: I,
-        filter: Filter,
-        opts: SubscribeOptions,
-    ) -> Result<Output<SubscriptionId>, Error>
+        events: Vec<Event>,
+        opts: BatchEventOptions,
+    ) -> Result<BatchOutput, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
         Error: From<<U as TryIntoUrl>::Err>,
     {
-        let id: SubscriptionId = SubscriptionId::generate();
-        let output: Output<()> = self
-            .subscribe_with_id_to(urls, id.clone(), filter, opts)
-            .await?;
-        Ok(Output {
-            val: id,
-            success: output.success,
-            failed: output.failed,
-        })
-    }
+        // Compose URLs
+        let set: HashSet<RelayUrl> = urls
+            .into_iter()
+            .map(|u| u.try_into_url())
+            .collect::<Result<_, _>>()?;
 
-    /// Subscribe to filters with custom [SubscriptionId] to specific relays
-    ///
-    /// This method doesn't add relays!
-    /// All the relays must be added to the pool with [`RelayPool::add_relay`].
-    /// If the specified relays don't exist, [`Error::RelayNotFound`] is returned.
-    ///
-    /// ### Auto-closing subscription
-    ///
-    /// It's possible to automatically close a subscription by configuring the [SubscribeOptions].
-    ///
-    /// Auto-closing subscriptions aren't saved in the subscription map!
-    pub async fn subscribe_with_id_to<I, U>(
-        &self,
-        urls: I,
-        id: SubscriptionId,
-        filter: Filter,
-        opts: SubscribeOptions,
-    ) -> Result<Output<()>, Error>
-    where
-        I: IntoIterator<Item = U>,
-        U: TryIntoUrl,
-        Error: From<<U as TryIntoUrl>::Err>,
-    {
-        let targets = urls.into_iter().map(|u| (u, filter.clone()));
-        self.subscribe_targeted(id, targets, opts).await
-    }
+        // Track the send, so a graceful shutdown can wait for it
+        let _in_flight = InFlightSend::new(&self.inner.atomic);
 
-    /// Targeted subscription
-    ///
-    /// Subscribe to specific relays with specific filters.
-    pub async fn subscribe_targeted<I, U>(
-        &self,
-        id: SubscriptionId,
-        targets: I,
-        opts: SubscribeOptions,
-    ) -> Result<Output<()>, Error>
-    where
-        I: IntoIterator<Item = (U, Filter)>,
-        U: TryIntoUrl,
-        Error: From<<U as TryIntoUrl>::Err>,
-    {
-        // Collect targets
-        let targets: HashMap<RelayUrl, Filter> = targets
-            .into_iter()
-            .map(|(u, f)| Ok((u.try_into_url()?, f)))
-            .collect::<Result<_, Error>>()?;
+        if self.is_shutdown() || self.inner.atomic.draining.load(Ordering::SeqCst) {
+            return Err(Error::Shutdown);
+        }
 
         // Check if urls set is empty
-        if targets.is_empty() {
+        if set.is_empty() {
             return Err(Error::NoRelaysSpecified);
         }
 
         // Lock with read shared access
         let relays = self.inner.atomic.relays.read().await;
 
-        // Check if relays map is empty
         if relays.is_empty() {
             return Err(Error::NoRelays);
         }
 
         // Check if urls set contains ONLY already added relays
-        if !targets.keys().all(|url| relays.contains_key(url)) {
+        if !set.iter().all(|url| relays.contains_key(url)) {
             return Err(Error::RelayNotFound);
         }
 
-        let mut urls: Vec<RelayUrl> = Vec::with_capacity(targets.len());
-        let mut futures = Vec::with_capacity(targets.len());
-        let mut output: Output<()> = Output::default();
+        // Save events into database
+        for event in events.iter() {
+            self.inner.state.database().save_event(event).await?;
+        }
+
+        let mut output: BatchOutput = BatchOutput {
+            events: events
+                .iter()
+                .map(|event| Output {
+                    val: event.id,
+                    success: HashSet::new(),
+                    failed: HashMap::new(),
+                })
+                .collect(),
+            success: HashSet::new(),
+            failed: HashMap::new(),
+        };
 
         // Compose futures
-        for (url, filter) in targets.into_iter() {
+        let mut futures = FuturesUnordered::new();
+        for url in set.into_iter() {
             let relay: &Relay = self.internal_relay(&relays, &url)?;
-            let id: SubscriptionId = id.clone();
-            urls.push(url);
-            futures.push(relay.subscribe_with_id(id, filter, opts));
+            let events: &[Event] = &events;
+            futures.push(async move {
+                let mut results: Vec<Result<(), String>> = Vec::with_capacity(events.len());
+
+                for event in events.iter() {
+                    let result = relay.send_event(event).await.map(|_| ());
+                    let failed: bool = result.is_err();
+
+                    results.push(result.map_err(|e| e.to_string()));
+
+                    if failed && opts.stop_on_failure {
+                        break;
+                    }
+                }
+
+                (url, results)
+            });
         }
 
-        // Join futures
-        let list = future::join_all(futures).await;
+        // Iter results, as soon as they are available, and construct output
+        while let Some((url, results)) = futures.next().await {
+            let mut first_failure: Option<(EventId, String)> = None;
+
+            for (event_output, result) in output.events.iter_mut().zip(results) {
+                match result {
+                    Ok(()) => {
+                        event_output.success.insert(url.clone());
+                    }
+                    Err(e) => {
+                        if first_failure.is_none() {
+                            first_failure = Some((event_output.val, e.clone()));
+                        }
 
-        // Iter results and construct output
-        for (url, result) in urls.into_iter().zip(list.into_iter()) {
-            match result {
-                Ok(..) => {
-                    // Success, insert relay url in 'success' set result
-                    output.success.insert(url);
+                        event_output.failed.insert(url.clone(), e);
+                    }
                 }
-                Err(e) => {
-                    output.failed.insert(url, e.to_string());
+            }
+
+            match first_failure {
+                Some(failure) => {
+                    output.failed.insert(url, failure);
+                }
+                None => {
+                    output.success.insert(url);
                 }
             }
         }
@@ -921,137 +1343,479 @@ impl RelayPool {
         Ok(output)
     }
 
-    /// Unsubscribe from subscription
-    pub async fn unsubscribe(&self, id: &SubscriptionId) {
-        // Remove subscription from pool
-        self.remove_subscription(id).await;
-
-        // Lock with read shared access
-        let relays = self.inner.atomic.relays.read().await;
-
-        // TODO: use join_all and return `Output`?
-
-        // Remove subscription from relays
-        for relay in relays.values() {
-            if let Err(e) = relay.unsubscribe(id).await {
-                tracing::error!("{e}");
-            }
-        }
+    /// Subscribe to filters to all relays with `READ` flag.
+    ///
+    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe(
+        &self,
+        filter: Filter,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        let id: SubscriptionId = SubscriptionId::generate();
+        let output: Output<()> = self.subscribe_with_id(id.clone(), filter, opts).await?;
+        Ok(Output {
+            val: id,
+            success: output.success,
+            failed: output.failed,
+        })
     }
 
-    /// Unsubscribe from all subscriptions
-    pub async fn unsubscribe_all(&self) {
-        // Remove subscriptions from pool
-        self.remove_all_subscriptions().await;
-
-        // Lock with read shared access
-        let relays = self.inner.atomic.relays.read().await;
-
-        // TODO: use join_all and return `Output`?
+    /// Subscribe to filters with custom [SubscriptionId] to all relays with `READ` flag.
+    ///
+    /// If a sliding window is set (see [`SubscribeOptions::sliding_window`]),
+    /// the `since` of the filter is periodically advanced and the REQ re-issued,
+    /// until the subscription is closed with [`RelayPool::unsubscribe`] or [`RelayPool::unsubscribe_all`].
+    ///
+    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe_with_id(
+        &self,
+        id: SubscriptionId,
+        filter: Filter,
+        opts: SubscribeOptions,
+    ) -> Result<Output<()>, Error> {
+        // Auto-closing subscriptions aren't tracked by the pool
+        if opts.is_auto_closing() {
+            return self
+                .subscribe_with_id_to_read_relays(id, filter, opts)
+                .await;
+        }
 
-        // Unsubscribe relays
-        for relay in relays.values() {
-            if let Err(e) = relay.unsubscribe_all().await {
-                tracing::error!("{e}");
+        // Stop the previous window of this subscription, if any
+        self.remove_sliding_window(&id).await;
+
+        match opts.sliding_window {
+            Some(window) => {
+                let output: Output<()> = self
+                    .subscribe_with_id_to_read_relays(
+                        id.clone(),
+                        window.apply(filter.clone()),
+                        opts,
+                    )
+                    .await?;
+                self.spawn_sliding_window(id, filter, window).await;
+                Ok(output)
+            }
+            None => {
+                self.subscribe_with_id_to_read_relays(id, filter, opts)
+                    .await
             }
         }
     }
 
-    /// Sync events with relays (negentropy reconciliation)
-    pub async fn sync(
+    async fn subscribe_with_id_to_read_relays(
         &self,
+        id: SubscriptionId,
         filter: Filter,
-        opts: &SyncOptions,
-    ) -> Result<Output<Reconciliation>, Error> {
-        let urls: Vec<RelayUrl> = self.__relay_urls().await;
-        self.sync_with(urls, filter, opts).await
+        opts: SubscribeOptions,
+    ) -> Result<Output<()>, Error> {
+        // Check if isn't auto-closing subscription
+        if !opts.is_auto_closing() {
+            // Save subscription
+            self.save_subscription(id.clone(), filter.clone()).await;
+        }
+
+        // Get relay urls
+        let urls: Vec<RelayUrl> = self.__read_relay_urls().await;
+
+        // Subscribe
+        self.subscribe_with_id_to(urls, id, filter, opts).await
     }
 
-    /// Sync events with specific relays (negentropy reconciliation)
-    pub async fn sync_with<I, U>(
+    /// Subscribe to filters to specific relays
+    ///
+    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe_to<I, U>(
         &self,
         urls: I,
         filter: Filter,
-        opts: &SyncOptions,
-    ) -> Result<Output<Reconciliation>, Error>
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
         Error: From<<U as TryIntoUrl>::Err>,
     {
-        // Get items
-        let items: Vec<(EventId, Timestamp)> = self
-            .inner
-            .state
-            .database()
-            .negentropy_items(filter.clone())
+        let id: SubscriptionId = SubscriptionId::generate();
+        let output: Output<()> = self
+            .subscribe_with_id_to(urls, id.clone(), filter, opts)
             .await?;
+        Ok(Output {
+            val: id,
+            success: output.success,
+            failed: output.failed,
+        })
+    }
 
-        let tup: (Filter, Vec<(EventId, Timestamp)>) = (filter, items);
+    /// Subscribe to filters to the best `n` relays with `READ` flag
+    ///
+    /// Relays are ranked like in [`RelayPool::send_event_to_n`].
+    ///
+    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe_to_n(
+        &self,
+        filter: Filter,
+        n: usize,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        let urls: Vec<RelayUrl> = self.relay_urls_by_priority(RelayServiceFlags::READ).await;
+        self.subscribe_to(urls.into_iter().take(n), filter, opts)
+            .await
+    }
 
-        // Reconcile
-        let targets = urls.into_iter().map(|u| (u, tup.clone()));
-        self.sync_targeted(targets, opts).await
+    /// Subscribe to filters to relays tagged with `tag`
+    ///
+    /// Check [`RelayOptions::tag`] and [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe_to_tag(
+        &self,
+        tag: &str,
+        filter: Filter,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        let urls: Vec<RelayUrl> = self.relay_urls_with_tag(tag).await;
+        self.subscribe_to(urls, filter, opts).await
     }
 
-    /// Sync events with specific relays and filters (negentropy reconciliation)
-    pub async fn sync_targeted<I, U>(
+    /// Subscribe to filters to all relays with `READ` flag, routing the events to a dedicated `handler`
+    ///
+    /// The `handler` is called for every event received for this subscription,
+    /// so it may be called more times for the same event, if received from many relays.
+    /// The `handler` is removed when the subscription is closed with [`RelayPool::unsubscribe`]
+    /// or [`RelayPool::unsubscribe_all`] or when the pool is shutdown.
+    ///
+    /// Events are still sent also to the global notification channel (see [`RelayPool::notifications`]).
+    ///
+    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe_with_handler<F>(
         &self,
-        targets: I,
-        opts: &SyncOptions,
-    ) -> Result<Output<Reconciliation>, Error>
+        filter: Filter,
+        opts: SubscribeOptions,
+        handler: F,
+    ) -> Result<Output<SubscriptionId>, Error>
     where
-        I: IntoIterator<Item = (U, (Filter, Vec<(EventId, Timestamp)>))>,
-        U: TryIntoUrl,
-        Error: From<<U as TryIntoUrl>::Err>,
+        F: Fn(RelayUrl, Event) + Send + Sync + 'static,
     {
-        // Collect targets
-        let targets: HashMap<RelayUrl, (Filter, Vec<(EventId, Timestamp)>)> = targets
-            .into_iter()
-            .map(|(u, v)| Ok((u.try_into_url()?, v)))
-            .collect::<Result<_, Error>>()?;
+        let id: SubscriptionId = SubscriptionId::generate();
 
-        // Check if urls set is empty
-        if targets.is_empty() {
-            return Err(Error::NoRelaysSpecified);
-        }
+        // Spawn the handler before subscribing, to not miss any event
+        self.spawn_subscription_handler(id.clone(), handler).await;
 
-        // Lock with read shared access
-        let relays = self.inner.atomic.relays.read().await;
+        let output: Output<()> = match self.subscribe_with_id(id.clone(), filter, opts).await {
+            Ok(output) => output,
+            Err(e) => {
+                self.remove_subscription_handler(&id).await;
+                return Err(e);
+            }
+        };
 
-        // Check if empty
-        if relays.is_empty() {
-            return Err(Error::NoRelays);
-        }
+        Ok(Output {
+            val: id,
+            success: output.success,
+            failed: output.failed,
+        })
+    }
 
-        // Check if urls set contains ONLY already added relays
-        if !targets.keys().all(|url| relays.contains_key(url)) {
-            return Err(Error::RelayNotFound);
-        }
+    /// Subscribe to filters to all relays with `READ` flag, streaming the events
+    ///
+    /// Every event is yielded once, as soon as received from the first relay.
+    /// If an EOSE quorum is set (see [`SubscribeOptions::eose_quorum`]),
+    /// [`SubscriptionStreamItem::Eose`] is yielded when the stored events have been loaded.
+    ///
+    /// The subscription is closed when the stream is dropped or when the pool is shutdown.
+    /// The stream is bounded: if it isn't polled, the older notifications may be skipped.
+    ///
+    /// Check [`RelayPool::subscribe_with_id_to`] docs to learn more.
+    pub async fn subscribe_stream(
+        &self,
+        filter: Filter,
+        opts: SubscribeOptions,
+    ) -> Result<SubscriptionStream, Error> {
+        let id: SubscriptionId = SubscriptionId::generate();
 
-        // TODO: shared reconciliation output to avoid to request duplicates?
+        // Listen before subscribing, to not miss any event
+        let notifications = self.notifications();
 
-        let mut urls: Vec<RelayUrl> = Vec::with_capacity(targets.len());
-        let mut futures = Vec::with_capacity(targets.len());
-        let mut output: Output<Reconciliation> = Output::default();
+        self.subscribe_with_id(id.clone(), filter, opts).await?;
 
-        // Compose futures
-        for (url, (filter, items)) in targets.into_iter() {
-            let relay: &Relay = self.internal_relay(&relays, &url)?;
-            urls.push(url);
-            futures.push(relay.sync_with_items(filter, items, opts));
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_STREAM_CHANNEL_SIZE);
+        self.spawn_subscription_stream(id.clone(), notifications, tx);
+
+        Ok(SubscriptionStream::new(id, rx))
+    }
+
+    fn spawn_subscription_stream(
+        &self,
+        id: SubscriptionId,
+        mut notifications: NotificationReceiver,
+        tx: mpsc::Sender<SubscriptionStreamItem>,
+    ) {
+        // Not counted as a pool reference, so it doesn't prevent the pool to be dropped
+        let pool: Self = self.stealth_clone();
+
+        task::spawn(async move {
+            let mut ids: HashSet<EventId> = HashSet::new();
+
+            loop {
+                let item: SubscriptionStreamItem = tokio::select! {
+                    notification = notifications.recv() => match notification {
+                        Ok(RelayPoolNotification::Message {
+                            relay_url,
+                            message: RelayMessage::Event { subscription_id, event },
+                        }) => {
+                            if *subscription_id != id || !ids.insert(event.id) {
+                                continue;
+                            }
+
+                            SubscriptionStreamItem::Event {
+                                relay_url,
+                                event: Box::new(event.into_owned()),
+                            }
+                        }
+                        Ok(RelayPoolNotification::Eose { subscription_id }) => {
+                            if subscription_id != id {
+                                continue;
+                            }
+
+                            SubscriptionStreamItem::Eose
+                        }
+                        Ok(RelayPoolNotification::Shutdown) => break,
+                        Ok(..) => continue,
+                        Err(RecvError::Lagged(num)) => {
+                            tracing::warn!(id = %id, lagged = num, "Subscription stream lagged behind, some events were skipped.");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    // Stream dropped
+                    _ = tx.closed() => break,
+                };
+
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+
+            if !pool.is_shutdown() {
+                pool.unsubscribe(&id).await;
+            }
+
+            tracing::debug!(id = %id, "Subscription stream terminated.");
+        });
+    }
+
+    async fn spawn_subscription_handler<F>(&self, id: SubscriptionId, handler: F)
+    where
+        F: Fn(RelayUrl, Event) + Send + Sync + 'static,
+    {
+        let mut notifications = self.notifications();
+        let terminate: Arc<Notify> = Arc::new(Notify::new());
+
+        let mut handlers = self.inner.atomic.handlers.write().await;
+        handlers.insert(id.clone(), terminate.clone());
+
+        task::spawn(async move {
+            loop {
+                tokio::select! {
+                    notification = notifications.recv() => match notification {
+                        Ok(RelayPoolNotification::Message {
+                            relay_url,
+                            message: RelayMessage::Event { subscription_id, event },
+                        }) => {
+                            if *subscription_id == id {
+                                handler(relay_url, event.into_owned());
+                            }
+                        }
+                        Ok(RelayPoolNotification::Shutdown) => break,
+                        Ok(..) => {}
+                        Err(RecvError::Lagged(num)) => {
+                            tracing::warn!(id = %id, lagged = num, "Subscription handler lagged behind, some events were skipped.");
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = terminate.notified() => break,
+                }
+            }
+
+            tracing::debug!(id = %id, "Subscription handler terminated.");
+        });
+    }
+
+    async fn remove_subscription_handler(&self, id: &SubscriptionId) {
+        let mut handlers = self.inner.atomic.handlers.write().await;
+        if let Some(terminate) = handlers.remove(id) {
+            // Store a permit, in case the handler isn't waiting right now
+            terminate.notify_one();
+        }
+    }
+
+    async fn remove_all_subscription_handlers(&self) {
+        let mut handlers = self.inner.atomic.handlers.write().await;
+        for (_, terminate) in handlers.drain() {
+            terminate.notify_one();
+        }
+    }
+
+    async fn spawn_sliding_window(
+        &self,
+        id: SubscriptionId,
+        filter: Filter,
+        window: SlidingWindow,
+    ) {
+        // Not counted as a pool reference, so it doesn't prevent the pool to be dropped
+        let pool: Self = self.stealth_clone();
+        let terminate: Arc<Notify> = Arc::new(Notify::new());
+
+        let mut windows = self.inner.atomic.windows.write().await;
+        windows.insert(id.clone(), terminate.clone());
+
+        task::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = time::sleep(window.refresh_interval) => {},
+                    _ = terminate.notified() => break,
+                }
+
+                if pool.is_shutdown() {
+                    break;
+                }
+
+                // Keep the lock while re-issuing the REQ, so a concurrent unsubscribe can't be overridden
+                let windows = pool.inner.atomic.windows.read().await;
+
+                // Check if the window was removed in the meanwhile
+                if !windows
+                    .get(&id)
+                    .is_some_and(|current| Arc::ptr_eq(current, &terminate))
+                {
+                    break;
+                }
+
+                // Advance the window and re-issue the REQ
+                let filter: Filter = window.apply(filter.clone());
+                if let Err(e) = pool
+                    .subscribe_with_id_to_read_relays(
+                        id.clone(),
+                        filter,
+                        SubscribeOptions::default(),
+                    )
+                    .await
+                {
+                    tracing::error!(id = %id, error = %e, "Impossible to advance subscription window.");
+                }
+            }
+
+            tracing::debug!(id = %id, "Subscription window terminated.");
+        });
+    }
+
+    async fn remove_sliding_window(&self, id: &SubscriptionId) {
+        let mut windows = self.inner.atomic.windows.write().await;
+        if let Some(terminate) = windows.remove(id) {
+            // Store a permit, in case the task isn't waiting right now
+            terminate.notify_one();
+        }
+    }
+
+    async fn remove_all_sliding_windows(&self) {
+        let mut windows = self.inner.atomic.windows.write().await;
+        for (_, terminate) in windows.drain() {
+            terminate.notify_one();
+        }
+    }
+
+    /// Subscribe to filters with custom [SubscriptionId] to specific relays
+    ///
+    /// This method doesn't add relays!
+    /// All the relays must be added to the pool with [`RelayPool::add_relay`].
+    /// If the specified relays don't exist, [`Error::RelayNotFound`] is returned.
+    ///
+    /// ### Auto-closing subscription
+    ///
+    /// It's possible to automatically close a subscription by configuring the [SubscribeOptions].
+    ///
+    /// Auto-closing subscriptions aren't saved in the subscription map!
+    pub async fn subscribe_with_id_to<I, U>(
+        &self,
+        urls: I,
+        id: SubscriptionId,
+        filter: Filter,
+        opts: SubscribeOptions,
+    ) -> Result<Output<()>, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let targets = urls.into_iter().map(|u| (u, filter.clone()));
+        self.subscribe_targeted(id, targets, opts).await
+    }
+
+    /// Targeted subscription
+    ///
+    /// Subscribe to specific relays with specific filters.
+    pub async fn subscribe_targeted<I, U>(
+        &self,
+        id: SubscriptionId,
+        targets: I,
+        opts: SubscribeOptions,
+    ) -> Result<Output<()>, Error>
+    where
+        I: IntoIterator<Item = (U, Filter)>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        // Collect targets
+        let targets: HashMap<RelayUrl, Filter> = targets
+            .into_iter()
+            .map(|(u, f)| Ok((u.try_into_url()?, f)))
+            .collect::<Result<_, Error>>()?;
+
+        // Check if urls set is empty
+        if targets.is_empty() {
+            return Err(Error::NoRelaysSpecified);
+        }
+
+        // Lock with read shared access
+        let relays = self.inner.atomic.relays.read().await;
+
+        // Check if relays map is empty
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        // Check if urls set contains ONLY already added relays
+        if !targets.keys().all(|url| relays.contains_key(url)) {
+            return Err(Error::RelayNotFound);
+        }
+
+        let mut urls: Vec<RelayUrl> = Vec::with_capacity(targets.len());
+        let mut futures = Vec::with_capacity(targets.len());
+        let mut output: Output<()> = Output::default();
+
+        // Listen before subscribing, to not miss any EOSE
+        let eose_tracker = match opts.eose_aggregation {
+            Some(aggregation) if !opts.is_auto_closing() => {
+                Some((aggregation, self.notifications()))
+            }
+            _ => None,
+        };
+
+        // Compose futures
+        for (url, filter) in targets.into_iter() {
+            let relay: &Relay = self.internal_relay(&relays, &url)?;
+            let id: SubscriptionId = id.clone();
+            urls.push(url);
+            futures.push(relay.subscribe_with_id(id, filter, opts));
         }
 
         // Join futures
         let list = future::join_all(futures).await;
 
-        // Iter results and constructs output
+        // Iter results and construct output
         for (url, result) in urls.into_iter().zip(list.into_iter()) {
             match result {
-                Ok(reconciliation) => {
+                Ok(..) => {
                     // Success, insert relay url in 'success' set result
                     output.success.insert(url);
-                    output.merge(reconciliation);
                 }
                 Err(e) => {
                     output.failed.insert(url, e.to_string());
@@ -1059,260 +1823,1692 @@ impl RelayPool {
             }
         }
 
-        // Check if sync failed (no success)
-        if output.success.is_empty() {
-            return Err(Error::NegentropyReconciliationFailed);
+        if let Some((aggregation, notifications)) = eose_tracker {
+            if !output.success.is_empty() {
+                self.spawn_eose_tracker(id, output.success.clone(), aggregation, notifications);
+            }
         }
 
         Ok(output)
     }
 
-    /// Fetch events from relays with [`RelayServiceFlags::READ`] flag.
-    pub async fn fetch_events(
+    fn spawn_eose_tracker(
+        &self,
+        id: SubscriptionId,
+        relays: HashSet<RelayUrl>,
+        aggregation: EoseAggregation,
+        mut notifications: NotificationReceiver,
+    ) {
+        let sender = self.inner.notification_sender.clone();
+        let required: usize = aggregation.quorum.required(relays.len());
+
+        task::spawn(async move {
+            let mut done: HashSet<RelayUrl> = HashSet::with_capacity(relays.len());
+
+            let res = time::timeout(Some(aggregation.timeout), async {
+                while done.len() < required {
+                    match notifications.recv().await {
+                        Ok(RelayPoolNotification::Message { relay_url, message }) => {
+                            // A closed subscription will never send the EOSE
+                            let subscription_id = match &message {
+                                RelayMessage::EndOfStoredEvents(subscription_id) => subscription_id,
+                                RelayMessage::Closed {
+                                    subscription_id, ..
+                                } => subscription_id,
+                                _ => continue,
+                            };
+
+                            if **subscription_id == id && relays.contains(&relay_url) {
+                                done.insert(relay_url);
+                            }
+                        }
+                        Ok(RelayPoolNotification::Shutdown) => return false,
+                        Ok(..) => {}
+                        Err(RecvError::Lagged(num)) => {
+                            tracing::warn!(id = %id, lagged = num, "EOSE tracker lagged behind, some notifications were skipped.");
+                        }
+                        Err(RecvError::Closed) => return false,
+                    }
+                }
+
+                true
+            })
+            .await;
+
+            match res {
+                Some(false) => return,
+                Some(true) => {
+                    tracing::debug!(id = %id, relays = done.len(), "EOSE quorum reached.");
+                }
+                None => {
+                    tracing::debug!(id = %id, relays = done.len(), required, "EOSE quorum not reached before timeout.");
+                }
+            }
+
+            let _ = sender.send(RelayPoolNotification::Eose {
+                subscription_id: id,
+            });
+        });
+    }
+
+    /// Unsubscribe from subscription
+    pub async fn unsubscribe(&self, id: &SubscriptionId) {
+        // Stop sliding window, if any
+        self.remove_sliding_window(id).await;
+
+        // Remove subscription from pool
+        self.remove_subscription(id).await;
+
+        // Terminate handler, if any
+        self.remove_subscription_handler(id).await;
+
+        // Lock with read shared access
+        let relays = self.inner.atomic.relays.read().await;
+
+        // TODO: use join_all and return `Output`?
+
+        // Remove subscription from relays
+        for relay in relays.values() {
+            if let Err(e) = relay.unsubscribe(id).await {
+                tracing::error!("{e}");
+            }
+        }
+    }
+
+    /// Unsubscribe from all subscriptions
+    pub async fn unsubscribe_all(&self) {
+        // Stop sliding windows
+        self.remove_all_sliding_windows().await;
+
+        // Remove subscriptions from pool
+        self.remove_all_subscriptions().await;
+
+        // Terminate handlers
+        self.remove_all_subscription_handlers().await;
+
+        // Lock with read shared access
+        let relays = self.inner.atomic.relays.read().await;
+
+        // TODO: use join_all and return `Output`?
+
+        // Unsubscribe relays
+        for relay in relays.values() {
+            if let Err(e) = relay.unsubscribe_all().await {
+                tracing::error!("{e}");
+            }
+        }
+    }
+
+    /// Sync events with relays (negentropy reconciliation)
+    pub async fn sync(
         &self,
         filter: Filter,
-        timeout: Duration,
-        policy: ReqExitPolicy,
-    ) -> Result<Events, Error> {
-        let urls: Vec<RelayUrl> = self.__read_relay_urls().await;
-        self.fetch_events_from(urls, filter, timeout, policy).await
+        opts: &SyncOptions,
+    ) -> Result<Output<Reconciliation>, Error> {
+        let urls: Vec<RelayUrl> = self.__relay_urls().await;
+        self.sync_with(urls, filter, opts).await
     }
 
-    /// Fetch events from specific relays
-    pub async fn fetch_events_from<I, U>(
+    /// Sync events with specific relays (negentropy reconciliation)
+    pub async fn sync_with<I, U>(
         &self,
         urls: I,
         filter: Filter,
-        timeout: Duration,
-        policy: ReqExitPolicy,
-    ) -> Result<Events, Error>
+        opts: &SyncOptions,
+    ) -> Result<Output<Reconciliation>, Error>
     where
         I: IntoIterator<Item = U>,
         U: TryIntoUrl,
         Error: From<<U as TryIntoUrl>::Err>,
     {
-        let mut events: Events = Events::new(&filter);
+        // Get items
+        let items: Vec<(EventId, Timestamp)> = self
+            .inner
+            .state
+            .database()
+            .negentropy_items(filter.clone())
+            .await?;
+
+        let tup: (Filter, Vec<(EventId, Timestamp)>) = (filter, items);
+
+        // Reconcile
+        let targets = urls.into_iter().map(|u| (u, tup.clone()));
+        self.sync_targeted(targets, opts).await
+    }
+
+    /// Sync events with specific relays and filters (negentropy reconciliation)
+    pub async fn sync_targeted<I, U>(
+        &self,
+        targets: I,
+        opts: &SyncOptions,
+    ) -> Result<Output<Reconciliation>, Error>
+    where
+        I: IntoIterator<Item = (U, (Filter, Vec<(EventId, Timestamp)>))>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        // Collect targets
+        let targets: HashMap<RelayUrl, (Filter, Vec<(EventId, Timestamp)>)> = targets
+            .into_iter()
+            .map(|(u, v)| Ok((u.try_into_url()?, v)))
+            .collect::<Result<_, Error>>()?;
+
+        // Check if urls set is empty
+        if targets.is_empty() {
+            return Err(Error::NoRelaysSpecified);
+        }
+
+        // Lock with read shared access
+        let relays = self.inner.atomic.relays.read().await;
+
+        // Check if empty
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        // Check if urls set contains ONLY already added relays
+        if !targets.keys().all(|url| relays.contains_key(url)) {
+            return Err(Error::RelayNotFound);
+        }
+
+        // TODO: shared reconciliation output to avoid to request duplicates?
+
+        let mut urls: Vec<RelayUrl> = Vec::with_capacity(targets.len());
+        let mut futures = Vec::with_capacity(targets.len());
+        let mut output: Output<Reconciliation> = Output::default();
+
+        // Compose futures
+        for (url, (filter, items)) in targets.into_iter() {
+            let relay: &Relay = self.internal_relay(&relays, &url)?;
+            urls.push(url);
+            futures.push(relay.sync_with_items(filter, items, opts));
+        }
+
+        // Join futures
+        let list = future::join_all(futures).await;
+
+        // Iter results and constructs output
+        for (url, result) in urls.into_iter().zip(list.into_iter()) {
+            match result {
+                Ok(reconciliation) => {
+                    // Success, insert relay url in 'success' set result
+                    output.success.insert(url);
+                    output.merge(reconciliation);
+                }
+                Err(e) => {
+                    output.failed.insert(url, e.to_string());
+                }
+            }
+        }
+
+        // Check if sync failed (no success)
+        if output.success.is_empty() {
+            return Err(Error::NegentropyReconciliationFailed);
+        }
+
+        Ok(output)
+    }
+
+    /// Fetch events from relays with [`RelayServiceFlags::READ`] flag.
+    pub async fn fetch_events(
+        &self,
+        filter: Filter,
+        timeout: Duration,
+        policy: ReqExitPolicy,
+    ) -> Result<Events, Error> {
+        let urls: Vec<RelayUrl> = self.__read_relay_urls().await;
+        self.fetch_events_from(urls, filter, timeout, policy).await
+    }
+
+    /// Fetch events from specific relays
+    pub async fn fetch_events_from<I, U>(
+        &self,
+        urls: I,
+        filter: Filter,
+        timeout: Duration,
+        policy: ReqExitPolicy,
+    ) -> Result<Events, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let mut events: Events = Events::new(&filter);
+
+        // Stream events
+        let mut stream = self
+            .stream_events_from(urls, filter, timeout, policy)
+            .await?;
+        while let Some(event) = stream.next().await {
+            // To find out more about why the `force_insert` was used, search for EVENTS_FORCE_INSERT ine the code.
+            events.force_insert(event);
+        }
+
+        Ok(events)
+    }
+
+    /// Fetch events from relays tagged with `tag`
+    ///
+    /// Check [`RelayOptions::tag`] to learn more.
+    pub async fn fetch_events_from_tag(
+        &self,
+        tag: &str,
+        filter: Filter,
+        timeout: Duration,
+        policy: ReqExitPolicy,
+    ) -> Result<Events, Error> {
+        let urls: Vec<RelayUrl> = self.relay_urls_with_tag(tag).await;
+        self.fetch_events_from(urls, filter, timeout, policy).await
+    }
+
+    /// Stream events from relays with `READ` flag.
+    pub async fn stream_events(
+        &self,
+        filter: Filter,
+        timeout: Duration,
+        policy: ReqExitPolicy,
+    ) -> Result<ReceiverStream<Event>, Error> {
+        let urls: Vec<RelayUrl> = self.__read_relay_urls().await;
+        self.stream_events_from(urls, filter, timeout, policy).await
+    }
+
+    /// Stream events from specific relays
+    pub async fn stream_events_from<I, U>(
+        &self,
+        urls: I,
+        filter: Filter,
+        timeout: Duration,
+        policy: ReqExitPolicy,
+    ) -> Result<ReceiverStream<Event>, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let targets = urls
+            .into_iter()
+            .map(|u| Ok((u.try_into_url()?, filter.clone())))
+            .collect::<Result<_, Error>>()?;
+        self.stream_events_targeted(targets, timeout, policy).await
+    }
+
+    /// Targeted streaming events
+    ///
+    /// Stream events from specific relays with specific filters
+    pub async fn stream_events_targeted(
+        &self,
+        targets: HashMap<RelayUrl, Filter>,
+        timeout: Duration,
+        policy: ReqExitPolicy,
+    ) -> Result<ReceiverStream<Event>, Error> {
+        // Check if `targets` map is empty
+        if targets.is_empty() {
+            return Err(Error::NoRelaysSpecified);
+        }
+
+        // Lock with read shared access
+        let relays = self.inner.atomic.relays.read().await;
+
+        // Check if empty
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        // Construct new map with also `Relay` struct
+        let mut map: HashMap<RelayUrl, (Relay, Filter)> = HashMap::with_capacity(targets.len());
+
+        // Populate the new map.
+        // Return an error if the relay doesn't exists.
+        for (url, filter) in targets.into_iter() {
+            // Get relay
+            let relay: Relay = self.internal_relay(&relays, &url).cloned()?;
+
+            // Insert into new map
+            map.insert(url, (relay, filter));
+        }
+
+        // Drop relays read guard
+        drop(relays);
+
+        // Create channel
+        // TODO: use unbounded channel otherwise events can be lost since below the `try_send` is used.
+        let (tx, rx) = mpsc::channel::<Event>(map.len() * 512);
+
+        // Spawn stream task
+        task::spawn(async move {
+            // IDs collection, needed to check if an event was already sent to the stream
+            let ids: Mutex<HashSet<EventId>> = Mutex::new(HashSet::new());
+
+            let mut urls: Vec<RelayUrl> = Vec::with_capacity(map.len());
+            let mut futures = Vec::with_capacity(map.len());
+
+            // Populate `urls` and `futures` vectors
+            for (url, (relay, filter)) in map.into_iter() {
+                urls.push(url);
+                futures.push(relay.fetch_events_with_callback_owned(
+                    filter,
+                    timeout,
+                    policy,
+                    |event| {
+                        // Use a synchronous mutex here!
+                        //
+                        // From tokio docs:
+                        // ```
+                        // A synchronous mutex will block the current thread when waiting to acquire the lock.
+                        // This, in turn, will block other tasks from processing.
+                        // However, switching to tokio::sync::Mutex usually does not help as the asynchronous mutex uses a synchronous mutex internally.
+                        //
+                        // As a rule of thumb, using a synchronous mutex from within asynchronous code is fine as long
+                        // as contention remains low and the lock is not held across calls to .await.
+                        // ```
+                        //
+                        // SAFETY: panics only if another user of this mutex panicked while holding the mutex.
+                        let mut ids = ids.lock().unwrap();
+
+                        // Check if ID was already seen or insert into set.
+                        if ids.insert(event.id) {
+                            // Immediately drop the set
+                            drop(ids);
+
+                            // Send event
+                            let _ = tx.try_send(event);
+                        }
+                    },
+                ));
+            }
+
+            // Join all futures
+            let list = future::join_all(futures).await;
+
+            // Iter results
+            for (url, result) in urls.into_iter().zip(list.into_iter()) {
+                if let Err(e) = result {
+                    tracing::error!(url = %url, error = %e, "Failed to stream events.");
+                }
+            }
+        });
+
+        // Return stream
+        Ok(ReceiverStream::new(rx))
+    }
+
+    /// Handle notifications
+    pub async fn handle_notifications<F, Fut>(&self, func: F) -> Result<(), Error>
+    where
+        F: Fn(RelayPoolNotification) -> Fut,
+        Fut: Future<Output = Result<bool>>,
+    {
+        let mut notifications = self.notifications();
+        while let Ok(notification) = notifications.recv().await {
+            let shutdown: bool = RelayPoolNotification::Shutdown == notification;
+            let exit: bool = func(notification)
+                .await
+                .map_err(|e| Error::Handler(e.to_string()))?;
+            if exit || shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return `true` if the relay can be removed
+///
+/// If it CAN'T be removed,
+/// the flags are automatically updated (remove `READ`, `WRITE` and `DISCOVERY` flags).
+fn can_remove_relay(relay: &Relay) -> bool {
+    let flags = relay.flags();
+    if flags.has_any(RelayServiceFlags::GOSSIP) {
+        // Remove READ, WRITE and DISCOVERY flags
+        flags.remove(
+            RelayServiceFlags::READ | RelayServiceFlags::WRITE | RelayServiceFlags::DISCOVERY,
+        );
+
+        // Relay has `GOSSIP` flag so it can't be removed.
+        return false;
+    }
+
+    // Relay can be removed
+    true
+}
+
+/// Compare relays by average latency (lowest first)
+///
+/// Relays without enough latency samples are put at the end.
+fn cmp_latency(a: &Relay, b: &Relay) -> cmp::Ordering {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let a: Option<Duration> = a.stats().latency();
+        let b: Option<Duration> = b.stats().latency();
+        a.is_none().cmp(&b.is_none()).then_with(|| a.cmp(&b))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = (a, b);
+        cmp::Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::{
+        LocalRelay, RelayBuilder, RelayBuilderNip42, RelayBuilderNip42Mode,
+    };
+    use nostr_relay_builder::MockRelay;
+
+    use super::options::{EventTtl, EventTtlBasis, NotificationOverflowPolicy, RelayFilter};
+    use super::*;
+    use crate::relay::{ConnectPolicy, EoseQuorum, SubscribeAutoCloseOptions};
+
+    fn relay_gossip_opts() -> RelayOptions {
+        let mut flags: RelayServiceFlags = RelayServiceFlags::default();
+        flags.add(RelayServiceFlags::GOSSIP);
+        RelayOptions::default().flags(flags)
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect().await;
+
+        assert!(!pool.is_shutdown());
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        pool.shutdown().await;
+
+        assert!(pool.is_shutdown());
+
+        assert!(matches!(
+            pool.add_relay(&url, RelayOptions::default())
+                .await
+                .unwrap_err(),
+            Error::Shutdown
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_event_skip_read_only_relays() {
+        let mock = MockRelay::run().await.unwrap();
+        let write_url = RelayUrl::parse(&mock.url()).unwrap();
+        let read_url = RelayUrl::parse("ws://127.0.0.1:6666").unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&write_url, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&read_url, RelayOptions::default().write(false))
+            .await
+            .unwrap();
+
+        // Check flags
+        let relay = pool.relay(&read_url).await.unwrap();
+        assert!(relay.flags().has_read());
+        assert!(!relay.flags().has_write());
+        assert_eq!(pool.__write_relay_urls().await, vec![write_url.clone()]);
+
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        // Send event
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let output = pool.send_event(&event).await.unwrap();
+
+        assert!(output.success.contains(&write_url));
+        assert!(!output.success.contains(&read_url));
+        assert!(!output.failed.contains_key(&read_url));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_graceful() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect().await;
+
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        // In-flight send
+        let p = pool.clone();
+        let e = event.clone();
+        let handle = tokio::spawn(async move { p.send_event(&e).await });
+
+        // Let the send start
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        pool.shutdown_graceful(Duration::from_secs(5)).await;
+
+        assert!(pool.is_shutdown());
+
+        // The in-flight send has been drained
+        let output = handle.await.unwrap().unwrap();
+        assert!(output.success.contains(&url));
+
+        // New sends are rejected
+        assert!(matches!(
+            pool.send_event(&event).await.unwrap_err(),
+            Error::Shutdown
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_relay_status_notification() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        let mut notifications = pool.notifications();
+
+        pool.connect().await;
+
+        let statuses = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut statuses: Vec<RelayStatus> = Vec::new();
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::RelayStatus { relay_url, status } = notification {
+                    assert_eq!(relay_url, url);
+                    statuses.push(status);
+
+                    if status.is_connected() {
+                        break;
+                    }
+                }
+            }
+            statuses
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            statuses,
+            vec![
+                RelayStatus::Pending,
+                RelayStatus::Connecting,
+                RelayStatus::Connected
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_with_handler() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect().await;
+
+        let keys = Keys::generate();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let output = pool
+            .subscribe_with_handler(
+                Filter::new().author(keys.public_key()),
+                SubscribeOptions::default(),
+                move |relay_url, event| {
+                    let _ = tx.send((relay_url, event));
+                },
+            )
+            .await
+            .unwrap();
+
+        // Other subscriptions aren't routed to the handler
+        pool.subscribe(
+            Filter::new().kind(Kind::TextNote),
+            SubscribeOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event(&event).await.unwrap();
+
+        let (relay_url, received) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(relay_url, url);
+        assert_eq!(received, event);
+
+        // Handler terminated on unsubscribe: the sender is dropped
+        pool.unsubscribe(&output.val).await;
+
+        let closed = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap();
+        assert!(closed.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_sliding_window() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect().await;
+
+        let opts = SubscribeOptions::default()
+            .sliding_window(Duration::from_secs(60 * 60), Duration::from_secs(1));
+        let output = pool
+            .subscribe(Filter::new().kind(Kind::TextNote), opts)
+            .await
+            .unwrap();
+        let id = output.val;
+
+        let first = pool.subscription(&id).await.unwrap();
+        let first_since = first.since.unwrap();
+        assert!(first_since <= Timestamp::now() - Duration::from_secs(60 * 60));
+
+        // Wait for the window to advance
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+
+        let advanced = pool.subscription(&id).await.unwrap();
+        assert!(advanced.since.unwrap() > first_since);
+        assert_eq!(advanced.kinds, first.kinds);
+
+        // Window stopped on unsubscribe
+        pool.unsubscribe(&id).await;
+        assert!(pool.inner.atomic.windows.read().await.is_empty());
+        assert!(pool.subscription(&id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_eose_quorum() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url1, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&url2, RelayOptions::default())
+            .await
+            .unwrap();
+
+        pool.connect().await;
+
+        let mut notifications = pool.notifications();
+
+        let opts =
+            SubscribeOptions::default().eose_quorum(EoseQuorum::All, Duration::from_secs(10));
+        let output = pool
+            .subscribe(Filter::new().kind(Kind::TextNote), opts)
+            .await
+            .unwrap();
+        assert_eq!(output.success.len(), 2);
+
+        let mut eose: usize = 0;
+
+        // Wait for the aggregated EOSE
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match notifications.recv().await.unwrap() {
+                    RelayPoolNotification::Message {
+                        message: RelayMessage::EndOfStoredEvents(..),
+                        ..
+                    } => eose += 1,
+                    RelayPoolNotification::Eose { subscription_id } => {
+                        assert_eq!(subscription_id, output.val);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        // Sent after the EOSE of both relays
+        assert_eq!(eose, 2);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_stream() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect().await;
+
+        let keys = Keys::generate();
+        let opts = SubscribeOptions::default().eose_quorum(EoseQuorum::All, Duration::from_secs(5));
+        let mut stream = pool
+            .subscribe_stream(Filter::new().author(keys.public_key()), opts)
+            .await
+            .unwrap();
+        let id = stream.id().clone();
+        assert!(pool.subscription(&id).await.is_some());
+
+        let item = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap();
+        assert_eq!(item, Some(SubscriptionStreamItem::Eose));
+
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event(&event).await.unwrap();
+
+        let item = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap();
+        assert_eq!(
+            item,
+            Some(SubscriptionStreamItem::Event {
+                relay_url: url,
+                event: Box::new(event),
+            })
+        );
+
+        // Closed on drop
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(pool.subscription(&id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_notifications_from() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url1, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&url2, RelayOptions::default())
+            .await
+            .unwrap();
+
+        pool.connect().await;
+        pool.wait_for_connection(Duration::from_secs(5)).await;
+
+        let unknown = RelayUrl::parse("ws://127.0.0.1:1").unwrap();
+        assert!(matches!(
+            pool.notifications_from([&unknown]).await,
+            Err(Error::RelayNotFound)
+        ));
+
+        let mut stream = pool.notifications_from([&url1]).await.unwrap();
+
+        // Send the same event to both relays
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event_to([&url2], &event).await.unwrap();
+        pool.send_event_to([&url1], &event).await.unwrap();
+
+        // Only the notifications of the first relay
+        let mut received: usize = 0;
+        while let Ok(Some(notification)) =
+            tokio::time::timeout(Duration::from_millis(500), stream.next()).await
+        {
+            if let RelayPoolNotification::Message { relay_url, .. } = notification {
+                assert_eq!(relay_url, url1);
+                received += 1;
+            }
+        }
+        assert!(received > 0);
+    }
+
+    #[test]
+    fn test_eose_quorum_required() {
+        assert_eq!(EoseQuorum::All.required(3), 3);
+        assert_eq!(EoseQuorum::Majority.required(3), 2);
+        assert_eq!(EoseQuorum::Majority.required(4), 3);
+        assert_eq!(EoseQuorum::First(1).required(3), 1);
+        assert_eq!(EoseQuorum::First(0).required(3), 1);
+        assert_eq!(EoseQuorum::First(5).required(3), 3);
+    }
+
+    #[tokio::test]
+    async fn test_notifications_stream_shutdown() {
+        let pool = RelayPool::default();
+
+        let mut stream = pool.notifications_stream();
+
+        pool.shutdown().await;
+
+        assert_eq!(stream.next().await, Some(RelayPoolNotification::Shutdown));
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_relays_sorted_by_latency() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+        let unreachable_url = RelayUrl::parse("ws://127.0.0.1:6666").unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.add_relay(&unreachable_url, RelayOptions::default())
+            .await
+            .unwrap();
+
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        // Each REQ-to-EOSE round-trip is a latency sample
+        for _ in 0..3 {
+            pool.fetch_events_from(
+                [&url],
+                Filter::new().kind(Kind::TextNote),
+                Duration::from_secs(5),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        }
+
+        let sorted = pool.relays_sorted_by_latency().await;
+        assert_eq!(sorted.len(), 2);
+        assert_eq!(sorted[0].0, url);
+        assert!(sorted[0].1.is_some());
+        assert_eq!(sorted[1], (unreachable_url, None));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_to_tag() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let dms_url = RelayUrl::parse(&mock1.url()).unwrap();
+        let feed_url = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&dms_url, RelayOptions::default().tag("dms"))
+            .await
+            .unwrap();
+        pool.add_relay(&feed_url, RelayOptions::default().tag("feed"))
+            .await
+            .unwrap();
+
+        let relays = pool.relays_with_tag("dms").await;
+        assert_eq!(relays.len(), 1);
+        assert!(relays.contains_key(&dms_url));
+        assert!(pool.relays_with_tag("unknown").await.is_empty());
+
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        let output = pool
+            .subscribe_to_tag(
+                "dms",
+                Filter::new().kind(Kind::GiftWrap),
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(output.success.contains(&dms_url));
+        assert!(!output.success.contains(&feed_url));
+
+        let dms_relay = pool.relay(&dms_url).await.unwrap();
+        let feed_relay = pool.relay(&feed_url).await.unwrap();
+        assert!(dms_relay.subscription(&output.val).await.is_some());
+        assert!(feed_relay.subscription(&output.val).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_all() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect().await;
+
+        for kind in [Kind::TextNote, Kind::Metadata] {
+            pool.subscribe(Filter::new().kind(kind), SubscribeOptions::default())
+                .await
+                .unwrap();
+        }
+
+        // Auto-closing subscriptions aren't counted
+        let opts = SubscribeOptions::default().close_on(Some(SubscribeAutoCloseOptions::default()));
+        pool.subscribe(Filter::new().kind(Kind::ContactList), opts)
+            .await
+            .unwrap();
+
+        assert_eq!(pool.subscription_count().await, 2);
+
+        pool.unsubscribe_all().await;
+
+        assert_eq!(pool.subscription_count().await, 0);
+
+        let relay = pool.relay(&url).await.unwrap();
+        assert!(relay.subscriptions().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_event_to_n() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let mock3 = MockRelay::run().await.unwrap();
+        let premium1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let premium2 = RelayUrl::parse(&mock2.url()).unwrap();
+        let free = RelayUrl::parse(&mock3.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&premium1, RelayOptions::default().priority(10))
+            .await
+            .unwrap();
+        pool.add_relay(&free, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&premium2, RelayOptions::default().priority(5))
+            .await
+            .unwrap();
+
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let output = pool.send_event_to_n(&event, 2).await.unwrap();
+
+        assert_eq!(
+            output.success,
+            HashSet::from([premium1.clone(), premium2.clone()])
+        );
+        assert!(output.failed.is_empty());
+
+        // A disconnected relay is ranked after the connected ones, regardless of the priority
+        pool.disconnect_relay(&premium1).await.unwrap();
+        let output = pool.send_event_to_n(&event, 2).await.unwrap();
+        assert_eq!(
+            output.success,
+            HashSet::from([premium2.clone(), free.clone()])
+        );
+
+        // Same ranking for subscriptions
+        let output = pool
+            .subscribe_to_n(
+                Filter::new().kind(Kind::TextNote),
+                2,
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.success, HashSet::from([premium2, free]));
+    }
+
+    #[test]
+    fn test_write_concern_required() {
+        assert_eq!(WriteConcern::FirstOk.required(3), 1);
+        assert_eq!(WriteConcern::Quorum(2).required(3), 2);
+        assert_eq!(WriteConcern::Quorum(0).required(3), 1);
+        assert_eq!(WriteConcern::Quorum(5).required(3), 5);
+        assert_eq!(WriteConcern::Majority.required(3), 2);
+        assert_eq!(WriteConcern::Majority.required(4), 3);
+        assert_eq!(WriteConcern::All.required(3), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_event_with_concern() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let mock3 = MockRelay::run().await.unwrap();
+
+        let pool = RelayPool::default();
+        for mock in [&mock1, &mock2, &mock3] {
+            pool.add_relay(mock.url(), RelayOptions::default())
+                .await
+                .unwrap();
+        }
+
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        let output = pool
+            .send_event_with_concern(&event, WriteConcern::Quorum(2))
+            .await
+            .unwrap();
+        assert!(output.success.len() >= 2);
+
+        let output = pool
+            .send_event_with_concern(&event, WriteConcern::All)
+            .await
+            .unwrap();
+        assert_eq!(output.success.len(), 3);
+
+        // More than the available relays
+        let err = pool
+            .send_event_with_concern(&event, WriteConcern::Quorum(4))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WriteConcernNotSatisfied {
+                required: 4,
+                accepted: 3
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_batch_event() {
+        let mock = MockRelay::run().await.unwrap();
+        let mock_url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // Relay that rejects the events of the unauthenticated clients
+        let opts = RelayBuilderNip42 {
+            mode: RelayBuilderNip42Mode::Write,
+        };
+        let auth = LocalRelay::run(RelayBuilder::default().nip42(opts))
+            .await
+            .unwrap();
+        let auth_url = RelayUrl::parse(&auth.url()).unwrap();
+
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().automatic_authentication(false))
+            .build();
+        pool.add_relay(&mock_url, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&auth_url, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..3)
+            .map(|i| {
+                EventBuilder::text_note(format!("Test {i}"))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        let output = pool
+            .batch_event(events.clone(), BatchEventOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            output.ids(),
+            events.iter().map(|e| e.id).collect::<Vec<_>>()
+        );
+        assert!(!output.is_complete());
+        assert!(output.success.contains(&mock_url));
+        assert_eq!(output.failed[&auth_url].0, events[0].id);
+        for event_output in output.events.iter() {
+            assert!(event_output.success.contains(&mock_url));
+            assert!(event_output.failed.contains_key(&auth_url));
+        }
+
+        // Stop sending to the relay after the first failure
+        let output = pool
+            .batch_event(events, BatchEventOptions::new().stop_on_failure(true))
+            .await
+            .unwrap();
+        assert!(output.success.contains(&mock_url));
+        assert!(output.events[0].failed.contains_key(&auth_url));
+        assert!(!output.events[1].failed.contains_key(&auth_url));
+        assert!(!output.events[2].success.contains(&auth_url));
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        let health = pool.health().await;
+        assert_eq!(health.get(&url).unwrap().status, RelayStatus::Initialized);
+
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event(&event).await.unwrap();
+        pool.fetch_events(
+            Filter::new().author(keys.public_key()),
+            Duration::from_secs(3),
+            ReqExitPolicy::ExitOnEOSE,
+        )
+        .await
+        .unwrap();
+
+        let health = pool.health().await;
+        let stats = health.get(&url).unwrap();
+        assert_eq!(stats.status, RelayStatus::Connected);
+        assert_eq!(stats.reconnects, 0);
+        assert_eq!(stats.events_received, 1);
+        assert!(stats.bytes_sent > 0);
+        assert!(stats.last_message_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_try_save_subscription() {
+        let pool = RelayPool::default();
+
+        let id = SubscriptionId::new("feed");
+        let filter = Filter::new().kind(Kind::TextNote);
+
+        pool.try_save_subscription(id.clone(), filter.clone())
+            .await
+            .unwrap();
+
+        // Collision: the existing filter is kept
+        let err = pool
+            .try_save_subscription(id.clone(), Filter::new().kind(Kind::Reaction))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SubscriptionExists(ref i) if i == &id));
+        assert_eq!(pool.subscription(&id).await, Some(filter));
+    }
+
+    #[tokio::test]
+    async fn test_lazy_relay_connection() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        // Pool subscription, to be inherited by the lazy relay
+        let sub_id = SubscriptionId::new("pool-sub");
+        pool.save_subscription(sub_id.clone(), Filter::new().kind(Kind::TextNote))
+            .await;
+
+        pool.add_relay(
+            &url,
+            RelayOptions::default().connect_policy(ConnectPolicy::Lazy),
+        )
+        .await
+        .unwrap();
 
-        // Stream events
-        let mut stream = self
-            .stream_events_from(urls, filter, timeout, policy)
-            .await?;
-        while let Some(event) = stream.next().await {
-            // To find out more about why the `force_insert` was used, search for EVENTS_FORCE_INSERT ine the code.
-            events.force_insert(event);
-        }
+        // Must not be connected by the pool
+        pool.try_connect(Duration::from_secs(1)).await;
+        pool.connect().await;
 
-        Ok(events)
-    }
+        let relay = pool.relay(&url).await.unwrap();
+        assert_eq!(relay.status(), RelayStatus::Initialized);
+
+        // First request starts the connection
+        let events = pool
+            .fetch_events_from(
+                [&url],
+                Filter::new().kind(Kind::Metadata),
+                Duration::from_secs(3),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert!(events.is_empty());
 
-    /// Stream events from relays with `READ` flag.
-    pub async fn stream_events(
-        &self,
-        filter: Filter,
-        timeout: Duration,
-        policy: ReqExitPolicy,
-    ) -> Result<ReceiverStream<Event>, Error> {
-        let urls: Vec<RelayUrl> = self.__read_relay_urls().await;
-        self.stream_events_from(urls, filter, timeout, policy).await
+        assert_eq!(relay.status(), RelayStatus::Connected);
+        assert!(relay.subscription(&sub_id).await.is_some());
     }
 
-    /// Stream events from specific relays
-    pub async fn stream_events_from<I, U>(
-        &self,
-        urls: I,
-        filter: Filter,
-        timeout: Duration,
-        policy: ReqExitPolicy,
-    ) -> Result<ReceiverStream<Event>, Error>
-    where
-        I: IntoIterator<Item = U>,
-        U: TryIntoUrl,
-        Error: From<<U as TryIntoUrl>::Err>,
-    {
-        let targets = urls
-            .into_iter()
-            .map(|u| Ok((u.try_into_url()?, filter.clone())))
-            .collect::<Result<_, Error>>()?;
-        self.stream_events_targeted(targets, timeout, policy).await
+    #[tokio::test]
+    async fn test_max_concurrent_connections() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().max_concurrent_connections(Some(1)))
+            .build();
+
+        for url in [&url1, &url2] {
+            pool.add_relay(
+                url,
+                RelayOptions::default().connect_policy(ConnectPolicy::Lazy),
+            )
+            .await
+            .unwrap();
+        }
+
+        let relay1 = pool.relay(&url1).await.unwrap();
+        let relay2 = pool.relay(&url2).await.unwrap();
+
+        let fetch = |url: RelayUrl| {
+            let pool = pool.clone();
+            async move {
+                pool.fetch_events_from(
+                    [url],
+                    Filter::new().kind(Kind::Metadata),
+                    Duration::from_secs(3),
+                    ReqExitPolicy::ExitOnEOSE,
+                )
+                .await
+                .unwrap();
+            }
+        };
+
+        fetch(url1.clone()).await;
+        assert_eq!(relay1.status(), RelayStatus::Connected);
+
+        // The first relay is idle: disconnected to connect the second one
+        fetch(url2.clone()).await;
+        assert_eq!(relay2.status(), RelayStatus::Connected);
+        assert_eq!(relay1.status(), RelayStatus::Terminated);
+        assert!(relay1.inner.is_lazy_and_idle());
+
+        // Reconnected on next usage
+        fetch(url1.clone()).await;
+        assert_eq!(relay1.status(), RelayStatus::Connected);
+        assert_eq!(relay2.status(), RelayStatus::Terminated);
     }
 
-    /// Targeted streaming events
-    ///
-    /// Stream events from specific relays with specific filters
-    pub async fn stream_events_targeted(
-        &self,
-        targets: HashMap<RelayUrl, Filter>,
-        timeout: Duration,
-        policy: ReqExitPolicy,
-    ) -> Result<ReceiverStream<Event>, Error> {
-        // Check if `targets` map is empty
-        if targets.is_empty() {
-            return Err(Error::NoRelaysSpecified);
+    #[tokio::test]
+    async fn test_max_concurrent_connections_busy_relay() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().max_concurrent_connections(Some(1)))
+            .build();
+
+        for url in [&url1, &url2] {
+            pool.add_relay(
+                url,
+                RelayOptions::default().connect_policy(ConnectPolicy::Lazy),
+            )
+            .await
+            .unwrap();
         }
 
-        // Lock with read shared access
-        let relays = self.inner.atomic.relays.read().await;
+        let relay1 = pool.relay(&url1).await.unwrap();
+        let relay2 = pool.relay(&url2).await.unwrap();
 
-        // Check if empty
-        if relays.is_empty() {
-            return Err(Error::NoRelays);
+        pool.fetch_events_from(
+            [&url1],
+            Filter::new().kind(Kind::Metadata),
+            Duration::from_secs(3),
+            ReqExitPolicy::ExitOnEOSE,
+        )
+        .await
+        .unwrap();
+        assert_eq!(relay1.status(), RelayStatus::Connected);
+
+        // The first relay is waiting for a response: not disconnected, the limit is exceeded
+        let in_flight = relay1.inner.in_flight();
+        pool.fetch_events_from(
+            [&url2],
+            Filter::new().kind(Kind::Metadata),
+            Duration::from_secs(3),
+            ReqExitPolicy::ExitOnEOSE,
+        )
+        .await
+        .unwrap();
+        assert_eq!(relay2.status(), RelayStatus::Connected);
+        assert_eq!(relay1.status(), RelayStatus::Connected);
+        drop(in_flight);
+    }
+
+    #[tokio::test]
+    async fn test_relay_filter() {
+        let allowed = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let denied = RelayUrl::parse("wss://nos.lol").unwrap();
+
+        // Denylist
+        let filter = RelayFilter::denylist()
+            .url(denied.clone())
+            .host("localhost")
+            .host("127.0.0.1");
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().relay_filter(filter))
+            .build();
+
+        assert!(pool
+            .add_relay(&allowed, RelayOptions::default())
+            .await
+            .unwrap());
+        for url in [
+            "wss://nos.lol",
+            "ws://localhost:7777",
+            "ws://127.0.0.1:7777",
+        ] {
+            assert!(matches!(
+                pool.add_relay(url, RelayOptions::default()).await,
+                Err(Error::RelayNotAllowed { .. })
+            ));
         }
 
-        // Construct new map with also `Relay` struct
-        let mut map: HashMap<RelayUrl, (Relay, Filter)> = HashMap::with_capacity(targets.len());
+        // Allowlist
+        let filter = RelayFilter::allowlist().host("relay.damus.io");
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().relay_filter(filter))
+            .build();
 
-        // Populate the new map.
-        // Return an error if the relay doesn't exists.
-        for (url, filter) in targets.into_iter() {
-            // Get relay
-            let relay: Relay = self.internal_relay(&relays, &url).cloned()?;
+        assert!(pool
+            .add_relay(&allowed, RelayOptions::default())
+            .await
+            .unwrap());
+        assert!(matches!(
+            pool.add_relay(&denied, RelayOptions::default()).await,
+            Err(Error::RelayNotAllowed { .. })
+        ));
+    }
 
-            // Insert into new map
-            map.insert(url, (relay, filter));
+    #[test]
+    fn test_relay_filter_networks() {
+        let filter = RelayFilter::denylist().network("10.0.0.0".parse().unwrap(), 8);
+        assert!(!filter.is_allowed(&RelayUrl::parse("ws://10.1.2.3:7777").unwrap()));
+        assert!(filter.is_allowed(&RelayUrl::parse("ws://11.1.2.3:7777").unwrap()));
+        assert!(!filter.is_address_allowed(&"::ffff:10.1.2.3".parse().unwrap()));
+
+        let filter = RelayFilter::allowlist().network("fd00::".parse().unwrap(), 8);
+        assert!(filter.is_allowed(&RelayUrl::parse("ws://[fd12::1]:7777").unwrap()));
+        assert!(!filter.is_allowed(&RelayUrl::parse("ws://[fe80::1]:7777").unwrap()));
+        assert!(!filter.is_allowed(&RelayUrl::parse("wss://relay.damus.io").unwrap()));
+
+        let filter = RelayFilter::denylist().deny_private_addresses(true);
+        for url in [
+            "ws://127.0.0.1:7777",
+            "ws://192.168.1.1:7777",
+            "ws://100.64.0.1:7777",
+            "ws://[::1]:7777",
+            "ws://[fd12::1]:7777",
+        ] {
+            assert!(!filter.is_allowed(&RelayUrl::parse(url).unwrap()));
         }
+        assert!(filter.is_allowed(&RelayUrl::parse("ws://1.1.1.1:7777").unwrap()));
+        assert!(filter.is_allowed(&RelayUrl::parse("ws://localhost:7777").unwrap()));
+    }
 
-        // Drop relays read guard
-        drop(relays);
+    #[tokio::test]
+    async fn test_relay_filter_resolved_private_address() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url().replace("127.0.0.1", "localhost")).unwrap();
 
-        // Create channel
-        // TODO: use unbounded channel otherwise events can be lost since below the `try_send` is used.
-        let (tx, rx) = mpsc::channel::<Event>(map.len() * 512);
+        let filter = RelayFilter::denylist().deny_private_addresses(true);
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().relay_filter(filter))
+            .build();
 
-        // Spawn stream task
-        task::spawn(async move {
-            // IDs collection, needed to check if an event was already sent to the stream
-            let ids: Mutex<HashSet<EventId>> = Mutex::new(HashSet::new());
+        // The domain is allowed, but resolves to a loopback address
+        pool.add_relay(&url, RelayOptions::default().reconnect(false))
+            .await
+            .unwrap();
 
-            let mut urls: Vec<RelayUrl> = Vec::with_capacity(map.len());
-            let mut futures = Vec::with_capacity(map.len());
+        let relay = pool.relay(&url).await.unwrap();
+        let res = relay.try_connect(Duration::from_secs(3)).await;
+        assert!(matches!(
+            res.unwrap_err(),
+            crate::relay::Error::AddressNotAllowed { .. }
+        ));
+    }
 
-            // Populate `urls` and `futures` vectors
-            for (url, (relay, filter)) in map.into_iter() {
-                urls.push(url);
-                futures.push(relay.fetch_events_with_callback_owned(
-                    filter,
-                    timeout,
-                    policy,
-                    |event| {
-                        // Use a synchronous mutex here!
-                        //
-                        // From tokio docs:
-                        // ```
-                        // A synchronous mutex will block the current thread when waiting to acquire the lock.
-                        // This, in turn, will block other tasks from processing.
-                        // However, switching to tokio::sync::Mutex usually does not help as the asynchronous mutex uses a synchronous mutex internally.
-                        //
-                        // As a rule of thumb, using a synchronous mutex from within asynchronous code is fine as long
-                        // as contention remains low and the lock is not held across calls to .await.
-                        // ```
-                        //
-                        // SAFETY: panics only if another user of this mutex panicked while holding the mutex.
-                        let mut ids = ids.lock().unwrap();
+    #[tokio::test]
+    async fn test_dedup_event_notifications() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let urls = [mock1.url(), mock2.url()];
 
-                        // Check if ID was already seen or insert into set.
-                        if ids.insert(event.id) {
-                            // Immediately drop the set
-                            drop(ids);
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().dedup_cache_size(100))
+            .build();
 
-                            // Send event
-                            let _ = tx.try_send(event);
-                        }
-                    },
-                ));
-            }
+        for url in urls.iter() {
+            pool.add_relay(url, RelayOptions::default()).await.unwrap();
+        }
 
-            // Join all futures
-            let list = future::join_all(futures).await;
+        pool.try_connect(Duration::from_secs(3)).await;
 
-            // Iter results
-            for (url, result) in urls.into_iter().zip(list.into_iter()) {
-                if let Err(e) = result {
-                    tracing::error!(url = %url, error = %e, "Failed to stream events.");
+        let mut notifications = pool.notifications();
+
+        // Ephemeral events aren't stored, so only the dedup cache can catch the duplicates
+        let kind = Kind::Custom(20_001);
+        pool.subscribe(Filter::new().kind(kind), SubscribeOptions::default())
+            .await
+            .unwrap();
+
+        // Publish the event to both relays from another pool
+        let publisher = RelayPool::default();
+        for url in urls.iter() {
+            publisher
+                .add_relay(url, RelayOptions::default())
+                .await
+                .unwrap();
+        }
+        publisher.try_connect(Duration::from_secs(3)).await;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::new(kind, "Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let output = publisher.send_event(&event).await.unwrap();
+        assert_eq!(output.success.len(), 2);
+
+        // Count the event notifications
+        let mut counter: usize = 0;
+        let _ = tokio::time::timeout(Duration::from_secs(2), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event { event: e, .. } = notification {
+                    if e.id == event.id {
+                        counter += 1;
+                    }
                 }
             }
-        });
+        })
+        .await;
 
-        // Return stream
-        Ok(ReceiverStream::new(rx))
+        assert_eq!(counter, 1);
     }
 
-    /// Handle notifications
-    pub async fn handle_notifications<F, Fut>(&self, func: F) -> Result<(), Error>
-    where
-        F: Fn(RelayPoolNotification) -> Fut,
-        Fut: Future<Output = Result<bool>>,
-    {
-        let mut notifications = self.notifications();
-        while let Ok(notification) = notifications.recv().await {
-            let shutdown: bool = RelayPoolNotification::Shutdown == notification;
-            let exit: bool = func(notification)
-                .await
-                .map_err(|e| Error::Handler(e.to_string()))?;
-            if exit || shutdown {
-                break;
-            }
+    #[tokio::test]
+    async fn test_notification_overflow_block() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        // Store some events
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..30)
+            .map(|i| {
+                EventBuilder::text_note(format!("Test {i}"))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        let publisher = RelayPool::default();
+        publisher
+            .add_relay(&url, RelayOptions::default())
+            .await
+            .unwrap();
+        publisher.try_connect(Duration::from_secs(3)).await;
+        for event in events.iter() {
+            publisher.send_event(event).await.unwrap();
         }
-        Ok(())
-    }
-}
 
-/// Return `true` if the relay can be removed
-///
-/// If it CAN'T be removed,
-/// the flags are automatically updated (remove `READ`, `WRITE` and `DISCOVERY` flags).
-fn can_remove_relay(relay: &Relay) -> bool {
-    let flags = relay.flags();
-    if flags.has_any(RelayServiceFlags::GOSSIP) {
-        // Remove READ, WRITE and DISCOVERY flags
-        flags.remove(
-            RelayServiceFlags::READ | RelayServiceFlags::WRITE | RelayServiceFlags::DISCOVERY,
-        );
+        // Subscribe with a small notification channel
+        let pool = RelayPool::builder()
+            .opts(
+                RelayPoolOptions::new()
+                    .notification_channel_size(8)
+                    .notification_overflow(NotificationOverflowPolicy::Block),
+            )
+            .build();
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.try_connect(Duration::from_secs(3)).await;
 
-        // Relay has `GOSSIP` flag so it can't be removed.
-        return false;
+        let mut notifications = pool.notifications();
+
+        pool.subscribe(
+            Filter::new().author(keys.public_key()),
+            SubscribeOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        // Slowly consume the notifications: no one must be dropped
+        let mut counter: usize = 0;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while counter < events.len() {
+                if let RelayPoolNotification::Event { .. } = notifications.recv().await.unwrap() {
+                    counter += 1;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(counter, events.len());
     }
 
-    // Relay can be removed
-    true
-}
+    #[tokio::test]
+    async fn test_automatic_authentication_failed_notification() {
+        // Mock relay
+        let opts = RelayBuilderNip42 {
+            mode: RelayBuilderNip42Mode::Read,
+        };
+        let builder = RelayBuilder::default().nip42(opts);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use nostr_relay_builder::MockRelay;
+        // Enable NIP42 auto auth, without a signer
+        let pool = RelayPool::builder()
+            .opts(RelayPoolOptions::new().automatic_authentication(true))
+            .build();
 
-    use super::*;
+        let mut notifications = pool.notifications();
 
-    fn relay_gossip_opts() -> RelayOptions {
-        let mut flags: RelayServiceFlags = RelayServiceFlags::default();
-        flags.add(RelayServiceFlags::GOSSIP);
-        RelayOptions::default().flags(flags)
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.try_connect(Duration::from_secs(3)).await;
+
+        pool.subscribe(
+            Filter::new().kind(Kind::TextNote),
+            SubscribeOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let relay_url = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(RelayPoolNotification::AuthenticationFailed { relay_url }) =
+                    notifications.recv().await
+                {
+                    return relay_url;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(relay_url, url);
     }
 
     #[tokio::test]
-    async fn test_shutdown() {
-        let mock = MockRelay::run().await.unwrap();
-        let url = mock.url();
+    async fn test_auth_required_notification() {
+        // Mock relay
+        let opts = RelayBuilderNip42 {
+            mode: RelayBuilderNip42Mode::Read,
+        };
+        let builder = RelayBuilder::default().nip42(opts);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // Publish an event from another pool (writes don't require authentication)
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let publisher = RelayPool::default();
+        publisher
+            .add_relay(&url, RelayOptions::default())
+            .await
+            .unwrap();
+        publisher.try_connect(Duration::from_secs(3)).await;
+        publisher.send_event(&event).await.unwrap();
 
+        // NIP42 auto auth disabled by default
         let pool = RelayPool::default();
-
+        pool.state().set_signer(keys).await;
         pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.try_connect(Duration::from_secs(3)).await;
 
-        pool.connect().await;
+        let mut notifications = pool.notifications();
 
-        assert!(!pool.is_shutdown());
+        let filter = Filter::new().kind(Kind::TextNote);
+        let output = pool
+            .subscribe(filter.clone(), SubscribeOptions::default())
+            .await
+            .unwrap();
+
+        let (relay_url, subscription_id, message) =
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    if let Ok(RelayPoolNotification::AuthRequired {
+                        relay_url,
+                        subscription_id,
+                        message,
+                    }) = notifications.recv().await
+                    {
+                        return (relay_url, subscription_id, message);
+                    }
+                }
+            })
+            .await
+            .unwrap();
 
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(relay_url, url);
+        assert_eq!(subscription_id, output.val);
+        assert_eq!(message, "you must auth");
 
-        pool.shutdown().await;
+        // With the auto auth, the subscriptions are re-sent after the authentication
+        pool.state().automatic_authentication(true);
+        pool.subscribe(filter, SubscribeOptions::default())
+            .await
+            .unwrap();
 
-        assert!(pool.is_shutdown());
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(RelayPoolNotification::Event { event, .. }) = notifications.recv().await {
+                    return event;
+                }
+            }
+        })
+        .await
+        .unwrap();
 
-        assert!(matches!(
-            pool.add_relay(&url, RelayOptions::default())
-                .await
-                .unwrap_err(),
-            Error::Shutdown
-        ));
+        assert_eq!(*received, event);
     }
 
     #[tokio::test]
@@ -1457,4 +3653,31 @@ mod tests {
             Error::RelayNotFound
         ));
     }
+
+    #[tokio::test]
+    async fn test_prune_expired_events_keeps_not_tracked_events() {
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        });
+
+        // Stored by someone else
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Old")
+            .custom_created_at(Timestamp::now() - 3600)
+            .sign_with_keys(&keys)
+            .unwrap();
+        database.save_event(&event).await.unwrap();
+
+        let ttl = EventTtl::new(Duration::from_secs(60))
+            .basis(EventTtlBasis::CreatedAt)
+            .prune_database(true);
+        let mut builder = RelayPoolBuilder::new().opts(RelayPoolOptions::new().event_ttl(ttl));
+        builder.__database = Arc::new(database);
+        let pool = builder.build();
+
+        pool.prune_expired_events().await.unwrap();
+
+        assert_eq!(pool.database().count(Filter::new()).await.unwrap(), 1);
+    }
 }
@@ -4,22 +4,377 @@
 
 //! Pool options
 
-use super::constants::DEFAULT_NOTIFICATION_CHANNEL_SIZE;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use nostr::RelayUrl;
+
+use super::constants::{DEFAULT_NOTIFICATION_CHANNEL_SIZE, DEFAULT_TTL_SWEEP_INTERVAL};
+
+/// Notification channel overflow policy
+///
+/// Defines what happens when a slow consumer falls behind and the notification channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NotificationOverflowPolicy {
+    /// Drop the oldest notifications (default)
+    ///
+    /// The slow receivers will get a [`RecvError::Lagged`](tokio::sync::broadcast::error::RecvError::Lagged) error.
+    #[default]
+    DropOldest,
+    /// Apply backpressure to the relays
+    ///
+    /// When the channel is half full, the relays stop reading new messages until
+    /// **all** the receivers have consumed enough notifications.
+    ///
+    /// The other half of the channel is kept as headroom for the messages already being processed:
+    /// if many relays are connected, increase the [`RelayPoolOptions::notification_channel_size`] accordingly.
+    ///
+    /// # Deadlock risk
+    ///
+    /// With this policy, a receiver that is never polled blocks all the relays forever.
+    /// Remember to drop the unused receivers returned by [`RelayPool::notifications`](crate::RelayPool::notifications).
+    ///
+    /// Also, don't wait for relay responses (i.e., [`RelayPool::fetch_events`](crate::RelayPool::fetch_events))
+    /// in the same task that is consuming the notifications:
+    /// the responses can't be read while the channel is full, so the task would wait forever.
+    ///
+    /// While blocked, the relays can't reply to pings, so may be disconnected and reconnected.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RelayFilterMode {
+    Allow,
+    Deny,
+}
+
+/// IP network (CIDR notation)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    fn new(addr: IpAddr, prefix: u8) -> Self {
+        let max: u8 = match addr {
+            IpAddr::V4(..) => 32,
+            IpAddr::V6(..) => 128,
+        };
+
+        Self {
+            addr: canonical_ip(addr),
+            prefix: prefix.min(max),
+        }
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, canonical_ip(*ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask: u32 = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask: u128 = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Convert the IPv4-mapped IPv6 addresses to IPv4
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(v6),
+        },
+        ip => ip,
+    }
+}
+
+/// Check if the IP address isn't publicly routable (loopback, private, link-local, ...)
+fn is_private_ip(ip: &IpAddr) -> bool {
+    match canonical_ip(*ip) {
+        IpAddr::V4(ip) => is_private_ipv4(&ip),
+        IpAddr::V6(ip) => is_private_ipv6(&ip),
+    }
+}
+
+fn is_private_ipv4(ip: &Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // Shared address space (100.64.0.0/10)
+        || (a == 100 && (b & 0b1100_0000) == 64)
+}
+
+fn is_private_ipv6(ip: &Ipv6Addr) -> bool {
+    let first: u16 = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local (fc00::/7)
+        || (first & 0xfe00) == 0xfc00
+        // Link-local (fe80::/10)
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// Filter of the relays that can be added to the pool
+///
+/// Useful to block untrusted targets (i.e., `localhost` or internal addresses) in relay lists from events.
+///
+/// The URLs and the hosts are matched exactly, while the IP networks match the URLs with an IP address as host.
+/// To block the domains that resolve to internal addresses, use [`RelayFilter::deny_private_addresses`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayFilter {
+    mode: RelayFilterMode,
+    urls: HashSet<RelayUrl>,
+    hosts: HashSet<String>,
+    networks: HashSet<IpNetwork>,
+    deny_private_addresses: bool,
+}
+
+impl RelayFilter {
+    /// Allow only the listed URLs and hosts
+    #[inline]
+    pub fn allowlist() -> Self {
+        Self::new(RelayFilterMode::Allow)
+    }
+
+    /// Allow all the URLs and hosts except the listed ones
+    #[inline]
+    pub fn denylist() -> Self {
+        Self::new(RelayFilterMode::Deny)
+    }
+
+    fn new(mode: RelayFilterMode) -> Self {
+        Self {
+            mode,
+            urls: HashSet::new(),
+            hosts: HashSet::new(),
+            networks: HashSet::new(),
+            deny_private_addresses: false,
+        }
+    }
+
+    /// Add relay URL
+    #[inline]
+    pub fn url(mut self, url: RelayUrl) -> Self {
+        self.urls.insert(url);
+        self
+    }
+
+    /// Add host (domain or IP address, as in the URL; i.e., `localhost`, `127.0.0.1` or `[::1]`)
+    #[inline]
+    pub fn host<S>(mut self, host: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.hosts.insert(host.as_ref().to_lowercase());
+        self
+    }
+
+    /// Add IP network, in CIDR notation (i.e., `10.0.0.0/8` is `network(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8)`)
+    ///
+    /// The prefix length is capped to the length of the address (32 for IPv4, 128 for IPv6).
+    #[inline]
+    pub fn network(mut self, addr: IpAddr, prefix: u8) -> Self {
+        self.networks.insert(IpNetwork::new(addr, prefix));
+        self
+    }
+
+    /// Deny the private addresses (default: false)
+    ///
+    /// Deny the loopback, private, link-local and unspecified addresses, both in the relay URLs
+    /// and in the addresses to which the domains resolve.
+    /// The resolved addresses are checked right before connecting
+    /// (only with [`ConnectionMode::Direct`](crate::ConnectionMode::Direct): a proxy resolves the domains remotely).
+    ///
+    /// Applies to both allowlist and denylist.
+    #[inline]
+    pub fn deny_private_addresses(mut self, deny: bool) -> Self {
+        self.deny_private_addresses = deny;
+        self
+    }
+
+    fn contains(&self, url: &RelayUrl) -> bool {
+        self.urls.contains(url)
+            || url.host().is_some_and(|host| self.hosts.contains(host))
+            || url_ip(url).is_some_and(|ip| self.contains_ip(&ip))
+    }
+
+    #[inline]
+    fn contains_ip(&self, ip: &IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(ip))
+    }
+
+    /// Check if the relay URL is allowed
+    pub fn is_allowed(&self, url: &RelayUrl) -> bool {
+        if let Some(ip) = url_ip(url) {
+            if !self.is_address_allowed(&ip) {
+                return false;
+            }
+        }
+
+        match self.mode {
+            RelayFilterMode::Allow => self.contains(url),
+            RelayFilterMode::Deny => !self.contains(url),
+        }
+    }
+
+    /// Check if a resolved address is allowed
+    ///
+    /// The address is denied if it's private and [`RelayFilter::deny_private_addresses`] is enabled,
+    /// or if it's in a network of the denylist.
+    /// For an allowlist, the host has already been allowed by [`RelayFilter::is_allowed`].
+    pub fn is_address_allowed(&self, ip: &IpAddr) -> bool {
+        if self.deny_private_addresses && is_private_ip(ip) {
+            return false;
+        }
+
+        match self.mode {
+            RelayFilterMode::Allow => true,
+            RelayFilterMode::Deny => !self.contains_ip(ip),
+        }
+    }
+
+    /// Check if the domains must be resolved to check the addresses
+    #[inline]
+    pub(crate) fn check_resolved_addresses(&self) -> bool {
+        self.deny_private_addresses
+            || (self.mode == RelayFilterMode::Deny && !self.networks.is_empty())
+    }
+}
+
+/// Get the IP address of the URL, if the host isn't a domain
+fn url_ip(url: &RelayUrl) -> Option<IpAddr> {
+    let host: &str = url.host()?;
+    let host: &str = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    host.parse().ok()
+}
+
+/// Event signature verification mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EventVerificationMode {
+    /// Verify in the relay message handler (default)
+    #[default]
+    Inline,
+    /// Verify on a pool of dedicated threads
+    ///
+    /// The async runtime isn't blocked by the verification, so the events of many relays are verified in parallel.
+    /// The events of a single relay aren't pipelined: the relay message handler waits for the verification of each event,
+    /// so the order of the events received from a relay is preserved.
+    ///
+    /// On WASM, threads aren't available: fallback to [`EventVerificationMode::Inline`].
+    ThreadPool(usize),
+    /// Don't verify the events
+    ///
+    /// Use only if the relays are trusted!
+    Disabled,
+}
+
+/// What the event time-to-live is measured from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EventTtlBasis {
+    /// Time at which the event was received (default)
+    ///
+    /// The hashes of the expired event IDs are kept, to not notify them again if received again.
+    #[default]
+    ReceivedAt,
+    /// Event `created_at`
+    ///
+    /// The incoming events older than the TTL are not stored and notified.
+    CreatedAt,
+}
+
+/// Event time-to-live
+///
+/// The expired events are dropped from the dedup cache (see [`RelayPoolOptions::dedup_cache_size`])
+/// and, optionally, from the database, and are no longer notified.
+///
+/// Only the events stored by the pool are pruned from the database.
+/// The replaceable and addressable events never expire.
+///
+/// The expired events are still returned to the subscriptions that request them
+/// (i.e., [`RelayPool::fetch_events`](crate::RelayPool::fetch_events)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventTtl {
+    pub(crate) ttl: Duration,
+    pub(crate) basis: EventTtlBasis,
+    pub(crate) prune_database: bool,
+    pub(crate) sweep_interval: Duration,
+}
+
+impl EventTtl {
+    /// New event TTL
+    #[inline]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            basis: EventTtlBasis::default(),
+            prune_database: false,
+            sweep_interval: DEFAULT_TTL_SWEEP_INTERVAL,
+        }
+    }
+
+    /// What the TTL is measured from (default: [`EventTtlBasis::ReceivedAt`])
+    #[inline]
+    pub fn basis(mut self, basis: EventTtlBasis) -> Self {
+        self.basis = basis;
+        self
+    }
+
+    /// Delete the expired events also from the database (default: false)
+    #[inline]
+    pub fn prune_database(mut self, enable: bool) -> Self {
+        self.prune_database = enable;
+        self
+    }
+
+    /// Interval between the expired events sweeps (default: 60 secs)
+    #[inline]
+    pub fn sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = interval;
+        self
+    }
+}
 
 /// Relay Pool Options
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RelayPoolOptions {
     pub(super) max_relays: Option<usize>,
+    pub(super) max_concurrent_connections: Option<usize>,
     pub(super) nip42_auto_authentication: bool,
     pub(super) notification_channel_size: usize,
+    pub(super) dedup_cache_size: usize,
+    pub(super) min_pow_difficulty: u8,
+    pub(super) notification_overflow: NotificationOverflowPolicy,
+    pub(super) verification_mode: EventVerificationMode,
+    pub(super) relay_filter: Option<RelayFilter>,
+    pub(super) event_ttl: Option<EventTtl>,
 }
 
 impl Default for RelayPoolOptions {
     fn default() -> Self {
         Self {
             max_relays: None,
-            nip42_auto_authentication: true,
+            max_concurrent_connections: None,
+            nip42_auto_authentication: false,
             notification_channel_size: DEFAULT_NOTIFICATION_CHANNEL_SIZE,
+            dedup_cache_size: 0,
+            min_pow_difficulty: 0,
+            notification_overflow: NotificationOverflowPolicy::default(),
+            verification_mode: EventVerificationMode::default(),
+            relay_filter: None,
+            event_ttl: None,
         }
     }
 }
@@ -38,7 +393,20 @@ impl RelayPoolOptions {
         self
     }
 
-    /// Auto authenticate to relays (default: true)
+    /// Max number of lazy relays connected at the same time (default: None)
+    ///
+    /// The lazy relays are connected on demand (see [`ConnectPolicy::Lazy`]):
+    /// when the limit is reached, the least-recently-used idle relay (i.e., without active subscriptions and not waiting for responses) is disconnected,
+    /// and reconnected again on next usage.
+    ///
+    /// [`ConnectPolicy::Lazy`]: crate::relay::ConnectPolicy::Lazy
+    #[inline]
+    pub fn max_concurrent_connections(mut self, num: Option<usize>) -> Self {
+        self.max_concurrent_connections = num;
+        self
+    }
+
+    /// Auto authenticate to relays (default: false)
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
     #[inline]
@@ -53,4 +421,96 @@ impl RelayPoolOptions {
         self.notification_channel_size = size;
         self
     }
+
+    /// Minimum POW difficulty for received events (default: 0)
+    ///
+    /// Events whose ID doesn't meet the difficulty, or without a `nonce` tag committing to at least the difficulty,
+    /// are discarded before being notified.
+    /// The number of discarded events is tracked by [`RelayConnectionStats::pow_rejected_events`](crate::RelayConnectionStats::pow_rejected_events).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    #[inline]
+    pub fn min_pow(mut self, difficulty: u8) -> Self {
+        self.min_pow_difficulty = difficulty;
+        self
+    }
+
+    /// Notification channel overflow policy (default: [`NotificationOverflowPolicy::DropOldest`])
+    ///
+    /// Check [`NotificationOverflowPolicy`] to learn more.
+    #[inline]
+    pub fn notification_overflow(mut self, policy: NotificationOverflowPolicy) -> Self {
+        self.notification_overflow = policy;
+        self
+    }
+
+    /// Size of the cache of recently notified event IDs (default: 0)
+    ///
+    /// When greater than zero, the [`RelayPoolNotification::Event`](crate::RelayPoolNotification::Event)
+    /// is sent at most once per event across all relays,
+    /// as long as the event ID is still in the cache.
+    ///
+    /// Set to `0` to disable the cache.
+    #[inline]
+    pub fn dedup_cache_size(mut self, size: usize) -> Self {
+        self.dedup_cache_size = size;
+        self
+    }
+
+    /// Event signature verification mode (default: [`EventVerificationMode::Inline`])
+    ///
+    /// Check [`EventVerificationMode`] to learn more.
+    #[inline]
+    pub fn verification_mode(mut self, mode: EventVerificationMode) -> Self {
+        self.verification_mode = mode;
+        self
+    }
+
+    /// Filter of the relays that can be added (default: None)
+    ///
+    /// The disallowed relays are rejected by [`RelayPool::add_relay`](crate::RelayPool::add_relay)
+    /// with [`Error::RelayNotAllowed`](crate::pool::Error::RelayNotAllowed).
+    /// The connections to denied resolved addresses fail with
+    /// [`Error::AddressNotAllowed`](crate::relay::Error::AddressNotAllowed).
+    #[inline]
+    pub fn relay_filter(mut self, filter: RelayFilter) -> Self {
+        self.relay_filter = Some(filter);
+        self
+    }
+
+    /// Expire the received events after a TTL (default: None)
+    ///
+    /// The expired events are periodically swept, starting from when the first relay is added.
+    ///
+    /// Check [`EventTtl`] to learn more.
+    #[inline]
+    pub fn event_ttl(mut self, ttl: EventTtl) -> Self {
+        self.event_ttl = Some(ttl);
+        self
+    }
+}
+
+/// Batch event options
+///
+/// Check [`RelayPool::batch_event`](crate::RelayPool::batch_event) to learn more.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchEventOptions {
+    pub(super) stop_on_failure: bool,
+}
+
+impl BatchEventOptions {
+    /// New default [`BatchEventOptions`]
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop sending the remaining events to a relay as soon as it rejects one (default: false)
+    ///
+    /// The skipped events are included neither in the `success` nor in the `failed` output of the event.
+    #[inline]
+    pub fn stop_on_failure(mut self, enable: bool) -> Self {
+        self.stop_on_failure = enable;
+        self
+    }
 }
@@ -6,6 +6,7 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 
+use nostr::message::MachineReadablePrefix;
 use nostr::{EventId, RelayUrl, SubscriptionId};
 
 /// Output
@@ -50,6 +51,83 @@ impl Output<EventId> {
     pub fn id(&self) -> &EventId {
         self.deref()
     }
+
+    /// Get the result of every relay
+    ///
+    /// The relays that rejected the event are mapped to a [`RejectReason`],
+    /// parsed from the `OK` message (or from the error, if the relay didn't reply).
+    pub fn results(&self) -> HashMap<&RelayUrl, Result<(), RejectReason>> {
+        let success = self.success.iter().map(|url| (url, Ok(())));
+        let failed = self
+            .failed
+            .iter()
+            .map(|(url, message)| (url, Err(RejectReason::parse(message))));
+        success.chain(failed).collect()
+    }
+
+    /// Get the reason why the relay rejected the event
+    ///
+    /// Returns `None` if the relay accepted the event or if it isn't part of the output.
+    pub fn reject_reason(&self, url: &RelayUrl) -> Option<RejectReason> {
+        self.failed.get(url).map(RejectReason::parse)
+    }
+}
+
+/// Batch event output
+///
+/// Check [`RelayPool::batch_event`](crate::RelayPool::batch_event) to learn more.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchOutput {
+    /// Output of every event, in the same order of the batch
+    pub events: Vec<Output<EventId>>,
+    /// Set of relays that accepted all the events
+    pub success: HashSet<RelayUrl>,
+    /// Map of relays that rejected at least an event, with the ID of the first rejected event and the related error.
+    pub failed: HashMap<RelayUrl, (EventId, String)>,
+}
+
+impl BatchOutput {
+    /// Get the IDs of the events, in the same order of the batch
+    pub fn ids(&self) -> Vec<EventId> {
+        self.events.iter().map(|output| output.val).collect()
+    }
+
+    /// Check if all the relays accepted all the events
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Reason of a relay rejection
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RejectReason {
+    /// Machine-readable prefix (i.e. `pow`, `rate-limited`, `duplicate`)
+    ///
+    /// `None` if the relay didn't use a known prefix or if it didn't reply at all (i.e. timeout).
+    pub prefix: Option<MachineReadablePrefix>,
+    /// Full message
+    pub message: String,
+}
+
+impl RejectReason {
+    /// Parse the reason from the relay message
+    pub fn parse<S>(message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let message: String = message.into();
+        Self {
+            prefix: MachineReadablePrefix::parse(&message),
+            message,
+        }
+    }
+
+    /// Check if the message has the machine-readable prefix
+    #[inline]
+    pub fn is(&self, prefix: MachineReadablePrefix) -> bool {
+        self.prefix == Some(prefix)
+    }
 }
 
 impl Output<SubscriptionId> {
@@ -59,3 +137,49 @@ impl Output<SubscriptionId> {
         self.deref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_event_results() {
+        let pow_relay = RelayUrl::parse("wss://pow.example.com").unwrap();
+        let limited_relay = RelayUrl::parse("wss://limited.example.com").unwrap();
+        let offline_relay = RelayUrl::parse("wss://offline.example.com").unwrap();
+        let ok_relay = RelayUrl::parse("wss://ok.example.com").unwrap();
+
+        let mut output: Output<EventId> = Output {
+            val: EventId::all_zeros(),
+            success: HashSet::new(),
+            failed: HashMap::new(),
+        };
+        output.success.insert(ok_relay.clone());
+        output.failed.insert(
+            pow_relay.clone(),
+            String::from("pow: difficulty 8 is less than 20"),
+        );
+        output.failed.insert(
+            limited_relay.clone(),
+            String::from("rate-limited: slow down"),
+        );
+        output
+            .failed
+            .insert(offline_relay.clone(), String::from("relay not connected"));
+
+        let results = output.results();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[&ok_relay], Ok(()));
+
+        let reason = results[&pow_relay].clone().unwrap_err();
+        assert!(reason.is(MachineReadablePrefix::Pow));
+        assert_eq!(reason.message, "pow: difficulty 8 is less than 20");
+
+        assert!(output
+            .reject_reason(&limited_relay)
+            .unwrap()
+            .is(MachineReadablePrefix::RateLimited));
+        assert_eq!(output.reject_reason(&offline_relay).unwrap().prefix, None);
+        assert!(output.reject_reason(&ok_relay).is_none());
+    }
+}
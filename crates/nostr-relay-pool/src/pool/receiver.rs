@@ -0,0 +1,81 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Pool notification receiver
+
+use std::sync::Arc;
+
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::Notify;
+
+use super::RelayPoolNotification;
+
+/// Receiver of the **pool** notifications
+///
+/// A wrapper around the broadcast [`Receiver`] that wakes up the relays waiting
+/// for room in the channel (see [`NotificationOverflowPolicy::Block`](crate::NotificationOverflowPolicy::Block))
+/// every time a notification is consumed.
+#[derive(Debug)]
+pub struct NotificationReceiver {
+    inner: Receiver<RelayPoolNotification>,
+    drained: Arc<Notify>,
+}
+
+impl NotificationReceiver {
+    #[inline]
+    pub(crate) fn new(inner: Receiver<RelayPoolNotification>, drained: Arc<Notify>) -> Self {
+        Self { inner, drained }
+    }
+
+    /// Receive the next notification, waiting if the channel is empty
+    ///
+    /// Check [`Receiver::recv`] to learn more.
+    pub async fn recv(&mut self) -> Result<RelayPoolNotification, RecvError> {
+        let res = self.inner.recv().await;
+        self.drained.notify_waiters();
+        res
+    }
+
+    /// Try to receive the next notification, without waiting
+    ///
+    /// Check [`Receiver::try_recv`] to learn more.
+    pub fn try_recv(&mut self) -> Result<RelayPoolNotification, TryRecvError> {
+        let res = self.inner.try_recv();
+        if res.is_ok() {
+            self.drained.notify_waiters();
+        }
+        res
+    }
+
+    /// Check if there are no pending notifications
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Get the number of pending notifications
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Create a new receiver that starts from the current tail of the channel
+    ///
+    /// Check [`Receiver::resubscribe`] to learn more.
+    #[inline]
+    pub fn resubscribe(&self) -> Self {
+        Self {
+            inner: self.inner.resubscribe(),
+            drained: self.drained.clone(),
+        }
+    }
+}
+
+impl Drop for NotificationReceiver {
+    fn drop(&mut self) {
+        // The pending notifications are released when the receiver is dropped
+        self.drained.notify_waiters();
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::sync::{Mutex, PoisonError};
+
+use lru::LruCache;
+use nostr::RelayUrl;
+
+use super::inner::InnerRelay;
+
+/// Limit the number of connected lazy relays
+///
+/// The relays are tracked in least-recently-used order:
+/// when the limit is reached, the least-recently-used idle relay is disconnected.
+#[derive(Debug)]
+pub(crate) struct ConnectionLimiter {
+    max: usize,
+    relays: Mutex<LruCache<RelayUrl, InnerRelay>>,
+}
+
+impl ConnectionLimiter {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            relays: Mutex::new(LruCache::unbounded()),
+        }
+    }
+
+    /// Track a new connection, evicting the least-recently-used idle relay if the limit is reached
+    ///
+    /// If all the tracked relays are busy, the limit is temporarily exceeded.
+    pub(super) fn acquire(&self, relay: &InnerRelay) {
+        let evicted: Option<InnerRelay> = {
+            let mut relays = self.relays.lock().unwrap_or_else(PoisonError::into_inner);
+
+            let evicted: Option<InnerRelay> = if relays.len() >= self.max {
+                // Iterate from the least-recently-used
+                let url: Option<RelayUrl> = relays
+                    .iter()
+                    .rev()
+                    .find(|(url, r)| **url != relay.url && r.is_idle())
+                    .map(|(url, ..)| url.clone());
+                url.and_then(|url| relays.pop(&url))
+            } else {
+                None
+            };
+
+            relays.put(relay.url.clone(), relay.clone());
+
+            evicted
+        };
+
+        // Evict after releasing the lock, since disconnecting untracks the relay
+        match evicted {
+            Some(evicted) => evicted.evict(),
+            None => {
+                if self.len() > self.max {
+                    tracing::debug!(url = %relay.url, max = self.max, "All the connected relays are busy, exceeding the connection limit.");
+                }
+            }
+        }
+    }
+
+    /// Mark the relay as recently used
+    pub(super) fn touch(&self, url: &RelayUrl) {
+        let mut relays = self.relays.lock().unwrap_or_else(PoisonError::into_inner);
+        relays.get(url);
+    }
+
+    /// Stop tracking the relay
+    pub(super) fn release(&self, url: &RelayUrl) {
+        let mut relays = self.relays.lock().unwrap_or_else(PoisonError::into_inner);
+        relays.pop(url);
+    }
+
+    fn len(&self) -> usize {
+        let relays = self.relays.lock().unwrap_or_else(PoisonError::into_inner);
+        relays.len()
+    }
+}
@@ -36,8 +36,12 @@ pub(super) const MIN_ATTEMPTS: usize = 1;
 pub(super) const MIN_SUCCESS_RATE: f64 = 0.90;
 
 pub(super) const PING_INTERVAL: Duration = Duration::from_secs(55); // Used also for latency calculation
+pub(super) const MIN_PING_INTERVAL: Duration = Duration::from_secs(1);
 
 pub(super) const WEBSOCKET_TX_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) const LATENCY_MIN_READS: u64 = 3;
+/// Number of samples of the latency rolling average
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const LATENCY_WINDOW: u64 = 20;
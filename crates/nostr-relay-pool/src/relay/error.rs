@@ -3,6 +3,7 @@
 // Distributed under the MIT software license
 
 use std::fmt;
+use std::net::IpAddr;
 use std::time::Duration;
 
 use nostr::event::{self, builder};
@@ -64,6 +65,11 @@ pub enum Error {
         /// Reason
         reason: Option<String>,
     },
+    /// Resolved address not allowed by the [`RelayFilter`](crate::RelayFilter)
+    AddressNotAllowed {
+        /// Resolved address
+        addr: IpAddr,
+    },
     /// Received termination request
     TerminationRequest,
     /// Received shutdown
@@ -101,8 +107,36 @@ pub enum Error {
         /// Max tags num
         max_size: usize,
     },
+    /// Message too large for the relay (NIP-11 `max_message_length`)
+    MessageTooLarge {
+        /// Message size
+        size: usize,
+        /// Max message size
+        max_size: usize,
+    },
+    /// Too many filters in a REQ for the relay (NIP-11 `max_filters`)
+    TooManyFilters {
+        /// Number of filters
+        size: usize,
+        /// Max number of filters
+        max_size: usize,
+    },
+    /// Too many active subscriptions for the relay (NIP-11 `max_subscriptions`)
+    TooManySubscriptions {
+        /// Max number of subscriptions
+        max: usize,
+    },
+    /// Filter with too many terms
+    TooManyFilterTerms {
+        /// Number of IDs and authors
+        size: usize,
+        /// Max number of IDs and authors
+        max_size: usize,
+    },
     /// Event expired
     EventExpired,
+    /// The event verification thread exited without replying
+    VerificationFailed,
     /// Notification Handler error
     Handler(String),
     /// Max latency exceeded
@@ -112,6 +146,11 @@ pub enum Error {
         /// Current
         current: Duration,
     },
+    /// Incoming rate limit exceeded
+    IncomingRateLimitExceeded {
+        /// Max events per second
+        max_per_second: u32,
+    },
     /// Auth failed
     AuthenticationFailed,
     /// Premature exit
@@ -149,6 +188,9 @@ impl fmt::Display for Error {
                 let reason: &str = reason.as_deref().unwrap_or("unknown");
                 write!(f, "connection rejected: reason={reason}")
             }
+            Self::AddressNotAllowed { addr } => {
+                write!(f, "resolved address '{addr}' not allowed")
+            }
             Self::TerminationRequest => write!(f, "received termination request"),
             Self::ReceivedShutdown => write!(f, "received shutdown"),
             Self::RelayMessage(message) => write!(f, "{message}"),
@@ -169,6 +211,21 @@ impl fmt::Display for Error {
                 f,
                 "Received event with too many tags: tags={size}, max_tags={max_size}"
             ),
+            Self::MessageTooLarge { size, max_size } => write!(
+                f,
+                "Message too large for relay: size={size}, max_size={max_size}"
+            ),
+            Self::TooManyFilters { size, max_size } => write!(
+                f,
+                "Too many filters for relay: filters={size}, max_filters={max_size}"
+            ),
+            Self::TooManySubscriptions { max } => {
+                write!(f, "Too many subscriptions for relay: max={max}")
+            }
+            Self::TooManyFilterTerms { size, max_size } => write!(
+                f,
+                "Filter with too many terms: terms={size}, max_terms={max_size}"
+            ),
             Self::EventExpired => write!(f, "event expired"),
             Self::Handler(e) => write!(f, "{e}"),
             Self::MaximumLatencyExceeded { max, current } => write!(
@@ -177,8 +234,17 @@ impl fmt::Display for Error {
                 max.as_millis(),
                 current.as_millis()
             ),
+            Self::IncomingRateLimitExceeded { max_per_second } => {
+                write!(
+                    f,
+                    "incoming rate limit exceeded: max={max_per_second} events/sec"
+                )
+            }
             Self::AuthenticationFailed => write!(f, "authentication failed"),
             Self::PrematureExit => write!(f, "premature exit"),
+            Self::VerificationFailed => {
+                write!(f, "event verification thread exited without replying")
+            }
         }
     }
 }
@@ -5,10 +5,15 @@
 use std::borrow::Cow;
 use std::cmp;
 use std::collections::{HashMap, HashSet};
+use std::mem;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::IpAddr;
+use std::slice;
 #[cfg(feature = "nip11")]
 use std::sync::atomic::AtomicU64;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::sync::{Mutex as StdMutex, PoisonError};
 use std::time::Duration;
 
 use async_utility::{task, time};
@@ -24,30 +29,38 @@ use tokio::sync::{broadcast, Mutex, MutexGuard, Notify, RwLock, RwLockWriteGuard
 use super::constants::{
     DEFAULT_CONNECTION_TIMEOUT, JITTER_RANGE, MAX_RETRY_INTERVAL, MIN_ATTEMPTS, MIN_SUCCESS_RATE,
     NEGENTROPY_BATCH_SIZE_DOWN, NEGENTROPY_FRAME_SIZE_LIMIT, NEGENTROPY_HIGH_WATER_UP,
-    NEGENTROPY_LOW_WATER_UP, PING_INTERVAL, WAIT_FOR_OK_TIMEOUT, WEBSOCKET_TX_TIMEOUT,
+    NEGENTROPY_LOW_WATER_UP, WAIT_FOR_OK_TIMEOUT, WEBSOCKET_TX_TIMEOUT,
 };
 use super::flags::AtomicRelayServiceFlags;
-use super::options::{RelayOptions, ReqExitPolicy, SubscribeAutoCloseOptions, SyncOptions};
+use super::options::{
+    ConnectPolicy, RateLimitAction, RelayOptions, ReqExitPolicy, SubscribeAutoCloseOptions,
+    SyncOptions,
+};
 use super::ping::PingTracker;
+use super::rate_limit::RateLimiter;
 use super::stats::RelayConnectionStats;
 use super::{
     Error, Reconciliation, RelayNotification, RelayStatus, SubscriptionActivity,
     SubscriptionAutoClosedReason,
 };
 use crate::policy::AdmitStatus;
+use crate::pool::options::NotificationOverflowPolicy;
 use crate::pool::RelayPoolNotification;
 use crate::relay::status::AtomicRelayStatus;
 use crate::shared::SharedState;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::error::TransportError;
 use crate::transport::websocket::{BoxSink, BoxStream};
 
 type ClientMessageJson = String;
 
-enum IngesterCommand {
+pub(super) enum IngesterCommand {
     Authenticate { challenge: String },
 }
 
 enum HandleClosedMsg {
     MarkAsClosed,
+    MarkAsAuthRequired,
     Remove,
 }
 
@@ -56,12 +69,21 @@ struct HandleAutoClosing {
     reason: Option<SubscriptionAutoClosedReason>,
 }
 
+#[derive(Debug)]
+struct OutgoingMsg {
+    json: ClientMessageJson,
+    /// Subject to the rate limit (`EVENT` and `REQ`)
+    rate_limited: bool,
+}
+
+#[derive(Debug)]
+struct OutgoingMsgs {
+    msgs: Vec<OutgoingMsg>,
+}
+
 #[derive(Debug)]
 struct RelayChannels {
-    nostr: (
-        Sender<Vec<ClientMessageJson>>,
-        Mutex<Receiver<Vec<ClientMessageJson>>>,
-    ),
+    nostr: (Sender<OutgoingMsgs>, Mutex<Receiver<OutgoingMsgs>>),
     ping: Notify,
     terminate: Notify,
 }
@@ -77,21 +99,43 @@ impl RelayChannels {
         }
     }
 
-    pub fn send_client_msgs(&self, msgs: Vec<ClientMessage>) -> Result<(), Error> {
+    /// Enqueue the messages, waiting for room in the channel if it's full
+    pub async fn send_client_msgs(
+        &self,
+        msgs: Vec<ClientMessage<'_>>,
+        max_message_length: Option<usize>,
+    ) -> Result<(), Error> {
         // Serialize messages to JSON
-        let msgs: Vec<ClientMessageJson> = msgs.into_iter().map(|msg| msg.as_json()).collect();
+        let msgs: Vec<OutgoingMsg> = msgs
+            .into_iter()
+            .map(|msg| OutgoingMsg {
+                rate_limited: msg.is_event() || msg.is_req(),
+                json: msg.as_json(),
+            })
+            .collect();
+
+        // Check messages size
+        if let Some(max_size) = max_message_length {
+            for msg in msgs.iter() {
+                let size: usize = msg.json.len();
+                if size > max_size {
+                    return Err(Error::MessageTooLarge { size, max_size });
+                }
+            }
+        }
 
         // Send
         self.nostr
             .0
-            .try_send(msgs)
+            .send(OutgoingMsgs { msgs })
+            .await
             .map_err(|_| Error::CantSendChannelMessage {
                 channel: String::from("nostr"),
             })
     }
 
     #[inline]
-    pub async fn rx_nostr(&self) -> MutexGuard<'_, Receiver<Vec<ClientMessageJson>>> {
+    pub async fn rx_nostr(&self) -> MutexGuard<'_, Receiver<OutgoingMsgs>> {
         self.nostr.1.lock().await
     }
 
@@ -116,6 +160,8 @@ struct SubscriptionData {
     pub subscribed_at: Timestamp,
     /// Subscription closed by relay
     pub closed: bool,
+    /// Timestamp of the newest received event
+    pub last_seen: Option<Timestamp>,
 }
 
 impl Default for SubscriptionData {
@@ -125,22 +171,101 @@ impl Default for SubscriptionData {
             filter: Filter::new(),
             subscribed_at: Timestamp::zero(),
             closed: false,
+            last_seen: None,
+        }
+    }
+}
+
+/// Limits advertised by the relay in the NIP-11 document (`0` means no limit)
+#[derive(Debug, Default)]
+struct DocumentLimits {
+    max_message_length: AtomicUsize,
+    max_subscriptions: AtomicUsize,
+    max_filters: AtomicUsize,
+}
+
+impl DocumentLimits {
+    #[cfg(feature = "nip11")]
+    fn update(&self, document: &RelayInformationDocument) {
+        let limitation: Option<&Limitation> = document.limitation.as_ref();
+
+        // Negative or missing values mean no limit
+        let get = |f: fn(&Limitation) -> Option<i32>| -> usize {
+            limitation
+                .and_then(f)
+                .and_then(|v| usize::try_from(v).ok())
+                .unwrap_or_default()
+        };
+
+        self.max_message_length
+            .store(get(|l| l.max_message_length), Ordering::SeqCst);
+        self.max_subscriptions
+            .store(get(|l| l.max_subscriptions), Ordering::SeqCst);
+        self.max_filters
+            .store(get(|l| l.max_filters), Ordering::SeqCst);
+    }
+
+    fn load(value: &AtomicUsize) -> Option<usize> {
+        match value.load(Ordering::SeqCst) {
+            0 => None,
+            v => Some(v),
         }
     }
 }
 
+/// Track an operation waiting for the relay responses, until dropped
+///
+/// A relay with in-flight operations is never disconnected by the connection limiter.
+pub(crate) struct InFlight {
+    atomic: Arc<AtomicPrivateData>,
+}
+
+impl InFlight {
+    fn new(atomic: &Arc<AtomicPrivateData>) -> Self {
+        atomic.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self {
+            atomic: atomic.clone(),
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.atomic.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 // Instead of wrap every field in an `Arc<T>`, which increases the number of atomic operations,
 // put all fields that require an `Arc` here.
 #[derive(Debug)]
 pub(super) struct AtomicPrivateData {
     status: AtomicRelayStatus,
     #[cfg(feature = "nip11")]
-    pub(super) document: RwLock<RelayInformationDocument>,
+    pub(super) document: RwLock<Option<RelayInformationDocument>>,
+    /// Limits of the information document, readable without locking
+    document_limits: DocumentLimits,
     #[cfg(feature = "nip11")]
     last_document_fetch: AtomicU64,
     channels: RelayChannels,
+    /// Outgoing messages rate limiter, set only if [`RelayOptions::rate_limit`] is enabled
+    rate_limiter: Option<RateLimiter>,
+    /// Incoming events rate limiter, set only if [`RelayOptions::incoming_rate_limit`] is enabled
+    incoming_rate_limiter: Option<(RateLimiter, RateLimitAction)>,
     subscriptions: RwLock<HashMap<SubscriptionId, SubscriptionData>>,
+    /// Filters of the auto-closing subscriptions, used only if [`RelayOptions::verify_subscriptions`] is enabled
+    auto_closing_filters: RwLock<HashMap<SubscriptionId, Filter>>,
+    /// REQs waiting for EOSE, used for latency calculation
+    #[cfg(not(target_arch = "wasm32"))]
+    pending_reqs: StdMutex<HashMap<SubscriptionId, Instant>>,
     running: AtomicBool,
+    /// Notified when the connection task exits
+    stopped: Notify,
+    /// Number of operations waiting for the relay responses (see [`InFlight`])
+    in_flight: AtomicUsize,
+    /// Disconnected by the connection limiter: reconnect on next usage
+    evicted: AtomicBool,
+    /// NIP42 challenge received in the current connection and not used yet to authenticate
+    auth_challenge: StdMutex<Option<String>>,
 }
 
 #[derive(Debug, Clone)]
@@ -152,7 +277,12 @@ pub(crate) struct InnerRelay {
     pub(super) stats: RelayConnectionStats,
     pub(super) state: SharedState,
     pub(super) internal_notification_sender: broadcast::Sender<RelayNotification>,
+    /// Pool notifications of this relay only, used by [`RelayPool::notifications_from`](crate::RelayPool::notifications_from)
+    pub(super) scoped_notification_sender: broadcast::Sender<RelayPoolNotification>,
     external_notification_sender: Option<broadcast::Sender<RelayPoolNotification>>,
+    /// Number of queued external notifications at which to stop reading messages,
+    /// and the signal notified when the notifications are consumed
+    external_notification_backpressure: Option<(usize, Arc<Notify>)>,
 }
 
 impl AtomicDestroyer for InnerRelay {
@@ -165,25 +295,41 @@ impl InnerRelay {
     pub(super) fn new(url: RelayUrl, state: SharedState, opts: RelayOptions) -> Self {
         let (relay_notification_sender, ..) =
             broadcast::channel::<RelayNotification>(opts.notification_channel_size);
+        let (scoped_notification_sender, ..) =
+            broadcast::channel::<RelayPoolNotification>(opts.notification_channel_size);
 
         Self {
             url,
             atomic: Arc::new(AtomicPrivateData {
                 status: AtomicRelayStatus::default(),
                 #[cfg(feature = "nip11")]
-                document: RwLock::new(RelayInformationDocument::new()),
+                document: RwLock::new(None),
+                document_limits: DocumentLimits::default(),
                 #[cfg(feature = "nip11")]
                 last_document_fetch: AtomicU64::new(0),
                 channels: RelayChannels::new(),
+                rate_limiter: opts.rate_limit.map(RateLimiter::new),
+                incoming_rate_limiter: opts
+                    .incoming_rate_limit
+                    .map(|(max, action)| (RateLimiter::new(max), action)),
                 subscriptions: RwLock::new(HashMap::new()),
+                auto_closing_filters: RwLock::new(HashMap::new()),
+                #[cfg(not(target_arch = "wasm32"))]
+                pending_reqs: StdMutex::new(HashMap::new()),
                 running: AtomicBool::new(false),
+                stopped: Notify::new(),
+                in_flight: AtomicUsize::new(0),
+                evicted: AtomicBool::new(false),
+                auth_challenge: StdMutex::new(None),
             }),
             flags: AtomicRelayServiceFlags::new(opts.flags),
             opts,
             stats: RelayConnectionStats::default(),
             state,
             internal_notification_sender: relay_notification_sender,
+            scoped_notification_sender,
             external_notification_sender: None,
+            external_notification_backpressure: None,
         }
     }
 
@@ -207,6 +353,9 @@ impl InnerRelay {
         // Change status
         self.atomic.status.set(status);
 
+        // Track uptime/downtime
+        self.stats.status_changed(status);
+
         // Log
         if log {
             match status {
@@ -223,7 +372,7 @@ impl InnerRelay {
         }
 
         // Send notification
-        self.send_notification(RelayNotification::RelayStatus { status }, false);
+        self.send_notification(RelayNotification::RelayStatus { status }, true);
 
         // If monitor is enabled, notify status change.
         if let Some(monitor) = &self.state.monitor {
@@ -235,9 +384,21 @@ impl InnerRelay {
     pub(super) fn health_check(&self) -> Result<(), Error> {
         let status: RelayStatus = self.status();
 
+        // Relay disconnected by the connection limiter
+        let evicted: bool =
+            status.is_terminated() && self.atomic.evicted.swap(false, Ordering::SeqCst);
+
         // Relay is not ready (never called connect method)
-        if status.is_initialized() {
-            return Err(Error::NotReady);
+        if status.is_initialized() || evicted {
+            match self.opts.connect_policy {
+                ConnectPolicy::Eager => return Err(Error::NotReady),
+                // Start the connection on first usage
+                ConnectPolicy::Lazy => self.lazy_connect(),
+            }
+        } else if let Some(limiter) = &self.state.connection_limiter {
+            if self.opts.connect_policy == ConnectPolicy::Lazy {
+                limiter.touch(&self.url);
+            }
         }
 
         // The relay has been banned
@@ -276,6 +437,12 @@ impl InnerRelay {
             Nip11GetOptions::default().timeout(DEFAULT_CONNECTION_TIMEOUT);
 
         let allowed: bool = match self.opts.connection_mode {
+            // The explicit HTTP proxy takes precedence over the connection mode
+            #[cfg(not(target_arch = "wasm32"))]
+            _ if self.opts.http_proxy.is_some() => {
+                opts.proxy = self.opts.http_proxy;
+                true
+            }
             ConnectionMode::Direct => true,
             #[cfg(not(target_arch = "wasm32"))]
             ConnectionMode::Proxy(proxy) => {
@@ -301,8 +468,10 @@ impl InnerRelay {
                 task::spawn(async move {
                     match RelayInformationDocument::get(url.clone().into(), opts).await {
                         Ok(document) => {
+                            atomic.document_limits.update(&document);
+
                             let mut d = atomic.document.write().await;
-                            *d = document
+                            *d = Some(document);
                         }
                         Err(e) => {
                             tracing::warn!(url = %url, error = %e, "Can't get information document.")
@@ -313,6 +482,34 @@ impl InnerRelay {
         }
     }
 
+    /// Max message length advertised by the relay (NIP-11)
+    #[inline]
+    fn max_message_length(&self) -> Option<usize> {
+        DocumentLimits::load(&self.atomic.document_limits.max_message_length)
+    }
+
+    /// Max number of filters per REQ advertised by the relay (NIP-11)
+    #[inline]
+    fn max_filters(&self) -> Option<usize> {
+        DocumentLimits::load(&self.atomic.document_limits.max_filters)
+    }
+
+    /// Check if a new subscription exceeds the max number of subscriptions advertised by the relay (NIP-11)
+    ///
+    /// Only the long-lived subscriptions are counted.
+    pub(super) async fn check_max_subscriptions(&self, id: &SubscriptionId) -> Result<(), Error> {
+        if let Some(max) = DocumentLimits::load(&self.atomic.document_limits.max_subscriptions) {
+            let subscriptions = self.atomic.subscriptions.read().await;
+
+            // Updating an existing subscription doesn't increase the count
+            if !subscriptions.contains_key(id) && subscriptions.len() >= max {
+                return Err(Error::TooManySubscriptions { max });
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Filter> {
         let subscription = self.atomic.subscriptions.read().await;
         subscription
@@ -326,6 +523,11 @@ impl InnerRelay {
         subscription.get(id).map(|d| d.filter.clone())
     }
 
+    async fn subscription_last_seen(&self, id: &SubscriptionId) -> Option<Timestamp> {
+        let subscriptions = self.atomic.subscriptions.read().await;
+        subscriptions.get(id).and_then(|d| d.last_seen)
+    }
+
     pub(crate) async fn update_subscription(
         &self,
         id: SubscriptionId,
@@ -341,6 +543,64 @@ impl InnerRelay {
         }
     }
 
+    /// Keep track of the filter of an auto-closing subscription, to verify the received events
+    pub(super) async fn add_auto_closing_filter(&self, id: SubscriptionId, filter: Filter) {
+        if self.opts.verify_subscriptions {
+            let mut filters = self.atomic.auto_closing_filters.write().await;
+            filters.insert(id, filter);
+        }
+    }
+
+    pub(super) async fn remove_auto_closing_filter(&self, id: &SubscriptionId) {
+        if self.opts.verify_subscriptions {
+            let mut filters = self.atomic.auto_closing_filters.write().await;
+            filters.remove(id);
+        }
+    }
+
+    /// Check if the event matches the filter of the subscription
+    ///
+    /// Events of unknown subscriptions are considered matching.
+    async fn subscription_match(&self, id: &SubscriptionId, event: &Event) -> bool {
+        let subscriptions = self.atomic.subscriptions.read().await;
+        if let Some(data) = subscriptions.get(id) {
+            return filter_match(&data.filter, event);
+        }
+        drop(subscriptions);
+
+        let filters = self.atomic.auto_closing_filters.read().await;
+        filters
+            .get(id)
+            .map_or(true, |filter| filter_match(filter, event))
+    }
+
+    /// Update the timestamp of the newest event received for the subscription
+    async fn update_subscription_last_seen(&self, id: &SubscriptionId, created_at: Timestamp) {
+        let mut subscriptions = self.atomic.subscriptions.write().await;
+        if let Some(data) = subscriptions.get_mut(id) {
+            if data.last_seen < Some(created_at) {
+                data.last_seen = Some(created_at);
+            }
+        }
+    }
+
+    fn set_auth_challenge(&self, challenge: Option<String>) {
+        let mut current = self
+            .atomic
+            .auth_challenge
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *current = challenge;
+    }
+
+    fn take_auth_challenge(&self) -> Option<String> {
+        self.atomic
+            .auth_challenge
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
     /// Mark subscription as closed
     async fn subscription_closed(&self, id: &SubscriptionId) {
         let mut subscriptions = self.atomic.subscriptions.write().await;
@@ -359,11 +619,20 @@ impl InnerRelay {
                 ..
             }) => {
                 // Never subscribed -> SHOULD subscribe
-                // Subscription closed by relay -> SHOULD subscribe
-                if subscribed_at.is_zero() || *closed {
+                if subscribed_at.is_zero() {
+                    return true;
+                }
+
+                // Subscription closed by relay (i.e., waiting for authentication) -> SHOULD subscribe
+                if *closed {
                     return true;
                 }
 
+                // Re-subscription disabled -> SHOULD NOT re-subscribe
+                if !self.opts.resubscribe_on_reconnect {
+                    return false;
+                }
+
                 // First connection and subscribed_at != 0 -> SHOULD NOT re-subscribe
                 // Many connections and subscription NOT done in current websocket session -> SHOULD re-subscribe
                 self.stats.connected_at() > *subscribed_at && self.stats.success() > 1
@@ -380,8 +649,40 @@ impl InnerRelay {
     pub(crate) fn set_notification_sender(
         &mut self,
         notification_sender: broadcast::Sender<RelayPoolNotification>,
+        notification_drained: Arc<Notify>,
+        overflow: NotificationOverflowPolicy,
+        capacity: usize,
     ) {
         self.external_notification_sender = Some(notification_sender);
+        self.external_notification_backpressure = match overflow {
+            NotificationOverflowPolicy::DropOldest => None,
+            // A single relay message may produce more than one notification
+            // and many relays share the same channel: keep half of the capacity as headroom.
+            NotificationOverflowPolicy::Block => {
+                Some(((capacity / 2).max(1), notification_drained))
+            }
+        };
+    }
+
+    /// Wait until the external notification channel has room for new notifications
+    ///
+    /// Returns immediately if the [`NotificationOverflowPolicy::Block`] isn't set.
+    async fn wait_for_notification_capacity(&self) {
+        if let (Some(sender), Some((capacity, drained))) = (
+            &self.external_notification_sender,
+            &self.external_notification_backpressure,
+        ) {
+            loop {
+                // Register before checking the length, to not miss a notification sent in between
+                let notified = drained.notified();
+
+                if sender.len() < *capacity {
+                    break;
+                }
+
+                notified.await;
+            }
+        }
     }
 
     fn send_notification(&self, notification: RelayNotification, external: bool) {
@@ -390,27 +691,18 @@ impl InnerRelay {
                 // Clone and send internal notification
                 let _ = self.internal_notification_sender.send(notification.clone());
 
-                // Convert relay to notification to pool notification
-                let notification: Option<RelayPoolNotification> = match notification {
-                    RelayNotification::Event {
-                        subscription_id,
-                        event,
-                    } => Some(RelayPoolNotification::Event {
-                        relay_url: self.url.clone(),
-                        subscription_id,
-                        event,
-                    }),
-                    RelayNotification::Message { message } => {
-                        Some(RelayPoolNotification::Message {
-                            relay_url: self.url.clone(),
-                            message,
-                        })
+                // Send relay-scoped notification, only if someone is listening
+                if self.scoped_notification_sender.receiver_count() > 0 {
+                    if let Some(notification) =
+                        self.to_pool_notification(notification.clone(), false)
+                    {
+                        let _ = self.scoped_notification_sender.send(notification);
                     }
-                    RelayNotification::RelayStatus { .. } => None,
-                    RelayNotification::Authenticated => None,
-                    RelayNotification::AuthenticationFailed => None,
-                    RelayNotification::Shutdown => Some(RelayPoolNotification::Shutdown),
-                };
+                }
+
+                // Convert relay to notification to pool notification
+                let notification: Option<RelayPoolNotification> =
+                    self.to_pool_notification(notification, true);
 
                 // Send external notification
                 if let Some(notification) = notification {
@@ -424,6 +716,69 @@ impl InnerRelay {
         }
     }
 
+    /// Convert relay notification to pool notification
+    ///
+    /// If `dedup` is enabled, the events already notified by other relays are skipped.
+    fn to_pool_notification(
+        &self,
+        notification: RelayNotification,
+        dedup: bool,
+    ) -> Option<RelayPoolNotification> {
+        match notification {
+            RelayNotification::Event {
+                subscription_id,
+                event,
+            } => {
+                // Skip events already notified by other relays
+                if dedup && matches!(self.state.already_notified(&event), Ok(true)) {
+                    None
+                } else {
+                    Some(RelayPoolNotification::Event {
+                        relay_url: self.url.clone(),
+                        subscription_id,
+                        event,
+                    })
+                }
+            }
+            RelayNotification::Message { message } => Some(RelayPoolNotification::Message {
+                relay_url: self.url.clone(),
+                message,
+            }),
+            RelayNotification::RelayStatus { status } => Some(RelayPoolNotification::RelayStatus {
+                relay_url: self.url.clone(),
+                status,
+            }),
+            RelayNotification::Authenticated => None,
+            RelayNotification::AuthenticationFailed => {
+                Some(RelayPoolNotification::AuthenticationFailed {
+                    relay_url: self.url.clone(),
+                })
+            }
+            RelayNotification::RelayMessageError { raw, error } => {
+                Some(RelayPoolNotification::RelayMessageError {
+                    relay_url: self.url.clone(),
+                    raw,
+                    error,
+                })
+            }
+            RelayNotification::RateLimitExceeded { max_per_second } => {
+                Some(RelayPoolNotification::RateLimitExceeded {
+                    relay_url: self.url.clone(),
+                    max_per_second,
+                })
+            }
+            RelayNotification::AuthRequired {
+                subscription_id,
+                message,
+            } => Some(RelayPoolNotification::AuthRequired {
+                relay_url: self.url.clone(),
+                subscription_id,
+                message,
+            }),
+            RelayNotification::Shutdown => Some(RelayPoolNotification::Shutdown),
+        }
+    }
+
     pub(super) async fn check_connection_policy(&self) -> Result<AdmitStatus, Error> {
         match &self.state.admit_policy {
             Some(policy) => Ok(policy.admit_connection(&self.url).await?),
@@ -431,6 +786,77 @@ impl InnerRelay {
         }
     }
 
+    /// Start the connection of a lazy relay
+    fn lazy_connect(&self) {
+        tracing::debug!(url = %self.url, "Starting lazy relay connection.");
+
+        // Track the connection, disconnecting another relay if the limit is reached
+        if let Some(limiter) = &self.state.connection_limiter {
+            limiter.acquire(self);
+        }
+
+        // Change it to pending to avoid issues with the health check (initialized check)
+        self.set_status(RelayStatus::Pending, false);
+
+        // The connection task of an evicted relay may be still exiting
+        if self.is_running() {
+            let relay: InnerRelay = self.clone();
+            task::spawn(async move {
+                loop {
+                    // Register before checking the status, to not miss the notification
+                    let stopped = relay.atomic.stopped.notified();
+
+                    if !relay.is_running() {
+                        break;
+                    }
+
+                    stopped.await;
+                }
+
+                relay.spawn_connection_task(None);
+            });
+            return;
+        }
+
+        // Spawn connection task
+        self.spawn_connection_task(None);
+    }
+
+    #[inline]
+    pub(crate) fn is_lazy_and_idle(&self) -> bool {
+        if self.opts.connect_policy != ConnectPolicy::Lazy {
+            return false;
+        }
+
+        let status: RelayStatus = self.status();
+        status.is_initialized()
+            || (status.is_terminated() && self.atomic.evicted.load(Ordering::SeqCst))
+    }
+
+    /// Check if there are no active subscriptions and no in-flight operations
+    pub(super) fn is_idle(&self) -> bool {
+        self.atomic.in_flight.load(Ordering::SeqCst) == 0
+            && self
+                .atomic
+                .subscriptions
+                .try_read()
+                .is_ok_and(|subscriptions| subscriptions.is_empty())
+    }
+
+    /// Track an in-flight operation, until the returned guard is dropped
+    #[inline]
+    pub(crate) fn in_flight(&self) -> InFlight {
+        InFlight::new(&self.atomic)
+    }
+
+    /// Disconnect, allowing to reconnect on next usage
+    pub(super) fn evict(&self) {
+        tracing::debug!(url = %self.url, "Disconnecting least-recently-used relay.");
+
+        self.atomic.evicted.store(true, Ordering::SeqCst);
+        self.disconnect();
+    }
+
     pub(super) fn spawn_connection_task(&self, stream: Option<(BoxSink, BoxStream)>) {
         if self.is_running() {
             tracing::warn!(url = %self.url, "Connection task is already running.");
@@ -529,12 +955,20 @@ impl InnerRelay {
 
         // Set that connection task is no longer running
         self.atomic.running.store(false, Ordering::SeqCst);
+        self.atomic.stopped.notify_waiters();
 
         tracing::debug!(url = %self.url, "Auto connect loop terminated.");
     }
 
     /// Depending on attempts and success, use default or incremental retry interval
     fn calculate_retry_interval(&self) -> Duration {
+        // Check if a backoff policy is set
+        if let Some(policy) = &self.opts.backoff {
+            // Calculate the difference between attempts and success
+            let failures: u32 = self.stats.attempts().saturating_sub(self.stats.success()) as u32;
+            return policy.delay(failures);
+        }
+
         // Check if the incremental interval is enabled
         if self.opts.adjust_retry_interval {
             // Calculate the difference between attempts and success
@@ -592,10 +1026,24 @@ impl InnerRelay {
         // Try to connect
         // If during connection the termination request is received, abort the connection and return error.
         // At this stem is NOT required to close the WebSocket connection.
+        let url: &Url = (&self.url).into();
+        let connect = async {
+            self.check_resolved_addresses(url).await?;
+
+            self.state
+                .transport
+                .connect(url, &self.opts.connection_mode, timeout)
+                .await
+                .map_err(Error::Transport)
+        };
+
         tokio::select! {
             // Connect
-            res = self.state.transport.connect((&self.url).into(), &self.opts.connection_mode, timeout) => match res {
+            res = connect => match res {
                 Ok((ws_tx, ws_rx)) => {
+                    // The challenges are valid only for the connection in which are sent
+                    self.set_auth_challenge(None);
+
                     // Update status
                     self.set_status(RelayStatus::Connected, true);
 
@@ -609,7 +1057,7 @@ impl InnerRelay {
                     self.set_status(status_on_failure, false);
 
                     // Return error
-                    Err(Error::Transport(e))
+                    Err(e)
                 }
             },
             // Handle termination notification
@@ -617,13 +1065,54 @@ impl InnerRelay {
         }
     }
 
+    /// Check the addresses to which the host resolves, if required by the [`RelayFilter`](crate::RelayFilter)
+    ///
+    /// With a proxy, the host is resolved remotely, so nothing is checked.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn check_resolved_addresses(&self, url: &Url) -> Result<(), Error> {
+        let filter = match &self.state.relay_filter {
+            Some(filter) if filter.check_resolved_addresses() => filter,
+            _ => return Ok(()),
+        };
+
+        if !matches!(self.opts.connection_mode, ConnectionMode::Direct) {
+            return Ok(());
+        }
+
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            return Ok(());
+        };
+        let host: &str = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+
+        let addrs = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| Error::Transport(TransportError::backend(e)))?;
+
+        for addr in addrs {
+            let addr: IpAddr = addr.ip();
+            if !filter.is_address_allowed(&addr) {
+                return Err(Error::AddressNotAllowed { addr });
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn check_resolved_addresses(&self, _url: &Url) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Connect and run message handler
     ///
     /// If `stream` arg is passed, no connection attempt will be done.
     async fn connect_and_run(
         &self,
         stream: Option<(BoxSink, BoxStream)>,
-        rx_nostr: &mut MutexGuard<'_, Receiver<Vec<ClientMessageJson>>>,
+        rx_nostr: &mut MutexGuard<'_, Receiver<OutgoingMsgs>>,
         last_ws_error: &mut Option<String>,
     ) {
         match stream {
@@ -632,7 +1121,12 @@ impl InnerRelay {
             // No stream is passed, try to connect
             // Set the status to "disconnected" to allow to automatic retries
             None => match self
-                ._try_connect(DEFAULT_CONNECTION_TIMEOUT, RelayStatus::Disconnected)
+                ._try_connect(
+                    self.opts
+                        .connection_timeout
+                        .unwrap_or(DEFAULT_CONNECTION_TIMEOUT),
+                    RelayStatus::Disconnected,
+                )
                 .await
             {
                 // Connection success, go to post-connection stage
@@ -666,7 +1160,7 @@ impl InnerRelay {
         &self,
         mut ws_tx: BoxSink,
         ws_rx: BoxStream,
-        rx_nostr: &mut MutexGuard<'_, Receiver<Vec<ClientMessageJson>>>,
+        rx_nostr: &mut MutexGuard<'_, Receiver<OutgoingMsgs>>,
     ) {
         // Request information document
         #[cfg(feature = "nip11")]
@@ -717,40 +1211,16 @@ impl InnerRelay {
     async fn sender_message_handler(
         &self,
         ws_tx: &mut BoxSink,
-        rx_nostr: &mut MutexGuard<'_, Receiver<Vec<ClientMessageJson>>>,
+        rx_nostr: &mut MutexGuard<'_, Receiver<OutgoingMsgs>>,
         ping: &PingTracker,
     ) -> Result<(), Error> {
         #[cfg(target_arch = "wasm32")]
         let _ping = ping;
 
         loop {
-            tokio::select! {
+            let OutgoingMsgs { msgs } = tokio::select! {
                 // Nostr channel receiver
-                Some(msgs) = rx_nostr.recv() => {
-                    // Compose WebSocket text messages
-                    let msgs: Vec<Message> = msgs
-                        .into_iter()
-                        .map(Message::Text)
-                        .collect();
-
-                    // Calculate messages size
-                    let size: usize = msgs.iter().map(|msg| msg.len()).sum();
-                    let len: usize = msgs.len();
-
-                    // Log
-                    if len == 1 {
-                        let json = &msgs[0]; // SAFETY: len checked above (len == 1)
-                        tracing::debug!("Sending '{json}' to '{}' (size: {size} bytes)", self.url);
-                    } else {
-                        tracing::debug!("Sending {len} messages to '{}' (size: {size} bytes)", self.url);
-                    };
-
-                    // Send WebSocket messages
-                    send_ws_msgs(ws_tx, msgs).await?;
-
-                    // Increase sent bytes
-                    self.stats.add_bytes_sent(size);
-                }
+                Some(msgs) = rx_nostr.recv() => msgs,
                 // Ping channel receiver
                 _ = self.atomic.channels.ping.notified() => {
                     #[cfg(not(target_arch = "wasm32"))]
@@ -778,14 +1248,78 @@ impl InnerRelay {
                         #[cfg(debug_assertions)]
                         tracing::debug!(url = %self.url, nonce = %nonce, "Ping sent.");
                     }
+
+                    continue;
                 }
                 else => break
+            };
+
+            match &self.atomic.rate_limiter {
+                // Throttle each message subject to the rate limit
+                Some(rate_limiter) => {
+                    let mut batch: Vec<ClientMessageJson> = Vec::with_capacity(msgs.len());
+
+                    for OutgoingMsg { json, rate_limited } in msgs.into_iter() {
+                        if rate_limited {
+                            // Flush the messages before waiting for the rate limit
+                            if !rate_limiter.wait_time().is_zero() && !batch.is_empty() {
+                                self.send_outgoing_msgs(ws_tx, mem::take(&mut batch))
+                                    .await?;
+                            }
+
+                            rate_limiter.acquire(1).await;
+                        }
+
+                        batch.push(json);
+                    }
+
+                    if !batch.is_empty() {
+                        self.send_outgoing_msgs(ws_tx, batch).await?;
+                    }
+                }
+                None => {
+                    let msgs: Vec<ClientMessageJson> =
+                        msgs.into_iter().map(|msg| msg.json).collect();
+                    self.send_outgoing_msgs(ws_tx, msgs).await?;
+                }
             }
         }
 
         Ok(())
     }
 
+    async fn send_outgoing_msgs(
+        &self,
+        ws_tx: &mut BoxSink,
+        msgs: Vec<ClientMessageJson>,
+    ) -> Result<(), Error> {
+        // Compose WebSocket text messages
+        let msgs: Vec<Message> = msgs.into_iter().map(Message::Text).collect();
+
+        // Calculate messages size
+        let size: usize = msgs.iter().map(|msg| msg.len()).sum();
+        let len: usize = msgs.len();
+
+        // Log
+        if len == 1 {
+            let json = &msgs[0]; // SAFETY: len checked above (len == 1)
+            tracing::debug!("Sending '{json}' to '{}' (size: {size} bytes)", self.url);
+        } else {
+            tracing::debug!(
+                "Sending {len} messages to '{}' (size: {size} bytes)",
+                self.url
+            );
+        };
+
+        // Send WebSocket messages
+        send_ws_msgs(ws_tx, msgs).await?;
+
+        // Increase sent bytes
+        self.stats.add_bytes_sent(size);
+
+        Ok(())
+    }
+
     async fn receiver_message_handler(
         &self,
         mut ws_rx: BoxStream,
@@ -797,7 +1331,17 @@ impl InnerRelay {
 
         while let Some(msg) = ws_rx.next().await {
             match msg? {
-                Message::Text(json) => self.handle_relay_message(&json, &ingester_tx).await,
+                Message::Text(json) => {
+                    // Apply the incoming rate limit, if required
+                    if is_event_msg(&json) {
+                        self.check_incoming_rate_limit().await?;
+                    }
+
+                    // Apply backpressure, if required
+                    self.wait_for_notification_capacity().await;
+
+                    self.handle_relay_message(&json, &ingester_tx).await
+                }
                 Message::Binary(_) => {
                     tracing::warn!(url = %self.url, "Binary messages aren't supported.");
                 }
@@ -848,6 +1392,32 @@ impl InnerRelay {
         Ok(())
     }
 
+    /// Throttle the reads or disconnect if the incoming rate limit is exceeded
+    async fn check_incoming_rate_limit(&self) -> Result<(), Error> {
+        if let Some((limiter, action)) = &self.atomic.incoming_rate_limiter {
+            match action {
+                RateLimitAction::Throttle => limiter.acquire(1).await,
+                RateLimitAction::Disconnect => {
+                    if !limiter.reserve(1).is_zero() {
+                        let max_per_second: u32 = limiter.max_per_second();
+
+                        tracing::warn!(url = %self.url, max = %max_per_second, "Incoming rate limit exceeded, disconnecting.");
+
+                        self.send_notification(
+                            RelayNotification::RateLimitExceeded { max_per_second },
+                            true,
+                        );
+                        self.disconnect();
+
+                        return Err(Error::IncomingRateLimitExceeded { max_per_second });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn ingester(
         &self,
         mut rx: mpsc::UnboundedReceiver<IngesterCommand>,
@@ -872,7 +1442,7 @@ impl InnerRelay {
                             }
                         }
                         Err(e) => {
-                            self.send_notification(RelayNotification::AuthenticationFailed, false);
+                            self.send_notification(RelayNotification::AuthenticationFailed, true);
 
                             tracing::error!(
                                 url = %self.url,
@@ -888,7 +1458,10 @@ impl InnerRelay {
         Ok(())
     }
 
-    /// Send a signal every [`PING_INTERVAL`] to the other tasks, asking to ping the relay.
+    /// Send a signal every ping interval (default: [`PING_INTERVAL`](super::constants::PING_INTERVAL)) to the other tasks, asking to ping the relay.
+    ///
+    /// If the relay didn't reply to the previous ping, the sender exits with [`Error::NotRepliedToPing`],
+    /// closing the connection.
     async fn pinger(&self) {
         loop {
             // Check if support ping
@@ -899,11 +1472,11 @@ impl InnerRelay {
             }
 
             // Sleep
-            time::sleep(PING_INTERVAL).await;
+            time::sleep(self.opts.ping_interval).await;
         }
     }
 
-    async fn handle_relay_message(
+    pub(super) async fn handle_relay_message(
         &self,
         msg: &str,
         ingester_tx: &mpsc::UnboundedSender<IngesterCommand>,
@@ -933,6 +1506,12 @@ impl InnerRelay {
                             id = %id,
                             "Received EOSE."
                         );
+
+                        // Save REQ-to-EOSE latency
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(sent_at) = self.take_pending_req(id) {
+                            self.stats.save_latency(sent_at.elapsed());
+                        }
                     }
                     RelayMessage::Closed {
                         subscription_id,
@@ -945,6 +1524,10 @@ impl InnerRelay {
                             "Subscription closed by relay."
                         );
 
+                        // No EOSE will be received
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.take_pending_req(subscription_id);
+
                         // Check machine-readable prefix
                         let res: HandleClosedMsg = match MachineReadablePrefix::parse(message) {
                             Some(MachineReadablePrefix::Duplicate) => HandleClosedMsg::Remove,
@@ -959,9 +1542,7 @@ impl InnerRelay {
                             Some(MachineReadablePrefix::Error) => HandleClosedMsg::Remove,
                             Some(MachineReadablePrefix::Unsupported) => HandleClosedMsg::Remove,
                             Some(MachineReadablePrefix::AuthRequired) => {
-                                // Authentication is handled in other parts of code,
-                                // so here just mark as closed.
-                                HandleClosedMsg::MarkAsClosed
+                                HandleClosedMsg::MarkAsAuthRequired
                             }
                             Some(MachineReadablePrefix::Restricted) => HandleClosedMsg::Remove,
                             None => {
@@ -976,6 +1557,28 @@ impl InnerRelay {
                             HandleClosedMsg::MarkAsClosed => {
                                 self.subscription_closed(subscription_id).await;
                             }
+                            HandleClosedMsg::MarkAsAuthRequired => {
+                                // Mark as closed, so it's re-sent by the resubscription after the authentication
+                                self.subscription_closed(subscription_id).await;
+
+                                self.send_notification(
+                                    RelayNotification::AuthRequired {
+                                        subscription_id: subscription_id.clone().into_owned(),
+                                        message: closed_reason(message).to_string(),
+                                    },
+                                    true,
+                                );
+
+                                // Authenticate with the unused challenge, if any.
+                                // Otherwise, the authentication is already in progress or will start
+                                // when the relay sends the challenge.
+                                if self.state.is_auto_authentication_enabled() {
+                                    if let Some(challenge) = self.take_auth_challenge() {
+                                        let _ = ingester_tx
+                                            .send(IngesterCommand::Authenticate { challenge });
+                                    }
+                                }
+                            }
                             HandleClosedMsg::Remove => {
                                 tracing::debug!(
                                     url = %self.url,
@@ -997,10 +1600,16 @@ impl InnerRelay {
 
                         // Check if NIP42 auto authentication is enabled
                         if self.state.is_auto_authentication_enabled() {
+                            self.set_auth_challenge(None);
+
                             // Forward action to ingester
                             let _ = ingester_tx.send(IngesterCommand::Authenticate {
                                 challenge: challenge.to_string(),
                             });
+                        } else {
+                            // Keep the challenge, to authenticate if a subscription requires it
+                            // after the automatic authentication is enabled
+                            self.set_auth_challenge(Some(challenge.to_string()));
                         }
                     }
                     _ => (),
@@ -1010,6 +1619,26 @@ impl InnerRelay {
                 self.send_notification(RelayNotification::Message { message }, true);
             }
             Ok(None) | Err(Error::MessageHandle(MessageHandleError::EmptyMsg)) => (),
+            Err(Error::MessageHandle(MessageHandleError::UnknownMessageType(t))) => {
+                tracing::debug!(url = %self.url, msg = %msg, r#type = %t, "Ignoring relay message of unknown type.");
+            }
+            Err(Error::MessageHandle(e)) => {
+                tracing::warn!(
+                    url = %self.url,
+                    msg = %msg,
+                    error = %e,
+                    "Received invalid relay message."
+                );
+
+                // Report the malformed message
+                self.send_notification(
+                    RelayNotification::RelayMessageError {
+                        raw: msg.to_string(),
+                        error: e.to_string(),
+                    },
+                    true,
+                );
+            }
             Err(e) => tracing::error!(
                 url = %self.url,
                 msg = %msg,
@@ -1027,8 +1656,9 @@ impl InnerRelay {
 
         tracing::trace!(url = %self.url, size = %size, msg = %msg, "Received new relay message.");
 
-        // Update bytes received
+        // Update bytes received and last message timestamp
         self.stats.add_bytes_received(size);
+        self.stats.new_message();
 
         // Check message size
         if let Some(max_size) = self.opts.limits.messages.max_size {
@@ -1054,8 +1684,10 @@ impl InnerRelay {
     async fn handle_event_msg(
         &self,
         subscription_id: SubscriptionId,
-        event: Event,
+        mut event: Event,
     ) -> Result<Option<RelayMessage<'static>>, Error> {
+        self.stats.new_event();
+
         // Check event size
         if let Some(max_size) = self.opts.limits.events.get_max_size(&event.kind) {
             let size: usize = event.as_json().len();
@@ -1082,6 +1714,14 @@ impl InnerRelay {
             return Err(Error::EventExpired);
         }
 
+        // Check POW
+        let min_pow: u8 = self.state.min_pow_difficulty();
+        if min_pow > 0 && !check_committed_pow(&event, min_pow) {
+            tracing::trace!(url = %self.url, id = %event.id, min = %min_pow, "Event POW difficulty too low.");
+            self.stats.new_pow_rejected_event();
+            return Ok(None);
+        }
+
         // Check event admission policy
         if let Some(policy) = &self.state.admit_policy {
             if let AdmitStatus::Rejected { .. } = policy
@@ -1092,6 +1732,12 @@ impl InnerRelay {
             }
         }
 
+        // Keep track of the newest event, to resume the subscription after a reconnection
+        if self.opts.resubscribe_since_last_seen {
+            self.update_subscription_last_seen(&subscription_id, event.created_at)
+                .await;
+        }
+
         // Check if event status
         let status: DatabaseEventStatus = self.state.database().check_id(&event.id).await?;
 
@@ -1113,7 +1759,13 @@ impl InnerRelay {
             }
         }
 
-        // TODO: check if filter match
+        // Check if the event matches the subscription filter
+        if self.opts.verify_subscriptions
+            && !self.subscription_match(&subscription_id, &event).await
+        {
+            tracing::warn!(url = %self.url, id = %event.id, subscription_id = %subscription_id, "Event doesn't match the subscription filter, discarding.");
+            return Ok(None);
+        }
 
         // Check if the event exists
         if let DatabaseEventStatus::NotExistent = status {
@@ -1125,8 +1777,18 @@ impl InnerRelay {
             // This may also be useful to avoid double verification if the event is received at the exact same time by many different Relay instances.
             //
             // This is important since event signature verification is a heavy job!
-            if !self.state.verified(&event.id)? {
-                event.verify()?;
+            if !self.state.verifier.is_disabled() && !self.state.verified(&event.id)? {
+                event = self.state.verifier.verify(event).await?;
+            }
+
+            // Check if the event TTL is elapsed:
+            // still answer the subscription, but don't store and notify the event again.
+            if self.state.is_expired(&event) {
+                tracing::trace!(url = %self.url, id = %event.id, "Event TTL elapsed, skipping notification.");
+                return Ok(Some(RelayMessage::Event {
+                    subscription_id: Cow::Owned(subscription_id),
+                    event: Cow::Owned(event),
+                }));
             }
 
             // Save into the database
@@ -1148,6 +1810,9 @@ impl InnerRelay {
                 return Ok(None);
             }
 
+            // Track the event, to expire it after the TTL
+            self.state.track_received(&event);
+
             // Send notification
             self.send_notification(
                 RelayNotification::Event {
@@ -1175,6 +1840,11 @@ impl InnerRelay {
         // Notify termination
         self.atomic.channels.terminate();
 
+        // Stop tracking the connection
+        if let Some(limiter) = &self.state.connection_limiter {
+            limiter.release(&self.url);
+        }
+
         // Update status
         self.set_status(RelayStatus::Terminated, true);
 
@@ -1200,12 +1870,19 @@ impl InnerRelay {
         self.send_notification(RelayNotification::Shutdown, false);
     }
 
+    /// Wait until the rate limit allows to send a new message
+    pub(super) async fn wait_for_rate_limit(&self) {
+        if let Some(rate_limiter) = &self.atomic.rate_limiter {
+            rate_limiter.ready().await;
+        }
+    }
+
     #[inline]
-    pub fn send_msg(&self, msg: ClientMessage<'_>) -> Result<(), Error> {
-        self.batch_msg(vec![msg])
+    pub async fn send_msg(&self, msg: ClientMessage<'_>) -> Result<(), Error> {
+        self.batch_msg(vec![msg]).await
     }
 
-    pub fn batch_msg(&self, msgs: Vec<ClientMessage<'_>>) -> Result<(), Error> {
+    pub async fn batch_msg(&self, mut msgs: Vec<ClientMessage<'_>>) -> Result<(), Error> {
         // Perform health checks
         self.health_check()?;
 
@@ -1224,21 +1901,116 @@ impl InnerRelay {
             return Err(Error::ReadDisabled);
         }
 
+        // Set the default limit to the filters without one
+        if let Some(limit) = self.opts.default_filter_limit {
+            for msg in msgs.iter_mut() {
+                match msg {
+                    ClientMessage::Req { filter, .. } => {
+                        if filter.limit.is_none() {
+                            filter.to_mut().limit = Some(limit);
+                        }
+                    }
+                    ClientMessage::ReqMultiFilter { filters, .. } => {
+                        for filter in filters.iter_mut() {
+                            filter.limit.get_or_insert(limit);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Check filters complexity
+        if let Some(max_size) = self.opts.max_filter_terms {
+            for msg in msgs.iter() {
+                let filters: &[Filter] = match msg {
+                    ClientMessage::Req { filter, .. } => slice::from_ref(filter.as_ref()),
+                    ClientMessage::ReqMultiFilter { filters, .. } => filters,
+                    _ => continue,
+                };
+
+                for filter in filters.iter() {
+                    let size: usize = filter_terms(filter);
+                    if size > max_size {
+                        return Err(Error::TooManyFilterTerms { size, max_size });
+                    }
+                }
+            }
+        }
+
+        // Check the max number of filters advertised by the relay
+        if let Some(max_size) = self.max_filters() {
+            for msg in msgs.iter() {
+                if let ClientMessage::ReqMultiFilter { filters, .. } = msg {
+                    let size: usize = filters.len();
+                    if size > max_size {
+                        return Err(Error::TooManyFilters { size, max_size });
+                    }
+                }
+            }
+        }
+
+        // Keep track of REQs sending time, for latency calculation
+        #[cfg(not(target_arch = "wasm32"))]
+        self.track_pending_reqs(&msgs);
+
         // Send messages
-        self.atomic.channels.send_client_msgs(msgs)
+        self.atomic
+            .channels
+            .send_client_msgs(msgs, self.max_message_length())
+            .await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn track_pending_reqs(&self, msgs: &[ClientMessage<'_>]) {
+        let mut pending = self
+            .atomic
+            .pending_reqs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now: Instant = Instant::now();
+
+        for msg in msgs.iter() {
+            match msg {
+                ClientMessage::Req {
+                    subscription_id, ..
+                }
+                | ClientMessage::ReqMultiFilter {
+                    subscription_id, ..
+                } => {
+                    pending.insert(subscription_id.clone().into_owned(), now);
+                }
+                ClientMessage::Close(subscription_id) => {
+                    pending.remove(subscription_id.as_ref());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn take_pending_req(&self, id: &SubscriptionId) -> Option<Instant> {
+        let mut pending = self
+            .atomic
+            .pending_reqs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        pending.remove(id)
     }
 
-    fn send_neg_msg(&self, id: &SubscriptionId, message: &str) -> Result<(), Error> {
+    async fn send_neg_msg(&self, id: &SubscriptionId, message: &str) -> Result<(), Error> {
         self.send_msg(ClientMessage::NegMsg {
             subscription_id: Cow::Borrowed(id),
             message: Cow::Borrowed(message),
         })
+        .await
     }
 
-    fn send_neg_close(&self, id: &SubscriptionId) -> Result<(), Error> {
+    async fn send_neg_close(&self, id: &SubscriptionId) -> Result<(), Error> {
         self.send_msg(ClientMessage::NegClose {
             subscription_id: Cow::Borrowed(id),
         })
+        .await
     }
 
     async fn auth(&self, challenge: String) -> Result<(), Error> {
@@ -1254,7 +2026,8 @@ impl InnerRelay {
         let mut notifications = self.internal_notification_sender.subscribe();
 
         // Send the AUTH message
-        self.send_msg(ClientMessage::Auth(Cow::Borrowed(&event)))?;
+        self.send_msg(ClientMessage::Auth(Cow::Borrowed(&event)))
+            .await?;
 
         // Wait for OK
         // The event ID is already checked in `wait_for_ok` method
@@ -1311,12 +2084,22 @@ impl InnerRelay {
     pub async fn resubscribe(&self) -> Result<(), Error> {
         // TODO: avoid subscriptions clone
         let subscriptions = self.subscriptions().await;
-        for (id, filter) in subscriptions.into_iter() {
+        for (id, mut filter) in subscriptions.into_iter() {
             if !filter.is_empty() && self.should_resubscribe(&id).await {
+                // Skip the events already received
+                if self.opts.resubscribe_since_last_seen {
+                    if let Some(last_seen) = self.subscription_last_seen(&id).await {
+                        if filter.since < Some(last_seen) {
+                            filter.since = Some(last_seen);
+                        }
+                    }
+                }
+
                 self.send_msg(ClientMessage::Req {
                     subscription_id: Cow::Owned(id),
                     filter: Cow::Owned(filter),
-                })?;
+                })
+                .await?;
             } else {
                 tracing::debug!("Skip re-subscription of '{id}'");
             }
@@ -1332,6 +2115,7 @@ impl InnerRelay {
         opts: SubscribeAutoCloseOptions,
         notifications: broadcast::Receiver<RelayNotification>,
         activity: Option<Sender<SubscriptionActivity>>,
+        in_flight: InFlight,
     ) {
         let relay = self.clone(); // <-- FULL RELAY CLONE HERE
         task::spawn(async move {
@@ -1353,18 +2137,28 @@ impl InnerRelay {
                 }
                 // Timeout
                 None => {
-                    tracing::warn!(id = %id, "Timeout reached for subscription, auto-closing.");
+                    if let ReqExitPolicy::WaitForTimeout = opts.exit_policy {
+                        tracing::debug!(id = %id, "Subscription completed, auto-closing.");
+                    } else {
+                        tracing::warn!(id = %id, "Timeout reached for subscription, auto-closing.");
+                    }
                     true
                 }
             };
 
+            // The relay is no longer busy: release it before notifying the end of the subscription,
+            // so it can be disconnected by the connection limiter as soon as the caller returns.
+            drop(in_flight);
+
             // Drop activity sender to terminate the receiver activity loop
             drop(activity);
 
+            relay.remove_auto_closing_filter(&id).await;
+
             // Close subscription
             if to_close {
                 tracing::debug!(id = %id, "Auto-closing subscription.");
-                relay.send_msg(ClientMessage::Close(Cow::Owned(id)))?;
+                relay.send_msg(ClientMessage::Close(Cow::Owned(id))).await?;
             }
 
             Ok::<(), Error>(())
@@ -1515,7 +2309,7 @@ impl InnerRelay {
                                 subscription_id: Cow::Borrowed(id),
                                 filter: Cow::Borrowed(filter),
                             };
-                            let _ = self.send_msg(msg);
+                            let _ = self.send_msg(msg).await;
                         }
                     }
                     RelayNotification::AuthenticationFailed => {
@@ -1590,21 +2384,21 @@ impl InnerRelay {
         .await?
     }
 
-    fn _unsubscribe(
+    async fn _unsubscribe(
         &self,
-        subscriptions: &mut RwLockWriteGuard<HashMap<SubscriptionId, SubscriptionData>>,
+        subscriptions: &mut RwLockWriteGuard<'_, HashMap<SubscriptionId, SubscriptionData>>,
         id: &SubscriptionId,
     ) -> Result<(), Error> {
         // Remove the subscription from the map
         subscriptions.remove(id);
 
         // Send CLOSE message
-        self.send_msg(ClientMessage::Close(Cow::Borrowed(id)))
+        self.send_msg(ClientMessage::Close(Cow::Borrowed(id))).await
     }
 
     pub async fn unsubscribe(&self, id: &SubscriptionId) -> Result<(), Error> {
         let mut subscriptions = self.atomic.subscriptions.write().await;
-        self._unsubscribe(&mut subscriptions, id)
+        self._unsubscribe(&mut subscriptions, id).await
     }
 
     pub async fn unsubscribe_all(&self) -> Result<(), Error> {
@@ -1615,14 +2409,14 @@ impl InnerRelay {
 
         // Unsubscribe
         for id in ids.into_iter() {
-            self._unsubscribe(&mut subscriptions, &id)?;
+            self._unsubscribe(&mut subscriptions, &id).await?;
         }
 
         Ok(())
     }
 
     #[inline(never)]
-    fn handle_neg_msg<I>(
+    async fn handle_neg_msg<I>(
         &self,
         subscription_id: &SubscriptionId,
         msg: Option<Vec<u8>>,
@@ -1638,39 +2432,52 @@ impl InnerRelay {
         I: Iterator<Item = EventId>,
     {
         let mut counter: u64 = 0;
+        let mut differences: u64 = 0;
 
         // If event ID wasn't already seen, add to the HAVE IDs
         // Add to HAVE IDs only if `do_up` is true
         for id in curr_have_ids.into_iter() {
-            if output.local.insert(id) && opts.do_up() {
-                have_ids.push(id);
-                counter += 1;
+            if output.local.insert(id) {
+                differences += 1;
+
+                if opts.do_up() {
+                    have_ids.push(id);
+                    counter += 1;
+                }
             }
         }
 
         // If event ID wasn't already seen, add to the NEED IDs
         // Add to NEED IDs only if `do_down` is true
         for id in curr_need_ids.into_iter() {
-            if output.remote.insert(id) && opts.do_down() {
-                need_ids.push(id);
-                counter += 1;
+            if output.remote.insert(id) {
+                differences += 1;
+
+                if opts.do_down() {
+                    need_ids.push(id);
+                    counter += 1;
+                }
             }
         }
 
+        let query: Option<String> = msg.map(hex::encode);
+
         if let Some(progress) = &opts.progress {
             progress.send_modify(|state| {
                 state.total += counter;
+                state.differences += differences;
+                state.bytes_sent += query.as_ref().map_or(0, |q| q.len() as u64);
             });
         }
 
-        match msg {
-            Some(query) => self.send_neg_msg(subscription_id, &hex::encode(query)),
+        match query {
+            Some(query) => self.send_neg_msg(subscription_id, &query).await,
             None => {
                 // Mark sync as done
                 *sync_done = true;
 
                 // Send NEG-CLOSE message
-                self.send_neg_close(subscription_id)
+                self.send_neg_close(subscription_id).await
             }
         }
     }
@@ -1694,7 +2501,7 @@ impl InnerRelay {
                 match self.state.database().event_by_id(&id).await {
                     Ok(Some(event)) => {
                         in_flight_up.insert(id);
-                        self.send_msg(ClientMessage::event(event))?;
+                        self.send_msg(ClientMessage::event(event)).await?;
                         num_sent += 1;
                     }
                     Ok(None) => {
@@ -1729,7 +2536,7 @@ impl InnerRelay {
     }
 
     #[inline(never)]
-    fn req_neg_events(
+    async fn req_neg_events(
         &self,
         need_ids: &mut Vec<EventId>,
         in_flight_down: &mut bool,
@@ -1768,7 +2575,8 @@ impl InnerRelay {
         self.send_msg(ClientMessage::Req {
             subscription_id: Cow::Borrowed(down_sub_id),
             filter: Cow::Owned(filter),
-        })?;
+        })
+        .await?;
 
         *in_flight_down = true;
 
@@ -1822,7 +2630,13 @@ impl InnerRelay {
             Negentropy::borrowed(&storage, NEGENTROPY_FRAME_SIZE_LIMIT)?;
 
         // Initiate reconciliation
-        let initial_message: Vec<u8> = negentropy.initiate()?;
+        let initial_message: String = hex::encode(negentropy.initiate()?);
+
+        if let Some(progress) = &opts.progress {
+            progress.send_modify(|state| {
+                state.bytes_sent += initial_message.len() as u64;
+            });
+        }
 
         // Subscribe
         let mut notifications = self.internal_notification_sender.subscribe();
@@ -1834,9 +2648,9 @@ impl InnerRelay {
             subscription_id: Cow::Borrowed(&sub_id),
             filter: Cow::Borrowed(filter),
             id_size: None,
-            initial_message: Cow::Owned(hex::encode(initial_message)),
+            initial_message: Cow::Owned(initial_message),
         };
-        self.send_msg(open_msg)?;
+        self.send_msg(open_msg).await?;
 
         // Check if negentropy is supported
         check_negentropy_support(&sub_id, opts, &mut temp_notifications).await?;
@@ -1858,6 +2672,14 @@ impl InnerRelay {
                             message,
                         } => {
                             if subscription_id.as_ref() == &sub_id {
+                                // Update progress
+                                if let Some(progress) = &opts.progress {
+                                    progress.send_modify(|state| {
+                                        state.rounds += 1;
+                                        state.bytes_received += message.len() as u64;
+                                    });
+                                }
+
                                 let mut curr_have_ids: Vec<Id> = Vec::new();
                                 let mut curr_need_ids: Vec<Id> = Vec::new();
 
@@ -1882,7 +2704,8 @@ impl InnerRelay {
                                     &mut have_ids,
                                     &mut need_ids,
                                     &mut sync_done,
-                                )?;
+                                )
+                                .await?;
                             }
                         }
                         RelayMessage::NegErr {
@@ -1934,7 +2757,8 @@ impl InnerRelay {
                         .await?;
 
                     // Get events
-                    self.req_neg_events(&mut need_ids, &mut in_flight_down, &down_sub_id, opts)?;
+                    self.req_neg_events(&mut need_ids, &mut in_flight_down, &down_sub_id, opts)
+                        .await?;
                 }
                 RelayNotification::RelayStatus { status } => {
                     if status.is_disconnected() {
@@ -1981,6 +2805,57 @@ async fn close_ws(tx: &mut BoxSink) -> Result<(), Error> {
     }
 }
 
+/// Get the human-readable part of a `CLOSED` message, without the machine-readable prefix
+fn closed_reason(message: &str) -> &str {
+    match message.split_once(':') {
+        Some((_, reason)) => reason.trim(),
+        None => message.trim(),
+    }
+}
+
+/// Check if the raw relay message is an `EVENT`, without parsing it
+fn is_event_msg(json: &str) -> bool {
+    json.trim_start()
+        .strip_prefix('[')
+        .is_some_and(|msg| msg.trim_start().starts_with("\"EVENT\""))
+}
+
+/// Check if the event matches the filter, ignoring the `search` field (relay-defined, NIP-50)
+fn filter_match(filter: &Filter, event: &Event) -> bool {
+    if filter.search.is_some() {
+        let mut filter: Filter = filter.clone();
+        filter.search = None;
+        return filter.match_event(event);
+    }
+
+    filter.match_event(event)
+}
+
+/// Check that the event ID meets the difficulty and that the `nonce` tag commits to at least the difficulty
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+fn check_committed_pow(event: &Event, difficulty: u8) -> bool {
+    let committed: Option<u8> = event
+        .tags
+        .iter()
+        .find_map(|tag| match tag.as_standardized() {
+            Some(TagStandard::POW { difficulty, .. }) => Some(*difficulty),
+            _ => None,
+        });
+
+    match committed {
+        Some(committed) => committed >= difficulty && event.check_pow(difficulty),
+        None => false,
+    }
+}
+
+/// Number of IDs and authors of a filter
+fn filter_terms(filter: &Filter) -> usize {
+    let ids: usize = filter.ids.as_ref().map_or(0, |ids| ids.len());
+    let authors: usize = filter.authors.as_ref().map_or(0, |authors| authors.len());
+    ids + authors
+}
+
 #[inline]
 fn neg_id_to_event_id(id: Id) -> EventId {
     EventId::from_byte_array(id.to_bytes())
@@ -2062,6 +2937,97 @@ async fn check_negentropy_support(
     .ok_or(Error::Timeout)?
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "nip11")]
+    fn test_document_limits() {
+        let limits = DocumentLimits::default();
+        assert_eq!(DocumentLimits::load(&limits.max_message_length), None);
+
+        let mut document = RelayInformationDocument::new();
+        document.limitation = Some(Limitation {
+            max_message_length: Some(16384),
+            max_subscriptions: Some(-1),
+            max_filters: Some(10),
+            ..Default::default()
+        });
+        limits.update(&document);
+
+        assert_eq!(
+            DocumentLimits::load(&limits.max_message_length),
+            Some(16384)
+        );
+        assert_eq!(DocumentLimits::load(&limits.max_subscriptions), None);
+        assert_eq!(DocumentLimits::load(&limits.max_filters), Some(10));
+
+        // Limitation removed
+        limits.update(&RelayInformationDocument::new());
+        assert_eq!(DocumentLimits::load(&limits.max_filters), None);
+    }
+
+    #[test]
+    fn test_is_event_msg() {
+        assert!(is_event_msg(r#"["EVENT","sub",{}]"#));
+        assert!(is_event_msg(r#" [ "EVENT", "sub", {}]"#));
+        assert!(!is_event_msg(r#"["EOSE","sub"]"#));
+        assert!(!is_event_msg(r#"["NOTICE","EVENT"]"#));
+        assert!(!is_event_msg("EVENT"));
+    }
+
+    #[tokio::test]
+    async fn test_send_client_msgs_max_message_length() {
+        let channels = RelayChannels::new();
+        let msg = ClientMessage::Close(Cow::Owned(SubscriptionId::new("test")));
+        let size: usize = msg.as_json().len();
+
+        assert!(channels
+            .send_client_msgs(vec![msg.clone()], None)
+            .await
+            .is_ok());
+        assert!(channels
+            .send_client_msgs(vec![msg.clone()], Some(size))
+            .await
+            .is_ok());
+        assert!(matches!(
+            channels.send_client_msgs(vec![msg], Some(size - 1)).await,
+            Err(Error::MessageTooLarge { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_client_msgs_wait_for_capacity() {
+        let channels = Arc::new(RelayChannels::new());
+        let msg = ClientMessage::Close(Cow::Owned(SubscriptionId::new("test")));
+
+        // Fill the channel
+        let capacity: usize = channels.nostr.0.max_capacity();
+        for _ in 0..capacity {
+            channels
+                .send_client_msgs(vec![msg.clone()], None)
+                .await
+                .unwrap();
+        }
+        assert_eq!(channels.nostr_queue(), capacity);
+
+        // Channel full: wait until a message is consumed, instead of failing
+        let c = channels.clone();
+        let handle = tokio::spawn(async move {
+            let msg = ClientMessage::Close(Cow::Owned(SubscriptionId::new("test")));
+            c.send_client_msgs(vec![msg], None).await
+        });
+
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(!handle.is_finished());
+
+        channels.rx_nostr().await.recv().await.unwrap();
+        handle.await.unwrap().unwrap();
+        assert_eq!(channels.nostr_queue(), capacity);
+    }
+}
+
 #[cfg(bench)]
 mod benches {
     use std::sync::LazyLock;
@@ -16,6 +16,7 @@ use atomic_destructor::AtomicDestructor;
 use nostr_database::prelude::*;
 use tokio::sync::{broadcast, mpsc};
 
+pub(crate) mod connections;
 pub mod constants;
 mod error;
 pub mod flags;
@@ -23,6 +24,7 @@ mod inner;
 pub mod limits;
 pub mod options;
 mod ping;
+mod rate_limit;
 pub mod stats;
 mod status;
 
@@ -32,12 +34,14 @@ pub use self::flags::{AtomicRelayServiceFlags, FlagCheck, RelayServiceFlags};
 use self::inner::InnerRelay;
 pub use self::limits::RelayLimits;
 pub use self::options::{
-    RelayOptions, ReqExitPolicy, SubscribeAutoCloseOptions, SubscribeOptions, SyncDirection,
-    SyncOptions, SyncProgress,
+    BackoffPolicy, BackoffStrategy, ConnectPolicy, EoseQuorum, RateLimitAction, RelayOptions,
+    ReqExitPolicy, SubscribeAutoCloseOptions, SubscribeOptions, SyncDirection, SyncOptions,
+    SyncProgress,
 };
-pub use self::stats::RelayConnectionStats;
+pub use self::stats::{RelayConnectionStats, RelayStats};
 pub use self::status::RelayStatus;
 use crate::policy::AdmitStatus;
+use crate::pool::RelayPoolNotification;
 use crate::shared::SharedState;
 use crate::transport::websocket::{BoxSink, BoxStream};
 
@@ -86,6 +90,27 @@ pub enum RelayNotification {
     Authenticated,
     /// Authentication failed
     AuthenticationFailed,
+    /// Received a message that can't be parsed
+    RelayMessageError {
+        /// Raw message
+        raw: String,
+        /// Parsing error
+        error: String,
+    },
+    /// The incoming rate limit has been exceeded and the relay has been disconnected
+    RateLimitExceeded {
+        /// Max events per second
+        max_per_second: u32,
+    },
+    /// A subscription has been closed by the relay because authentication is required
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    AuthRequired {
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+        /// Reason, without the `auth-required:` prefix
+        message: String,
+    },
     /// Shutdown
     Shutdown,
 }
@@ -105,6 +130,10 @@ pub struct Reconciliation {
     pub local: HashSet<EventId>,
     /// Events that were stored on relay (missing locally)
     pub remote: HashSet<EventId>,
+    /// Events that were stored both locally and on relay
+    ///
+    /// When merging the output of many relays, contains the events stored locally and on at least one relay.
+    pub common: HashSet<EventId>,
     /// Events that are **successfully** sent to relays during reconciliation
     pub sent: HashSet<EventId>,
     /// Event that are **successfully** received from relay during reconciliation
@@ -117,6 +146,7 @@ impl Reconciliation {
     pub(crate) fn merge(&mut self, other: Reconciliation) {
         self.local.extend(other.local);
         self.remote.extend(other.remote);
+        self.common.extend(other.common);
         self.sent.extend(other.sent);
         self.received.extend(other.received);
         self.send_failures.extend(other.send_failures);
@@ -187,9 +217,15 @@ impl Relay {
     }
 
     /// Get [`RelayInformationDocument`]
+    ///
+    /// The document is fetched on connection (and then cached for 1 hour).
+    /// Returns `None` if not fetched yet.
+    ///
+    /// The `limitation` of the document are enforced when sending messages
+    /// (i.e., `max_message_length`, `max_subscriptions` and `max_filters`).
     #[inline]
     #[cfg(feature = "nip11")]
-    pub async fn document(&self) -> RelayInformationDocument {
+    pub async fn document(&self) -> Option<RelayInformationDocument> {
         let document = self.inner.atomic.document.read().await;
         document.clone()
     }
@@ -212,12 +248,40 @@ impl Relay {
         &self.inner.opts
     }
 
+    /// Get tags
+    ///
+    /// Check [`RelayOptions::tag`] to learn more.
+    #[inline]
+    pub fn tags(&self) -> &HashSet<String> {
+        &self.inner.opts.tags
+    }
+
+    /// Check if relay has a tag
+    #[inline]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.inner.opts.tags.contains(tag)
+    }
+
+    /// Get priority
+    ///
+    /// Check [`RelayOptions::priority`] to learn more.
+    #[inline]
+    pub fn priority(&self) -> u8 {
+        self.inner.opts.priority
+    }
+
     /// Get [`RelayConnectionStats`]
     #[inline]
     pub fn stats(&self) -> &RelayConnectionStats {
         &self.inner.stats
     }
 
+    /// Get a snapshot of the relay stats, including the current connection status
+    #[inline]
+    pub fn health(&self) -> RelayStats {
+        self.inner.stats.snapshot(self.status())
+    }
+
     /// Get queue len
     #[inline]
     pub fn queue(&self) -> usize {
@@ -232,6 +296,12 @@ impl Relay {
         self.inner.internal_notification_sender.subscribe()
     }
 
+    /// Get new **pool** notification listener, scoped to this relay
+    #[inline]
+    pub(crate) fn scoped_notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
+        self.inner.scoped_notification_sender.subscribe()
+    }
+
     /// Connect to the relay
     ///
     /// # Overview
@@ -364,15 +434,19 @@ impl Relay {
     }
 
     /// Send msg to relay
+    ///
+    /// Waits for room in the outgoing queue if it's full.
     #[inline]
-    pub fn send_msg(&self, msg: ClientMessage<'_>) -> Result<(), Error> {
-        self.inner.send_msg(msg)
+    pub async fn send_msg(&self, msg: ClientMessage<'_>) -> Result<(), Error> {
+        self.inner.send_msg(msg).await
     }
 
     /// Send multiple [`ClientMessage`] at once
+    ///
+    /// Waits for room in the outgoing queue if it's full.
     #[inline]
-    pub fn batch_msg(&self, msgs: Vec<ClientMessage<'_>>) -> Result<(), Error> {
-        self.inner.batch_msg(msgs)
+    pub async fn batch_msg(&self, msgs: Vec<ClientMessage<'_>>) -> Result<(), Error> {
+        self.inner.batch_msg(msgs).await
     }
 
     async fn _send_event(
@@ -380,9 +454,13 @@ impl Relay {
         notifications: &mut broadcast::Receiver<RelayNotification>,
         event: &Event,
     ) -> Result<(bool, String), Error> {
+        // Apply backpressure if the rate limit is reached
+        self.inner.wait_for_rate_limit().await;
+
         // Send the EVENT message
         self.inner
-            .send_msg(ClientMessage::Event(Cow::Borrowed(event)))?;
+            .send_msg(ClientMessage::Event(Cow::Borrowed(event)))
+            .await?;
 
         // Wait for OK
         self.inner
@@ -394,6 +472,9 @@ impl Relay {
     pub async fn send_event(&self, event: &Event) -> Result<EventId, Error> {
         // Health, write permission and number of messages checks are executed in `batch_msg` method.
 
+        // Keep the relay busy while waiting for the OK
+        let _in_flight = self.inner.in_flight();
+
         // Subscribe to notifications
         let mut notifications = self.inner.internal_notification_sender.subscribe();
 
@@ -499,18 +580,21 @@ impl Relay {
     ) -> Result<(), Error> {
         // Check if auto-close condition is set
         match opts.auto_close {
-            Some(opts) => self.subscribe_auto_closing(id, filter, opts, None),
+            Some(opts) => self.subscribe_auto_closing(id, filter, opts, None).await,
             None => self.subscribe_long_lived(id, filter).await,
         }
     }
 
-    fn subscribe_auto_closing(
+    async fn subscribe_auto_closing(
         &self,
         id: SubscriptionId,
         filter: Filter,
         opts: SubscribeAutoCloseOptions,
         activity: Option<mpsc::Sender<SubscriptionActivity>>,
     ) -> Result<(), Error> {
+        // Keep the relay busy until the subscription is closed
+        let in_flight = self.inner.in_flight();
+
         // Compose REQ message
         let msg: ClientMessage = ClientMessage::Req {
             subscription_id: Cow::Borrowed(&id),
@@ -520,18 +604,29 @@ impl Relay {
         // Subscribe to notifications
         let notifications = self.inner.internal_notification_sender.subscribe();
 
+        // Keep track of the filter before sending the REQ, to verify the received events
+        self.inner
+            .add_auto_closing_filter(id.clone(), filter.clone())
+            .await;
+
         // Send REQ message
-        self.inner.send_msg(msg)?;
+        if let Err(e) = self.inner.send_msg(msg).await {
+            self.inner.remove_auto_closing_filter(&id).await;
+            return Err(e);
+        }
 
         // Spawn auto-closing handler
         self.inner
-            .spawn_auto_closing_handler(id, filter, opts, notifications, activity);
+            .spawn_auto_closing_handler(id, filter, opts, notifications, activity, in_flight);
 
         // Return
         Ok(())
     }
 
     async fn subscribe_long_lived(&self, id: SubscriptionId, filter: Filter) -> Result<(), Error> {
+        // Check relay limits
+        self.inner.check_max_subscriptions(&id).await?;
+
         // Compose REQ message
         let msg: ClientMessage = ClientMessage::Req {
             subscription_id: Cow::Borrowed(&id),
@@ -539,7 +634,7 @@ impl Relay {
         };
 
         // Send REQ message
-        self.inner.send_msg(msg)?;
+        self.inner.send_msg(msg).await?;
 
         // No auto-close subscription: update subscription filter
         self.inner.update_subscription(id, filter, true).await;
@@ -581,7 +676,8 @@ impl Relay {
 
         // Subscribe
         let id: SubscriptionId = SubscriptionId::generate();
-        self.subscribe_auto_closing(id, filter, opts, Some(tx))?;
+        self.subscribe_auto_closing(id, filter, opts, Some(tx))
+            .await?;
 
         // Handle subscription activity
         while let Some(activity) = rx.recv().await {
@@ -649,12 +745,15 @@ impl Relay {
 
     /// Count events
     pub async fn count_events(&self, filter: Filter, timeout: Duration) -> Result<usize, Error> {
+        // Keep the relay busy while waiting for the COUNT
+        let _in_flight = self.inner.in_flight();
+
         let id = SubscriptionId::generate();
         let msg = ClientMessage::Count {
             subscription_id: Cow::Borrowed(&id),
             filter: Cow::Owned(filter),
         };
-        self.inner.send_msg(msg)?;
+        self.inner.send_msg(msg).await?;
 
         let mut count = 0;
 
@@ -680,7 +779,7 @@ impl Relay {
         .ok_or(Error::Timeout)?;
 
         // Unsubscribe
-        self.inner.send_msg(ClientMessage::close(id))?;
+        self.inner.send_msg(ClientMessage::close(id)).await?;
 
         Ok(count)
     }
@@ -703,6 +802,9 @@ impl Relay {
         items: Vec<(EventId, Timestamp)>,
         opts: &SyncOptions,
     ) -> Result<Reconciliation, Error> {
+        // Keep the relay busy during the reconciliation
+        let _in_flight = self.inner.in_flight();
+
         // Perform health checks
         self.inner.health_check()?;
 
@@ -713,9 +815,15 @@ impl Relay {
 
         let mut output: Reconciliation = Reconciliation::default();
 
-        self.inner
-            .sync(&filter, items.clone(), opts, &mut output)
-            .await?;
+        let ids: Vec<EventId> = items.iter().map(|(id, ..)| *id).collect();
+
+        self.inner.sync(&filter, items, opts, &mut output).await?;
+
+        // Local events not missing on relay
+        output.common = ids
+            .into_iter()
+            .filter(|id| !output.local.contains(id))
+            .collect();
 
         Ok(output)
     }
@@ -835,6 +943,29 @@ mod tests {
         relay.send_event(&event).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_rate_limit() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay: Relay = new_relay(url, RelayOptions::default().rate_limit(5));
+
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let keys = Keys::generate();
+
+        // The first 5 events are sent as a burst, the next ones must wait
+        let now = Instant::now();
+        for i in 0..8 {
+            let event = EventBuilder::text_note(i.to_string())
+                .sign_with_keys(&keys)
+                .unwrap();
+            relay.send_event(&event).await.unwrap();
+        }
+        assert!(now.elapsed() >= Duration::from_millis(500));
+    }
+
     #[tokio::test]
     async fn test_status_with_reconnection_enabled() {
         // Mock relay
@@ -952,6 +1083,32 @@ mod tests {
         assert!(relay.inner.is_running());
     }
 
+    #[tokio::test]
+    async fn test_relay_message_error() {
+        let url = RelayUrl::parse("wss://127.0.0.1:666").unwrap();
+        let relay: Relay = new_relay(url, RelayOptions::default());
+
+        let mut notifications = relay.notifications();
+        let (ingester_tx, ..) = mpsc::unbounded_channel();
+
+        // Unknown message type: ignored
+        relay
+            .inner
+            .handle_relay_message(r#"["FOO","bar"]"#, &ingester_tx)
+            .await;
+        assert!(notifications.try_recv().is_err());
+
+        // Invalid JSON: reported
+        let raw: &str = r#"["NOTICE","#;
+        relay.inner.handle_relay_message(raw, &ingester_tx).await;
+        match notifications.try_recv().unwrap() {
+            RelayNotification::RelayMessageError { raw: received, .. } => {
+                assert_eq!(received, raw)
+            }
+            n => panic!("Unexpected notification: {n:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn test_connect_to_unreachable_relay() {
         let url = RelayUrl::parse("wss://127.0.0.1:666").unwrap();
@@ -1064,6 +1221,31 @@ mod tests {
         assert!(!relay.inner.is_running());
     }
 
+    #[tokio::test]
+    async fn test_connection_timeout() {
+        // Mock relay
+        let opts = RelayTestOptions {
+            unresponsive_connection: Some(Duration::from_secs(10)),
+        };
+        let mock = MockRelay::run_with_opts(opts).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let opts = RelayOptions::default().connection_timeout(Some(Duration::from_secs(1)));
+        let relay: Relay = new_relay(url, opts);
+
+        relay.connect();
+
+        time::sleep(Duration::from_secs(2)).await;
+
+        // The attempt timed out and a new one is scheduled
+        assert_eq!(relay.status(), RelayStatus::Disconnected);
+        assert_eq!(relay.stats().attempts(), 1);
+        assert_eq!(relay.stats().success(), 0);
+        assert!(relay.inner.is_running());
+
+        relay.disconnect();
+    }
+
     #[tokio::test]
     async fn test_disconnect_unresponsive_during_try_connect() {
         // Mock relay
@@ -1401,6 +1583,35 @@ mod tests {
         assert_eq!(events.len(), 6); // 5 events received until EOSE + 1 new events
     }
 
+    #[tokio::test]
+    async fn test_fetch_events_wait_for_timeout() {
+        let (relay, _mock) = setup_event_fetching_relay(3).await;
+
+        // Task to send an additional event after EOSE
+        let r = relay.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            let keys = Keys::generate();
+            let event = EventBuilder::text_note("Additional")
+                .sign_with_keys(&keys)
+                .unwrap();
+            r.send_event(&event).await.unwrap();
+        });
+
+        let now = Instant::now();
+        let events = relay
+            .fetch_events(
+                Filter::new().kind(Kind::TextNote),
+                Duration::from_secs(3),
+                ReqExitPolicy::WaitForTimeout,
+            )
+            .await
+            .unwrap();
+        assert!(now.elapsed() >= Duration::from_secs(3));
+        assert_eq!(events.len(), 4); // 3 events received until EOSE + 1 new event
+    }
+
     #[tokio::test]
     async fn test_subscribe_ephemeral_event() {
         // Mock relay
@@ -1493,6 +1704,347 @@ mod tests {
         relay.subscriptions().await.is_empty();
     }
 
+    #[tokio::test]
+    async fn test_resubscribe_on_reconnect() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        for enable in [true, false] {
+            let relay: Relay = new_relay(
+                url.clone(),
+                RelayOptions::default().resubscribe_on_reconnect(enable),
+            );
+
+            // Never subscribed: must always subscribe
+            let id = SubscriptionId::new("inherited");
+            relay
+                .inner
+                .update_subscription(id.clone(), Filter::new().kind(Kind::TextNote), false)
+                .await;
+            assert!(relay.inner.should_resubscribe(&id).await);
+
+            relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+            let id = relay
+                .subscribe(
+                    Filter::new().kind(Kind::TextNote),
+                    SubscribeOptions::default(),
+                )
+                .await
+                .unwrap();
+
+            // Simulate a reconnection
+            time::sleep(Duration::from_millis(1100)).await;
+            relay.inner.stats.new_success();
+
+            assert_eq!(relay.inner.should_resubscribe(&id).await, enable);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe_closed_with_resubscribe_on_reconnect_disabled() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay: Relay = new_relay(url, RelayOptions::default().resubscribe_on_reconnect(false));
+
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let id = relay
+            .subscribe(
+                Filter::new().kind(Kind::TextNote),
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!relay.inner.should_resubscribe(&id).await);
+
+        // Closed by the relay, waiting for the authentication: must be re-sent
+        let (ingester_tx, ..) = mpsc::unbounded_channel();
+        let msg = RelayMessage::closed(id.clone(), "auth-required: we only serve known users");
+        relay
+            .inner
+            .handle_relay_message(&msg.as_json(), &ingester_tx)
+            .await;
+        assert!(relay.inner.should_resubscribe(&id).await);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_too_many_filter_terms() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let opts = RelayOptions::default().max_filter_terms(Some(2));
+        let relay: Relay = new_relay(url, opts);
+
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let authors = (0..3).map(|_| Keys::generate().public_key());
+        let filter = Filter::new().authors(authors);
+        let err = relay
+            .subscribe(filter, SubscribeOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TooManyFilterTerms {
+                size: 3,
+                max_size: 2
+            }
+        ));
+
+        let filter = Filter::new().author(Keys::generate().public_key());
+        assert!(relay
+            .subscribe(filter, SubscribeOptions::default())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_default_filter_limit() {
+        let (relay, mock) = setup_event_fetching_relay(5).await;
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+        relay.disconnect();
+
+        let opts = RelayOptions::default().default_filter_limit(Some(2));
+        let relay: Relay = new_relay(url, opts);
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        // No limit: the default one is applied
+        let events = relay
+            .fetch_events(
+                Filter::new().kind(Kind::TextNote),
+                Duration::from_secs(5),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+
+        // Explicit limit: untouched
+        let events = relay
+            .fetch_events(
+                Filter::new().kind(Kind::TextNote).limit(4),
+                Duration::from_secs(5),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_incoming_rate_limit_throttle() {
+        let (relay, mock) = setup_event_fetching_relay(20).await;
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+        relay.disconnect();
+
+        let opts = RelayOptions::default().incoming_rate_limit(10, RateLimitAction::Throttle);
+        let relay: Relay = new_relay(url, opts);
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        // Burst of 10 events, then 10 events/sec
+        let now = Instant::now();
+        let events = relay
+            .fetch_events(
+                Filter::new().kind(Kind::TextNote),
+                Duration::from_secs(5),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 20);
+        assert!(now.elapsed() >= Duration::from_millis(800));
+        assert_eq!(relay.status(), RelayStatus::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_incoming_rate_limit_disconnect() {
+        let (relay, mock) = setup_event_fetching_relay(20).await;
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+        relay.disconnect();
+
+        let opts = RelayOptions::default().incoming_rate_limit(10, RateLimitAction::Disconnect);
+        let relay: Relay = new_relay(url, opts);
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let mut notifications = relay.notifications();
+
+        let _ = relay
+            .fetch_events(
+                Filter::new().kind(Kind::TextNote),
+                Duration::from_secs(2),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await;
+
+        assert_eq!(relay.status(), RelayStatus::Terminated);
+
+        let mut exceeded = false;
+        while let Ok(notification) = notifications.try_recv() {
+            if let RelayNotification::RateLimitExceeded { max_per_second } = notification {
+                assert_eq!(max_per_second, 10);
+                exceeded = true;
+            }
+        }
+        assert!(exceeded);
+    }
+
+    #[tokio::test]
+    async fn test_verify_subscriptions() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay: Relay = new_relay(
+            url.clone(),
+            RelayOptions::default().verify_subscriptions(true),
+        );
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let expected = Keys::generate();
+        let other = Keys::generate();
+
+        let id = relay
+            .subscribe(
+                Filter::new().kind(Kind::TextNote),
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        // Narrow the local copy of the filter, to simulate a relay returning unrelated events
+        relay
+            .inner
+            .update_subscription(
+                id.clone(),
+                Filter::new()
+                    .kind(Kind::TextNote)
+                    .author(expected.public_key()),
+                false,
+            )
+            .await;
+
+        let mut notifications = relay.notifications();
+
+        // Publish events
+        let publisher: Relay = new_relay(url, RelayOptions::default());
+        publisher.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let unrelated = EventBuilder::text_note("Unrelated")
+            .sign_with_keys(&other)
+            .unwrap();
+        let matching = EventBuilder::text_note("Matching")
+            .sign_with_keys(&expected)
+            .unwrap();
+        publisher.send_event(&unrelated).await.unwrap();
+        publisher.send_event(&matching).await.unwrap();
+
+        let mut received: Vec<Event> = Vec::new();
+        let _ = time::timeout(Some(Duration::from_secs(2)), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayNotification::Event {
+                    subscription_id,
+                    event,
+                } = notification
+                {
+                    if subscription_id == id {
+                        received.push(*event);
+                    }
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(received, vec![matching]);
+    }
+
+    #[tokio::test]
+    async fn test_min_pow() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // Publish events with and without POW
+        let publisher: Relay = new_relay(url.clone(), RelayOptions::default());
+        publisher.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let keys = Keys::generate();
+        let with_pow = EventBuilder::text_note("POW")
+            .pow(8)
+            .sign_with_keys(&keys)
+            .unwrap();
+        let without_pow = EventBuilder::text_note("No POW")
+            .sign_with_keys(&keys)
+            .unwrap();
+        publisher.send_event(&with_pow).await.unwrap();
+        publisher.send_event(&without_pow).await.unwrap();
+
+        // Fetch with min POW
+        let relay: Relay = new_relay(url, RelayOptions::default());
+        relay.inner.state.set_pow(8);
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        let events = relay
+            .fetch_events(
+                Filter::new().author(keys.public_key()),
+                Duration::from_secs(3),
+                ReqExitPolicy::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert!(events.contains(&with_pow));
+        assert_eq!(relay.stats().pow_rejected_events(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_common_events() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let keys = Keys::generate();
+        let remote_only = EventBuilder::text_note("Remote")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let common = EventBuilder::text_note("Common")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let local_only = EventBuilder::text_note("Local")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        // Publish events to relay
+        let publisher: Relay = new_relay(url.clone(), RelayOptions::default());
+        publisher.try_connect(Duration::from_secs(3)).await.unwrap();
+        publisher.send_event(&remote_only).await.unwrap();
+        publisher.send_event(&common).await.unwrap();
+
+        let relay: Relay = new_relay(url, RelayOptions::default());
+        relay.try_connect(Duration::from_secs(3)).await.unwrap();
+
+        // Local events
+        let items = vec![
+            (common.id, common.created_at),
+            (local_only.id, local_only.created_at),
+        ];
+
+        let opts = SyncOptions::default().dry_run();
+        let output = relay
+            .sync_with_items(Filter::new().author(keys.public_key()), items, &opts)
+            .await
+            .unwrap();
+
+        assert_eq!(output.local, HashSet::from([local_only.id]));
+        assert_eq!(output.remote, HashSet::from([remote_only.id]));
+        assert_eq!(output.common, HashSet::from([common.id]));
+    }
+
     #[tokio::test]
     async fn test_admit_connection() {
         // Mock relay
@@ -4,12 +4,20 @@
 
 //! Relay options
 
+use std::collections::HashSet;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use async_wsocket::ConnectionMode;
+use nostr::secp256k1::rand::{self, Rng};
+use nostr::{Filter, Timestamp};
 use tokio::sync::watch::{self, Receiver, Sender};
 
-use super::constants::{DEFAULT_NOTIFICATION_CHANNEL_SIZE, DEFAULT_RETRY_INTERVAL};
+use super::constants::{
+    DEFAULT_NOTIFICATION_CHANNEL_SIZE, DEFAULT_RETRY_INTERVAL, MAX_RETRY_INTERVAL,
+    MIN_PING_INTERVAL, PING_INTERVAL,
+};
 use super::flags::RelayServiceFlags;
 use crate::RelayLimits;
 
@@ -17,26 +25,56 @@ use crate::RelayLimits;
 #[derive(Debug, Clone)]
 pub struct RelayOptions {
     pub(super) connection_mode: ConnectionMode,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) http_proxy: Option<SocketAddr>,
+    pub(super) connect_policy: ConnectPolicy,
     pub(super) flags: RelayServiceFlags,
+    pub(super) ping_interval: Duration,
+    pub(super) connection_timeout: Option<Duration>,
     pub(super) reconnect: bool,
+    pub(super) resubscribe_on_reconnect: bool,
+    pub(super) resubscribe_since_last_seen: bool,
+    pub(super) verify_subscriptions: bool,
     pub(super) retry_interval: Duration,
     pub(super) adjust_retry_interval: bool,
+    pub(super) backoff: Option<BackoffPolicy>,
     pub(super) limits: RelayLimits,
     pub(super) max_avg_latency: Option<Duration>,
+    pub(super) max_filter_terms: Option<usize>,
+    pub(super) default_filter_limit: Option<usize>,
+    pub(super) rate_limit: Option<u32>,
+    pub(super) incoming_rate_limit: Option<(u32, RateLimitAction)>,
     pub(super) notification_channel_size: usize,
+    pub(super) tags: HashSet<String>,
+    pub(super) priority: u8,
 }
 
 impl Default for RelayOptions {
     fn default() -> Self {
         Self {
             connection_mode: ConnectionMode::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            http_proxy: None,
+            connect_policy: ConnectPolicy::default(),
             flags: RelayServiceFlags::default(),
+            ping_interval: PING_INTERVAL,
+            connection_timeout: None,
             reconnect: true,
+            resubscribe_on_reconnect: true,
+            resubscribe_since_last_seen: false,
+            verify_subscriptions: false,
             retry_interval: DEFAULT_RETRY_INTERVAL,
             adjust_retry_interval: true,
+            backoff: None,
             limits: RelayLimits::default(),
             max_avg_latency: None,
+            max_filter_terms: None,
+            default_filter_limit: None,
+            rate_limit: None,
+            incoming_rate_limit: None,
             notification_channel_size: DEFAULT_NOTIFICATION_CHANNEL_SIZE,
+            tags: HashSet::new(),
+            priority: 0,
         }
     }
 }
@@ -55,6 +93,25 @@ impl RelayOptions {
         self
     }
 
+    /// Route the HTTP requests (i.e., NIP-11 document) through a SOCKS5 proxy (default: None)
+    ///
+    /// If not set, the proxy of the [`ConnectionMode`] is used, if any.
+    #[inline]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http_proxy(mut self, proxy: Option<SocketAddr>) -> Self {
+        self.http_proxy = proxy;
+        self
+    }
+
+    /// Set connect policy (default: [`ConnectPolicy::Eager`])
+    ///
+    /// Check [`ConnectPolicy`] to learn more.
+    #[inline]
+    pub fn connect_policy(mut self, policy: ConnectPolicy) -> Self {
+        self.connect_policy = policy;
+        self
+    }
+
     /// Set Relay Service Flags
     pub fn flags(mut self, flags: RelayServiceFlags) -> Self {
         self.flags = flags;
@@ -62,6 +119,8 @@ impl RelayOptions {
     }
 
     /// Set read flag
+    ///
+    /// Check [`RelayServiceFlags::READ`] to learn more.
     pub fn read(mut self, read: bool) -> Self {
         if read {
             self.flags.add(RelayServiceFlags::READ);
@@ -72,6 +131,9 @@ impl RelayOptions {
     }
 
     /// Set write flag
+    ///
+    /// Relays without the write flag will not receive the events sent through the pool.
+    /// Check [`RelayServiceFlags::WRITE`] to learn more.
     pub fn write(mut self, write: bool) -> Self {
         if write {
             self.flags.add(RelayServiceFlags::WRITE);
@@ -91,6 +153,61 @@ impl RelayOptions {
         self
     }
 
+    /// Set the ping interval, to detect the dead connections (default: 55 secs)
+    ///
+    /// If the relay doesn't reply to a ping before the next one,
+    /// the connection is considered dead: the relay is disconnected and, if enabled, reconnected.
+    /// Useful to detect the half-open connections (i.e. behind a NAT) that the TCP keepalive misses.
+    ///
+    /// `None` disables the pings (same as `ping(false)`). The min interval is 1 sec.
+    pub fn ping_interval(mut self, interval: Option<Duration>) -> Self {
+        match interval {
+            Some(interval) => {
+                self.ping_interval = interval.max(MIN_PING_INTERVAL);
+                self.flags.add(RelayServiceFlags::PING);
+            }
+            None => self.flags.remove(RelayServiceFlags::PING),
+        }
+        self
+    }
+
+    /// Add a tag (i.e. `dms`, `feed`)
+    ///
+    /// Tags allow to group relays and target only a subset of them.
+    /// Check [`RelayPool::relays_with_tag`](crate::RelayPool::relays_with_tag) to learn more.
+    pub fn tag<S>(mut self, tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    /// Set relay priority (default: 0)
+    ///
+    /// Higher priority relays are preferred when only a subset of relays is targeted
+    /// (i.e., [`RelayPool::send_event_to_n`](crate::RelayPool::send_event_to_n) and
+    /// [`RelayPool::subscribe_to_n`](crate::RelayPool::subscribe_to_n)).
+    /// Connected relays are always preferred over the disconnected ones.
+    #[inline]
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set timeout for the connection attempts of the connection task (default: 60 secs)
+    ///
+    /// Bounds the websocket handshake (including TLS).
+    /// When the timeout is reached, the attempt is considered failed
+    /// and a new one is scheduled according to the reconnection options.
+    ///
+    /// Pass `None` to use the default timeout.
+    #[inline]
+    pub fn connection_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connection_timeout = timeout;
+        self
+    }
+
     /// Enable/disable auto reconnection (default: true)
     pub fn reconnect(mut self, reconnect: bool) -> Self {
         self.reconnect = reconnect;
@@ -109,6 +226,44 @@ impl RelayOptions {
         self
     }
 
+    /// Re-send the subscriptions after a reconnection (default: true)
+    ///
+    /// The subscriptions never sent to the relay (i.e., inherited from the pool) are always sent on connection.
+    #[inline]
+    pub fn resubscribe_on_reconnect(mut self, enable: bool) -> Self {
+        self.resubscribe_on_reconnect = enable;
+        self
+    }
+
+    /// Adjust the `since` of the re-sent subscriptions to the timestamp of the last received event (default: false)
+    ///
+    /// Avoids re-downloading the events already received before the disconnection.
+    #[inline]
+    pub fn resubscribe_since_last_seen(mut self, enable: bool) -> Self {
+        self.resubscribe_since_last_seen = enable;
+        self
+    }
+
+    /// Verify that the received events match the subscription filter (default: false)
+    ///
+    /// The events that don't match the filter are discarded.
+    /// The `search` field isn't verified, since its matching is relay-defined (NIP-50).
+    #[inline]
+    pub fn verify_subscriptions(mut self, enable: bool) -> Self {
+        self.verify_subscriptions = enable;
+        self
+    }
+
+    /// Set reconnection backoff policy (default: None)
+    ///
+    /// When set, the reconnection delay is computed by the [`BackoffPolicy`],
+    /// and the [`RelayOptions::retry_interval`] and [`RelayOptions::adjust_retry_interval`] options are ignored.
+    #[inline]
+    pub fn reconnect_backoff(mut self, policy: BackoffPolicy) -> Self {
+        self.backoff = Some(policy);
+        self
+    }
+
     /// Set custom limits
     pub fn limits(mut self, limits: RelayLimits) -> Self {
         self.limits = limits;
@@ -124,6 +279,48 @@ impl RelayOptions {
         self
     }
 
+    /// Set max number of IDs and authors per outgoing filter (default: None)
+    ///
+    /// REQs with a filter exceeding the limit are rejected with [`Error::TooManyFilterTerms`](crate::relay::Error::TooManyFilterTerms).
+    #[inline]
+    pub fn max_filter_terms(mut self, max: Option<usize>) -> Self {
+        self.max_filter_terms = max;
+        self
+    }
+
+    /// Set the `limit` of the outgoing REQ filters that don't specify one (default: None)
+    ///
+    /// Useful as a guardrail against accidentally requesting the whole history of a relay.
+    /// The filters with an explicit `limit` are left untouched.
+    #[inline]
+    pub fn default_filter_limit(mut self, limit: Option<usize>) -> Self {
+        self.default_filter_limit = limit;
+        self
+    }
+
+    /// Limit the outgoing `EVENT` and `REQ` messages per second (default: no limit)
+    ///
+    /// The messages exceeding the limit are queued and sent as soon as allowed,
+    /// and [`Relay::send_event`](crate::Relay::send_event) waits for the queue to drain instead of failing.
+    /// Bursts of up to `max_per_second` messages are allowed.
+    #[inline]
+    pub fn rate_limit(mut self, max_per_second: u32) -> Self {
+        self.rate_limit = Some(max_per_second);
+        self
+    }
+
+    /// Limit the incoming `EVENT` messages per second (default: no limit)
+    ///
+    /// Protects against the relays flooding the client with events,
+    /// without affecting the other relays of the pool.
+    /// Bursts of up to `max_per_second` events are allowed:
+    /// when the limit is exceeded, the `action` is applied (see [`RateLimitAction`]).
+    #[inline]
+    pub fn incoming_rate_limit(mut self, max_per_second: u32, action: RateLimitAction) -> Self {
+        self.incoming_rate_limit = Some((max_per_second, action));
+        self
+    }
+
     /// Notification channel size (default: [`DEFAULT_NOTIFICATION_CHANNEL_SIZE`])
     #[inline]
     pub fn notification_channel_size(mut self, size: usize) -> Self {
@@ -132,6 +329,109 @@ impl RelayOptions {
     }
 }
 
+/// Backoff strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BackoffStrategy {
+    /// Always wait the same interval
+    Fixed,
+    /// Increase the interval linearly: `base * (failures + 1)`
+    Linear,
+    /// Double the interval at every failure: `base * 2^failures`
+    Exponential,
+}
+
+/// Reconnection backoff policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BackoffPolicy {
+    strategy: BackoffStrategy,
+    base: Duration,
+    max: Duration,
+    jitter: bool,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self::exponential(DEFAULT_RETRY_INTERVAL, MAX_RETRY_INTERVAL)
+    }
+}
+
+impl BackoffPolicy {
+    /// Fixed interval
+    #[inline]
+    pub fn fixed(interval: Duration) -> Self {
+        Self {
+            strategy: BackoffStrategy::Fixed,
+            base: interval,
+            max: interval,
+            jitter: false,
+        }
+    }
+
+    /// Linear backoff, capped at `max`
+    #[inline]
+    pub fn linear(base: Duration, max: Duration) -> Self {
+        Self {
+            strategy: BackoffStrategy::Linear,
+            base,
+            max,
+            jitter: false,
+        }
+    }
+
+    /// Exponential backoff, capped at `max`
+    #[inline]
+    pub fn exponential(base: Duration, max: Duration) -> Self {
+        Self {
+            strategy: BackoffStrategy::Exponential,
+            base,
+            max,
+            jitter: false,
+        }
+    }
+
+    /// Randomize the delay (default: false)
+    ///
+    /// The jitter helps to avoid that many clients (or relays) retry to connect at the same time.
+    /// When enabled, the delay is picked randomly between the half and the full computed delay.
+    #[inline]
+    pub fn jitter(mut self, enable: bool) -> Self {
+        self.jitter = enable;
+        self
+    }
+
+    /// Get the strategy
+    #[inline]
+    pub fn strategy(&self) -> BackoffStrategy {
+        self.strategy
+    }
+
+    /// Compute the delay after N consecutive failures
+    pub fn delay(&self, failures: u32) -> Duration {
+        let delay: Duration = match self.strategy {
+            BackoffStrategy::Fixed => self.base,
+            BackoffStrategy::Linear => self
+                .base
+                .checked_mul(failures.saturating_add(1))
+                .unwrap_or(self.max),
+            BackoffStrategy::Exponential => self
+                .base
+                .checked_mul(2u32.saturating_pow(failures))
+                .unwrap_or(self.max),
+        };
+
+        // Cap the delay
+        let delay: Duration = delay.min(self.max);
+
+        if self.jitter {
+            let half: Duration = delay / 2;
+            let jitter: u64 = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+            return half + Duration::from_millis(jitter);
+        }
+
+        delay
+    }
+}
+
 /// Auto-closing subscribe options
 #[derive(Debug, Clone, Copy, Default)]
 pub struct SubscribeAutoCloseOptions {
@@ -141,6 +441,26 @@ pub struct SubscribeAutoCloseOptions {
 }
 
 impl SubscribeAutoCloseOptions {
+    /// Close subscription on EOSE
+    #[inline]
+    pub fn on_eose() -> Self {
+        Self::default().exit_policy(ReqExitPolicy::ExitOnEOSE)
+    }
+
+    /// Close subscription after [`Duration`], ignoring EOSE
+    #[inline]
+    pub fn on_timeout(timeout: Duration) -> Self {
+        Self::default()
+            .exit_policy(ReqExitPolicy::WaitForTimeout)
+            .timeout(Some(timeout))
+    }
+
+    /// Close subscription on EOSE or after [`Duration`], whichever comes first
+    #[inline]
+    pub fn on_eose_or_timeout(timeout: Duration) -> Self {
+        Self::on_eose().timeout(Some(timeout))
+    }
+
     /// Close subscription when [`ReqExitPolicy`] is satisfied
     pub fn exit_policy(mut self, policy: ReqExitPolicy) -> Self {
         self.exit_policy = policy;
@@ -164,6 +484,8 @@ impl SubscribeAutoCloseOptions {
 #[derive(Debug, Clone, Copy, Default)]
 pub struct SubscribeOptions {
     pub(super) auto_close: Option<SubscribeAutoCloseOptions>,
+    pub(crate) sliding_window: Option<SlidingWindow>,
+    pub(crate) eose_aggregation: Option<EoseAggregation>,
 }
 
 impl SubscribeOptions {
@@ -173,11 +495,143 @@ impl SubscribeOptions {
         self
     }
 
+    /// Keep the subscription as a rolling window of the last `window` time
+    ///
+    /// Every `refresh_interval`, the `since` of the filter is advanced to `now - window`
+    /// and the REQ is re-issued with the same subscription ID.
+    ///
+    /// Only supported by the pool subscriptions to the `READ` relays (i.e., [`RelayPool::subscribe`](crate::RelayPool::subscribe)).
+    /// Ignored for auto-closing subscriptions.
+    pub fn sliding_window(mut self, window: Duration, refresh_interval: Duration) -> Self {
+        self.sliding_window = Some(SlidingWindow {
+            window,
+            refresh_interval,
+        });
+        self
+    }
+
+    /// Aggregate the EOSE of the relays
+    ///
+    /// When the `quorum` of the subscribed relays sent the EOSE (or closed the subscription),
+    /// a single [`RelayPoolNotification::Eose`](crate::RelayPoolNotification::Eose) is sent.
+    /// If the quorum isn't reached within the `timeout`, the notification is sent anyway.
+    ///
+    /// Only supported by the pool subscriptions (i.e., [`RelayPool::subscribe`](crate::RelayPool::subscribe)).
+    /// Ignored for auto-closing subscriptions.
+    pub fn eose_quorum(mut self, quorum: EoseQuorum, timeout: Duration) -> Self {
+        self.eose_aggregation = Some(EoseAggregation { quorum, timeout });
+        self
+    }
+
     pub(crate) fn is_auto_closing(&self) -> bool {
         self.auto_close.is_some()
     }
 }
 
+/// Number of relays that must send the EOSE to consider the stored events loaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EoseQuorum {
+    /// All the relays
+    #[default]
+    All,
+    /// More than half of the relays
+    Majority,
+    /// The first `N` relays
+    First(usize),
+}
+
+impl EoseQuorum {
+    /// Get the number of required EOSE, out of `relays`
+    pub(crate) fn required(&self, relays: usize) -> usize {
+        match self {
+            Self::All => relays,
+            Self::Majority => relays / 2 + 1,
+            Self::First(n) => (*n).max(1),
+        }
+        .min(relays)
+    }
+}
+
+/// Number of relays that must accept an event to consider it published
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WriteConcern {
+    /// The first relay
+    FirstOk,
+    /// At least `N` relays
+    ///
+    /// Isn't reduced to the number of target relays: if there are less than `N` relays, it can't be satisfied.
+    Quorum(usize),
+    /// More than half of the relays
+    Majority,
+    /// All the relays
+    All,
+}
+
+impl WriteConcern {
+    /// Get the number of required `OK`, out of `relays`
+    pub(crate) fn required(&self, relays: usize) -> usize {
+        match self {
+            Self::FirstOk => 1,
+            Self::Quorum(n) => (*n).max(1),
+            Self::Majority => relays / 2 + 1,
+            Self::All => relays,
+        }
+    }
+}
+
+/// EOSE aggregation of a subscription
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EoseAggregation {
+    pub(crate) quorum: EoseQuorum,
+    pub(crate) timeout: Duration,
+}
+
+/// Sliding window of a subscription
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SlidingWindow {
+    pub(crate) window: Duration,
+    pub(crate) refresh_interval: Duration,
+}
+
+impl SlidingWindow {
+    /// Get the filter with `since` set to the start of the window
+    pub(crate) fn apply(&self, filter: Filter) -> Filter {
+        filter.since(Timestamp::now() - self.window)
+    }
+}
+
+/// Relay connect policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ConnectPolicy {
+    /// Connect when the pool connects (i.e., [`RelayPool::connect`](crate::RelayPool::connect))
+    #[default]
+    Eager,
+    /// Connect only when a subscription, a request or an event first targets the relay
+    ///
+    /// Lazy relays are skipped by [`RelayPool::connect`](crate::RelayPool::connect),
+    /// [`RelayPool::try_connect`](crate::RelayPool::try_connect) and
+    /// [`RelayPool::wait_for_connection`](crate::RelayPool::wait_for_connection),
+    /// until the first connection is started.
+    /// The messages sent in the meantime are queued and delivered once connected.
+    ///
+    /// Useful to avoid keeping idle connections to rarely used relays (i.e., gossip relays).
+    Lazy,
+}
+
+/// Action applied when a relay exceeds the incoming rate limit
+///
+/// Check [`RelayOptions::incoming_rate_limit`] to learn more.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RateLimitAction {
+    /// Slow down the reads from the relay, until its rate is under the limit
+    #[default]
+    Throttle,
+    /// Disconnect the relay, without reconnecting
+    ///
+    /// A [`RelayPoolNotification::RateLimitExceeded`](crate::RelayPoolNotification::RateLimitExceeded) is sent.
+    Disconnect,
+}
+
 /// Request (REQ) exit policy
 #[derive(Debug, Clone, Copy, Default)]
 pub enum ReqExitPolicy {
@@ -190,6 +644,10 @@ pub enum ReqExitPolicy {
     WaitForEventsAfterEOSE(u16),
     /// After EOSE is received, keep listening for matching events for [`Duration`] more time.
     WaitDurationAfterEOSE(Duration),
+    /// Ignore EOSE and keep listening until the timeout is reached.
+    ///
+    /// Without a timeout, the subscription is closed only by the relay or on disconnection.
+    WaitForTimeout,
 }
 
 /// Negentropy Sync direction
@@ -211,6 +669,16 @@ pub struct SyncProgress {
     pub total: u64,
     /// Processed events
     pub current: u64,
+    /// Completed reconciliation rounds (`NEG-MSG` received)
+    pub rounds: u64,
+    /// Differences found so far between the local and the relay sets
+    ///
+    /// Grows while reconciling, until the full set difference is known.
+    pub differences: u64,
+    /// Size of the negentropy messages sent (hex-encoded, as on the wire)
+    pub bytes_sent: u64,
+    /// Size of the negentropy messages received (hex-encoded, as on the wire)
+    pub bytes_received: u64,
 }
 
 impl SyncProgress {
@@ -267,7 +735,7 @@ impl SyncOptions {
 
     /// Negentropy Sync direction (default: down)
     ///
-    /// If `true`, perform the set reconciliation on each side.
+    /// Use [`SyncDirection::Both`] to perform the set reconciliation on each side.
     #[inline]
     pub fn direction(mut self, direction: SyncDirection) -> Self {
         self.direction = direction;
@@ -330,6 +798,53 @@ mod tests {
         assert_eq!(opt.initial_timeout, Duration::from_secs(5));
     }
 
+    #[test]
+    fn test_ping_interval() {
+        use crate::relay::AtomicRelayServiceFlags;
+
+        let opts = RelayOptions::default();
+        assert!(AtomicRelayServiceFlags::new(opts.flags).has_ping());
+        assert_eq!(opts.ping_interval, PING_INTERVAL);
+
+        let opts = RelayOptions::default().ping_interval(Some(Duration::from_secs(10)));
+        assert!(AtomicRelayServiceFlags::new(opts.flags).has_ping());
+        assert_eq!(opts.ping_interval, Duration::from_secs(10));
+
+        let opts = RelayOptions::default().ping_interval(Some(Duration::ZERO));
+        assert_eq!(opts.ping_interval, MIN_PING_INTERVAL);
+
+        let opts = RelayOptions::default().ping_interval(None);
+        assert!(!AtomicRelayServiceFlags::new(opts.flags).has_ping());
+    }
+
+    #[test]
+    fn test_backoff_policy() {
+        let base = Duration::from_secs(2);
+        let max = Duration::from_secs(30);
+
+        let policy = BackoffPolicy::fixed(base);
+        assert_eq!(policy.delay(0), base);
+        assert_eq!(policy.delay(10), base);
+
+        let policy = BackoffPolicy::linear(base, max);
+        assert_eq!(policy.delay(0), base);
+        assert_eq!(policy.delay(2), Duration::from_secs(6));
+        assert_eq!(policy.delay(100), max);
+
+        let policy = BackoffPolicy::exponential(base, max);
+        assert_eq!(policy.delay(0), base);
+        assert_eq!(policy.delay(3), Duration::from_secs(16));
+        assert_eq!(policy.delay(4), max);
+        assert_eq!(policy.delay(u32::MAX), max);
+
+        let policy = BackoffPolicy::exponential(base, max).jitter(true);
+        for failures in 0..10 {
+            let delay = policy.delay(failures);
+            assert!(delay <= max);
+            assert!(delay >= base / 2);
+        }
+    }
+
     #[test]
     fn test_close() {
         let opts = SubscribeOptions::default();
@@ -0,0 +1,131 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use async_utility::time;
+use nostr::types::time::Instant;
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens (negative if there are pending reservations)
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket rate limiter
+///
+/// The bucket capacity is equal to the rate, so bursts of up to one second of messages are allowed.
+#[derive(Debug)]
+pub(super) struct RateLimiter {
+    /// Tokens per second
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_second: u32) -> Self {
+        let rate: f64 = max_per_second.max(1) as f64;
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Get the max number of tokens per second
+    #[inline]
+    pub fn max_per_second(&self) -> u32 {
+        self.rate as u32
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now: Instant = Instant::now();
+        let elapsed: f64 = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.rate);
+        bucket.last_refill = now;
+    }
+
+    /// Reserve `n` tokens and return how long to wait before using them
+    pub fn reserve(&self, n: usize) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        self.refill(&mut bucket);
+
+        bucket.tokens -= n as f64;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// Get how long to wait until a token is available, without consuming it
+    pub fn wait_time(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        self.refill(&mut bucket);
+
+        if bucket.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+        }
+    }
+
+    /// Reserve `n` tokens and wait until they are available
+    pub async fn acquire(&self, n: usize) {
+        let delay: Duration = self.reserve(n);
+        if !delay.is_zero() {
+            time::sleep(delay).await;
+        }
+    }
+
+    /// Wait until a token is available, without consuming it
+    pub async fn ready(&self) {
+        loop {
+            let delay: Duration = self.wait_time();
+
+            if delay.is_zero() {
+                break;
+            }
+
+            time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(10);
+
+        // Burst
+        assert_eq!(limiter.reserve(10), Duration::ZERO);
+        assert!(!limiter.wait_time().is_zero());
+
+        // Over the limit: about 1 sec of wait for 10 more tokens
+        let delay = limiter.reserve(10);
+        assert!(delay > Duration::from_millis(900));
+        assert!(delay <= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_acquire() {
+        let limiter = RateLimiter::new(20);
+        limiter.acquire(20).await;
+
+        let now = Instant::now();
+        limiter.acquire(2).await;
+        assert!(now.elapsed() >= Duration::from_millis(90));
+
+        limiter.ready().await;
+        assert!(limiter.wait_time().is_zero());
+    }
+}
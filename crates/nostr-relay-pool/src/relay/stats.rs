@@ -4,21 +4,24 @@
 
 //! Relay Stats
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::cmp;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-#[cfg(not(target_arch = "wasm32"))]
 use std::time::Duration;
 
 use nostr::Timestamp;
 
+use super::status::RelayStatus;
+
 #[cfg(not(target_arch = "wasm32"))]
-use super::constants::LATENCY_MIN_READS;
+use super::constants::{LATENCY_MIN_READS, LATENCY_WINDOW};
 
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Default)]
 struct AverageLatency {
-    /// Sum of all latencies in milliseconds
-    total: AtomicU64,
+    /// Rolling average of the latencies in milliseconds
+    avg: AtomicU64,
     /// Count of latencies
     count: AtomicU64,
 }
@@ -29,13 +32,58 @@ struct InnerRelayConnectionStats {
     success: AtomicUsize,
     bytes_sent: AtomicUsize,
     bytes_received: AtomicUsize,
+    pow_rejected_events: AtomicUsize,
+    events_received: AtomicUsize,
+    last_message_at: AtomicU64,
     // TODO: keep track of msg/event sending attempts and success?
     connected_at: AtomicU64,
     first_connection_at: AtomicU64,
+    /// UNIX timestamp of the start of the current connection (`0` if not connected)
+    connected_since: AtomicU64,
+    /// UNIX timestamp of the start of the current outage (`0` if not in an outage)
+    disconnected_since: AtomicU64,
+    /// Cumulative seconds of the closed connections
+    uptime: AtomicU64,
+    /// Cumulative seconds of the closed outages
+    downtime: AtomicU64,
     #[cfg(not(target_arch = "wasm32"))]
     latency: AverageLatency,
 }
 
+/// Relay stats snapshot
+///
+/// Returned by [`Relay::health`](crate::Relay::health) and [`RelayPool::health`](crate::RelayPool::health).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayStats {
+    /// Current connection status
+    pub status: RelayStatus,
+    /// Number of connection attempts
+    pub attempts: usize,
+    /// Number of successful connections
+    pub success: usize,
+    /// Number of reconnections (successful connections after the first one)
+    pub reconnects: usize,
+    /// Bytes sent
+    pub bytes_sent: usize,
+    /// Bytes received
+    pub bytes_received: usize,
+    /// Number of events received
+    pub events_received: usize,
+    /// UNIX timestamp of the last connection
+    pub connected_at: Option<Timestamp>,
+    /// UNIX timestamp of the last received message
+    pub last_message_at: Option<Timestamp>,
+    /// UNIX timestamp of the start of the current connection
+    pub connected_since: Option<Timestamp>,
+    /// Cumulative time spent connected
+    pub uptime: Duration,
+    /// Cumulative time spent disconnected, while trying to (re)connect
+    pub downtime: Duration,
+    /// Average latency
+    #[cfg(not(target_arch = "wasm32"))]
+    pub latency: Option<Duration>,
+}
+
 /// Relay connection stats
 #[derive(Debug, Clone, Default)]
 pub struct RelayConnectionStats {
@@ -77,6 +125,34 @@ impl RelayConnectionStats {
         self.inner.bytes_received.load(Ordering::SeqCst)
     }
 
+    /// Number of received events discarded for not meeting the minimum POW difficulty
+    #[inline]
+    pub fn pow_rejected_events(&self) -> usize {
+        self.inner.pow_rejected_events.load(Ordering::SeqCst)
+    }
+
+    /// The number of reconnections (successful connections after the first one)
+    #[inline]
+    pub fn reconnects(&self) -> usize {
+        self.success().saturating_sub(1)
+    }
+
+    /// Number of events received
+    #[inline]
+    pub fn events_received(&self) -> usize {
+        self.inner.events_received.load(Ordering::SeqCst)
+    }
+
+    /// Get UNIX timestamp of the last received message
+    ///
+    /// Returns `None` if no message has been received yet.
+    pub fn last_message_at(&self) -> Option<Timestamp> {
+        match self.inner.last_message_at.load(Ordering::SeqCst) {
+            0 => None,
+            ts => Some(Timestamp::from(ts)),
+        }
+    }
+
     /// Get UNIX timestamp of the last connection
     #[inline]
     pub fn connected_at(&self) -> Timestamp {
@@ -89,10 +165,66 @@ impl RelayConnectionStats {
         Timestamp::from(self.inner.first_connection_at.load(Ordering::SeqCst))
     }
 
-    /// Calculate latency
+    /// Get UNIX timestamp of the start of the current connection
+    ///
+    /// Returns `None` if the relay is not connected.
+    pub fn connected_since(&self) -> Option<Timestamp> {
+        match self.inner.connected_since.load(Ordering::SeqCst) {
+            0 => None,
+            ts => Some(Timestamp::from(ts)),
+        }
+    }
+
+    /// Cumulative time spent connected, including the current connection
+    pub fn uptime(&self) -> Duration {
+        self.uptime_at(Timestamp::now())
+    }
+
+    /// Cumulative time spent disconnected, while trying to (re)connect, including the current outage
+    ///
+    /// The time after a manual disconnection (i.e., [`Relay::disconnect`](crate::Relay::disconnect)) or a ban isn't counted.
+    pub fn downtime(&self) -> Duration {
+        self.downtime_at(Timestamp::now())
+    }
+
+    /// Availability ratio (`uptime / (uptime + downtime)`), between `0.0` and `1.0`
+    ///
+    /// Returns `None` if no connection has been attempted yet.
+    pub fn availability(&self) -> Option<f64> {
+        let now: Timestamp = Timestamp::now();
+        let uptime: f64 = self.uptime_at(now).as_secs_f64();
+        let downtime: f64 = self.downtime_at(now).as_secs_f64();
+        let total: f64 = uptime + downtime;
+
+        if total > 0.0 {
+            Some(uptime / total)
+        } else if self.connected_since().is_some() {
+            Some(1.0)
+        } else if self.attempts() > 0 {
+            Some(0.0)
+        } else {
+            None
+        }
+    }
+
+    fn uptime_at(&self, now: Timestamp) -> Duration {
+        let closed: u64 = self.inner.uptime.load(Ordering::SeqCst);
+        let current: u64 = elapsed_since(&self.inner.connected_since, now);
+        Duration::from_secs(closed.saturating_add(current))
+    }
+
+    fn downtime_at(&self, now: Timestamp) -> Duration {
+        let closed: u64 = self.inner.downtime.load(Ordering::SeqCst);
+        let current: u64 = elapsed_since(&self.inner.disconnected_since, now);
+        Duration::from_secs(closed.saturating_add(current))
+    }
+
+    /// Average latency
+    ///
+    /// Rolling average of the round-trip times measured from ping/pong and REQ-to-EOSE.
+    /// Returns `None` if not enough samples have been collected yet.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn latency(&self) -> Option<Duration> {
-        let total: u64 = self.inner.latency.total.load(Ordering::SeqCst);
         let count: u64 = self.inner.latency.count.load(Ordering::SeqCst);
 
         // Check number of reads
@@ -100,8 +232,8 @@ impl RelayConnectionStats {
             return None;
         }
 
-        // Calc latency
-        total.checked_div(count).map(Duration::from_millis)
+        let avg: u64 = self.inner.latency.avg.load(Ordering::SeqCst);
+        Some(Duration::from_millis(avg))
     }
 
     #[inline]
@@ -109,6 +241,13 @@ impl RelayConnectionStats {
         self.inner.attempts.fetch_add(1, Ordering::SeqCst);
     }
 
+    #[inline]
+    pub(super) fn new_pow_rejected_event(&self) {
+        self.inner
+            .pow_rejected_events
+            .fetch_add(1, Ordering::SeqCst);
+    }
+
     pub(super) fn new_success(&self) {
         self.inner.success.fetch_add(1, Ordering::SeqCst);
 
@@ -121,6 +260,38 @@ impl RelayConnectionStats {
         }
     }
 
+    /// Track the uptime/downtime transitions
+    pub(super) fn status_changed(&self, status: RelayStatus) {
+        self.status_changed_at(status, Timestamp::now())
+    }
+
+    fn status_changed_at(&self, status: RelayStatus, now: Timestamp) {
+        let now: u64 = now.as_u64();
+
+        match status {
+            RelayStatus::Initialized | RelayStatus::Pending => {}
+            // Outage starts with the first attempt, or when the connection is lost
+            RelayStatus::Connecting | RelayStatus::Disconnected => {
+                close_period(&self.inner.connected_since, &self.inner.uptime, now);
+                let _ = self.inner.disconnected_since.compare_exchange(
+                    0,
+                    now,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+            }
+            RelayStatus::Connected => {
+                close_period(&self.inner.disconnected_since, &self.inner.downtime, now);
+                self.inner.connected_since.store(now, Ordering::SeqCst);
+            }
+            // Manually disconnected or banned: neither up nor down
+            RelayStatus::Terminated | RelayStatus::Banned => {
+                close_period(&self.inner.connected_since, &self.inner.uptime, now);
+                close_period(&self.inner.disconnected_since, &self.inner.downtime, now);
+            }
+        }
+    }
+
     #[inline]
     pub(super) fn add_bytes_sent(&self, size: usize) {
         if size > 0 {
@@ -135,19 +306,77 @@ impl RelayConnectionStats {
         }
     }
 
+    #[inline]
+    pub(super) fn new_message(&self) {
+        self.inner
+            .last_message_at
+            .store(Timestamp::now().as_u64(), Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub(super) fn new_event(&self) {
+        self.inner.events_received.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Take a snapshot of the stats
+    pub(super) fn snapshot(&self, status: RelayStatus) -> RelayStats {
+        let connected_at: Timestamp = self.connected_at();
+        RelayStats {
+            status,
+            attempts: self.attempts(),
+            success: self.success(),
+            reconnects: self.reconnects(),
+            bytes_sent: self.bytes_sent(),
+            bytes_received: self.bytes_received(),
+            events_received: self.events_received(),
+            connected_at: (!connected_at.is_zero()).then_some(connected_at),
+            last_message_at: self.last_message_at(),
+            connected_since: self.connected_since(),
+            uptime: self.uptime(),
+            downtime: self.downtime(),
+            #[cfg(not(target_arch = "wasm32"))]
+            latency: self.latency(),
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub(super) fn save_latency(&self, latency: Duration) {
         let ms: u128 = latency.as_millis();
         if ms <= u64::MAX as u128 {
-            self.inner
-                .latency
-                .total
-                .fetch_add(ms as u64, Ordering::SeqCst);
-            self.inner.latency.count.fetch_add(1, Ordering::SeqCst);
+            let ms: u64 = ms as u64;
+
+            // Until the window is full, the samples have the same weight
+            let count: u64 = self.inner.latency.count.fetch_add(1, Ordering::SeqCst) + 1;
+            let weight: u64 = cmp::min(count, LATENCY_WINDOW);
+
+            let _ =
+                self.inner
+                    .latency
+                    .avg
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |avg| {
+                        let total: u128 = avg as u128 * (weight - 1) as u128 + ms as u128;
+                        Some((total / weight as u128) as u64)
+                    });
         }
     }
 }
 
+/// Seconds elapsed from the timestamp stored in `since` (`0` if not set)
+fn elapsed_since(since: &AtomicU64, now: Timestamp) -> u64 {
+    match since.load(Ordering::SeqCst) {
+        0 => 0,
+        since => now.as_u64().saturating_sub(since),
+    }
+}
+
+/// Reset the `since` timestamp and add the elapsed seconds to the `total`
+fn close_period(since: &AtomicU64, total: &AtomicU64, now: u64) {
+    let since: u64 = since.swap(0, Ordering::SeqCst);
+    if since > 0 {
+        total.fetch_add(now.saturating_sub(since), Ordering::SeqCst);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,6 +393,73 @@ mod tests {
         assert!(stats.first_connection_timestamp().as_u64() > 0);
     }
 
+    #[test]
+    fn test_snapshot() {
+        let stats = RelayConnectionStats::default();
+
+        let snapshot = stats.snapshot(RelayStatus::Initialized);
+        assert_eq!(snapshot.reconnects, 0);
+        assert_eq!(snapshot.connected_at, None);
+        assert_eq!(snapshot.last_message_at, None);
+
+        stats.new_attempt();
+        stats.new_success();
+        stats.new_attempt();
+        stats.new_success();
+        stats.new_message();
+        stats.new_event();
+
+        let snapshot = stats.snapshot(RelayStatus::Connected);
+        assert_eq!(snapshot.status, RelayStatus::Connected);
+        assert_eq!(snapshot.reconnects, 1);
+        assert_eq!(snapshot.events_received, 1);
+        assert!(snapshot.connected_at.is_some());
+        assert!(snapshot.last_message_at.is_some());
+    }
+
+    #[test]
+    fn test_uptime_downtime() {
+        let stats = RelayConnectionStats::default();
+        assert_eq!(stats.availability(), None);
+
+        // 10 secs to connect
+        stats.status_changed_at(RelayStatus::Connecting, Timestamp::from(1_000));
+        assert_eq!(stats.connected_since(), None);
+        stats.status_changed_at(RelayStatus::Connected, Timestamp::from(1_010));
+        assert_eq!(stats.connected_since(), Some(Timestamp::from(1_010)));
+        assert_eq!(
+            stats.downtime_at(Timestamp::from(1_050)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            stats.uptime_at(Timestamp::from(1_050)),
+            Duration::from_secs(40)
+        );
+
+        // Connection lost after 90 secs, reconnected after 3 failed attempts (20 secs)
+        stats.status_changed_at(RelayStatus::Disconnected, Timestamp::from(1_100));
+        assert_eq!(stats.connected_since(), None);
+        stats.status_changed_at(RelayStatus::Connecting, Timestamp::from(1_105));
+        stats.status_changed_at(RelayStatus::Disconnected, Timestamp::from(1_110));
+        stats.status_changed_at(RelayStatus::Connecting, Timestamp::from(1_115));
+        stats.status_changed_at(RelayStatus::Connected, Timestamp::from(1_120));
+
+        // Manually disconnected after 80 secs: not counted as downtime
+        stats.status_changed_at(RelayStatus::Terminated, Timestamp::from(1_200));
+        assert_eq!(
+            stats.uptime_at(Timestamp::from(2_000)),
+            Duration::from_secs(170)
+        );
+        assert_eq!(
+            stats.downtime_at(Timestamp::from(2_000)),
+            Duration::from_secs(30)
+        );
+        assert_eq!(stats.connected_since(), None);
+
+        let availability = stats.availability().unwrap();
+        assert!((availability - 170.0 / 200.0).abs() < f64::EPSILON);
+    }
+
     #[test]
     fn test_add_bytes() {
         let stats = RelayConnectionStats::default();
@@ -176,4 +472,24 @@ mod tests {
         stats.add_bytes_received(30);
         assert_eq!(stats.bytes_received(), 30);
     }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_latency() {
+        let stats = RelayConnectionStats::default();
+
+        // Not enough reads
+        stats.save_latency(Duration::from_millis(100));
+        stats.save_latency(Duration::from_millis(200));
+        assert!(stats.latency().is_none());
+
+        stats.save_latency(Duration::from_millis(300));
+        assert_eq!(stats.latency(), Some(Duration::from_millis(200)));
+
+        // Old samples are progressively forgotten
+        for _ in 0..LATENCY_WINDOW * 10 {
+            stats.save_latency(Duration::from_millis(50));
+        }
+        assert_eq!(stats.latency(), Some(Duration::from_millis(50)));
+    }
 }
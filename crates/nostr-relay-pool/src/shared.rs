@@ -6,18 +6,23 @@ use std::collections::hash_map::DefaultHasher;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
 use lru::LruCache;
 use nostr::prelude::IntoNostrSigner;
-use nostr::{EventId, NostrSigner};
-use nostr_database::{IntoNostrDatabase, MemoryDatabase, NostrDatabase};
+use nostr::{Event, EventId, Filter, NostrSigner, Timestamp};
+use nostr_database::{DatabaseError, IntoNostrDatabase, MemoryDatabase, NostrDatabase};
 use tokio::sync::RwLock;
 
 use crate::monitor::Monitor;
 use crate::policy::AdmitPolicy;
+use crate::pool::options::{EventTtl, EventVerificationMode, RelayFilter};
+use crate::relay::connections::ConnectionLimiter;
 use crate::transport::websocket::{DefaultWebsocketTransport, WebSocketTransport};
+use crate::ttl::EventTtlTracker;
+use crate::verifier::EventVerifier;
 
 // LruCache pre-allocate, so keep this at a reasonable value.
 // A good value may be <= 128k, considering that stored values are the 64-bit hashes of the event IDs.
@@ -47,8 +52,15 @@ pub struct SharedState {
     signer: Arc<RwLock<Option<Arc<dyn NostrSigner>>>>,
     nip42_auto_authentication: Arc<AtomicBool>,
     verification_cache: Arc<Mutex<LruCache<u64, ()>>>,
+    pub(crate) verifier: EventVerifier,
+    /// Hashes of the notified event IDs, with the event `created_at`
+    dedup_cache: Option<Arc<Mutex<LruCache<u64, Timestamp>>>>,
+    ttl: Option<Arc<EventTtlTracker>>,
+    min_pow_difficulty: Arc<AtomicU8>,
     pub(crate) admit_policy: Option<Arc<dyn AdmitPolicy>>,
     pub(crate) monitor: Option<Monitor>,
+    pub(crate) connection_limiter: Option<Arc<ConnectionLimiter>>,
+    pub(crate) relay_filter: Option<RelayFilter>,
 }
 
 impl Default for SharedState {
@@ -58,7 +70,13 @@ impl Default for SharedState {
             Arc::new(DefaultWebsocketTransport),
             None,
             None,
-            true,
+            false,
+            0,
+            0,
+            EventVerificationMode::default(),
+            None,
+            None,
+            None,
             None,
         )
     }
@@ -71,7 +89,13 @@ impl SharedState {
         signer: Option<Arc<dyn NostrSigner>>,
         admit_policy: Option<Arc<dyn AdmitPolicy>>,
         nip42_auto_authentication: bool,
+        dedup_cache_size: usize,
+        min_pow_difficulty: u8,
+        verification_mode: EventVerificationMode,
         monitor: Option<Monitor>,
+        max_concurrent_connections: Option<usize>,
+        event_ttl: Option<EventTtl>,
+        relay_filter: Option<RelayFilter>,
     ) -> Self {
         let max_verification_cache_size: NonZeroUsize =
             NonZeroUsize::new(MAX_VERIFICATION_CACHE_SIZE)
@@ -83,8 +107,16 @@ impl SharedState {
             signer: Arc::new(RwLock::new(signer)),
             nip42_auto_authentication: Arc::new(AtomicBool::new(nip42_auto_authentication)),
             verification_cache: Arc::new(Mutex::new(LruCache::new(max_verification_cache_size))),
+            verifier: EventVerifier::new(verification_mode),
+            dedup_cache: NonZeroUsize::new(dedup_cache_size)
+                .map(|size| Arc::new(Mutex::new(LruCache::new(size)))),
+            ttl: event_ttl.map(|opts| Arc::new(EventTtlTracker::new(opts))),
+            min_pow_difficulty: Arc::new(AtomicU8::new(min_pow_difficulty)),
             admit_policy,
             monitor,
+            connection_limiter: max_concurrent_connections
+                .map(|max| Arc::new(ConnectionLimiter::new(max))),
+            relay_filter,
         }
     }
 
@@ -105,13 +137,18 @@ impl SharedState {
     /// Minimum POW difficulty for received events
     ///
     /// All received events must have a difficulty equal or greater than the set one.
+    /// Set to `0` to disable the check.
     ///
-    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
-    #[deprecated(
-        since = "0.40.0",
-        note = "This no longer works, please use `AdmitPolicy` instead."
-    )]
-    pub fn set_pow(&self, _difficulty: u8) {}
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    pub fn set_pow(&self, difficulty: u8) {
+        self.min_pow_difficulty.store(difficulty, Ordering::SeqCst);
+    }
+
+    /// Get minimum POW difficulty for received events
+    #[inline]
+    pub fn min_pow_difficulty(&self) -> u8 {
+        self.min_pow_difficulty.load(Ordering::SeqCst)
+    }
 
     /// Get database
     #[inline]
@@ -160,9 +197,76 @@ impl SharedState {
         // Returns `Some(T)` if the key already exists
         Ok(cache.put(id, ()).is_some())
     }
+
+    /// Check if the event was already notified, marking it as notified.
+    ///
+    /// Always returns `false` if the dedup cache is disabled.
+    pub(crate) fn already_notified(&self, event: &Event) -> Result<bool, SharedStateError> {
+        match &self.dedup_cache {
+            Some(cache) => {
+                let mut cache = cache.lock().map_err(|_| SharedStateError::MutexPoisoned)?;
+
+                // Returns `Some(T)` if the key already exists
+                Ok(cache.put(hash(&event.id), event.created_at).is_some())
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Check if the event is expired, according to the event TTL.
+    ///
+    /// Always returns `false` if the event TTL is disabled.
+    pub(crate) fn is_expired(&self, event: &Event) -> bool {
+        match &self.ttl {
+            Some(ttl) => ttl.is_expired(event, hash(&event.id), Timestamp::now()),
+            None => false,
+        }
+    }
+
+    /// Track the stored event, to expire it after the TTL
+    pub(crate) fn track_received(&self, event: &Event) {
+        if let Some(ttl) = &self.ttl {
+            ttl.track(event, Timestamp::now());
+        }
+    }
+
+    /// Get the event TTL sweep interval, if the event TTL is enabled
+    pub(crate) fn ttl_sweep_interval(&self) -> Option<Duration> {
+        self.ttl.as_ref().map(|ttl| ttl.opts.sweep_interval)
+    }
+
+    /// Drop the expired events from the dedup cache and, if enabled, from the database
+    pub(crate) async fn prune_expired_events(&self) -> Result<(), DatabaseError> {
+        let ttl: &EventTtlTracker = match &self.ttl {
+            Some(ttl) => ttl,
+            None => return Ok(()),
+        };
+
+        let cutoff: Timestamp = ttl.cutoff(Timestamp::now());
+
+        let ids: Vec<EventId> = ttl.pop_expired(cutoff, hash);
+
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(cache) = &self.dedup_cache {
+            let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+            for id in ids.iter() {
+                cache.pop(&hash(id));
+            }
+        }
+
+        // Delete only the tracked events, not the ones stored by others
+        if ttl.opts.prune_database {
+            self.database.delete(Filter::new().ids(ids)).await?;
+        }
+
+        Ok(())
+    }
 }
 
-fn hash<T>(val: &T) -> u64
+pub(crate) fn hash<T>(val: &T) -> u64
 where
     T: Hash,
 {
@@ -8,6 +8,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use async_utility::futures_util::Stream;
+use nostr::{Event, RelayUrl, SubscriptionId};
 use tokio::sync::mpsc::Receiver;
 
 /// A wrapper around [`Receiver`] that implements [`Stream`].
@@ -57,3 +58,56 @@ impl<T> AsMut<Receiver<T>> for ReceiverStream<T> {
         &mut self.inner
     }
 }
+
+/// Item of a [`SubscriptionStream`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionStreamItem {
+    /// Received a new event
+    ///
+    /// Each event is yielded once, even if received from many relays.
+    Event {
+        /// The URL of the relay from which the event was received first.
+        relay_url: RelayUrl,
+        /// The received event.
+        event: Box<Event>,
+    },
+    /// The stored events have been loaded
+    ///
+    /// Yielded once, only if an EOSE quorum is set
+    /// (see [`SubscribeOptions::eose_quorum`](crate::SubscribeOptions::eose_quorum)).
+    /// The following events are new ones.
+    Eose,
+}
+
+/// Stream of the events of a subscription
+///
+/// The subscription is closed when the stream is dropped.
+#[derive(Debug)]
+pub struct SubscriptionStream {
+    id: SubscriptionId,
+    inner: ReceiverStream<SubscriptionStreamItem>,
+}
+
+impl SubscriptionStream {
+    #[inline]
+    pub(crate) fn new(id: SubscriptionId, recv: Receiver<SubscriptionStreamItem>) -> Self {
+        Self {
+            id,
+            inner: ReceiverStream::new(recv),
+        }
+    }
+
+    /// Get subscription ID
+    #[inline]
+    pub fn id(&self) -> &SubscriptionId {
+        &self.id
+    }
+}
+
+impl Stream for SubscriptionStream {
+    type Item = SubscriptionStreamItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
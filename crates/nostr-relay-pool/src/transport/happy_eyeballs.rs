@@ -0,0 +1,148 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Happy Eyeballs (RFC 8305)
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_utility::futures_util::stream::FuturesUnordered;
+use async_utility::futures_util::StreamExt;
+use async_utility::time;
+use tokio::net::TcpStream;
+
+/// Delay before starting the next connection attempt, while the previous ones are pending (RFC 8305, section 5)
+pub(crate) const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Resolve the host and race the connection attempts to the resolved addresses
+///
+/// A new attempt is started every `delay` or as soon as an attempt fails.
+/// The first established connection wins and the other attempts are dropped.
+pub(crate) async fn connect(host: &str, port: u16, delay: Duration) -> io::Result<TcpStream> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    connect_to(addrs, delay).await
+}
+
+async fn connect_to(addrs: Vec<SocketAddr>, delay: Duration) -> io::Result<TcpStream> {
+    let mut addrs = interleave(addrs).into_iter();
+    let mut attempts = FuturesUnordered::new();
+
+    match addrs.next() {
+        Some(addr) => attempts.push(TcpStream::connect(addr)),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no addresses resolved",
+            ))
+        }
+    }
+
+    let mut last_error: Option<io::Error> = None;
+
+    loop {
+        tokio::select! {
+            res = attempts.next() => match res {
+                Some(Ok(stream)) => return Ok(stream),
+                Some(Err(e)) => {
+                    last_error = Some(e);
+
+                    // Don't wait the delay to start the next attempt
+                    if let Some(addr) = addrs.next() {
+                        attempts.push(TcpStream::connect(addr));
+                    }
+                }
+                // All the attempts failed
+                None => {
+                    return Err(last_error.unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotConnected, "all connection attempts failed")
+                    }))
+                }
+            },
+            _ = time::sleep(delay), if !addrs.as_slice().is_empty() => {
+                if let Some(addr) = addrs.next() {
+                    attempts.push(TcpStream::connect(addr));
+                }
+            }
+        }
+    }
+}
+
+/// Interleave the address families, starting with the family of the first address (RFC 8305, section 4)
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_ipv6: bool = match addrs.first() {
+        Some(addr) => addr.is_ipv6(),
+        None => return addrs,
+    };
+
+    let (preferred, other): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_ipv6);
+
+    let mut list: Vec<SocketAddr> = Vec::with_capacity(preferred.len() + other.len());
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => break,
+            (a, b) => {
+                list.extend(a);
+                list.extend(b);
+            }
+        }
+    }
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_interleave() {
+        let v6a: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let v6b: SocketAddr = "[2001:db8::2]:443".parse().unwrap();
+        let v6c: SocketAddr = "[2001:db8::3]:443".parse().unwrap();
+        let v4a: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        let v4b: SocketAddr = "192.0.2.2:443".parse().unwrap();
+
+        assert_eq!(
+            interleave(vec![v6a, v6b, v6c, v4a, v4b]),
+            vec![v6a, v4a, v6b, v4b, v6c]
+        );
+        assert_eq!(
+            interleave(vec![v4a, v6a, v6b, v4b]),
+            vec![v4a, v6a, v4b, v6b]
+        );
+        assert!(interleave(Vec::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_next_address_on_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+
+        // Bind and drop, to get a port with nothing listening
+        let refused: SocketAddr = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        let stream = connect_to(vec![refused, addr], Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+
+        assert!(connect_to(vec![refused], CONNECTION_ATTEMPT_DELAY)
+            .await
+            .is_err());
+        assert!(connect_to(Vec::new(), CONNECTION_ATTEMPT_DELAY)
+            .await
+            .is_err());
+    }
+}
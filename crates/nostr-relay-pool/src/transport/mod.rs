@@ -5,4 +5,6 @@
 //! Nostr transports
 
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+mod happy_eyeballs;
 pub mod websocket;
@@ -5,15 +5,21 @@
 //! WebSocket transport
 
 use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
 use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(not(target_arch = "wasm32"))]
+use async_utility::time;
 use async_wsocket::futures_util::{Sink, SinkExt, Stream, StreamExt, TryStreamExt};
 use async_wsocket::{ConnectionMode, Message, WebSocket};
 use nostr::util::BoxedFuture;
 use nostr::Url;
 
 use super::error::TransportError;
+#[cfg(not(target_arch = "wasm32"))]
+use super::happy_eyeballs::{self, CONNECTION_ATTEMPT_DELAY};
 
 /// WebSocket transport sink
 #[cfg(not(target_arch = "wasm32"))]
@@ -63,6 +69,9 @@ pub trait WebSocketTransport: fmt::Debug + Send + Sync {
     fn support_ping(&self) -> bool;
 
     /// Connect
+    ///
+    /// The implementation is in charge of the DNS resolution and of the TCP connection:
+    /// implement a custom transport to use a custom DNS resolver.
     fn connect<'a>(
         &'a self,
         url: &'a Url,
@@ -72,6 +81,9 @@ pub trait WebSocketTransport: fmt::Debug + Send + Sync {
 }
 
 /// Default websocket transport
+///
+/// With [`ConnectionMode::Direct`], the connection attempts to the resolved addresses are raced (happy eyeballs, RFC 8305),
+/// so a broken IPv6 (or IPv4) network doesn't delay the connection.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DefaultWebsocketTransport;
 
@@ -88,6 +100,15 @@ impl WebSocketTransport for DefaultWebsocketTransport {
     ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>> {
         Box::pin(async move {
             // Connect
+            #[cfg(not(target_arch = "wasm32"))]
+            let socket: WebSocket = match mode {
+                ConnectionMode::Direct => connect_direct(url, timeout).await?,
+                mode => WebSocket::connect(url, mode, timeout)
+                    .await
+                    .map_err(TransportError::backend)?,
+            };
+
+            #[cfg(target_arch = "wasm32")]
             let socket: WebSocket = WebSocket::connect(url, mode, timeout)
                 .await
                 .map_err(TransportError::backend)?;
@@ -100,3 +121,40 @@ impl WebSocketTransport for DefaultWebsocketTransport {
         })
     }
 }
+
+/// Race the TCP connections to the resolved addresses, then do the TLS and websocket handshakes on the winner
+///
+/// The handshakes use the original URL, so the TLS SNI and the `Host` header are the ones of the relay host.
+#[cfg(not(target_arch = "wasm32"))]
+async fn connect_direct(url: &Url, timeout: Duration) -> Result<WebSocket, TransportError> {
+    let host: &str = url
+        .host_str()
+        .ok_or_else(|| TransportError::backend(invalid_input("empty host")))?;
+    let host: &str = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    let port: u16 = url
+        .port_or_known_default()
+        .ok_or_else(|| TransportError::backend(invalid_input("invalid port")))?;
+
+    // Use `Box::pin` to avoid a stack overflow on windows targets due to the large future
+    let connect = Box::pin(async {
+        let stream = happy_eyeballs::connect(host, port, CONNECTION_ATTEMPT_DELAY)
+            .await
+            .map_err(TransportError::backend)?;
+        let (socket, _) = tokio_tungstenite::client_async_tls(url.as_str(), stream)
+            .await
+            .map_err(TransportError::backend)?;
+        Ok(WebSocket::Tokio(socket))
+    });
+
+    time::timeout(Some(timeout), connect)
+        .await
+        .ok_or_else(|| TransportError::backend(io::Error::from(io::ErrorKind::TimedOut)))?
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event time-to-live tracker
+
+use std::collections::BTreeSet;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, PoisonError};
+
+use lru::LruCache;
+use nostr::{Event, EventId, Kind, Timestamp};
+
+use crate::pool::options::{EventTtl, EventTtlBasis};
+
+// LruCache pre-allocate, so keep this at a reasonable value.
+// Stored values are the 64-bit hashes of the event IDs.
+const MAX_EXPIRED_CACHE_SIZE: usize = 128_000;
+
+#[derive(Debug)]
+pub(crate) struct EventTtlTracker {
+    pub(crate) opts: EventTtl,
+    /// Hashes of the expired event IDs (only for [`EventTtlBasis::ReceivedAt`])
+    expired: Mutex<LruCache<u64, ()>>,
+    /// Tracked events, ordered by the timestamp from which the TTL is measured
+    tracked: Mutex<BTreeSet<(Timestamp, EventId)>>,
+}
+
+impl EventTtlTracker {
+    pub(crate) fn new(opts: EventTtl) -> Self {
+        let max_expired_cache_size: NonZeroUsize = NonZeroUsize::new(MAX_EXPIRED_CACHE_SIZE)
+            .expect("MAX_EXPIRED_CACHE_SIZE must be greater than 0");
+
+        Self {
+            opts,
+            expired: Mutex::new(LruCache::new(max_expired_cache_size)),
+            tracked: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Events at or before this timestamp are expired
+    #[inline]
+    pub(crate) fn cutoff(&self, now: Timestamp) -> Timestamp {
+        now - self.opts.ttl
+    }
+
+    /// Check if an incoming event is expired
+    ///
+    /// The replaceable and addressable events never expire.
+    pub(crate) fn is_expired(&self, event: &Event, id_hash: u64, now: Timestamp) -> bool {
+        if is_exempt(&event.kind) {
+            return false;
+        }
+
+        match self.opts.basis {
+            EventTtlBasis::CreatedAt => event.created_at <= self.cutoff(now),
+            EventTtlBasis::ReceivedAt => {
+                let expired = self.expired.lock().unwrap_or_else(PoisonError::into_inner);
+                expired.contains(&id_hash)
+            }
+        }
+    }
+
+    /// Track a new stored event, to expire it after the TTL
+    ///
+    /// The replaceable and addressable events are never tracked.
+    pub(crate) fn track(&self, event: &Event, now: Timestamp) {
+        if is_exempt(&event.kind) {
+            return;
+        }
+
+        let timestamp: Timestamp = match self.opts.basis {
+            EventTtlBasis::CreatedAt => event.created_at,
+            EventTtlBasis::ReceivedAt => now,
+        };
+
+        let mut tracked = self.tracked.lock().unwrap_or_else(PoisonError::into_inner);
+        tracked.insert((timestamp, event.id));
+    }
+
+    /// Pop the tracked events expired at the `cutoff`
+    ///
+    /// For [`EventTtlBasis::ReceivedAt`], the popped events are marked as expired.
+    pub(crate) fn pop_expired<F>(&self, cutoff: Timestamp, hash: F) -> Vec<EventId>
+    where
+        F: Fn(&EventId) -> u64,
+    {
+        let mut tracked = self.tracked.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut ids: Vec<EventId> = Vec::new();
+
+        while let Some((timestamp, id)) = tracked.first() {
+            if *timestamp > cutoff {
+                break;
+            }
+
+            ids.push(*id);
+            tracked.pop_first();
+        }
+
+        if let EventTtlBasis::ReceivedAt = self.opts.basis {
+            let mut expired = self.expired.lock().unwrap_or_else(PoisonError::into_inner);
+            for id in ids.iter() {
+                expired.put(hash(id), ());
+            }
+        }
+
+        ids
+    }
+}
+
+/// The replaceable and addressable events are kept until replaced
+#[inline]
+fn is_exempt(kind: &Kind) -> bool {
+    kind.is_replaceable() || kind.is_addressable()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use nostr::{EventBuilder, Keys, Metadata, Tag};
+
+    use super::*;
+
+    #[test]
+    fn test_received_at_ttl() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let hash = |id: &EventId| u64::from_be_bytes(id.as_bytes()[..8].try_into().unwrap());
+
+        let tracker = EventTtlTracker::new(EventTtl::new(Duration::from_secs(60)));
+
+        let now = Timestamp::from(1_700_000_000);
+        tracker.track(&event, now);
+        assert!(!tracker.is_expired(&event, hash(&event.id), now));
+
+        // Not expired yet
+        assert!(tracker
+            .pop_expired(tracker.cutoff(now + 30), hash)
+            .is_empty());
+
+        let later = now + 60;
+        assert_eq!(
+            tracker.pop_expired(tracker.cutoff(later), hash),
+            vec![event.id]
+        );
+        assert!(tracker.is_expired(&event, hash(&event.id), later));
+    }
+
+    #[test]
+    fn test_created_at_ttl() {
+        let keys = Keys::generate();
+        let now = Timestamp::from(1_700_000_000);
+        let event = EventBuilder::text_note("Hello")
+            .custom_created_at(now - 120)
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let tracker = EventTtlTracker::new(
+            EventTtl::new(Duration::from_secs(60)).basis(EventTtlBasis::CreatedAt),
+        );
+        assert!(tracker.is_expired(&event, 0, now));
+        assert!(!tracker.is_expired(&event, 0, now - 90));
+
+        // Only the tracked events are popped
+        assert!(tracker.pop_expired(tracker.cutoff(now), |_| 0).is_empty());
+        tracker.track(&event, now);
+        assert_eq!(
+            tracker.pop_expired(tracker.cutoff(now), |_| 0),
+            vec![event.id]
+        );
+    }
+
+    #[test]
+    fn test_replaceable_and_addressable_never_expire() {
+        let keys = Keys::generate();
+        let now = Timestamp::from(1_700_000_000);
+        let metadata = EventBuilder::metadata(&Metadata::new().name("Alice"))
+            .custom_created_at(now - 120)
+            .sign_with_keys(&keys)
+            .unwrap();
+        let article = EventBuilder::long_form_text_note("Hello")
+            .tag(Tag::identifier("article"))
+            .custom_created_at(now - 120)
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        for basis in [EventTtlBasis::CreatedAt, EventTtlBasis::ReceivedAt] {
+            let tracker = EventTtlTracker::new(EventTtl::new(Duration::from_secs(60)).basis(basis));
+
+            for event in [&metadata, &article] {
+                tracker.track(event, now - 120);
+                assert!(!tracker.is_expired(event, 0, now));
+            }
+
+            assert!(tracker.pop_expired(tracker.cutoff(now), |_| 0).is_empty());
+        }
+    }
+}
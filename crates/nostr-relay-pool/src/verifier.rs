@@ -0,0 +1,177 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event signature verifier
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+#[cfg(not(target_arch = "wasm32"))]
+use nostr::event;
+use nostr::event::Event;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::sync::{mpsc, oneshot};
+
+use crate::pool::options::EventVerificationMode;
+use crate::relay::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+type Job = (Event, oneshot::Sender<(Event, Result<(), event::Error>)>);
+
+/// Pool of threads verifying the event signatures
+///
+/// The threads exit when the last handle is dropped.
+#[derive(Debug)]
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct VerificationPool {
+    sender: mpsc::UnboundedSender<Job>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl VerificationPool {
+    fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            let res = thread::Builder::new()
+                .name(format!("nostr-verifier-{i}"))
+                .spawn(move || loop {
+                    // Keep the lock only while waiting for the next job
+                    let job: Option<Job> = receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .blocking_recv();
+
+                    match job {
+                        Some((event, tx)) => {
+                            let res = event.verify();
+                            let _ = tx.send((event, res));
+                        }
+                        None => break,
+                    }
+                });
+
+            if let Err(e) = res {
+                tracing::error!(error = %e, "Impossible to spawn verification thread.");
+            }
+        }
+
+        Self { sender }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum EventVerifier {
+    Inline,
+    #[cfg(not(target_arch = "wasm32"))]
+    ThreadPool(Arc<VerificationPool>),
+    Disabled,
+}
+
+impl EventVerifier {
+    pub(crate) fn new(mode: EventVerificationMode) -> Self {
+        match mode {
+            EventVerificationMode::Inline => Self::Inline,
+            #[cfg(not(target_arch = "wasm32"))]
+            EventVerificationMode::ThreadPool(threads) => {
+                Self::ThreadPool(Arc::new(VerificationPool::new(threads)))
+            }
+            // Threads aren't available: fallback to inline verification
+            #[cfg(target_arch = "wasm32")]
+            EventVerificationMode::ThreadPool(..) => Self::Inline,
+            EventVerificationMode::Disabled => Self::Disabled,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_disabled(&self) -> bool {
+        matches!(self, Self::Disabled)
+    }
+
+    /// Verify the event ID and signature, giving back the event
+    ///
+    /// With [`EventVerifier::ThreadPool`], the caller still waits for the verification of the event:
+    /// the verification doesn't block the async runtime, but the events of the same caller aren't pipelined.
+    pub(crate) async fn verify(&self, event: Event) -> Result<Event, Error> {
+        match self {
+            Self::Inline => {
+                event.verify()?;
+                Ok(event)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::ThreadPool(pool) => {
+                let (tx, rx) = oneshot::channel();
+
+                // If the threads are gone, verify inline
+                if let Err(mpsc::error::SendError((event, ..))) = pool.sender.send((event, tx)) {
+                    event.verify()?;
+                    return Ok(event);
+                }
+
+                // The job is always completed, unless the thread panicked
+                let (event, res) = rx.await.map_err(|_| Error::VerificationFailed)?;
+                res?;
+                Ok(event)
+            }
+            Self::Disabled => Ok(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    async fn check_verifier(verifier: EventVerifier) {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let verified = verifier.verify(event.clone()).await.unwrap();
+        assert_eq!(verified, event);
+
+        // Tamper the signature of another event
+        let other = EventBuilder::text_note("Other")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let mut invalid = event;
+        invalid.sig = other.sig;
+
+        assert!(matches!(
+            verifier.verify(invalid).await,
+            Err(Error::Event(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_inline_verifier() {
+        check_verifier(EventVerifier::new(EventVerificationMode::Inline)).await;
+    }
+
+    #[tokio::test]
+    async fn test_thread_pool_verifier() {
+        check_verifier(EventVerifier::new(EventVerificationMode::ThreadPool(2))).await;
+    }
+
+    #[tokio::test]
+    async fn test_disabled_verifier() {
+        let verifier = EventVerifier::new(EventVerificationMode::Disabled);
+        assert!(verifier.is_disabled());
+
+        let keys = Keys::generate();
+        let mut event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        event.content = String::from("Tampered");
+
+        assert!(verifier.verify(event).await.is_ok());
+    }
+}
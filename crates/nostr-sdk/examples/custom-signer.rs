@@ -0,0 +1,88 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::borrow::Cow;
+
+use nostr_sdk::prelude::*;
+
+/// Signer that asks for confirmation before signing
+///
+/// An hardware wallet or a remote signer (i.e. NIP-46) can be plugged in the same way.
+#[derive(Debug)]
+struct ConfirmSigner {
+    keys: Keys,
+}
+
+impl NostrSigner for ConfirmSigner {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::Custom(Cow::Borrowed("confirm"))
+    }
+
+    fn get_public_key(&self) -> BoxedFuture<Result<PublicKey, SignerError>> {
+        Box::pin(async { Ok(self.keys.public_key) })
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> BoxedFuture<Result<Event, SignerError>> {
+        Box::pin(async move {
+            println!(
+                "Signing event of kind {}: {}",
+                unsigned.kind, unsigned.content
+            );
+            self.keys.sign_event(unsigned).await
+        })
+    }
+
+    fn nip04_encrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.keys.nip04_encrypt(public_key, content)
+    }
+
+    fn nip04_decrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        encrypted_content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.keys.nip04_decrypt(public_key, encrypted_content)
+    }
+
+    fn nip44_encrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.keys.nip44_encrypt(public_key, content)
+    }
+
+    fn nip44_decrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        payload: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.keys.nip44_decrypt(public_key, payload)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let signer = ConfirmSigner {
+        keys: Keys::generate(),
+    };
+
+    // Any `NostrSigner` can be used, also as `Arc<dyn NostrSigner>`
+    let client = Client::new(signer);
+
+    client.add_relay("wss://relay.damus.io").await?;
+    client.connect().await;
+
+    let builder = EventBuilder::text_note("Hello from a custom signer");
+    let output = client.send_event_builder(builder).await?;
+    println!("Event ID: {}", output.id().to_bech32()?);
+
+    Ok(())
+}
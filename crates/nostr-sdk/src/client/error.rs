@@ -4,6 +4,8 @@
 
 use std::fmt;
 
+#[cfg(not(target_arch = "wasm32"))]
+use async_utility::tokio::task::JoinError;
 use nostr::prelude::*;
 use nostr::serde_json;
 use nostr_database::prelude::*;
@@ -27,6 +29,12 @@ pub enum Error {
     Json(serde_json::Error),
     /// Shared state error
     SharedState(SharedStateError),
+    /// Thread error
+    #[cfg(not(target_arch = "wasm32"))]
+    Thread(JoinError),
+    /// NIP05
+    #[cfg(feature = "nip05")]
+    NIP05(nip05::Error),
     /// NIP59
     #[cfg(feature = "nip59")]
     NIP59(nip59::Error),
@@ -52,6 +60,10 @@ impl fmt::Display for Error {
             Self::EventBuilder(e) => write!(f, "{e}"),
             Self::Json(e) => write!(f, "{e}"),
             Self::SharedState(e) => write!(f, "{e}"),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Thread(e) => write!(f, "{e}"),
+            #[cfg(feature = "nip05")]
+            Self::NIP05(e) => write!(f, "{e}"),
             #[cfg(feature = "nip59")]
             Self::NIP59(e) => write!(f, "{e}"),
             Self::EventNotFound(id) => {
@@ -110,6 +122,20 @@ impl From<SharedStateError> for Error {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl From<JoinError> for Error {
+    fn from(e: JoinError) -> Self {
+        Self::Thread(e)
+    }
+}
+
+#[cfg(feature = "nip05")]
+impl From<nip05::Error> for Error {
+    fn from(e: nip05::Error) -> Self {
+        Self::NIP05(e)
+    }
+}
+
 #[cfg(feature = "nip59")]
 impl From<nip59::Error> for Error {
     fn from(e: nip59::Error) -> Self {
@@ -7,13 +7,17 @@
 use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::iter;
+#[cfg(feature = "nip05")]
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::time::Duration;
 
+use async_utility::task;
 use nostr::prelude::*;
 use nostr_database::prelude::*;
 use nostr_relay_pool::prelude::*;
-use tokio::sync::broadcast;
+use tokio::sync::mpsc;
 
 pub mod builder;
 mod error;
@@ -84,7 +88,7 @@ impl Client {
             websocket_transport: builder.websocket_transport,
             admit_policy: builder.admit_policy,
             monitor: builder.monitor,
-            opts: builder.opts.pool,
+            opts: builder.opts.pool.clone(),
             __database: builder.database,
             __signer: builder.signer,
         };
@@ -100,13 +104,12 @@ impl Client {
     /// Update minimum POW difficulty for received events
     ///
     /// Events with a POW lower than the current value will be ignored to prevent resources exhaustion.
-    #[deprecated(
-        since = "0.40.0",
-        note = "This no longer works, please use `AdmitPolicy` instead."
-    )]
-    pub fn update_min_pow_difficulty(&self, _difficulty: u8) {}
+    #[inline]
+    pub fn update_min_pow_difficulty(&self, difficulty: u8) {
+        self.pool.state().set_pow(difficulty);
+    }
 
-    /// Auto authenticate to relays (default: true)
+    /// Auto authenticate to relays (default: false)
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
     #[inline]
@@ -131,6 +134,12 @@ impl Client {
     }
 
     /// Set nostr signer
+    ///
+    /// Replace the current signer, if any, to switch account without rebuilding the client.
+    /// Relays and subscriptions are kept: only the new signing operations and the next
+    /// NIP-42 authentications use the new signer.
+    ///
+    /// Use [`Client::reset`] to also clear the relays and the subscriptions.
     #[inline]
     pub async fn set_signer<T>(&self, signer: T)
     where
@@ -157,6 +166,14 @@ impl Client {
         self.pool.database()
     }
 
+    /// Drop the expired events from the dedup cache and, if enabled, from the database
+    ///
+    /// Check [`Options::event_ttl`] to learn more.
+    #[inline]
+    pub async fn prune_expired_events(&self) -> Result<(), Error> {
+        Ok(self.pool.prune_expired_events().await?)
+    }
+
     /// Get the relay monitor
     #[inline]
     pub fn monitor(&self) -> Option<&Monitor> {
@@ -188,14 +205,31 @@ impl Client {
         self.pool.shutdown().await
     }
 
+    /// Gracefully shutdown client
+    ///
+    /// Wait up to `timeout` for the in-flight events to be sent, then shutdown.
+    /// Check [`RelayPool::shutdown_graceful`] to learn more.
+    #[inline]
+    pub async fn shutdown_graceful(&self, timeout: Duration) {
+        self.pool.shutdown_graceful(timeout).await
+    }
+
     /// Get new notification listener
     ///
     /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the channel!</div>
     #[inline]
-    pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
+    pub fn notifications(&self) -> NotificationReceiver {
         self.pool.notifications()
     }
 
+    /// Get new notifications stream
+    ///
+    /// Check [`RelayPool::notifications_stream`] to learn more.
+    #[inline]
+    pub fn notifications_stream(&self) -> ReceiverStream<RelayPoolNotification> {
+        self.pool.notifications_stream()
+    }
+
     /// Get relays with [`RelayServiceFlags::READ`] or [`RelayServiceFlags::WRITE`] flags
     ///
     /// Call [`RelayPool::all_relays`] to get all relays
@@ -245,9 +279,23 @@ impl Client {
             },
         };
 
+        // Set HTTP proxy
+        #[cfg(not(target_arch = "wasm32"))]
+        let opts: RelayOptions = opts.http_proxy(self.opts.proxy);
+
+        // Set reconnection backoff
+        let opts: RelayOptions = match self.opts.backoff {
+            Some(policy) => opts.reconnect_backoff(policy),
+            None => opts,
+        };
+
         // Set limits
         opts.limits(self.opts.relay_limits.clone())
             .max_avg_latency(self.opts.max_avg_latency)
+            .max_filter_terms(self.opts.max_filter_terms)
+            .default_filter_limit(self.opts.default_filter_limit)
+            .connection_timeout(self.opts.connection_timeout)
+            .verify_subscriptions(self.opts.verify_subscriptions)
     }
 
     /// If return `false` means that already existed
@@ -505,6 +553,12 @@ impl Client {
         self.pool.subscriptions().await
     }
 
+    /// Get the number of pool subscriptions
+    #[inline]
+    pub async fn subscription_count(&self) -> usize {
+        self.pool.subscription_count().await
+    }
+
     /// Get pool subscription
     #[inline]
     pub async fn subscription(&self, id: &SubscriptionId) -> Option<Filter> {
@@ -657,6 +711,42 @@ impl Client {
         Ok(self.pool.subscribe_targeted(id, targets, opts).await?)
     }
 
+    /// Subscribe to filters, streaming the events
+    ///
+    /// The subscription is closed when the stream is dropped.
+    /// The events are requested only to the relays with `READ` flag, also if `gossip` is enabled.
+    ///
+    /// Check [`RelayPool::subscribe_stream`] to learn more.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use nostr_sdk::prelude::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// #   let client = Client::default();
+    /// let filter = Filter::new().kind(Kind::TextNote).limit(10);
+    /// let opts = SubscribeOptions::default().eose_quorum(EoseQuorum::All, Duration::from_secs(10));
+    /// let mut stream = client.subscribe_stream(filter, opts).await?;
+    ///
+    /// while let Some(item) = stream.next().await {
+    ///     match item {
+    ///         SubscriptionStreamItem::Event { event, .. } => println!("{}", event.as_json()),
+    ///         SubscriptionStreamItem::Eose => println!("History loaded"),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn subscribe_stream(
+        &self,
+        filter: Filter,
+        opts: SubscribeOptions,
+    ) -> Result<SubscriptionStream, Error> {
+        Ok(self.pool.subscribe_stream(filter, opts).await?)
+    }
+
     /// Unsubscribe
     #[inline]
     pub async fn unsubscribe(&self, id: &SubscriptionId) {
@@ -664,6 +754,8 @@ impl Client {
     }
 
     /// Unsubscribe from all subscriptions
+    ///
+    /// Send `CLOSE` to all relays for every subscription and clear the saved subscriptions.
     #[inline]
     pub async fn unsubscribe_all(&self) {
         self.pool.unsubscribe_all().await;
@@ -671,10 +763,32 @@ impl Client {
 
     /// Sync events with relays (negentropy reconciliation)
     ///
+    /// Only the events matching the filter are reconciled, so a subset of the events (i.e., by kind, author or time) can be synced.
+    /// The direction is set with [`SyncOptions::direction`].
+    ///
     /// If `gossip` is enabled (see [`Options::gossip`]) the events will be reconciled also from
     /// NIP65 relays (automatically discovered) of public keys included in filters (if any).
     ///
     /// <https://github.com/hoytech/negentropy>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::prelude::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// #   let client = Client::default();
+    /// #   let public_key = Keys::generate().public_key();
+    /// // Sync only the gift wraps (i.e., NIP-17 DMs) of the user, in both directions
+    /// let filter = Filter::new().kind(Kind::GiftWrap).pubkey(public_key);
+    /// let opts = SyncOptions::default().direction(SyncDirection::Both);
+    /// let output = client.sync(filter, &opts).await?;
+    ///
+    /// println!("Sent: {:?}", output.sent);
+    /// println!("Received: {:?}", output.received);
+    /// println!("Send failures: {:?}", output.send_failures);
+    /// # Ok(())
+    /// # }
+    /// ```
     #[inline]
     pub async fn sync(
         &self,
@@ -749,6 +863,55 @@ impl Client {
             .await?)
     }
 
+    /// Fetch events, keeping only the newest version of replaceable and addressable events
+    ///
+    /// Relays may return different versions of the same replaceable event:
+    /// this method collapses them, as described in [`Events::dedup_replaceable`].
+    ///
+    /// Check [`Client::fetch_events`] to learn more.
+    #[inline]
+    pub async fn fetch_latest_events(
+        &self,
+        filter: Filter,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        let events: Events = self.fetch_events(filter, timeout).await?;
+        Ok(events.dedup_replaceable())
+    }
+
+    /// Backfill events, splitting the time range in buckets
+    ///
+    /// The buckets are fetched sequentially, from the newest to the oldest (see [`Filter::split_by_time`]),
+    /// so the relays aren't asked for a huge time window at once.
+    /// The events of each bucket are yielded as soon as fetched: drop the stream to stop the backfill.
+    /// If a bucket fetch fails, the error is yielded and the backfill continues with the next bucket.
+    ///
+    /// Each bucket is fetched with [`Client::fetch_events`], so the gossip is used if enabled.
+    pub fn backfill_events(
+        &self,
+        filter: Filter,
+        range: RangeInclusive<Timestamp>,
+        bucket: Duration,
+        timeout: Duration,
+    ) -> ReceiverStream<Result<Events, Error>> {
+        // Fetch the next bucket only when the previous one has been consumed
+        let (tx, rx) = mpsc::channel(1);
+
+        let client: Client = self.clone();
+        task::spawn(async move {
+            for filter in filter.split_by_time(range, bucket).into_iter() {
+                let res: Result<Events, Error> = client.fetch_events(filter, timeout).await;
+
+                // Stream dropped: stop the backfill
+                if tx.send(res).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+
     /// Fetch events from specific relays
     ///
     /// # Overview
@@ -957,7 +1120,30 @@ impl Client {
         self.gossip.process_event(event).await;
 
         // Send event using gossip
-        self.gossip_send_event(event, false).await
+        self.gossip_send_event(event, false, None).await
+    }
+
+    /// Send the event to relays, returning as soon as the [`WriteConcern`] is satisfied
+    ///
+    /// Useful to consider an event published only when enough relays accepted it,
+    /// without waiting for the slower ones.
+    ///
+    /// Check [`Client::send_event`] and [`RelayPool::send_event_to_with_concern`] to learn more.
+    pub async fn send_event_with_concern(
+        &self,
+        event: &Event,
+        concern: WriteConcern,
+    ) -> Result<Output<EventId>, Error> {
+        // NOT gossip, send event to all relays
+        if !self.opts.gossip {
+            return Ok(self.pool.send_event_with_concern(event, concern).await?);
+        }
+
+        // Update gossip graph
+        self.gossip.process_event(event).await;
+
+        // Send event using gossip
+        self.gossip_send_event(event, false, Some(concern)).await
     }
 
     /// Send event to specific relays
@@ -986,6 +1172,45 @@ impl Client {
         Ok(self.pool.send_event_to(urls, event).await?)
     }
 
+    /// Send multiple events, as a unit, to specific relays
+    ///
+    /// If `gossip` is enabled (see [`Options::gossip`]), the gossip data will be updated.
+    ///
+    /// Check [`RelayPool::batch_event_to`] to learn more.
+    pub async fn batch_event_to<I, U>(
+        &self,
+        urls: I,
+        events: Vec<Event>,
+        opts: BatchEventOptions,
+    ) -> Result<BatchOutput, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        // If gossip is enabled, update the gossip graph
+        if self.opts.gossip {
+            for event in events.iter() {
+                self.gossip.process_event(event).await;
+            }
+        }
+
+        // Send events to relays
+        Ok(self.pool.batch_event_to(urls, events, opts).await?)
+    }
+
+    /// Send event to the best `n` relays with `WRITE` flag
+    ///
+    /// Check [`RelayPool::send_event_to_n`] to learn more.
+    pub async fn send_event_to_n(&self, event: &Event, n: usize) -> Result<Output<EventId>, Error> {
+        // If gossip is enabled, update the gossip graph
+        if self.opts.gossip {
+            self.gossip.process_event(event).await;
+        }
+
+        Ok(self.pool.send_event_to_n(event, n).await?)
+    }
+
     /// Build, sign and return [`Event`]
     ///
     /// This method requires a [`NostrSigner`].
@@ -994,6 +1219,45 @@ impl Client {
         Ok(builder.sign(&signer).await?)
     }
 
+    /// Build, mine POW and sign [`Event`], within a `deadline`
+    ///
+    /// Mine using all the cores until `difficulty` is reached or `deadline` expires:
+    /// in the latter case, the event with the highest difficulty found is returned.
+    /// The achieved difficulty can be checked with [`nip13::get_leading_zero_bits`] on the event ID.
+    ///
+    /// The [`EventBuilder::pow`] difficulty, if any, is ignored.
+    ///
+    /// This method requires a [`NostrSigner`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn mine_pow(
+        &self,
+        mut builder: EventBuilder,
+        difficulty: u8,
+        deadline: Duration,
+    ) -> Result<Event, Error> {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+
+        // Avoid mining without deadline when building
+        builder.pow = None;
+        let unsigned: UnsignedEvent = builder.build(public_key);
+
+        // Mine in a blocking task, to not block the runtime
+        let unsigned: UnsignedEvent =
+            task::spawn_blocking(move || nip13::mine(unsigned, difficulty, deadline)).await?;
+
+        let event: Event = signer.sign_event(unsigned).await?;
+
+        tracing::debug!(
+            id = %event.id,
+            difficulty = nip13::get_leading_zero_bits(event.id.as_bytes()),
+            target = difficulty,
+            "Mined POW."
+        );
+
+        Ok(event)
+    }
+
     /// Take an [`EventBuilder`], sign it by using the [`NostrSigner`] and broadcast to relays.
     ///
     /// This method requires a [`NostrSigner`].
@@ -1030,7 +1294,12 @@ impl Client {
 
     /// Fetch the newest public key metadata from relays.
     ///
-    /// Returns [`None`] if the [`Metadata`] of the  [`PublicKey`] has not been found.
+    /// The `kind:0` events received from all the `READ` relays are deduplicated
+    /// and only the newest one (by `created_at`) is parsed.
+    /// The `timeout` is applied to every relay, so the method returns
+    /// even if some relays never reply.
+    ///
+    /// Returns [`None`] if the [`Metadata`] of the [`PublicKey`] has not been found.
     ///
     /// Check [`Client::fetch_events`] for more details.
     ///
@@ -1054,6 +1323,46 @@ impl Client {
         }
     }
 
+    /// Verify NIP-05 identifier
+    ///
+    /// Fetch the `.well-known/nostr.json` of the identifier domain and check if the name maps to the [`PublicKey`].
+    /// Use [`Client::fetch_nip05_profile`] to get also the relay hints.
+    ///
+    /// If a proxy is set in [`Options::proxy`] or [`Options::connection`], the request is routed through it.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+    #[inline]
+    #[cfg(feature = "nip05")]
+    pub async fn verify_nip05(&self, public_key: &PublicKey, nip05: &str) -> Result<bool, Error> {
+        Ok(nip05::verify(public_key, nip05, self.proxy()).await?)
+    }
+
+    /// Fetch NIP-05 profile (public key and relay hints)
+    ///
+    /// If a proxy is set in [`Options::proxy`] or [`Options::connection`], the request is routed through it.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+    #[inline]
+    #[cfg(feature = "nip05")]
+    pub async fn fetch_nip05_profile(&self, nip05: &str) -> Result<Nip05Profile, Error> {
+        Ok(nip05::profile(nip05, self.proxy()).await?)
+    }
+
+    #[cfg(feature = "nip05")]
+    fn proxy(&self) -> Option<SocketAddr> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(addr) = self.opts.proxy {
+            return Some(addr);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let ConnectionMode::Proxy(addr) = self.opts.connection.mode {
+            return Some(addr);
+        }
+
+        None
+    }
+
     /// Update metadata
     ///
     /// This method requires a [`NostrSigner`].
@@ -1159,7 +1468,7 @@ impl Client {
         let filter: Filter = Filter::new().authors(public_keys).kind(Kind::Metadata);
         let events: Events = self.fetch_events(filter, timeout).await?;
         for event in events.into_iter() {
-            let metadata = Metadata::from_json(&event.content)?;
+            let metadata = Metadata::try_from(&event)?;
             if let Some(m) = contacts.get_mut(&event.pubkey) {
                 *m = metadata
             };
@@ -1202,7 +1511,7 @@ impl Client {
             return self.send_event(&event).await;
         }
 
-        self.gossip_send_event(&event, true).await
+        self.gossip_send_event(&event, true, None).await
     }
 
     /// Send a private direct message to specific relays
@@ -1377,19 +1686,23 @@ impl Client {
         self.check_and_update_gossip(public_keys).await?;
 
         // Broken-down filters
-        let filters: HashMap<RelayUrl, Filter> = match self.gossip.break_down_filter(filter).await {
-            BrokenDownFilters::Filters(filters) => filters,
-            BrokenDownFilters::Orphan(filter) | BrokenDownFilters::Other(filter) => {
-                // Get read relays
-                let read_relays: Vec<RelayUrl> = self.pool.__read_relay_urls().await;
-
-                let mut map = HashMap::with_capacity(read_relays.len());
-                for url in read_relays.into_iter() {
-                    map.insert(url, filter.clone());
+        let mut filters: HashMap<RelayUrl, Filter> =
+            match self.gossip.break_down_filter(filter).await {
+                BrokenDownFilters::Filters(filters) => filters,
+                BrokenDownFilters::Orphan(filter) | BrokenDownFilters::Other(filter) => {
+                    // Get read relays
+                    let read_relays: Vec<RelayUrl> = self.pool.__read_relay_urls().await;
+
+                    let mut map = HashMap::with_capacity(read_relays.len());
+                    for url in read_relays.into_iter() {
+                        map.insert(url, filter.clone());
+                    }
+                    map
                 }
-                map
-            }
-        };
+            };
+
+        // Skip the relays not allowed by the pool
+        filters.retain(|url, _| self.pool.is_relay_allowed(url));
 
         // Add gossip (outbox and inbox) relays
         for url in filters.keys() {
@@ -1411,6 +1724,7 @@ impl Client {
         &self,
         event: &Event,
         is_nip17: bool,
+        concern: Option<WriteConcern>,
     ) -> Result<Output<EventId>, Error> {
         let is_gift_wrap: bool = event.kind == Kind::GiftWrap;
 
@@ -1433,11 +1747,14 @@ impl Client {
         let urls: HashSet<RelayUrl> = if is_nip17 && is_gift_wrap {
             // Get NIP17 relays
             // Get only for relays for p tags since gift wraps are signed with random key (random author)
-            let relays = self
+            let mut relays = self
                 .gossip
                 .get_nip17_inbox_relays(event.tags.public_keys())
                 .await;
 
+            // Skip the relays not allowed by the pool
+            relays.retain(|url| self.pool.is_relay_allowed(url));
+
             // Clients SHOULD publish kind 14 events to the 10050-listed relays.
             // If that is not found, that indicates the user is not ready to receive messages under this NIP and clients shouldn't try.
             //
@@ -1457,11 +1774,15 @@ impl Client {
         } else {
             // Get NIP65 relays
             let mut outbox = self.gossip.get_nip65_outbox_relays(&[event.pubkey]).await;
-            let inbox = self
+            let mut inbox = self
                 .gossip
                 .get_nip65_inbox_relays(event.tags.public_keys())
                 .await;
 
+            // Skip the relays not allowed by the pool
+            outbox.retain(|url| self.pool.is_relay_allowed(url));
+            inbox.retain(|url| self.pool.is_relay_allowed(url));
+
             // Add outbox and inbox relays
             for url in outbox.iter().chain(inbox.iter()) {
                 if self.add_gossip_relay(url).await? {
@@ -1483,7 +1804,13 @@ impl Client {
         };
 
         // Send event
-        Ok(self.pool.send_event_to(urls, event).await?)
+        match concern {
+            Some(concern) => Ok(self
+                .pool
+                .send_event_to_with_concern(urls, event, concern)
+                .await?),
+            None => Ok(self.pool.send_event_to(urls, event).await?),
+        }
     }
 
     async fn gossip_stream_events(
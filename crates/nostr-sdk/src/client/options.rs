@@ -19,8 +19,15 @@ pub struct Options {
     pub(super) gossip: bool,
     #[cfg(not(target_arch = "wasm32"))]
     pub(super) connection: Connection,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) proxy: Option<SocketAddr>,
     pub(super) relay_limits: RelayLimits,
     pub(super) max_avg_latency: Option<Duration>,
+    pub(super) max_filter_terms: Option<usize>,
+    pub(super) default_filter_limit: Option<usize>,
+    pub(super) connection_timeout: Option<Duration>,
+    pub(super) verify_subscriptions: bool,
+    pub(super) backoff: Option<BackoffPolicy>,
     pub(super) pool: RelayPoolOptions,
 }
 
@@ -41,21 +48,28 @@ impl Options {
     }
 
     /// Minimum POW difficulty for received events (default: 0)
-    #[deprecated(
-        since = "0.40.0",
-        note = "This no longer works, please use `AdmitPolicy` instead."
-    )]
-    pub fn min_pow(self, _difficulty: u8) -> Self {
+    ///
+    /// Check [`RelayPoolOptions::min_pow`] to learn more.
+    #[inline]
+    pub fn min_pow(mut self, difficulty: u8) -> Self {
+        self.pool = self.pool.min_pow(difficulty);
         self
     }
 
     /// REQ filters chunk size (default: 10)
-    #[deprecated(since = "0.39.0")]
+    ///
+    /// No longer used: every REQ carries a single filter, so there is nothing to chunk.
+    /// The `max_filters` and `max_subscriptions` limits advertised by every relay (NIP-11)
+    /// are already checked per relay, before sending the REQ.
+    #[deprecated(since = "0.39.0", note = "Every REQ carries a single filter")]
     pub fn req_filters_chunk_size(self, _size: u8) -> Self {
         self
     }
 
-    /// Auto authenticate to relays (default: true)
+    /// Auto authenticate to relays (default: false)
+    ///
+    /// When enabled and a signer is set, the client automatically replies to relay `AUTH` challenges.
+    /// If the authentication fails, a [`RelayPoolNotification::AuthenticationFailed`] is sent.
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
     #[inline]
@@ -64,6 +78,53 @@ impl Options {
         self
     }
 
+    /// Size of the cache used to deduplicate event notifications across relays (default: 0)
+    ///
+    /// Check [`RelayPoolOptions::dedup_cache_size`] to learn more.
+    #[inline]
+    pub fn dedup_cache_size(mut self, size: usize) -> Self {
+        self.pool = self.pool.dedup_cache_size(size);
+        self
+    }
+
+    /// Notification channel overflow policy (default: [`NotificationOverflowPolicy::DropOldest`])
+    ///
+    /// Check [`NotificationOverflowPolicy`] to learn more.
+    #[inline]
+    pub fn notification_overflow(mut self, policy: NotificationOverflowPolicy) -> Self {
+        self.pool = self.pool.notification_overflow(policy);
+        self
+    }
+
+    /// Event signature verification mode (default: [`EventVerificationMode::Inline`])
+    ///
+    /// Check [`EventVerificationMode`] to learn more.
+    #[inline]
+    pub fn verification_mode(mut self, mode: EventVerificationMode) -> Self {
+        self.pool = self.pool.verification_mode(mode);
+        self
+    }
+
+    /// Filter of the relays that can be added (default: None)
+    ///
+    /// In gossip mode, the disallowed relays found in the relay lists are skipped.
+    ///
+    /// Check [`RelayPoolOptions::relay_filter`] to learn more.
+    #[inline]
+    pub fn relay_filter(mut self, filter: RelayFilter) -> Self {
+        self.pool = self.pool.relay_filter(filter);
+        self
+    }
+
+    /// Expire the received events after a TTL (default: None)
+    ///
+    /// Check [`RelayPoolOptions::event_ttl`] to learn more.
+    #[inline]
+    pub fn event_ttl(mut self, ttl: EventTtl) -> Self {
+        self.pool = self.pool.event_ttl(ttl);
+        self
+    }
+
     /// Enable gossip model (default: false)
     #[inline]
     pub fn gossip(mut self, enable: bool) -> Self {
@@ -79,6 +140,18 @@ impl Options {
         self
     }
 
+    /// Route all the outbound HTTP requests through a SOCKS5 proxy (default: None)
+    ///
+    /// Covers the NIP-05 requests and the NIP-11 documents fetched by the relays,
+    /// regardless of the [`Connection`] mode and target.
+    /// If not set, the proxy of the [`Connection`] (if any) is used.
+    #[inline]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, addr: SocketAddr) -> Self {
+        self.proxy = Some(addr);
+        self
+    }
+
     /// Set relay limits
     #[inline]
     pub fn relay_limits(mut self, limits: RelayLimits) -> Self {
@@ -86,6 +159,52 @@ impl Options {
         self
     }
 
+    /// Set max size of the events received from relays, in bytes
+    ///
+    /// Larger events are dropped before the signature verification.
+    /// This overwrites the `events.max_size` of the [`Options::relay_limits`].
+    #[inline]
+    pub fn max_event_size(mut self, bytes: usize) -> Self {
+        self.relay_limits.events.max_size = Some(u32::try_from(bytes).unwrap_or(u32::MAX));
+        self
+    }
+
+    /// Set max number of IDs and authors per outgoing filter (default: None)
+    ///
+    /// Check [`RelayOptions::max_filter_terms`] to learn more.
+    #[inline]
+    pub fn max_filter_terms(mut self, n: usize) -> Self {
+        self.max_filter_terms = Some(n);
+        self
+    }
+
+    /// Set the `limit` of the outgoing filters that don't specify one (default: None)
+    ///
+    /// Check [`RelayOptions::default_filter_limit`] to learn more.
+    #[inline]
+    pub fn default_filter_limit(mut self, limit: usize) -> Self {
+        self.default_filter_limit = Some(limit);
+        self
+    }
+
+    /// Set timeout for relays connection attempts (default: 60 secs)
+    ///
+    /// Check [`RelayOptions::connection_timeout`] to learn more.
+    #[inline]
+    pub fn connection_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connection_timeout = timeout;
+        self
+    }
+
+    /// Verify that the received events match the subscription filter (default: false)
+    ///
+    /// Check [`RelayOptions::verify_subscriptions`] to learn more.
+    #[inline]
+    pub fn verify_subscriptions(mut self, enable: bool) -> Self {
+        self.verify_subscriptions = enable;
+        self
+    }
+
     /// Set max latency (default: None)
     ///
     /// Relays with an avg. latency greater that this value will be skipped.
@@ -95,6 +214,15 @@ impl Options {
         self
     }
 
+    /// Set relays reconnection backoff policy (default: None)
+    ///
+    /// Check [`RelayOptions::reconnect_backoff`] to learn more.
+    #[inline]
+    pub fn reconnect_backoff(mut self, policy: BackoffPolicy) -> Self {
+        self.backoff = Some(policy);
+        self
+    }
+
     /// Notification channel size (default: [`DEFAULT_NOTIFICATION_CHANNEL_SIZE`])
     #[deprecated(since = "0.42.0", note = "Use `Options::pool` instead.")]
     pub fn notification_channel_size(mut self, size: usize) -> Self {
@@ -8,6 +8,7 @@ use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::fmt;
 use core::ops::Range;
+use core::time::Duration;
 
 #[cfg(feature = "std")]
 use secp256k1::rand::rngs::OsRng;
@@ -158,6 +159,10 @@ pub struct EventBuilder {
     pub custom_created_at: Option<Timestamp>,
     /// POW difficulty
     pub pow: Option<u8>,
+    /// POW mining deadline
+    ///
+    /// For more details check [`EventBuilder::pow_deadline`].
+    pub pow_deadline: Option<Duration>,
     /// Allow self-tagging
     ///
     /// If enabled, the `p` tags that match the signing keypair will not be discarded.
@@ -181,6 +186,7 @@ impl EventBuilder {
             content: content.into(),
             custom_created_at: None,
             pow: None,
+            pow_deadline: None,
             allow_self_tagging: false,
             dedup_tags: false,
         }
@@ -223,6 +229,18 @@ impl EventBuilder {
         self
     }
 
+    /// Set POW mining deadline (default: none)
+    ///
+    /// If the [`EventBuilder::pow`] difficulty isn't reached before the deadline,
+    /// the event is built without the `nonce` tag, instead of mining forever.
+    ///
+    /// To get the best difficulty reached before a deadline, using all the cores, check [`nip13::mine`](crate::nips::nip13::mine).
+    #[inline]
+    pub fn pow_deadline(mut self, deadline: Duration) -> Self {
+        self.pow_deadline = Some(deadline);
+        self
+    }
+
     /// Allow self-tagging
     ///
     /// When this mode is enabled, any `p` tags referencing the author’s public key will not be discarded.
@@ -269,56 +287,64 @@ impl EventBuilder {
         }
 
         // Check if should be POW
-        match self.pow {
-            Some(difficulty) if difficulty > 0 => {
-                let mut nonce: u128 = 0;
-
-                loop {
-                    nonce += 1;
-
-                    self.tags.push(Tag::pow(nonce, difficulty));
-
-                    let created_at: Timestamp = self
-                        .custom_created_at
-                        .unwrap_or_else(|| Timestamp::now_with_supplier(supplier));
-                    let id: EventId = EventId::new(
-                        &public_key,
-                        &created_at,
-                        &self.kind,
-                        &self.tags,
-                        &self.content,
-                    );
-
-                    if id.check_pow(difficulty) {
-                        return UnsignedEvent {
-                            id: Some(id),
-                            pubkey: public_key,
-                            created_at,
-                            kind: self.kind,
-                            tags: self.tags,
-                            content: self.content,
-                        };
+        if let Some(difficulty) = self.pow.filter(|d| *d > 0) {
+            let started: T::Now = supplier.instant_now();
+            let mut nonce: u128 = 0;
+
+            loop {
+                nonce += 1;
+
+                // Check the deadline every 1024 attempts, to not slow down the mining
+                if let Some(deadline) = self.pow_deadline {
+                    if nonce % 1024 == 0
+                        && supplier.elapsed_instant_since(supplier.instant_now(), started.clone())
+                            >= deadline
+                    {
+                        break;
                     }
+                }
 
-                    self.tags.pop();
+                self.tags.push(Tag::pow(nonce, difficulty));
+
+                let created_at: Timestamp = self
+                    .custom_created_at
+                    .unwrap_or_else(|| Timestamp::now_with_supplier(supplier));
+                let id: EventId = EventId::new(
+                    &public_key,
+                    &created_at,
+                    &self.kind,
+                    &self.tags,
+                    &self.content,
+                );
+
+                if id.check_pow(difficulty) {
+                    return UnsignedEvent {
+                        id: Some(id),
+                        pubkey: public_key,
+                        created_at,
+                        kind: self.kind,
+                        tags: self.tags,
+                        content: self.content,
+                    };
                 }
-            }
-            // No POW difficulty set OR difficulty == 0
-            _ => {
-                let mut unsigned: UnsignedEvent = UnsignedEvent {
-                    id: None,
-                    pubkey: public_key,
-                    created_at: self
-                        .custom_created_at
-                        .unwrap_or_else(|| Timestamp::now_with_supplier(supplier)),
-                    kind: self.kind,
-                    tags: self.tags,
-                    content: self.content,
-                };
-                unsigned.ensure_id();
-                unsigned
+
+                self.tags.pop();
             }
         }
+
+        // No POW or deadline expired
+        let mut unsigned: UnsignedEvent = UnsignedEvent {
+            id: None,
+            pubkey: public_key,
+            created_at: self
+                .custom_created_at
+                .unwrap_or_else(|| Timestamp::now_with_supplier(supplier)),
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+        };
+        unsigned.ensure_id();
+        unsigned
     }
 
     /// Build an unsigned event
@@ -378,6 +404,8 @@ impl EventBuilder {
 
     /// Profile metadata
     ///
+    /// The external identities (NIP39) are added as `i` tags.
+    ///
     /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
     ///
     /// # Example
@@ -396,7 +424,10 @@ impl EventBuilder {
     /// ```
     #[inline]
     pub fn metadata(metadata: &Metadata) -> Self {
-        Self::new(Kind::Metadata, metadata.as_json())
+        let tags = metadata.identities.iter().map(|identity| {
+            Tag::from_standardized_without_cell(TagStandard::ExternalIdentity(identity.clone()))
+        });
+        Self::new(Kind::Metadata, metadata.as_json()).tags(tags)
     }
 
     /// Relay list metadata
@@ -779,6 +810,35 @@ impl EventBuilder {
         Self::new(Kind::Reaction, reaction).tags(tags)
     }
 
+    /// Add custom emoji reaction to an event
+    ///
+    /// The content is set to `:<shortcode>:` and the emoji image is referenced by an `emoji` tag.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/25.md>
+    /// <https://github.com/nostr-protocol/nips/blob/master/30.md>
+    pub fn emoji_reaction<S>(event: &Event, shortcode: S, url: Url) -> Self
+    where
+        S: Into<String>,
+    {
+        let shortcode: String = shortcode.into();
+        Self::reaction(event, format!(":{shortcode}:")).tag(Tag::from_standardized_without_cell(
+            TagStandard::Emoji { shortcode, url },
+        ))
+    }
+
+    /// Group chat message
+    ///
+    /// The message must be sent to the relay that hosts the group.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_message<S, T>(group_id: S, content: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        Self::new(Kind::ChatMessage, content).tag(Tag::custom(TagKind::h(), [group_id]))
+    }
+
     /// Create a new channel
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/28.md>
@@ -2051,6 +2111,38 @@ mod tests {
         assert_eq!(profile_badges.tags, example_event.tags);
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_reaction() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let reactor = Keys::generate();
+        let reaction = EventBuilder::reaction(&event, "+")
+            .sign_with_keys(&reactor)
+            .unwrap();
+        assert_eq!(reaction.kind, Kind::Reaction);
+        assert_eq!(reaction.content, "+");
+        assert_eq!(reaction.tags.event_ids().next(), Some(&event.id));
+        assert_eq!(reaction.tags.public_keys().next(), Some(&keys.public_key()));
+
+        let url = Url::parse("https://example.com/soapbox.png").unwrap();
+        let reaction = EventBuilder::emoji_reaction(&event, "soapbox", url.clone())
+            .sign_with_keys(&reactor)
+            .unwrap();
+        assert_eq!(reaction.content, ":soapbox:");
+        let emoji = TagStandard::Emoji {
+            shortcode: String::from("soapbox"),
+            url,
+        };
+        assert!(reaction
+            .tags
+            .iter()
+            .any(|tag| tag.as_standardized() == Some(&emoji)));
+    }
+
     #[test]
     #[cfg(feature = "std")]
     fn test_text_note_reply() {
@@ -2093,6 +2185,28 @@ mod tests {
         assert_eq!(ids.next().unwrap(), reply.id);
         assert_eq!(ids.next().unwrap(), root_event.id);
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pow_deadline() {
+        let keys = Keys::generate();
+
+        let event = EventBuilder::text_note("POW")
+            .pow(8)
+            .pow_deadline(Duration::from_secs(60))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(event.id.check_pow(8));
+
+        // Unreachable difficulty: built without nonce after the deadline
+        let event = EventBuilder::text_note("POW")
+            .pow(255)
+            .pow_deadline(Duration::from_millis(100))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(event.tags.find(TagKind::Nonce).is_none());
+        assert!(event.verify().is_ok());
+    }
 }
 
 #[cfg(bench)]
@@ -159,6 +159,11 @@ kind_variants! {
     CodeSnippet => 1337, "Code Snippets", "<https://github.com/nostr-protocol/nips/blob/master/C0.md>",
     Poll => 1068, "Poll", "<https://github.com/nostr-protocol/nips/blob/master/88.md>",
     PollResponse => 1018, "Poll response", "<https://github.com/nostr-protocol/nips/blob/master/88.md>",
+    ChatMessage => 9, "Chat Message", "<https://github.com/nostr-protocol/nips/blob/master/C7.md>",
+    GroupMetadata => 39000, "Group Metadata", "<https://github.com/nostr-protocol/nips/blob/master/29.md>",
+    GroupAdmins => 39001, "Group Admins", "<https://github.com/nostr-protocol/nips/blob/master/29.md>",
+    GroupMembers => 39002, "Group Members", "<https://github.com/nostr-protocol/nips/blob/master/29.md>",
+    GroupRoles => 39003, "Group Roles", "<https://github.com/nostr-protocol/nips/blob/master/29.md>",
 }
 
 impl PartialEq for Kind {
@@ -298,7 +298,19 @@ impl TryFrom<&Event> for Metadata {
     type Error = serde_json::Error;
 
     fn try_from(event: &Event) -> Result<Self, Self::Error> {
-        Metadata::from_json(&event.content)
+        let mut metadata: Metadata = Metadata::from_json(&event.content)?;
+
+        // Parse the external identities (NIP39)
+        metadata.identities = event
+            .tags
+            .filter_standardized(TagKind::i())
+            .filter_map(|tag| match tag {
+                TagStandard::ExternalIdentity(identity) => Some(identity.clone()),
+                _ => None,
+            })
+            .collect();
+
+        Ok(metadata)
     }
 }
 
@@ -7,9 +7,12 @@
 
 use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt;
 use core::hash::Hash;
+use core::ops::RangeInclusive;
 use core::str::FromStr;
+use core::time::Duration;
 
 use serde::de::{Deserializer, MapAccess, Visitor};
 use serde::ser::{SerializeMap, Serializer};
@@ -743,6 +746,40 @@ impl Filter {
         self
     }
 
+    /// Split the time range in buckets, returning a filter for each of them
+    ///
+    /// Each filter has `since` and `until` set to the bounds (inclusive) of its bucket, overriding the ones of this filter.
+    /// The filters are ordered from the newest to the oldest bucket.
+    /// The last (oldest) bucket may be shorter than `bucket`.
+    ///
+    /// Useful to backfill large time ranges, since the relays often time out on huge REQs.
+    pub fn split_by_time(&self, range: RangeInclusive<Timestamp>, bucket: Duration) -> Vec<Self> {
+        let start: u64 = range.start().as_u64();
+        let bucket: u64 = bucket.as_secs().max(1);
+
+        let mut filters: Vec<Self> = Vec::new();
+        let mut until: u64 = range.end().as_u64();
+
+        while until >= start {
+            let since: u64 = until.saturating_sub(bucket - 1).max(start);
+
+            filters.push(
+                self.clone()
+                    .since(Timestamp::from_secs(since))
+                    .until(Timestamp::from_secs(until)),
+            );
+
+            // Avoid underflow
+            if since == 0 {
+                break;
+            }
+
+            until = since - 1;
+        }
+
+        filters
+    }
+
     /// Check if [`Filter`] is empty
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -1073,6 +1110,47 @@ mod tests {
         assert_eq!(filter, Filter::new().search("test"));
     }
 
+    #[test]
+    fn test_split_by_time() {
+        let filter = Filter::new().kind(Kind::TextNote);
+        let range = Timestamp::from_secs(100)..=Timestamp::from_secs(349);
+
+        let filters = filter.split_by_time(range.clone(), Duration::from_secs(100));
+        assert_eq!(
+            filters,
+            vec![
+                filter
+                    .clone()
+                    .since(Timestamp::from_secs(250))
+                    .until(Timestamp::from_secs(349)),
+                filter
+                    .clone()
+                    .since(Timestamp::from_secs(150))
+                    .until(Timestamp::from_secs(249)),
+                filter
+                    .clone()
+                    .since(Timestamp::from_secs(100))
+                    .until(Timestamp::from_secs(149)),
+            ]
+        );
+
+        // Single bucket
+        let filters = filter.split_by_time(range, Duration::from_secs(1000));
+        assert_eq!(filters.len(), 1);
+
+        // From zero
+        let range = Timestamp::from_secs(0)..=Timestamp::from_secs(9);
+        let filters = filter.split_by_time(range, Duration::from_secs(5));
+        assert_eq!(filters.len(), 2);
+        assert_eq!(filters[1].since, Some(Timestamp::from_secs(0)));
+
+        // Empty range
+        let range = Timestamp::from_secs(10)..=Timestamp::from_secs(9);
+        assert!(filter
+            .split_by_time(range, Duration::from_secs(5))
+            .is_empty());
+    }
+
     #[test]
     fn test_filter_is_empty() {
         let filter = Filter::new().identifier("test");
@@ -28,6 +28,10 @@ pub mod vanity;
 
 pub use self::public_key::PublicKey;
 pub use self::secret_key::SecretKey;
+#[cfg(all(feature = "std", feature = "nip49"))]
+use crate::nips::nip19::{self, FromBech32};
+#[cfg(all(feature = "std", feature = "nip49"))]
+use crate::nips::nip49::EncryptedSecretKey;
 #[cfg(feature = "std")]
 use crate::signer::{NostrSigner, SignerBackend, SignerError};
 use crate::util::hex;
@@ -76,6 +80,8 @@ impl From<hex::Error> for Error {
 }
 
 /// Nostr keys
+///
+/// The secret key and the keypair are erased from memory when dropped.
 #[derive(Clone)]
 pub struct Keys {
     /// Public key
@@ -166,6 +172,21 @@ impl Keys {
         Ok(Self::new_with_ctx(secp, secret_key))
     }
 
+    /// Decrypt a NIP49 encrypted secret key (`ncryptsec`) and construct keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`nip19::Error::NIP49`] with [`WrongPassword`](crate::nips::nip49::Error::WrongPassword)
+    /// if the password is wrong. Any other error means that the `ncryptsec` is malformed.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/49.md>
+    #[cfg(all(feature = "std", feature = "nip49"))]
+    pub fn from_encrypted(ncryptsec: &str, password: &str) -> Result<Self, nip19::Error> {
+        let encrypted: EncryptedSecretKey = EncryptedSecretKey::from_bech32(ncryptsec)?;
+        let secret_key: SecretKey = encrypted.decrypt(password)?;
+        Ok(Self::new(secret_key))
+    }
+
     /// Generate random keys
     ///
     /// This constructor uses a random number generator that retrieves randomness from the operating system (see [`OsRng`]).
@@ -256,6 +277,106 @@ impl Keys {
         let keypair: &Keypair = self.key_pair(secp);
         secp.sign_schnorr_with_rng(message, keypair, rng)
     }
+
+    /// Generate random ephemeral keys
+    ///
+    /// Check [`EphemeralKeys`] to learn more.
+    #[inline]
+    #[cfg(feature = "std")]
+    pub fn generate_ephemeral() -> EphemeralKeys {
+        EphemeralKeys(Self::generate())
+    }
+
+    /// Overwrite the keypair, if constructed
+    ///
+    /// The secret key is overwritten by its own [`Drop`].
+    fn erase_key_pair(&mut self) {
+        if let Some(key_pair) = self.key_pair.get_mut() {
+            key_pair.non_secure_erase();
+        }
+    }
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        self.erase_key_pair();
+    }
+}
+
+/// Ephemeral keys
+///
+/// Like [`Keys`], but can't be cloned and doesn't expose the secret key:
+/// the secret key lives only in this value and is erased from memory when it's dropped.
+///
+/// Sign and encrypt with the [`NostrSigner`] implementation.
+///
+/// Use it for short-lived sessions (i.e., a single NIP-46 connection or a throwaway identity).
+#[cfg(feature = "std")]
+pub struct EphemeralKeys(Keys);
+
+#[cfg(feature = "std")]
+impl fmt::Debug for EphemeralKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EphemeralKeys")
+            .field(&self.0.public_key)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl EphemeralKeys {
+    /// Get public key
+    #[inline]
+    pub fn public_key(&self) -> PublicKey {
+        self.0.public_key()
+    }
+}
+
+#[cfg(feature = "std")]
+impl NostrSigner for EphemeralKeys {
+    fn backend(&self) -> SignerBackend {
+        self.0.backend()
+    }
+
+    fn get_public_key(&self) -> BoxedFuture<Result<PublicKey, SignerError>> {
+        self.0.get_public_key()
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> BoxedFuture<Result<Event, SignerError>> {
+        self.0.sign_event(unsigned)
+    }
+
+    fn nip04_encrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.0.nip04_encrypt(public_key, content)
+    }
+
+    fn nip04_decrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.0.nip04_decrypt(public_key, content)
+    }
+
+    fn nip44_encrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.0.nip44_encrypt(public_key, content)
+    }
+
+    fn nip44_decrypt<'a>(
+        &'a self,
+        public_key: &'a PublicKey,
+        content: &'a str,
+    ) -> BoxedFuture<'a, Result<String, SignerError>> {
+        self.0.nip44_decrypt(public_key, content)
+    }
 }
 
 #[cfg(feature = "std")]
@@ -366,6 +487,29 @@ mod tests {
         "nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99";
     const SECRET_KEY_HEX: &str = "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e";
 
+    #[test]
+    fn test_erase_key_pair() {
+        let mut keys = Keys::parse(SECRET_KEY_HEX).unwrap();
+        let key_pair: Keypair = *keys.key_pair(SECP256K1);
+        assert_eq!(key_pair.secret_bytes(), keys.secret_key().secret_bytes());
+
+        keys.erase_key_pair();
+        assert_ne!(
+            keys.key_pair(SECP256K1).secret_bytes(),
+            key_pair.secret_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_keys() {
+        let keys = Keys::generate_ephemeral();
+        assert_eq!(keys.get_public_key().await.unwrap(), keys.public_key());
+
+        let unsigned = crate::EventBuilder::text_note("Ephemeral").build(keys.public_key());
+        let event = keys.sign_event(unsigned).await.unwrap();
+        assert!(event.verify().is_ok());
+    }
+
     #[test]
     fn parse_keys() -> Result<(), Error> {
         Keys::parse(SECRET_KEY_BECH32)?;
@@ -386,6 +530,28 @@ mod tests {
             Error::InvalidSecretKey
         );
     }
+
+    #[test]
+    #[cfg(feature = "nip49")]
+    fn keys_from_encrypted() {
+        use crate::nips::nip19::ToBech32;
+        use crate::nips::nip49;
+
+        let keys = Keys::parse(SECRET_KEY_HEX).unwrap();
+        let encrypted = keys.secret_key().encrypt("nostr", 8).unwrap();
+        assert_eq!(encrypted.log_n(), 8);
+
+        let ncryptsec: String = encrypted.to_bech32().unwrap();
+        assert_eq!(Keys::from_encrypted(&ncryptsec, "nostr").unwrap(), keys);
+        assert_eq!(
+            Keys::from_encrypted(&ncryptsec, "wrong").unwrap_err(),
+            nip19::Error::NIP49(nip49::Error::WrongPassword)
+        );
+        assert!(!matches!(
+            Keys::from_encrypted("ncryptsec1qgg9947rlpvqu", "nostr").unwrap_err(),
+            nip19::Error::NIP49(nip49::Error::WrongPassword)
+        ));
+    }
 }
 
 #[cfg(bench)]
@@ -131,12 +131,15 @@ impl SecretKey {
 
     /// Encrypt secret key
     ///
-    /// By default, `LOG_N` is set to `16` and [`KeySecurity::Unknown`].
+    /// The `log_n` is the scrypt work factor: [`nip49::DEFAULT_LOG_N`] is a sane default.
+    /// Higher values are more secure but slower (and require more memory) to encrypt and decrypt.
+    ///
+    /// The [`KeySecurity`] is set to [`KeySecurity::Unknown`].
     /// To use custom values check [`EncryptedSecretKey`] constructors.
     #[inline]
     #[cfg(all(feature = "std", feature = "nip49"))]
-    pub fn encrypt(&self, password: &str) -> Result<EncryptedSecretKey, nip49::Error> {
-        EncryptedSecretKey::new(self, password, 16, KeySecurity::Unknown)
+    pub fn encrypt(&self, password: &str, log_n: u8) -> Result<EncryptedSecretKey, nip49::Error> {
+        EncryptedSecretKey::new(self, password, log_n, KeySecurity::Unknown)
     }
 }
 
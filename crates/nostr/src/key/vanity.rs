@@ -8,8 +8,8 @@ use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::fmt;
-use core::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{sync_channel, RecvError};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, sync_channel, RecvError};
 use std::thread;
 
 use secp256k1::rand;
@@ -20,16 +20,33 @@ use crate::nips::nip19::{ToBech32, PREFIX_BECH32_PUBLIC_KEY};
 const BECH32_SPAN: usize = PREFIX_BECH32_PUBLIC_KEY.len() + 1;
 const BECH32_CHARS: &str = "023456789acdefghjklmnpqrstuvwxyz";
 const HEX_CHARS: &str = "0123456789abcdef";
+/// Number of bech32 chars encoding the 32-byte public key
+const BECH32_DATA_LEN: usize = (32 * 8 + 4) / 5;
+/// Number of hex chars encoding the 32-byte public key
+const HEX_DATA_LEN: usize = 32 * 2;
 
 /// [`Keys`] vanity error
 #[derive(Debug, Eq, PartialEq)]
 pub enum Error {
     /// Unsupported char
     InvalidChar(char),
+    /// No prefixes specified
+    NoPrefixes,
+    /// Empty prefix
+    EmptyPrefix,
+    /// Prefix longer than the bech32 encoded public key
+    PrefixTooLong {
+        /// Prefix
+        prefix: String,
+        /// Max length
+        max: usize,
+    },
     /// RecvError
     RecvError(RecvError),
     /// Thread Join failed
     JoinHandleError,
+    /// Search aborted with [`VanityProgress::abort`]
+    Aborted,
 }
 
 impl std::error::Error for Error {}
@@ -38,8 +55,14 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::InvalidChar(c) => write!(f, "Unsupported char: {c}"),
+            Self::NoPrefixes => write!(f, "no prefixes specified"),
+            Self::EmptyPrefix => write!(f, "empty prefix"),
+            Self::PrefixTooLong { prefix, max } => {
+                write!(f, "prefix '{prefix}' too long: max {max} chars")
+            }
             Self::RecvError(e) => write!(f, "{e}"),
             Self::JoinHandleError => write!(f, "impossible to join threads"),
+            Self::Aborted => write!(f, "search aborted"),
         }
     }
 }
@@ -50,6 +73,50 @@ impl From<RecvError> for Error {
     }
 }
 
+/// Format of the vanity prefixes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VanityFormat {
+    /// Prefix of the bech32 public key, after `npub1` (default)
+    #[default]
+    Bech32,
+    /// Prefix of the hex public key
+    Hex,
+}
+
+/// Progress of a vanity search, shared with the mining threads
+#[derive(Debug, Default)]
+pub struct VanityProgress {
+    attempts: AtomicU64,
+    aborted: AtomicBool,
+}
+
+impl VanityProgress {
+    /// New progress
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of keys generated so far
+    #[inline]
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Abort the search
+    ///
+    /// [`Keys::vanity_parallel_with_progress`] stops all the threads and returns [`Error::Aborted`].
+    #[inline]
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Relaxed)
+    }
+}
+
 impl Keys {
     /// check validity of prefix characters
     fn check_prefix_chars(prefixes: &[String], valid_chars: &str) -> Result<(), Error> {
@@ -125,3 +192,228 @@ impl Keys {
         Ok(rx.recv()?)
     }
 }
+
+impl Keys {
+    /// Generate new keys with the bech32 public key (after `npub1`) starting with any of the `prefixes`
+    ///
+    /// The search is split across `num_threads` threads, all stopped as soon as a key is found.
+    /// The prefixes are case-insensitive and may include the `npub1` part.
+    ///
+    /// Every char more in the prefix makes the search ~32 times slower.
+    #[inline]
+    pub fn vanity_parallel(prefixes: &[&str], num_threads: usize) -> Result<Self, Error> {
+        Self::vanity_parallel_with_progress(
+            prefixes,
+            VanityFormat::Bech32,
+            num_threads,
+            Arc::new(VanityProgress::new()),
+        )
+    }
+
+    /// Generate new keys with the public key, in the specified `format`, starting with any of the `prefixes`
+    ///
+    /// Same as [`Keys::vanity_parallel`], but the hex prefixes are supported too
+    /// and the search can be monitored and aborted with the [`VanityProgress`].
+    pub fn vanity_parallel_with_progress(
+        prefixes: &[&str],
+        format: VanityFormat,
+        num_threads: usize,
+        progress: Arc<VanityProgress>,
+    ) -> Result<Self, Error> {
+        if prefixes.is_empty() {
+            return Err(Error::NoPrefixes);
+        }
+
+        let prefixes: Vec<String> = prefixes
+            .iter()
+            .map(|prefix| match format {
+                VanityFormat::Bech32 => Self::parse_bech32_prefix(prefix),
+                VanityFormat::Hex => Self::parse_hex_prefix(prefix),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let (tx, rx) = channel::<Keys>();
+        let found = Arc::new(AtomicBool::new(false));
+        let num_threads: usize = num_threads.max(1);
+        let mut handles = Vec::with_capacity(num_threads);
+
+        for _ in 0..num_threads {
+            let tx = tx.clone();
+            let found = found.clone();
+            let prefixes = prefixes.clone();
+            let progress = progress.clone();
+            let handle = thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                while !found.load(Ordering::Relaxed) && !progress.is_aborted() {
+                    let keys: Keys = Keys::generate_with_rng(&mut rng);
+                    progress.attempts.fetch_add(1, Ordering::Relaxed);
+
+                    let public_key: String = match format {
+                        VanityFormat::Bech32 => {
+                            let mut bech32_key = keys
+                                .public_key
+                                .to_bech32()
+                                .expect("Unable to convert key to bech32");
+                            bech32_key.drain(..BECH32_SPAN);
+                            bech32_key
+                        }
+                        VanityFormat::Hex => keys.public_key.to_hex(),
+                    };
+
+                    if prefixes
+                        .iter()
+                        .any(|prefix| public_key.starts_with(prefix.as_str()))
+                    {
+                        // Only the first found key is sent
+                        if !found.swap(true, Ordering::SeqCst) {
+                            let _ = tx.send(keys);
+                        }
+                        break;
+                    }
+                }
+            });
+            handles.push(handle);
+        }
+
+        // Drop the original sender, so the channel closes if all the threads exit
+        drop(tx);
+
+        let keys: Result<Keys, RecvError> = rx.recv();
+
+        // Stop the other threads
+        found.store(true, Ordering::SeqCst);
+        for handle in handles {
+            handle.join().map_err(|_| Error::JoinHandleError)?;
+        }
+
+        match keys {
+            Ok(keys) => Ok(keys),
+            Err(..) if progress.is_aborted() => Err(Error::Aborted),
+            Err(e) => Err(Error::RecvError(e)),
+        }
+    }
+
+    fn parse_bech32_prefix(prefix: &str) -> Result<String, Error> {
+        let prefix: String = prefix.to_lowercase();
+        let prefix: &str = prefix.strip_prefix("npub1").unwrap_or(&prefix);
+
+        if prefix.is_empty() {
+            return Err(Error::EmptyPrefix);
+        }
+
+        if prefix.len() > BECH32_DATA_LEN {
+            return Err(Error::PrefixTooLong {
+                prefix: prefix.to_string(),
+                max: BECH32_DATA_LEN,
+            });
+        }
+
+        if let Some(c) = prefix.chars().find(|c| !BECH32_CHARS.contains(*c)) {
+            return Err(Error::InvalidChar(c));
+        }
+
+        Ok(prefix.to_string())
+    }
+
+    fn parse_hex_prefix(prefix: &str) -> Result<String, Error> {
+        let prefix: String = prefix.to_lowercase();
+
+        if prefix.is_empty() {
+            return Err(Error::EmptyPrefix);
+        }
+
+        if prefix.len() > HEX_DATA_LEN {
+            return Err(Error::PrefixTooLong {
+                prefix,
+                max: HEX_DATA_LEN,
+            });
+        }
+
+        if let Some(c) = prefix.chars().find(|c| !HEX_CHARS.contains(*c)) {
+            return Err(Error::InvalidChar(c));
+        }
+
+        Ok(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vanity_parallel() {
+        let keys = Keys::vanity_parallel(&["q", "P"], 2).unwrap();
+        let npub: String = keys.public_key.to_bech32().unwrap();
+        assert!(npub.starts_with("npub1q") || npub.starts_with("npub1p"));
+
+        let keys = Keys::vanity_parallel(&["npub1z"], 0).unwrap();
+        assert!(keys.public_key.to_bech32().unwrap().starts_with("npub1z"));
+    }
+
+    #[test]
+    fn test_vanity_parallel_invalid_prefixes() {
+        assert_eq!(
+            Keys::vanity_parallel(&[], 1).unwrap_err(),
+            Error::NoPrefixes
+        );
+        assert_eq!(
+            Keys::vanity_parallel(&["npub1"], 1).unwrap_err(),
+            Error::EmptyPrefix
+        );
+        assert_eq!(
+            Keys::vanity_parallel(&["q", "n0str1"], 1).unwrap_err(),
+            Error::InvalidChar('1')
+        );
+        assert_eq!(
+            Keys::vanity_parallel(&["bob"], 1).unwrap_err(),
+            Error::InvalidChar('b')
+        );
+
+        let long: String = "q".repeat(BECH32_DATA_LEN + 1);
+        assert!(matches!(
+            Keys::vanity_parallel(&[long.as_str()], 1).unwrap_err(),
+            Error::PrefixTooLong { .. }
+        ));
+    }
+
+    #[test]
+    fn test_vanity_parallel_hex() {
+        let progress = Arc::new(VanityProgress::new());
+        let keys =
+            Keys::vanity_parallel_with_progress(&["A"], VanityFormat::Hex, 2, progress.clone())
+                .unwrap();
+        assert!(keys.public_key.to_hex().starts_with('a'));
+        assert!(progress.attempts() > 0);
+
+        assert_eq!(
+            Keys::vanity_parallel_with_progress(
+                &["0g"],
+                VanityFormat::Hex,
+                1,
+                Arc::new(VanityProgress::new())
+            )
+            .unwrap_err(),
+            Error::InvalidChar('g')
+        );
+    }
+
+    #[test]
+    fn test_vanity_parallel_abort() {
+        let progress = Arc::new(VanityProgress::new());
+        progress.abort();
+
+        // Unreachable prefix: stopped by the abort
+        let prefix: String = "q".repeat(BECH32_DATA_LEN);
+        assert_eq!(
+            Keys::vanity_parallel_with_progress(
+                &[prefix.as_str()],
+                VanityFormat::Bech32,
+                2,
+                progress
+            )
+            .unwrap_err(),
+            Error::Aborted
+        );
+    }
+}
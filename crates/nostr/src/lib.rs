@@ -60,6 +60,9 @@ pub use self::event::{Event, EventBuilder, EventId, Kind, UnsignedEvent};
 #[doc(hidden)]
 pub use self::filter::{Alphabet, Filter, SingleLetterTag};
 #[doc(hidden)]
+#[cfg(feature = "std")]
+pub use self::key::EphemeralKeys;
+#[doc(hidden)]
 pub use self::key::{Keys, PublicKey, SecretKey};
 #[doc(hidden)]
 pub use self::message::{ClientMessage, RelayMessage, SubscriptionId};
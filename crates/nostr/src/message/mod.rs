@@ -31,6 +31,8 @@ pub enum MessageHandleError {
     Event(event::Error),
     /// Invalid message format
     InvalidMessageFormat,
+    /// Valid message with unknown type
+    UnknownMessageType(String),
     /// Empty message
     EmptyMsg,
 }
@@ -44,6 +46,7 @@ impl fmt::Display for MessageHandleError {
             Self::Json(e) => write!(f, "{e}"),
             Self::Event(e) => write!(f, "{e}"),
             Self::InvalidMessageFormat => write!(f, "Invalid format"),
+            Self::UnknownMessageType(t) => write!(f, "Unknown message type: {t}"),
             Self::EmptyMsg => write!(f, "Empty message"),
         }
     }
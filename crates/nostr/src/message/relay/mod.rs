@@ -341,7 +341,7 @@ impl RelayMessage<'_> {
                     message: next_and_deser(&mut v_iter)?,         // Index 2
                 })
             }
-            _ => Err(MessageHandleError::InvalidMessageFormat),
+            _ => Err(MessageHandleError::UnknownMessageType(v_type)),
         }
     }
 
@@ -448,6 +448,21 @@ mod tests {
     use super::*;
     use crate::{Kind, PublicKey, Timestamp};
 
+    #[test]
+    fn test_handle_unknown_message_type() {
+        let msg = r#"["FOO","bar"]"#;
+        assert!(matches!(
+            RelayMessage::from_json(msg).unwrap_err(),
+            MessageHandleError::UnknownMessageType(t) if t == "FOO"
+        ));
+
+        // Not valid JSON
+        assert!(matches!(
+            RelayMessage::from_json(r#"["NOTICE","#).unwrap_err(),
+            MessageHandleError::Json(..)
+        ));
+    }
+
     #[test]
     fn test_handle_valid_notice() {
         let valid_notice_msg = r#"["NOTICE","Invalid event format!"]"#;
@@ -27,6 +27,7 @@ pub mod nip19;
 pub mod nip21;
 pub mod nip22;
 pub mod nip26;
+pub mod nip29;
 pub mod nip34;
 pub mod nip35;
 pub mod nip38;
@@ -8,6 +8,7 @@
 
 use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt;
 use core::num::ParseIntError;
 use core::str::FromStr;
@@ -19,6 +20,7 @@ use serde_json::Value;
 
 use super::nip19::FromBech32;
 use super::nip21::FromNostrUri;
+use super::nip39::{ExternalIdentity, Identity};
 use crate::types::Url;
 use crate::{event, key, Filter, JsonUtil, Kind, PublicKey, Tag};
 
@@ -302,6 +304,14 @@ pub struct Metadata {
     )]
     #[serde(default)]
     pub custom: BTreeMap<String, Value>,
+    /// External identities (NIP39)
+    ///
+    /// Not serialized in the JSON content: published as `i` tags by [`EventBuilder::metadata`](crate::EventBuilder::metadata)
+    /// and parsed back by `Metadata::try_from(&Event)`.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/39.md>
+    #[serde(skip)]
+    pub identities: Vec<Identity>,
 }
 
 impl Metadata {
@@ -410,6 +420,39 @@ impl Metadata {
         self.custom.insert(field_name.into(), value.into());
         self
     }
+
+    /// Add external identity (NIP39)
+    ///
+    /// If the identity already exists, its proof is replaced.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/39.md>
+    pub fn add_identity<S1, S2>(
+        mut self,
+        platform: ExternalIdentity,
+        identity: S1,
+        proof: S2,
+    ) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        let identity: Identity = Identity {
+            platform,
+            ident: identity.into(),
+            proof: proof.into(),
+        };
+
+        match self
+            .identities
+            .iter_mut()
+            .find(|i| i.platform == identity.platform && i.ident == identity.ident)
+        {
+            Some(existing) => existing.proof = identity.proof,
+            None => self.identities.push(identity),
+        }
+
+        self
+    }
 }
 
 impl JsonUtil for Metadata {
@@ -463,6 +506,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{EventBuilder, Keys};
 
     #[test]
     fn test_deserialize_metadata() {
@@ -505,6 +549,28 @@ mod tests {
         assert_eq!(metadata, Metadata::from_json(metadata.as_json()).unwrap());
     }
 
+    #[test]
+    fn test_metadata_identities() {
+        let metadata = Metadata::new()
+            .name("alice")
+            .add_identity(ExternalIdentity::GitHub, "alice", "old")
+            .add_identity(ExternalIdentity::Telegram, "alice", "1687765220/1")
+            .add_identity(ExternalIdentity::GitHub, "alice", "gist-id");
+
+        assert_eq!(metadata.identities.len(), 2);
+        assert_eq!(metadata.identities[0].proof, "gist-id");
+
+        // Not in the JSON content
+        assert_eq!(metadata.as_json(), r#"{"name":"alice"}"#);
+
+        // Published as tags and parsed back
+        let event = EventBuilder::metadata(&metadata)
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert_eq!(event.tags.len(), 2);
+        assert_eq!(Metadata::try_from(&event).unwrap(), metadata);
+    }
+
     #[test]
     fn parse_valid_coordinate() {
         let coordinate: &str =
@@ -6,12 +6,17 @@
 //!
 //! <https://github.com/nostr-protocol/nips/blob/master/06.md>
 
+#[cfg(feature = "std")]
+use alloc::borrow::Cow;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt;
-use core::str::FromStr;
 
-use bip39::Mnemonic;
+use bip39::{Language, Mnemonic};
+#[cfg(feature = "std")]
+use secp256k1::rand::rngs::OsRng;
+#[cfg(feature = "std")]
+use secp256k1::rand::RngCore;
 use secp256k1::{Secp256k1, Signing};
 
 mod bip32;
@@ -31,6 +36,10 @@ pub enum Error {
     BIP32(bip32::Error),
     /// BIP39 error
     BIP39(bip39::Error),
+    /// Unsupported wordlist
+    ///
+    /// Only the ENGLISH wordlist is supported.
+    UnsupportedWordlist,
 }
 
 #[cfg(feature = "std")]
@@ -41,6 +50,9 @@ impl fmt::Display for Error {
         match self {
             Self::BIP32(e) => write!(f, "BIP32: {e}"),
             Self::BIP39(e) => write!(f, "BIP39: {e}"),
+            Self::UnsupportedWordlist => {
+                write!(f, "unsupported wordlist: only ENGLISH is supported")
+            }
         }
     }
 }
@@ -57,6 +69,43 @@ impl From<bip39::Error> for Error {
     }
 }
 
+/// Mnemonic word count
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WordCount {
+    /// 12 words
+    #[default]
+    W12,
+    /// 15 words
+    W15,
+    /// 18 words
+    W18,
+    /// 21 words
+    W21,
+    /// 24 words
+    W24,
+}
+
+impl WordCount {
+    /// Get the number of words
+    #[inline]
+    pub fn as_usize(&self) -> usize {
+        match self {
+            Self::W12 => 12,
+            Self::W15 => 15,
+            Self::W18 => 18,
+            Self::W21 => 21,
+            Self::W24 => 24,
+        }
+    }
+
+    /// Get the entropy length (bytes)
+    #[inline]
+    fn entropy_len(&self) -> usize {
+        // Each word encodes 11 bits, of which 1/33 are checksum
+        self.as_usize() * 4 / 3
+    }
+}
+
 /// NIP06 utils
 ///
 /// <https://github.com/nostr-protocol/nips/blob/master/06.md>
@@ -144,40 +193,101 @@ impl FromMnemonic for Keys {
         S: AsRef<str>,
     {
         // Parse menmonic
-        let mnemonic: Mnemonic = Mnemonic::from_str(mnemonic.as_ref())?;
+        let mnemonic: Mnemonic = parse_mnemonic(mnemonic.as_ref())?;
 
         // Convert mnemonic to seed
         let seed: [u8; 64] = mnemonic
             .to_seed_normalized(passphrase.as_ref().map(|s| s.as_ref()).unwrap_or_default());
 
-        // Derive BIP32 root key
-        let root_key: Xpriv = Xpriv::new_master(&seed)?;
-
-        // Unwrap idx
-        let account: u32 = account.unwrap_or_default();
-        let _type: u32 = r#type.unwrap_or_default();
-        let index: u32 = index.unwrap_or_default();
-
-        // Compose derivation path
-        let path: Vec<ChildNumber> = vec![
-            ChildNumber::from_hardened_idx(PURPOSE)?,
-            ChildNumber::from_hardened_idx(COIN)?,
-            ChildNumber::from_hardened_idx(account)?,
-            ChildNumber::from_normal_idx(_type)?,
-            ChildNumber::from_normal_idx(index)?,
-        ];
+        // Derive keys
+        derive(secp, &seed, account, r#type, index)
+    }
+}
+
+impl Keys {
+    /// Generate a new BIP-39 mnemonic (ENGLISH wordlist) and derive the keys from it.
+    ///
+    /// The keys are derived with the default `account`, `type` and `index` (`0`) and without passphrase.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/06.md>
+    #[cfg(feature = "std")]
+    pub fn generate_mnemonic(word_count: WordCount) -> Result<(Self, Mnemonic), Error> {
+        // Generate entropy
+        let mut entropy: [u8; 32] = [0u8; 32];
+        let entropy: &mut [u8] = &mut entropy[..word_count.entropy_len()];
+        OsRng.fill_bytes(entropy);
+
+        // Compose mnemonic
+        let mnemonic: Mnemonic = Mnemonic::from_entropy_in(Language::English, entropy)?;
+
+        // Derive keys
+        let seed: [u8; 64] = mnemonic.to_seed_normalized("");
+        let keys: Self = derive(SECP256K1, &seed, None, None, None)?;
 
-        // Derive secret key
-        let child_xprv = root_key.derive_xpriv(secp, path);
-        let secret_key = SecretKey::from(child_xprv.private_key);
+        Ok((keys, mnemonic))
+    }
+}
 
-        // Compose keys
-        Ok(Self::new_with_ctx(secp, secret_key))
+/// Parse mnemonic (ENGLISH wordlist)
+///
+/// If the first word isn't in the ENGLISH wordlist, the mnemonic is considered of another language
+/// and is rejected with [`Error::UnsupportedWordlist`].
+fn parse_mnemonic(mnemonic: &str) -> Result<Mnemonic, Error> {
+    // Normalize (NFKD), as required by BIP39
+    #[cfg(feature = "std")]
+    let mnemonic: Cow<str> = {
+        let mut mnemonic: Cow<str> = Cow::Borrowed(mnemonic);
+        Mnemonic::normalize_utf8_cow(&mut mnemonic);
+        mnemonic
+    };
+    let mnemonic: &str = &mnemonic;
+
+    match Mnemonic::parse_in_normalized(Language::English, mnemonic) {
+        Ok(mnemonic) => Ok(mnemonic),
+        Err(bip39::Error::UnknownWord(0)) => Err(Error::UnsupportedWordlist),
+        Err(e) => Err(Error::BIP39(e)),
     }
 }
 
+fn derive<C>(
+    secp: &Secp256k1<C>,
+    seed: &[u8; 64],
+    account: Option<u32>,
+    r#type: Option<u32>,
+    index: Option<u32>,
+) -> Result<Keys, Error>
+where
+    C: Signing,
+{
+    // Derive BIP32 root key
+    let root_key: Xpriv = Xpriv::new_master(seed)?;
+
+    // Unwrap idx
+    let account: u32 = account.unwrap_or_default();
+    let _type: u32 = r#type.unwrap_or_default();
+    let index: u32 = index.unwrap_or_default();
+
+    // Compose derivation path
+    let path: Vec<ChildNumber> = vec![
+        ChildNumber::from_hardened_idx(PURPOSE)?,
+        ChildNumber::from_hardened_idx(COIN)?,
+        ChildNumber::from_hardened_idx(account)?,
+        ChildNumber::from_normal_idx(_type)?,
+        ChildNumber::from_normal_idx(index)?,
+    ];
+
+    // Derive secret key
+    let child_xprv = root_key.derive_xpriv(secp, path);
+    let secret_key = SecretKey::from(child_xprv.private_key);
+
+    // Compose keys
+    Ok(Keys::new_with_ctx(secp, secret_key))
+}
+
 #[cfg(test)]
 mod tests {
+    use core::str::FromStr;
+
     use super::*;
 
     #[test]
@@ -199,4 +309,56 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_invalid_mnemonic() {
+        // Invalid checksum
+        let mnemonic =
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        assert_eq!(
+            Keys::from_mnemonic(mnemonic, None).unwrap_err(),
+            Error::BIP39(bip39::Error::InvalidChecksum)
+        );
+
+        // Non-english wordlist
+        let mnemonic =
+            "ábaco abdomen abeja abierto abogado abono aborto abrazo abrir abuelo abuso acabar";
+        assert_eq!(
+            Keys::from_mnemonic(mnemonic, None).unwrap_err(),
+            Error::UnsupportedWordlist
+        );
+
+        // Non-english wordlist, with other words also in the ENGLISH one
+        let mnemonic = "abaisser abandon abdiquer abeille abolir aborder aboutir aboyer abrasif abreuver abriter abroger";
+        assert_eq!(
+            Keys::from_mnemonic(mnemonic, None).unwrap_err(),
+            Error::UnsupportedWordlist
+        );
+
+        // Typo in an english mnemonic
+        let mnemonic =
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abuot";
+        assert!(matches!(
+            Keys::from_mnemonic(mnemonic, None).unwrap_err(),
+            Error::BIP39(bip39::Error::UnknownWord(11))
+        ));
+
+        // Non-english wordlist, starting with a word also in the ENGLISH one
+        let mnemonic = "abandon abaisser abdiquer abeille abolir aborder aboutir aboyer abrasif abreuver abriter abroger";
+        assert!(matches!(
+            Keys::from_mnemonic(mnemonic, None).unwrap_err(),
+            Error::BIP39(bip39::Error::UnknownWord(1))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_generate_mnemonic() {
+        let (keys, mnemonic) = Keys::generate_mnemonic(WordCount::W24).unwrap();
+        assert_eq!(mnemonic.word_count(), WordCount::W24.as_usize());
+
+        let restored = Keys::from_mnemonic(mnemonic.to_string(), None).unwrap();
+        assert_eq!(keys, restored);
+    }
 }
@@ -9,6 +9,21 @@
 
 use alloc::string::String;
 use alloc::vec::Vec;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use core::num::NonZeroUsize;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use core::sync::atomic::{AtomicU8, Ordering};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use core::time::Duration;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::sync::Mutex;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::thread;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::time::Instant;
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use crate::{EventId, Tag, Tags, UnsignedEvent};
 
 /// Gets the number of leading zero bits. Result is between 0 and 255.
 #[inline]
@@ -57,6 +72,92 @@ pub fn get_prefixes_for_difficulty(leading_zero_bits: u8) -> Vec<String> {
     r
 }
 
+/// Mine POW for an [`UnsignedEvent`], using all the cores, until `difficulty` is reached or `deadline` expires.
+///
+/// Returns the event with the highest difficulty found. Use [`get_leading_zero_bits`] on the event ID
+/// to know the achieved difficulty, which may be lower than the requested one.
+///
+/// The `nonce` tag always commits the achieved difficulty (or a lower one), never the requested one if not reached.
+/// If no nonce was found before the deadline, the event is returned without the `nonce` tag.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn mine(mut unsigned: UnsignedEvent, difficulty: u8, deadline: Duration) -> UnsignedEvent {
+    let num_threads: usize = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1);
+
+    let started: Instant = Instant::now();
+    let best_difficulty: AtomicU8 = AtomicU8::new(0);
+    let best: Mutex<Option<UnsignedEvent>> = Mutex::new(None);
+
+    thread::scope(|s| {
+        for i in 0..num_threads {
+            let unsigned: &UnsignedEvent = &unsigned;
+            let best_difficulty: &AtomicU8 = &best_difficulty;
+            let best: &Mutex<Option<UnsignedEvent>> = &best;
+
+            s.spawn(move || {
+                let mut tags: Tags = unsigned.tags.clone();
+
+                // Every thread iterates a different nonce space
+                let mut nonce: u128 = i as u128;
+
+                loop {
+                    let current: u8 = best_difficulty.load(Ordering::Relaxed);
+
+                    if current >= difficulty || started.elapsed() >= deadline {
+                        break;
+                    }
+
+                    // Commit the next level, instead of the requested difficulty,
+                    // so the nonce tag of the best event is honest also if the deadline expires.
+                    let target: u8 = current + 1;
+
+                    tags.push(Tag::pow(nonce, target));
+
+                    let id: EventId = EventId::new(
+                        &unsigned.pubkey,
+                        &unsigned.created_at,
+                        &unsigned.kind,
+                        &tags,
+                        &unsigned.content,
+                    );
+                    let achieved: u8 = get_leading_zero_bits(id.as_bytes());
+
+                    if achieved >= target {
+                        if let Ok(mut best) = best.lock() {
+                            // Another thread may have found a better one in the meantime
+                            if achieved > best_difficulty.load(Ordering::SeqCst) {
+                                best_difficulty.store(achieved, Ordering::SeqCst);
+                                *best = Some(UnsignedEvent {
+                                    id: Some(id),
+                                    pubkey: unsigned.pubkey,
+                                    created_at: unsigned.created_at,
+                                    kind: unsigned.kind,
+                                    tags: tags.clone(),
+                                    content: unsigned.content.clone(),
+                                });
+                            }
+                        }
+                    }
+
+                    tags.pop();
+
+                    nonce += num_threads as u128;
+                }
+            });
+        }
+    });
+
+    match best.into_inner() {
+        Ok(Some(best)) => best,
+        // Nothing found
+        _ => {
+            unsigned.ensure_id();
+            unsigned
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use core::str::FromStr;
@@ -65,6 +166,41 @@ pub mod tests {
 
     use super::*;
 
+    #[test]
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    fn test_mine() {
+        use crate::{EventBuilder, Keys};
+
+        let keys = Keys::generate();
+        let unsigned = EventBuilder::text_note("POW").build(keys.public_key());
+
+        // Low difficulty: reached
+        let mined = mine(unsigned.clone(), 8, Duration::from_secs(60));
+        let id = mined.id.unwrap();
+        assert!(id.check_pow(8));
+        assert_eq!(mined.sign_with_keys(&keys).unwrap().id, id);
+
+        // Unreachable difficulty: return the best found before the deadline
+        let started = Instant::now();
+        let mined = mine(unsigned, 255, Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let id = mined.id.unwrap();
+        let achieved: u8 = get_leading_zero_bits(id.as_bytes());
+        assert!(achieved > 0);
+
+        // Nonce commits a difficulty not greater than the achieved one
+        let committed: u8 = mined
+            .tags
+            .iter()
+            .find_map(|t| match t.as_standardized() {
+                Some(crate::TagStandard::POW { difficulty, .. }) => Some(*difficulty),
+                _ => None,
+            })
+            .unwrap();
+        assert!(committed <= achieved);
+    }
+
     #[test]
     fn check_get_leading_zeroes() {
         assert_eq!(
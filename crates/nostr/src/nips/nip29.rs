@@ -0,0 +1,78 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2025 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP29: Relay-based Groups
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/29.md>
+
+use alloc::string::String;
+
+use crate::{Alphabet, Event, Filter, Kind, SingleLetterTag, TagKind};
+
+/// Kinds of the group metadata events, generated by the relay
+pub const GROUP_METADATA_KINDS: [Kind; 4] = [
+    Kind::GroupMetadata,
+    Kind::GroupAdmins,
+    Kind::GroupMembers,
+    Kind::GroupRoles,
+];
+
+/// Filter of the events posted to a group (tagged with the `h` tag)
+pub fn group_messages_filter<S>(group_id: S) -> Filter
+where
+    S: Into<String>,
+{
+    Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::H), group_id)
+}
+
+/// Filter of the metadata, admins, members and roles of a group (addressed by the `d` tag)
+pub fn group_metadata_filter<S>(group_id: S) -> Filter
+where
+    S: Into<String>,
+{
+    Filter::new()
+        .kinds(GROUP_METADATA_KINDS)
+        .identifier(group_id)
+}
+
+/// Check if it's a group metadata event (see [`GROUP_METADATA_KINDS`])
+#[inline]
+pub fn is_group_metadata(event: &Event) -> bool {
+    GROUP_METADATA_KINDS.contains(&event.kind)
+}
+
+/// Get the ID of the group which the event was posted to (`h` tag)
+#[inline]
+pub fn event_group_id(event: &Event) -> Option<&str> {
+    event.tags.find(TagKind::h()).and_then(|tag| tag.content())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Tag};
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_group_events() {
+        let keys = Keys::generate();
+
+        let message = EventBuilder::group_message("pizza-lovers", "Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(message.kind, Kind::ChatMessage);
+        assert_eq!(event_group_id(&message), Some("pizza-lovers"));
+        assert!(!is_group_metadata(&message));
+        assert!(group_messages_filter("pizza-lovers").match_event(&message));
+        assert!(!group_messages_filter("other").match_event(&message));
+
+        let metadata = EventBuilder::new(Kind::GroupMetadata, "")
+            .tag(Tag::identifier("pizza-lovers"))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(is_group_metadata(&metadata));
+        assert!(group_metadata_filter("pizza-lovers").match_event(&metadata));
+        assert!(!group_messages_filter("pizza-lovers").match_event(&metadata));
+    }
+}
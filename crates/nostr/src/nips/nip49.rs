@@ -29,6 +29,9 @@ const NONCE_SIZE: usize = 24;
 const CIPHERTEXT_SIZE: usize = 48;
 const KEY_SIZE: usize = 32;
 
+/// Default scrypt `log N` value
+pub const DEFAULT_LOG_N: u8 = 16;
+
 /// NIP49 error
 #[derive(Debug, Eq, PartialEq)]
 pub enum Error {
@@ -65,6 +68,8 @@ pub enum Error {
     KeySecurityNotFound,
     /// Cipthertext not found
     CipherTextNotFound,
+    /// Wrong password
+    WrongPassword,
 }
 
 #[cfg(feature = "std")]
@@ -89,6 +94,7 @@ impl fmt::Display for Error {
             Self::NonceNotFound => write!(f, "nonce not found"),
             Self::KeySecurityNotFound => write!(f, "security not found"),
             Self::CipherTextNotFound => write!(f, "ciphertext not found"),
+            Self::WrongPassword => write!(f, "wrong password"),
         }
     }
 }
@@ -331,6 +337,10 @@ impl EncryptedSecretKey {
     }
 
     /// Decrypt secret key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WrongPassword`] if the ciphertext can't be authenticated with the provided password.
     pub fn decrypt(&self, password: &str) -> Result<SecretKey, Error> {
         // Derive key
         let key: [u8; KEY_SIZE] = derive_key(password, &self.salt, self.log_n)?;
@@ -345,7 +355,9 @@ impl EncryptedSecretKey {
         };
 
         // Decrypt
-        let bytes: Vec<u8> = cipher.decrypt(&self.nonce.into(), payload)?;
+        let bytes: Vec<u8> = cipher
+            .decrypt(&self.nonce.into(), payload)
+            .map_err(|_| Error::WrongPassword)?;
 
         // Parse secret key from bytes
         Ok(SecretKey::from_slice(&bytes)?)
@@ -399,6 +411,15 @@ mod tests {
         assert_eq!(secret_key.to_secret_hex(), SECRET_KEY)
     }
 
+    #[test]
+    fn test_encrypted_secret_key_wrong_password() {
+        let encrypted_secret_key = EncryptedSecretKey::from_bech32(CRYPTSEC).unwrap();
+        assert_eq!(
+            encrypted_secret_key.decrypt("wrong").unwrap_err(),
+            Error::WrongPassword
+        );
+    }
+
     #[test]
     fn test_encrypted_secret_key_serialization() {
         let encrypted_secret_key = EncryptedSecretKey::from_bech32(CRYPTSEC).unwrap();
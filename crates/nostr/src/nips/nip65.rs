@@ -6,16 +6,24 @@
 //!
 //! <https://github.com/nostr-protocol/nips/blob/master/65.md>
 
+use alloc::collections::btree_map::{self, BTreeMap};
 use core::fmt;
 use core::str::FromStr;
 
-use crate::{Event, RelayUrl, TagStandard};
+use crate::{Event, Kind, RelayUrl, TagStandard};
 
 /// NIP56 error
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
     /// Invalid Relay Metadata
     InvalidRelayMetadata,
+    /// Wrong event kind
+    WrongKind {
+        /// The expected kind
+        expected: Kind,
+        /// The found kind
+        found: Kind,
+    },
 }
 
 #[cfg(feature = "std")]
@@ -25,6 +33,9 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::InvalidRelayMetadata => write!(f, "Invalid relay metadata"),
+            Self::WrongKind { expected, found } => {
+                write!(f, "Wrong kind: expected={expected}, found={found}")
+            }
         }
     }
 }
@@ -101,3 +112,181 @@ pub fn extract_owned_relay_list(
         }
     })
 }
+
+/// Relay list
+///
+/// Use [`EventBuilder::relay_list`](crate::EventBuilder::relay_list) to build the event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayList {
+    relays: BTreeMap<RelayUrl, Option<RelayMetadata>>,
+}
+
+impl RelayList {
+    /// New empty relay list
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse relay list from a `kind:10002` event
+    ///
+    /// If a relay is listed more times, with both the `read` and `write` markers, it's considered as read and write.
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::RelayList {
+            return Err(Error::WrongKind {
+                expected: Kind::RelayList,
+                found: event.kind,
+            });
+        }
+
+        let mut list: Self = Self::new();
+
+        for (url, metadata) in extract_relay_list(event) {
+            list.insert(url.clone(), *metadata);
+        }
+
+        Ok(list)
+    }
+
+    /// Add a relay
+    ///
+    /// If the relay is already in the list with a different marker, it becomes read and write.
+    pub fn insert(&mut self, url: RelayUrl, metadata: Option<RelayMetadata>) {
+        match self.relays.entry(url) {
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(metadata);
+            }
+            btree_map::Entry::Occupied(mut entry) => {
+                if *entry.get() != metadata {
+                    entry.insert(None);
+                }
+            }
+        }
+    }
+
+    /// Remove a relay
+    #[inline]
+    pub fn remove(&mut self, url: &RelayUrl) -> Option<Option<RelayMetadata>> {
+        self.relays.remove(url)
+    }
+
+    /// Get the number of relays
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.relays.len()
+    }
+
+    /// Check if the list is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.relays.is_empty()
+    }
+
+    /// Iterate relays
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&RelayUrl, &Option<RelayMetadata>)> {
+        self.relays.iter()
+    }
+
+    /// Iterate the read relays (without marker or with the `read` marker)
+    #[inline]
+    pub fn read_relays(&self) -> impl Iterator<Item = &RelayUrl> {
+        self.relays
+            .iter()
+            .filter(|(_, m)| !matches!(m, Some(RelayMetadata::Write)))
+            .map(|(url, _)| url)
+    }
+
+    /// Iterate the write relays (without marker or with the `write` marker)
+    #[inline]
+    pub fn write_relays(&self) -> impl Iterator<Item = &RelayUrl> {
+        self.relays
+            .iter()
+            .filter(|(_, m)| !matches!(m, Some(RelayMetadata::Read)))
+            .map(|(url, _)| url)
+    }
+}
+
+impl IntoIterator for RelayList {
+    type Item = (RelayUrl, Option<RelayMetadata>);
+    type IntoIter = btree_map::IntoIter<RelayUrl, Option<RelayMetadata>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.relays.into_iter()
+    }
+}
+
+impl FromIterator<(RelayUrl, Option<RelayMetadata>)> for RelayList {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (RelayUrl, Option<RelayMetadata>)>,
+    {
+        let mut list: Self = Self::new();
+        for (url, metadata) in iter.into_iter() {
+            list.insert(url, metadata);
+        }
+        list
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_relay_list_roundtrip() {
+        let damus = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let nos = RelayUrl::parse("wss://nos.lol").unwrap();
+        let primal = RelayUrl::parse("wss://relay.primal.net").unwrap();
+
+        let list: RelayList = [
+            (damus.clone(), None),
+            (nos.clone(), Some(RelayMetadata::Read)),
+            (primal.clone(), Some(RelayMetadata::Write)),
+        ]
+        .into_iter()
+        .collect();
+
+        let keys = Keys::generate();
+        let event = EventBuilder::relay_list(list.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::RelayList);
+
+        let parsed = RelayList::from_event(&event).unwrap();
+        assert_eq!(parsed, list);
+
+        let read: Vec<&RelayUrl> = parsed.read_relays().collect();
+        assert_eq!(read, vec![&nos, &damus]);
+
+        let write: Vec<&RelayUrl> = parsed.write_relays().collect();
+        assert_eq!(write, vec![&damus, &primal]);
+    }
+
+    #[test]
+    fn test_relay_list_merge_markers() {
+        let url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+
+        let mut list = RelayList::new();
+        list.insert(url.clone(), Some(RelayMetadata::Read));
+        list.insert(url.clone(), Some(RelayMetadata::Write));
+
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.iter().next(), Some((&url, &None)));
+    }
+
+    #[test]
+    fn test_relay_list_wrong_kind() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("").sign_with_keys(&keys).unwrap();
+        assert_eq!(
+            RelayList::from_event(&event).unwrap_err(),
+            Error::WrongKind {
+                expected: Kind::RelayList,
+                found: Kind::TextNote,
+            }
+        );
+    }
+}
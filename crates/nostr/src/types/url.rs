@@ -166,6 +166,24 @@ impl RelayUrl {
         self.url.domain()
     }
 
+    /// Return the host (domain or IP address) of the URL, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nostr::types::url::RelayUrl;
+    ///
+    /// let url = RelayUrl::parse("wss://127.0.0.1:7777").unwrap();
+    /// assert_eq!(url.host(), Some("127.0.0.1"));
+    ///
+    /// let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+    /// assert_eq!(url.host(), Some("relay.example.com"));
+    /// ```
+    #[inline]
+    pub fn host(&self) -> Option<&str> {
+        self.url.host_str()
+    }
+
     /// Return the serialization of this relay URL without the trailing slash.
     ///
     /// This method will always remove the trailing slash.